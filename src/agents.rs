@@ -0,0 +1,365 @@
+//! Inventory of user-session background agents: XDG autostart entries, user systemd
+//! services, and tray (StatusNotifierItem) apps, with their running state and cost.
+
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use sysinfo::System;
+use tabled::{Table, Tabled};
+
+use crate::control;
+use crate::dbus;
+use crate::error::CliError;
+use crate::output;
+use crate::paths;
+use crate::selector::{ProcessIndex, Selection};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AgentKind {
+    Autostart,
+    Systemd,
+    Tray,
+}
+
+impl AgentKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            AgentKind::Autostart => "autostart",
+            AgentKind::Systemd => "systemd",
+            AgentKind::Tray => "tray",
+        }
+    }
+}
+
+pub struct Agent {
+    pub kind: AgentKind,
+    /// Desktop file stem, unit name, or tray app process name
+    pub id: String,
+    pub enabled: bool,
+    pub pids: Vec<u32>,
+    /// The desktop file that is in effect, for autostart entries
+    source: Option<PathBuf>,
+}
+
+impl Agent {
+    /// Memory in bytes and CPU percent of the agent's processes, summed.
+    pub fn usage(&self, system: &System) -> (u64, f32) {
+        let processes: Vec<&sysinfo::Process> =
+            self.pids.iter().filter_map(|pid| system.process(sysinfo::Pid::from(*pid as usize))).collect();
+        (processes.iter().map(|p| p.memory()).sum(), processes.iter().map(|p| p.cpu_usage()).sum())
+    }
+}
+
+#[derive(Tabled)]
+struct AgentRow {
+    #[tabled(rename = "Kind")]
+    kind: &'static str,
+
+    #[tabled(rename = "Name")]
+    name: String,
+
+    #[tabled(rename = "Status")]
+    status: String,
+
+    #[tabled(rename = "PIDs")]
+    pids: String,
+
+    #[tabled(rename = "Memory")]
+    memory: String,
+
+    #[tabled(rename = "CPU %")]
+    cpu_percent: String,
+}
+
+pub fn show_agents() -> Result<()> {
+    let system = sampled_system();
+    let agents = collect_agents(&system);
+
+    if agents.is_empty() {
+        println!("No autostart entries, user services, or tray apps found");
+        return Ok(());
+    }
+
+    let rows: Vec<AgentRow> = agents
+        .iter()
+        .map(|agent| {
+            let (memory, cpu) = agent.usage(&system);
+            let running = agent.pids.iter().any(|pid| system.process(sysinfo::Pid::from(*pid as usize)).is_some());
+            let status = match (agent.enabled, agent.pids.is_empty()) {
+                (_, false) => "running",
+                (true, true) => "not running",
+                (false, true) => "disabled",
+            };
+
+            AgentRow {
+                kind: agent.kind.name(),
+                name: agent.id.clone(),
+                status: status.to_string(),
+                pids: if agent.pids.is_empty() {
+                    "-".to_string()
+                } else {
+                    agent.pids.iter().map(|p| p.to_string()).collect::<Vec<_>>().join(",")
+                },
+                memory: if running { output::format_memory(memory) } else { "-".to_string() },
+                cpu_percent: if running { format!("{:.1}", cpu) } else { "-".to_string() },
+            }
+        })
+        .collect();
+
+    println!("{}", Table::new(rows));
+    Ok(())
+}
+
+/// Disables (or re-enables) an autostart entry or user service so it no longer starts with the session.
+pub fn set_agent_enabled(name: &str, enable: bool) -> Result<()> {
+    let system = System::new_all();
+    let agents = collect_agents(&system);
+    let agent = agents
+        .iter()
+        .find(|a| a.kind != AgentKind::Tray && a.id.eq_ignore_ascii_case(name))
+        .or_else(|| agents.iter().find(|a| a.id.eq_ignore_ascii_case(name)))
+        .ok_or_else(|| CliError::not_found(format!("No agent named '{}'", name)))?;
+
+    match agent.kind {
+        AgentKind::Autostart => {
+            let source = agent.source.as_deref().context("Autostart entry has no desktop file")?;
+            set_autostart_hidden(source, !enable)?;
+        }
+        AgentKind::Systemd => {
+            let action = if enable { "enable" } else { "disable" };
+            let status = Command::new("systemctl")
+                .args(["--user", action, "--now", &agent.id])
+                .status()
+                .context("Failed to run systemctl")?;
+            if !status.success() {
+                return Err(anyhow::anyhow!("systemctl --user {} {} failed", action, agent.id));
+            }
+        }
+        AgentKind::Tray => {
+            return Err(anyhow::anyhow!(
+                "'{}' is a tray app without its own autostart entry or service; disable whatever launches it",
+                agent.id
+            ));
+        }
+    }
+
+    println!("{} {} ({})", if enable { "Enabled" } else { "Disabled" }, agent.id, agent.kind.name());
+    Ok(())
+}
+
+/// Samples twice so CPU usage reflects current activity.
+pub fn sampled_system() -> System {
+    let mut system = System::new_all();
+    system.refresh_all();
+    std::thread::sleep(sysinfo::MINIMUM_CPU_UPDATE_INTERVAL);
+    system.refresh_processes();
+    system
+}
+
+/// Everything that starts with the session, with the processes it is running now.
+pub fn collect_agents(system: &System) -> Vec<Agent> {
+    let mut agents = autostart_agents(system);
+    agents.extend(systemd_agents(system));
+
+    // Tray apps already covered by an autostart entry or service are not listed twice
+    for tray in tray_agents(system) {
+        if !agents.iter().any(|a| a.pids.iter().any(|pid| tray.pids.contains(pid))) {
+            agents.push(tray);
+        }
+    }
+
+    agents
+}
+
+/// Autostart directories, highest precedence first.
+fn autostart_dirs() -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = paths::config_home().into_iter().map(|dir| dir.join("autostart")).collect();
+    let system_dirs = env::var("XDG_CONFIG_DIRS").unwrap_or_else(|_| "/etc/xdg".to_string());
+    dirs.extend(system_dirs.split(':').filter(|d| !d.is_empty()).map(|d| Path::new(d).join("autostart")));
+    dirs
+}
+
+fn autostart_agents(system: &System) -> Vec<Agent> {
+    // A file in a higher-precedence directory overrides files with the same name below it
+    let mut entries: BTreeMap<String, PathBuf> = BTreeMap::new();
+    for dir in autostart_dirs() {
+        let Ok(files) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for file in files.flatten() {
+            let path = file.path();
+            if path.extension().is_some_and(|ext| ext == "desktop") {
+                let name = file.file_name().to_string_lossy().to_string();
+                entries.entry(name).or_insert(path);
+            }
+        }
+    }
+
+    entries
+        .into_iter()
+        .filter_map(|(name, path)| {
+            let content = fs::read_to_string(&path).ok()?;
+            let hidden = desktop_key(&content, "Hidden") == Some("true")
+                || desktop_key(&content, "X-GNOME-Autostart-enabled") == Some("false");
+            let program = desktop_key(&content, "Exec").and_then(exec_program);
+
+            Some(Agent {
+                kind: AgentKind::Autostart,
+                id: name.trim_end_matches(".desktop").to_string(),
+                enabled: !hidden,
+                pids: program.map(|p| processes_running(system, &p)).unwrap_or_default(),
+                source: Some(path),
+            })
+        })
+        .collect()
+}
+
+fn desktop_key<'a>(content: &'a str, key: &str) -> Option<&'a str> {
+    content
+        .lines()
+        .skip_while(|line| line.trim() != "[Desktop Entry]")
+        .take_while(|line| !line.starts_with('[') || line.trim() == "[Desktop Entry]")
+        .find_map(|line| line.strip_prefix(key)?.trim_start().strip_prefix('='))
+        .map(str::trim)
+}
+
+/// The program an Exec line runs, skipping `env VAR=value` prefixes.
+fn exec_program(exec: &str) -> Option<String> {
+    exec.split_whitespace()
+        .find(|word| *word != "env" && !word.contains('='))
+        .map(|word| word.trim_matches('"').rsplit('/').next().unwrap_or(word).to_string())
+}
+
+fn processes_running(system: &System, program: &str) -> Vec<u32> {
+    // Kernel process names are truncated to 15 characters
+    let short: String = program.chars().take(15).collect();
+    let mut pids: Vec<u32> = system
+        .processes()
+        .values()
+        .filter(|p| p.thread_kind().is_none())
+        .filter(|p| {
+            p.name() == short
+                || p.exe().and_then(|exe| exe.file_name()).is_some_and(|exe| exe == program)
+        })
+        .map(|p| p.pid().as_u32())
+        .collect();
+    pids.sort();
+    pids
+}
+
+fn systemctl_units(args: &[&str]) -> Vec<String> {
+    let Ok(output) = Command::new("systemctl")
+        .args(["--user", "--no-legend", "--plain"])
+        .args(args)
+        .output()
+    else {
+        return Vec::new();
+    };
+
+    if !output.status.success() {
+        return Vec::new();
+    }
+
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.split_whitespace().next().map(str::to_string))
+        .filter(|unit| unit.ends_with(".service") && !unit.contains("@."))
+        .collect()
+}
+
+fn systemd_agents(system: &System) -> Vec<Agent> {
+    let enabled = systemctl_units(&["list-unit-files", "--type=service", "--state=enabled"]);
+    let running = systemctl_units(&["list-units", "--type=service", "--state=running"]);
+
+    let mut units: Vec<String> = enabled.iter().chain(&running).cloned().collect();
+    units.sort();
+    units.dedup();
+
+    // One index for all units, so each process's cgroup is read once rather than once per unit
+    let mut index = ProcessIndex::new(system);
+    units
+        .into_iter()
+        .map(|unit| {
+            let pids = Selection::parse(&format!("unit:{}", unit))
+                .map(|selection| selection.resolve_in(&mut index))
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|pid| !control::is_thread(system, *pid))
+                .collect();
+
+            Agent {
+                kind: AgentKind::Systemd,
+                enabled: enabled.contains(&unit),
+                id: unit,
+                pids,
+                source: None,
+            }
+        })
+        .collect()
+}
+
+fn tray_agents(system: &System) -> Vec<Agent> {
+    let pids = dbus::block_on(async {
+        let connection = dbus::session().await.ok()?;
+        let items: Vec<String> = dbus::property(
+            &connection,
+            "org.kde.StatusNotifierWatcher",
+            "/StatusNotifierWatcher",
+            "org.kde.StatusNotifierWatcher",
+            "RegisteredStatusNotifierItems",
+        )
+        .await?;
+
+        let mut pids = Vec::new();
+        for item in items {
+            // Items are "<bus name>/<object path>", or a bare well-known name
+            if let Some(bus_name) = item.split('/').next().filter(|name| !name.is_empty())
+                && let Some(pid) = dbus::connection_pid(&connection, bus_name).await
+            {
+                pids.push(pid);
+            }
+        }
+        Some(pids)
+    });
+
+    pids.unwrap_or_default()
+        .into_iter()
+        .filter_map(|pid| {
+            let process = system.process(sysinfo::Pid::from(pid as usize))?;
+
+            Some(Agent {
+                kind: AgentKind::Tray,
+                id: process.name().to_string(),
+                enabled: true,
+                pids: vec![pid],
+                source: None,
+            })
+        })
+        .collect()
+}
+
+/// Hides or unhides an autostart entry through a copy in the user's autostart directory,
+/// which takes precedence over system-wide entries without modifying them.
+fn set_autostart_hidden(source: &Path, hidden: bool) -> Result<()> {
+    let content = fs::read_to_string(source).with_context(|| format!("Failed to read {}", source.display()))?;
+    let dir = paths::config_home().context("Cannot determine the user config directory")?.join("autostart");
+    fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    let target = dir.join(source.file_name().context("Invalid desktop file path")?);
+
+    let mut lines: Vec<String> = content
+        .lines()
+        .filter(|line| !line.starts_with("Hidden=") && !line.starts_with("X-GNOME-Autostart-enabled="))
+        .map(str::to_string)
+        .collect();
+    let section = lines
+        .iter()
+        .position(|line| line.trim() == "[Desktop Entry]")
+        .context("Desktop file has no [Desktop Entry] section")?;
+    lines.insert(section + 1, format!("Hidden={}", hidden));
+
+    fs::write(&target, lines.join("\n") + "\n").with_context(|| format!("Failed to write {}", target.display()))?;
+    Ok(())
+}
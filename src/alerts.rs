@@ -0,0 +1,306 @@
+//! Threshold alerts from the `[[alert]]` rules in the config file, evaluated by `monitor` along
+//! with the `[[hook]]` rules (see `hooks`).
+//!
+//! ```toml
+//! [[alert]]
+//! name = "chrome memory"
+//! process = "name:chrome"   # Sum over matching processes; omit for the whole system
+//! metric = "memory"         # "memory" (MB) or "cpu" (%)
+//! above = 4000
+//! for = "30s"               # Must hold this long before firing
+//! cooldown = "15m"          # Minimum time between notifications
+//! sinks = ["desktop"]
+//! ```
+//!
+//! A rule is pending while its condition holds for less than `for`, then fires. When the
+//! condition clears, a resolution notice is sent (unless `resolve = false`). A rule that fires
+//! again within `cooldown` of its last notification stays silent, and so does its resolution.
+//!
+//! Processes keep counting towards a rule once its selector has matched them, so a wrapper
+//! script selected by name is still followed after it execs into the real program.
+
+use anyhow::Result;
+use serde::Deserialize;
+use std::time::{Duration, Instant};
+use sysinfo::System;
+
+use crate::config::Config;
+use crate::history::{self, History};
+use crate::hooks::Hooks;
+use crate::identity::{Followed, ProcessKey};
+use crate::otlp::Exporter;
+use crate::output::{self, MonitorFormat};
+use crate::process;
+use crate::report;
+use crate::selector::{ProcessIndex, Selection};
+use crate::sink::{self, Message, Urgency};
+use crate::smoothing::Smoother;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AlertRule {
+    pub name: String,
+    /// Process selector; without one the metric is system-wide
+    pub process: Option<String>,
+    pub metric: Metric,
+    pub above: f64,
+    #[serde(default, rename = "for", deserialize_with = "report::deserialize_duration")]
+    pub hold: Duration,
+    #[serde(default, deserialize_with = "report::deserialize_duration")]
+    pub cooldown: Duration,
+    #[serde(default = "default_resolve")]
+    pub resolve: bool,
+    #[serde(default)]
+    pub sinks: Vec<String>,
+}
+
+fn default_resolve() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Metric {
+    /// Resident memory in MB
+    Memory,
+    /// CPU usage in percent (of one core per process; of all cores system-wide)
+    Cpu,
+}
+
+impl Metric {
+    fn format(&self, value: f64) -> String {
+        match self {
+            Metric::Memory => format!("{:.0} MB", value),
+            Metric::Cpu => format!("{:.0}%", value),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum State {
+    Ok,
+    Pending { since: Instant },
+    Firing { since: Instant, notified: bool },
+}
+
+/// An alert rule with its evaluation state.
+pub struct Alert {
+    pub rule: AlertRule,
+    state: State,
+    last_notified: Option<Instant>,
+    /// Processes the selector matched, still counted after they exec into something else
+    followed: Followed,
+    /// The process contributing most to the last value, offered for switch/kill in notifications
+    top_process: Option<ProcessKey>,
+}
+
+/// What a rule evaluation produced.
+pub enum Event {
+    Fired { value: f64, held: Duration },
+    Resolved { value: f64, lasted: Duration },
+}
+
+impl Event {
+    fn name(&self) -> &'static str {
+        match self {
+            Event::Fired { .. } => "firing",
+            Event::Resolved { .. } => "resolved",
+        }
+    }
+}
+
+impl Alert {
+    pub fn new(rule: AlertRule) -> Self {
+        Alert { rule, state: State::Ok, last_notified: None, followed: Followed::default(), top_process: None }
+    }
+
+    /// The rule's metric right now, resolving its selector with `index` (built over `system`).
+    pub fn value(&mut self, system: &System, index: &mut ProcessIndex) -> Result<f64> {
+        let Some(selector) = &self.rule.process else {
+            return Ok(match self.rule.metric {
+                Metric::Memory => system.used_memory() as f64 / 1024.0 / 1024.0,
+                Metric::Cpu => system.global_cpu_info().cpu_usage() as f64,
+            });
+        };
+
+        let matched = Selection::parse(selector)?.resolve_in(index);
+        let processes = self.followed.update(system, &matched);
+        let values: Vec<(ProcessKey, f64)> = processes
+            .iter()
+            .filter_map(|pid| system.process(sysinfo::Pid::from(*pid as usize)))
+            .map(|process| {
+                let value = match self.rule.metric {
+                    Metric::Memory => process.memory() as f64 / 1024.0 / 1024.0,
+                    Metric::Cpu => process.cpu_usage() as f64,
+                };
+                (ProcessKey::of(process), value)
+            })
+            .collect();
+        self.top_process = values.iter().max_by(|a, b| a.1.total_cmp(&b.1)).map(|(key, _)| *key);
+        Ok(values.iter().map(|(_, value)| value).sum())
+    }
+
+    /// Advances the rule with a new value, returning an event to announce if any.
+    pub fn update(&mut self, value: f64, now: Instant) -> Option<Event> {
+        let breached = value > self.rule.above;
+        match (self.state, breached) {
+            (State::Ok, true) => {
+                self.state = State::Pending { since: now };
+                self.check_pending(value, now, now)
+            }
+            (State::Pending { since }, true) => self.check_pending(value, since, now),
+            (State::Pending { .. }, false) => {
+                self.state = State::Ok;
+                None
+            }
+            (State::Firing { since, notified }, false) => {
+                self.state = State::Ok;
+                // A silenced alert resolves silently too
+                (notified && self.rule.resolve).then(|| Event::Resolved { value, lasted: now - since })
+            }
+            (State::Firing { .. }, true) | (State::Ok, false) => None,
+        }
+    }
+
+    fn check_pending(&mut self, value: f64, since: Instant, now: Instant) -> Option<Event> {
+        if now - since < self.rule.hold {
+            return None;
+        }
+        let cooling_down = self.last_notified.is_some_and(|last| now - last < self.rule.cooldown);
+        self.state = State::Firing { since, notified: !cooling_down };
+        if cooling_down {
+            return None;
+        }
+        self.last_notified = Some(now);
+        Some(Event::Fired { value, held: now - since })
+    }
+
+    pub fn message(&self, event: &Event) -> Message {
+        let subject = self.rule.process.as_deref().unwrap_or("system");
+        match event {
+            Event::Fired { value, held } => Message {
+                title: format!("[firing] {}", self.rule.name),
+                body: format!(
+                    "{} {} is {}, above {}{}",
+                    subject,
+                    metric_name(self.rule.metric),
+                    self.rule.metric.format(*value),
+                    self.rule.metric.format(self.rule.above),
+                    if held.is_zero() {
+                        String::new()
+                    } else {
+                        format!(" for {}", output::format_duration(held.as_millis() as u64))
+                    }
+                ),
+                urgency: Urgency::Critical,
+                process: self.top_process,
+            },
+            Event::Resolved { value, lasted } => Message {
+                title: format!("[resolved] {}", self.rule.name),
+                body: format!(
+                    "{} {} is back to {} after {}",
+                    subject,
+                    metric_name(self.rule.metric),
+                    self.rule.metric.format(*value),
+                    output::format_duration(lasted.as_millis() as u64)
+                ),
+                urgency: Urgency::Normal,
+                process: None,
+            },
+        }
+    }
+}
+
+fn metric_name(metric: Metric) -> &'static str {
+    match metric {
+        Metric::Memory => "memory",
+        Metric::Cpu => "CPU",
+    }
+}
+
+/// Evaluates the configured alert rules and hooks every `interval` until interrupted, comparing
+/// values smoothed by `smoothing` (0 for raw samples) against the alert thresholds. With an
+/// `exporter`, every evaluation also exports metrics to its OpenTelemetry collector.
+pub async fn monitor(interval: Duration, smoothing: f64, format: MonitorFormat, exporter: Option<Exporter>) -> Result<()> {
+    let config = Config::load()?;
+    // Streaming samples is a reason to run on its own
+    let streams = format == MonitorFormat::Ndjson || exporter.is_some();
+    if config.alerts.is_empty() && config.hooks.is_empty() && !streams {
+        return Err(anyhow::anyhow!(
+            "No alert rules or hooks configured; add [[alert]] or [[hook]] sections to {}",
+            Config::path().map(|p| p.display().to_string()).unwrap_or_else(|| "the config file".to_string())
+        ));
+    }
+    for rule in &config.alerts {
+        if let Some(selector) = &rule.process {
+            Selection::parse(selector)?;
+        }
+    }
+
+    let mut hooks = Hooks::new(config.hooks)?;
+    // Alerts are kept in the history for the daily report, which can do without them
+    let history = match history::default_path().and_then(|path| History::open(&path)) {
+        Ok(history) => Some(history),
+        Err(err) if !config.alerts.is_empty() => {
+            eprintln!("Warning: alerts will not be recorded for the daily report: {:#}", err);
+            None
+        }
+        Err(_) => None,
+    };
+
+    let mut alerts: Vec<Alert> = config.alerts.into_iter().map(Alert::new).collect();
+    let mut system = System::new_all();
+    system.refresh_all();
+    let banner = format!("Monitoring {} alert rules and {} hooks every {}s", alerts.len(), hooks.len(), interval.as_secs());
+    match format {
+        MonitorFormat::Text => println!("{}", banner),
+        // Keep stdout to the samples
+        MonitorFormat::Ndjson => {
+            eprintln!("{}", banner);
+            sink::reserve_stdout();
+        }
+    }
+    hooks.evaluate(&system, &mut ProcessIndex::new(&system));
+
+    let mut values = Smoother::new(smoothing);
+    let mut ticker = tokio::time::interval(interval);
+    // The first tick completes immediately, before CPU usage can be measured
+    ticker.tick().await;
+    loop {
+        ticker.tick().await;
+        system.refresh_all();
+        let now = Instant::now();
+        let mut index = ProcessIndex::new(&system);
+
+        for (position, alert) in alerts.iter_mut().enumerate() {
+            let value = match alert.value(&system, &mut index) {
+                Ok(value) => values.update(position, value),
+                Err(err) => {
+                    eprintln!("Warning: alert '{}': {:#}", alert.rule.name, err);
+                    continue;
+                }
+            };
+            let Some(event) = alert.update(value, now) else {
+                continue;
+            };
+            let message = alert.message(&event);
+            if let Some(history) = &history
+                && let Err(err) = history.record_alert(chrono::Utc::now().timestamp(), &alert.rule.name, event.name(), &message.body)
+            {
+                eprintln!("Warning: alert '{}' could not be recorded: {:#}", alert.rule.name, err);
+            }
+            if let Err(err) = sink::deliver(&message, &alert.rule.sinks) {
+                eprintln!("Warning: alert '{}' could not be delivered: {:#}", alert.rule.name, err);
+            }
+        }
+        hooks.evaluate(&system, &mut index);
+        if format == MonitorFormat::Ndjson {
+            process::write_ndjson_sample(&system)?;
+        }
+        if let Some(exporter) = &exporter
+            && let Err(err) = exporter.export(&system).await
+        {
+            eprintln!("Warning: OTLP export failed: {:#}", err);
+        }
+    }
+}
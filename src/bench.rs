@@ -0,0 +1,131 @@
+use anyhow::Result;
+use std::io::{self, IsTerminal, Write};
+use std::process::{Command, Stdio};
+use tabled::{Table, Tabled};
+
+use crate::output;
+use crate::run::{self, RunSummary};
+
+#[derive(Tabled)]
+struct BenchRow {
+    #[tabled(rename = "Command")]
+    command: String,
+
+    #[tabled(rename = "Runs")]
+    runs: usize,
+
+    #[tabled(rename = "Failed")]
+    failed: usize,
+
+    #[tabled(rename = "Wall time (mean ± σ)")]
+    wall: String,
+
+    #[tabled(rename = "Wall min..max")]
+    range: String,
+
+    #[tabled(rename = "CPU time (mean)")]
+    cpu: String,
+
+    #[tabled(rename = "Peak RSS (max)")]
+    peak_rss: String,
+}
+
+struct Stats {
+    wall: Vec<f64>,
+    cpu: Vec<f64>,
+    peak_rss: u64,
+    failed: usize,
+}
+
+impl Stats {
+    fn from_runs(runs: &[RunSummary]) -> Self {
+        Stats {
+            wall: runs.iter().map(|r| r.duration_secs).collect(),
+            cpu: runs.iter().map(|r| r.user_cpu_secs + r.system_cpu_secs).collect(),
+            peak_rss: runs.iter().map(|r| r.peak_tree_rss_bytes).max().unwrap_or(0),
+            failed: runs.iter().filter(|r| r.exit_code != Some(0)).count(),
+        }
+    }
+}
+
+fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len().max(1) as f64
+}
+
+fn std_dev(values: &[f64]) -> f64 {
+    let mean = mean(values);
+    (values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / values.len().max(1) as f64).sqrt()
+}
+
+/// Benchmarks need sub-second precision, unlike report durations.
+fn secs(value: f64) -> String {
+    match value {
+        v if v < 1.0 => format!("{:.1} ms", v * 1000.0),
+        v if v < 60.0 => format!("{:.3} s", v),
+        v => output::format_duration((v * 1000.0) as u64),
+    }
+}
+
+/// Runs each shell command `runs` times and compares wall time, CPU time, and peak memory.
+pub fn bench(commands: &[String], runs: usize, show_output: bool) -> Result<()> {
+    let runs = runs.max(1);
+    let progress = io::stderr().is_terminal();
+
+    let mut results = Vec::new();
+    for command in commands {
+        let mut summaries = Vec::new();
+        for i in 1..=runs {
+            if progress {
+                eprint!("\r\x1b[KRunning `{}` ({}/{})", command, i, runs);
+                io::stderr().flush().ok();
+            }
+
+            let mut process = Command::new("sh");
+            process.args(["-c", command]);
+            if !show_output {
+                process.stdout(Stdio::null()).stderr(Stdio::null());
+            }
+            summaries.push(run::measure(&mut process, vec![command.clone()], false)?);
+        }
+        results.push((command, Stats::from_runs(&summaries)));
+    }
+    if progress {
+        eprint!("\r\x1b[K");
+    }
+
+    let rows: Vec<BenchRow> = results
+        .iter()
+        .map(|(command, stats)| BenchRow {
+            command: command.to_string(),
+            runs,
+            failed: stats.failed,
+            wall: format!("{} ± {}", secs(mean(&stats.wall)), secs(std_dev(&stats.wall))),
+            range: format!(
+                "{}..{}",
+                secs(stats.wall.iter().copied().fold(f64::INFINITY, f64::min)),
+                secs(stats.wall.iter().copied().fold(0.0, f64::max))
+            ),
+            cpu: secs(mean(&stats.cpu)),
+            peak_rss: output::format_memory(stats.peak_rss),
+        })
+        .collect();
+    println!("{}", Table::new(rows));
+
+    if let [(first, a), (second, b)] = results.as_slice() {
+        let ratio = |b: f64, a: f64| if a > 0.0 { format!("{:.2}x", b / a) } else { "-".to_string() };
+        println!(
+            "\n`{}` relative to `{}`: {} wall time, {} CPU time, {} peak memory",
+            second,
+            first,
+            ratio(mean(&b.wall), mean(&a.wall)),
+            ratio(mean(&b.cpu), mean(&a.cpu)),
+            ratio(b.peak_rss as f64, a.peak_rss as f64)
+        );
+    }
+
+    if results.iter().any(|(_, stats)| stats.failed > 0) {
+        println!("\nNote: some runs exited with a non-zero status; rerun with --show-output to see why");
+    }
+
+    Ok(())
+}
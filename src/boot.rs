@@ -0,0 +1,200 @@
+//! `boot`: what slows down boot and login, from `systemd-analyze`, and which of the things that
+//! start with the session are still costly now that they are running.
+//!
+//! Units are timed by `systemd-analyze blame`, for the system manager and the user manager, and
+//! joined with the processes in their cgroups. The session's agents are the same inventory
+//! `agents` shows: XDG autostart entries, user services, and tray apps.
+
+use anyhow::{Context, Result};
+use std::process::Command;
+use sysinfo::System;
+use tabled::{Table, Tabled};
+
+use crate::agents;
+use crate::output;
+use crate::selector::{ProcessIndex, Selection};
+
+/// Agents using at least this much CPU count as heavy whatever their memory.
+const HEAVY_CPU_PERCENT: f32 = 5.0;
+
+#[derive(Tabled)]
+struct UnitRow {
+    #[tabled(rename = "Manager")]
+    manager: &'static str,
+
+    #[tabled(rename = "Unit")]
+    unit: String,
+
+    #[tabled(rename = "Startup")]
+    startup: String,
+
+    #[tabled(rename = "Memory")]
+    memory: String,
+
+    #[tabled(rename = "CPU %")]
+    cpu_percent: String,
+}
+
+#[derive(Tabled)]
+struct AgentRow {
+    #[tabled(rename = "Kind")]
+    kind: &'static str,
+
+    #[tabled(rename = "Name")]
+    name: String,
+
+    #[tabled(rename = "Startup")]
+    startup: String,
+
+    #[tabled(rename = "Memory")]
+    memory: String,
+
+    #[tabled(rename = "CPU %")]
+    cpu_percent: String,
+}
+
+/// One line of `systemd-analyze blame`.
+struct UnitTime {
+    /// "system" or "user"
+    manager: &'static str,
+    unit: String,
+    ms: u64,
+}
+
+/// Reports the slowest `top` units of boot and login and the session agents using at least
+/// `min_memory_mb` or notable CPU.
+pub fn show_boot_report(top: usize, min_memory_mb: u64) -> Result<()> {
+    let system = agents::sampled_system();
+    let mut index = ProcessIndex::new(&system);
+
+    let mut units: Vec<UnitTime> = Vec::new();
+    for (manager, label, user) in [("system", "Boot", false), ("user", "Login", true)] {
+        match startup_summary(user) {
+            Ok(summary) => println!("{}: {}", label, summary),
+            Err(err) => println!("{}: unknown ({:#})", label, err),
+        }
+        units.extend(blame(manager, user).unwrap_or_default());
+    }
+
+    if units.is_empty() {
+        println!("\nNo unit startup times available");
+    } else {
+        units.sort_by_key(|unit| std::cmp::Reverse(unit.ms));
+        let rows: Vec<UnitRow> = units
+            .iter()
+            .take(top)
+            .map(|unit| {
+                let (memory, cpu) = unit_usage(&system, &mut index, &unit.unit);
+                UnitRow {
+                    manager: unit.manager,
+                    unit: unit.unit.clone(),
+                    startup: output::format_duration(unit.ms),
+                    memory,
+                    cpu_percent: cpu,
+                }
+            })
+            .collect();
+        println!("\nSlowest to start:\n{}", Table::new(rows));
+    }
+
+    let mut heavy: Vec<(u64, AgentRow)> = agents::collect_agents(&system)
+        .into_iter()
+        .filter(|agent| !agent.pids.is_empty())
+        .filter_map(|agent| {
+            let (memory, cpu) = agent.usage(&system);
+            if memory < min_memory_mb * 1024 * 1024 && cpu < HEAVY_CPU_PERCENT {
+                return None;
+            }
+            let startup = units
+                .iter()
+                .find(|unit| unit.manager == "user" && unit.unit == agent.id)
+                .map(|unit| output::format_duration(unit.ms))
+                .unwrap_or_else(|| "-".to_string());
+            Some((
+                memory,
+                AgentRow {
+                    kind: agent.kind.name(),
+                    name: agent.id,
+                    startup,
+                    memory: output::format_memory(memory),
+                    cpu_percent: format!("{:.1}", cpu),
+                },
+            ))
+        })
+        .collect();
+
+    if heavy.is_empty() {
+        println!("\nNothing started with the session uses {} MB or {}% CPU or more", min_memory_mb, HEAVY_CPU_PERCENT);
+        return Ok(());
+    }
+    heavy.sort_by_key(|(memory, _)| std::cmp::Reverse(*memory));
+    println!("\nStarted with the session and still heavy:\n{}", Table::new(heavy.into_iter().map(|(_, row)| row)));
+    println!("\nStop one from starting with `top-helper boot --disable <Name>`");
+    Ok(())
+}
+
+/// The "Startup finished in ..." line of `systemd-analyze time`, without that prefix.
+fn startup_summary(user: bool) -> Result<String> {
+    let output = systemd_analyze(user, "time")?;
+    let line = output.lines().next().unwrap_or_default();
+    Ok(line.strip_prefix("Startup finished in ").unwrap_or(line).trim().to_string())
+}
+
+fn blame(manager: &'static str, user: bool) -> Result<Vec<UnitTime>> {
+    Ok(systemd_analyze(user, "blame")?
+        .lines()
+        .filter_map(|line| {
+            // "1min 2.345s NetworkManager-wait-online.service"
+            let (time, unit) = line.trim().rsplit_once(' ')?;
+            Some(UnitTime { manager, unit: unit.to_string(), ms: parse_systemd_time(time)? })
+        })
+        .collect())
+}
+
+fn systemd_analyze(user: bool, verb: &str) -> Result<String> {
+    let mut command = Command::new("systemd-analyze");
+    if user {
+        command.arg("--user");
+    }
+    let output = command.arg(verb).output().context("Failed to run systemd-analyze")?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow::anyhow!("{}", stderr.lines().next().unwrap_or("systemd-analyze failed").trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Parses a systemd time span such as `1min 2.345s`, `850ms`, or `1h 3min`, in milliseconds.
+fn parse_systemd_time(text: &str) -> Option<u64> {
+    let mut ms = 0.0;
+    for part in text.split_whitespace() {
+        let split = part.find(|c: char| !c.is_ascii_digit() && c != '.')?;
+        let (number, unit) = part.split_at(split);
+        let multiplier = match unit {
+            "us" | "µs" => 0.001,
+            "ms" => 1.0,
+            "s" => 1000.0,
+            "min" => 60_000.0,
+            "h" => 3_600_000.0,
+            _ => return None,
+        };
+        ms += number.parse::<f64>().ok()? * multiplier;
+    }
+    Some(ms as u64)
+}
+
+/// The memory and CPU of the unit's processes now, formatted; dashes if none are running.
+fn unit_usage(system: &System, index: &mut ProcessIndex, unit: &str) -> (String, String) {
+    let pids = Selection::parse(&format!("unit:{}", unit)).map(|s| s.resolve_in(index)).unwrap_or_default();
+    let processes: Vec<&sysinfo::Process> = pids
+        .iter()
+        .filter_map(|pid| system.process(sysinfo::Pid::from(*pid as usize)))
+        .filter(|process| process.thread_kind().is_none())
+        .collect();
+    if processes.is_empty() {
+        return ("-".to_string(), "-".to_string());
+    }
+    let memory: u64 = processes.iter().map(|p| p.memory()).sum();
+    let cpu: f32 = processes.iter().map(|p| p.cpu_usage()).sum();
+    (output::format_memory(memory), format!("{:.1}", cpu))
+}
@@ -0,0 +1,252 @@
+//! Browser child processes: what a Chrome/Chromium or Firefox helper process is for, and which
+//! tabs it renders.
+//!
+//! The process type comes from the command line (`--type=renderer` for Chromium, the trailing
+//! process type after `-contentproc` for Firefox). Tabs need the browser to be remotely
+//! controllable:
+//!
+//! - Firefox started with `--marionette` reports, for every content process, the documents it
+//!   hosts (the data behind about:processes), so a content process maps to exact tabs.
+//! - Chromium started with `--remote-debugging-port` lists its open tabs over DevTools, but
+//!   DevTools doesn't say which renderer hosts which tab, so all of them are shown as candidates.
+
+use anyhow::{Context, Result};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::process::Command;
+use std::time::Duration;
+use sysinfo::System;
+
+use crate::redact;
+
+/// Marionette's port unless `marionette.port` was changed.
+const MARIONETTE_PORT: u16 = 2828;
+
+/// How long to wait for the browser's remote interface before giving up.
+const TIMEOUT: Duration = Duration::from_secs(3);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Browser {
+    Chromium,
+    Firefox,
+}
+
+impl Browser {
+    fn name(&self) -> &'static str {
+        match self {
+            Browser::Chromium => "Chromium",
+            Browser::Firefox => "Firefox",
+        }
+    }
+}
+
+/// A browser helper process and what it is known to be doing.
+#[derive(Debug, Clone)]
+pub struct BrowserContext {
+    pub browser: Browser,
+    /// Process type, e.g. "renderer", "gpu-process", or "tab"
+    pub kind: String,
+    /// The browser's main process
+    pub main_pid: u32,
+    /// Tabs as (title, URL)
+    pub tabs: Vec<(String, String)>,
+    /// Whether `tabs` are exactly the ones this process renders, rather than every open tab
+    pub exact: bool,
+    /// Why no tabs could be listed, if none were
+    pub note: Option<String>,
+}
+
+/// Identifies a Chromium or Firefox child process and, for renderers, looks up its tabs.
+pub fn browser_context(system: &System, pid: u32) -> Option<BrowserContext> {
+    let process = system.process(sysinfo::Pid::from(pid as usize))?;
+    let (browser, kind) = child_type(process.cmd())?;
+
+    // The main process is the nearest ancestor that isn't a child process itself
+    let mut main = process;
+    while child_type(main.cmd()).is_some() {
+        main = system.process(main.parent()?)?;
+    }
+
+    let mut context = BrowserContext {
+        browser,
+        kind: kind.clone(),
+        main_pid: main.pid().as_u32(),
+        tabs: Vec::new(),
+        exact: false,
+        note: None,
+    };
+    if !matches!(kind.as_str(), "renderer" | "tab") {
+        return Some(context);
+    }
+
+    let result = match browser {
+        Browser::Firefox => firefox_tabs(main.cmd(), pid).map(|tabs| (tabs, true)),
+        Browser::Chromium => chromium_tabs(main.cmd()).map(|tabs| (tabs, false)),
+    };
+    match result {
+        Ok((tabs, exact)) => {
+            context.tabs = tabs;
+            context.exact = exact;
+        }
+        Err(err) => context.note = Some(format!("{:#}", err)),
+    }
+    Some(context)
+}
+
+impl BrowserContext {
+    /// One line describing the process, e.g. "Chromium renderer (browser PID 1234)".
+    pub fn summary(&self) -> String {
+        let kind = match (self.browser, self.kind.as_str()) {
+            (Browser::Firefox, "tab") => "content process",
+            (_, kind) => kind,
+        };
+        format!("{} {} (browser PID {})", self.browser.name(), kind, self.main_pid)
+    }
+
+    pub fn json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "browser": self.browser.name(),
+            "type": self.kind,
+            "main_pid": self.main_pid,
+            "tabs": self.tabs.iter().map(|(title, url)| {
+                serde_json::json!({ "title": redact::text(title), "url": redact::text(url) })
+            }).collect::<Vec<_>>(),
+            // Otherwise the tabs are every open tab of the browser
+            "tabs_exact": self.exact,
+            "tabs_unknown": self.note,
+        })
+    }
+}
+
+/// The browser and process type of a browser child process, from its command line.
+fn child_type(cmd: &[String]) -> Option<(Browser, String)> {
+    if let Some(kind) = cmd.iter().find_map(|arg| arg.strip_prefix("--type=")) {
+        return Some((Browser::Chromium, kind.to_string()));
+    }
+    // Firefox passes the process type as the last argument
+    if cmd.iter().any(|arg| arg == "-contentproc") {
+        return Some((Browser::Firefox, cmd.last()?.clone()));
+    }
+    None
+}
+
+/// Asks Firefox over Marionette which documents content process `pid` hosts.
+fn firefox_tabs(main_cmd: &[String], pid: u32) -> Result<Vec<(String, String)>> {
+    if !main_cmd.iter().any(|arg| arg == "--marionette" || arg == "-marionette") {
+        return Err(anyhow::anyhow!("start Firefox with --marionette to see which tabs this process renders"));
+    }
+
+    let stream = TcpStream::connect_timeout(&([127, 0, 0, 1], MARIONETTE_PORT).into(), TIMEOUT)
+        .with_context(|| format!("Marionette is not listening on port {}", MARIONETTE_PORT))?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    let mut marionette = Marionette { reader: BufReader::new(stream.try_clone()?), writer: stream, next_id: 0 };
+
+    marionette.read_message()?; // Greeting
+    marionette.call("WebDriver:NewSession", serde_json::json!({}))?;
+    marionette.call("Marionette:SetContext", serde_json::json!({ "value": "chrome" }))?;
+    // The same data as about:processes
+    let script = "let resolve = arguments[arguments.length - 1];
+        ChromeUtils.requestProcInfo().then(info => resolve(info.children.map(child => ({
+            pid: child.pid,
+            windows: child.windows.map(w => [w.documentTitle || '', w.documentURI ? w.documentURI.spec : '']),
+        }))));";
+    let result = marionette.call("WebDriver:ExecuteAsyncScript", serde_json::json!({ "script": script, "args": [] }));
+    marionette.call("WebDriver:DeleteSession", serde_json::json!({})).ok();
+
+    let children = result?;
+    let tabs = children["value"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .find(|child| child["pid"].as_u64() == Some(pid as u64))
+        .and_then(|child| child["windows"].as_array())
+        .map(|windows| {
+            windows
+                .iter()
+                .filter_map(|window| Some((window[0].as_str()?.to_string(), window[1].as_str()?.to_string())))
+                // Frames without a document of their own
+                .filter(|(_, url)| !url.is_empty() && url != "about:blank")
+                .collect()
+        })
+        .unwrap_or_default();
+    Ok(tabs)
+}
+
+/// A Marionette connection: `length:json` messages, commands as `[0, id, name, params]` and
+/// responses as `[1, id, error, result]`.
+struct Marionette {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+    next_id: u64,
+}
+
+impl Marionette {
+    fn call(&mut self, command: &str, params: serde_json::Value) -> Result<serde_json::Value> {
+        self.next_id += 1;
+        let message = serde_json::json!([0, self.next_id, command, params]).to_string();
+        write!(self.writer, "{}:{}", message.len(), message)?;
+
+        let response = self.read_message()?;
+        if !response[2].is_null() {
+            return Err(anyhow::anyhow!(
+                "Marionette {} failed: {}",
+                command,
+                response[2]["message"].as_str().unwrap_or("unknown error")
+            ));
+        }
+        Ok(response[3].clone())
+    }
+
+    fn read_message(&mut self) -> Result<serde_json::Value> {
+        let mut length = Vec::new();
+        self.reader.read_until(b':', &mut length).context("Marionette closed the connection")?;
+        let length: usize = String::from_utf8_lossy(&length)
+            .trim_end_matches(':')
+            .parse()
+            .context("Unexpected data from Marionette")?;
+        let mut body = vec![0; length];
+        self.reader.read_exact(&mut body)?;
+        Ok(serde_json::from_slice(&body)?)
+    }
+}
+
+/// Lists the open tabs of a Chromium browser over its DevTools HTTP endpoint.
+fn chromium_tabs(main_cmd: &[String]) -> Result<Vec<(String, String)>> {
+    let port = debugging_port(main_cmd).ok_or_else(|| {
+        anyhow::anyhow!("start the browser with --remote-debugging-port=9222 to list its tabs")
+    })?;
+
+    let output = Command::new("curl")
+        .args(["-fsS", "--max-time", "3", &format!("http://127.0.0.1:{}/json/list", port)])
+        .output()
+        .context("Failed to run curl")?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!("DevTools on port {} did not answer", port));
+    }
+    let targets: serde_json::Value = serde_json::from_slice(&output.stdout).context("Unexpected reply from DevTools")?;
+
+    Ok(targets
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|target| target["type"] == "page")
+        .map(|target| {
+            (
+                target["title"].as_str().unwrap_or_default().to_string(),
+                target["url"].as_str().unwrap_or_default().to_string(),
+            )
+        })
+        .collect())
+}
+
+/// The DevTools port from `--remote-debugging-port`, or from the profile's DevToolsActivePort
+/// file when the browser picked one itself (`--remote-debugging-port=0`).
+fn debugging_port(cmd: &[String]) -> Option<u16> {
+    let port: u16 = cmd.iter().find_map(|arg| arg.strip_prefix("--remote-debugging-port="))?.parse().ok()?;
+    if port != 0 {
+        return Some(port);
+    }
+    let profile = cmd.iter().find_map(|arg| arg.strip_prefix("--user-data-dir="))?;
+    let content = std::fs::read_to_string(std::path::Path::new(profile).join("DevToolsActivePort")).ok()?;
+    content.lines().next()?.trim().parse().ok()
+}
@@ -0,0 +1,204 @@
+//! `bug-report`: collects what is needed to diagnose a report like "it didn't find my window"
+//! into a tarball that can be attached to an issue.
+//!
+//! The bundle holds a `report.txt` (version, system, session detection, which window backends
+//! are usable, and how each one fared, with timings) and a fixture of the process table (see
+//! `fixture`). It is anonymized: the home directory, user name, and host name are replaced,
+//! process environments are dropped, and window titles are left out.
+
+use anyhow::{Context, Result};
+use std::env;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Instant;
+use sysinfo::System;
+
+use crate::fixture;
+use crate::selector::Selection;
+use crate::session::SessionType;
+use crate::window::{self, Backend};
+
+/// Session variables worth reporting verbatim.
+const SESSION_VARS: &[&str] = &["XDG_SESSION_TYPE", "XDG_CURRENT_DESKTOP", "DESKTOP_SESSION", "DISPLAY", "WAYLAND_DISPLAY"];
+
+/// Variables that locate a compositor's socket; only whether they are set is reported.
+const SOCKET_VARS: &[&str] = &["SWAYSOCK", "I3SOCK", "HYPRLAND_INSTANCE_SIGNATURE", "TMUX"];
+
+/// External programs top-helper can use.
+const TOOLS: &[&str] = &["xdotool", "wmctrl", "tool-goto-window", "tmux", "hyprctl", "swaymsg", "notify-send"];
+
+/// Replaces identifying strings (home directory, user name, host name) in bundle contents.
+struct Anonymizer {
+    replacements: Vec<(String, String)>,
+}
+
+impl Anonymizer {
+    fn new() -> Self {
+        let mut replacements = Vec::new();
+        if let Some(home) = env::var("HOME").ok().filter(|home| home.len() > 1) {
+            replacements.push((home, "~".to_string()));
+        }
+        if let Some(user) = env::var("USER").ok().filter(|user| user.len() > 2) {
+            replacements.push((user, "user".to_string()));
+        }
+        if let Some(host) = System::host_name().filter(|host| host.len() > 2) {
+            replacements.push((host, "host".to_string()));
+        }
+        Anonymizer { replacements }
+    }
+
+    fn apply(&self, text: &str) -> String {
+        self.replacements
+            .iter()
+            .fold(text.to_string(), |text, (from, to)| text.replace(from.as_str(), to))
+    }
+}
+
+/// Writes the bundle to `output` (by default a timestamped tarball in the current directory).
+/// With `process`, also records how each window backend fares at finding that process's window.
+pub fn bug_report(output: Option<&Path>, process: Option<&str>) -> Result<()> {
+    let name = format!("top-helper-bug-report-{}", chrono::Local::now().format("%Y%m%d-%H%M%S"));
+    let output = output.map(Path::to_path_buf).unwrap_or_else(|| PathBuf::from(format!("{}.tar.gz", name)));
+    let anonymizer = Anonymizer::new();
+
+    let staging = env::temp_dir().join(format!("{}-{}", name, std::process::id()));
+    let bundle = staging.join(&name);
+    fs::create_dir_all(&bundle).with_context(|| format!("Failed to create {}", bundle.display()))?;
+    let result = write_bundle(&bundle, process, &anonymizer).and_then(|()| pack(&staging, &name, &output));
+    let _ = fs::remove_dir_all(&staging);
+    result?;
+
+    println!("Wrote {}", output.display());
+    println!("Please look through it before attaching it to an issue; it contains process command lines.");
+    Ok(())
+}
+
+fn write_bundle(bundle: &Path, process: Option<&str>, anonymizer: &Anonymizer) -> Result<()> {
+    let started = Instant::now();
+    let mut system = System::new_all();
+    system.refresh_all();
+    let mut report = String::new();
+
+    writeln!(report, "top-helper {}", env!("CARGO_PKG_VERSION"))?;
+    writeln!(report, "Generated: {}", chrono::Local::now().to_rfc3339())?;
+    writeln!(report, "Kernel: {}", System::kernel_version().unwrap_or_default())?;
+    writeln!(report, "OS: {}", System::long_os_version().unwrap_or_default())?;
+    writeln!(report, "Processes: {}", system.processes().len())?;
+
+    let session = SessionType::detect();
+    writeln!(report, "\n== Session ==")?;
+    writeln!(report, "Detected: {}", session.name())?;
+    for var in SESSION_VARS {
+        writeln!(report, "{}={}", var, env::var(var).unwrap_or_else(|_| "(unset)".to_string()))?;
+    }
+    for var in SOCKET_VARS {
+        writeln!(report, "{}: {}", var, if env::var_os(var).is_some() { "set" } else { "unset" })?;
+    }
+    let displays = window::x11_displays();
+    writeln!(report, "X displays: {}", if displays.is_empty() { "(none)".to_string() } else { displays.join(" ") })?;
+
+    writeln!(report, "\n== Tools ==")?;
+    for tool in TOOLS {
+        writeln!(report, "{}: {}", tool, if window::command_exists(tool) { "found" } else { "not found" })?;
+    }
+
+    writeln!(report, "\n== Window backends ==")?;
+    for backend in Backend::all() {
+        let supported = backend.supports_session(session, None);
+        let available = backend.is_available(session, None);
+        write!(report, "{}: session {}, ", backend.name(), if supported { "supported" } else { "unsupported" })?;
+        if !available {
+            writeln!(report, "not available")?;
+            continue;
+        }
+        let probe = Instant::now();
+        match backend.list_windows(None) {
+            Ok(windows) => {
+                let classes: Vec<&str> = windows.iter().map(|w| w.class.as_str()).collect();
+                writeln!(
+                    report,
+                    "listed {} windows in {:?} (classes: {})",
+                    windows.len(),
+                    probe.elapsed(),
+                    classes.join(", ")
+                )?;
+            }
+            Err(err) => writeln!(report, "failed after {:?}: {:#}", probe.elapsed(), err)?,
+        }
+    }
+
+    if let Some(identifier) = process {
+        writeln!(report, "\n== Window lookup for '{}' ==", identifier)?;
+        probe_process(&mut report, &system, identifier, session)?;
+    }
+
+    // Anonymized here; `fixture::save` redacts secrets on top
+    let probe = Instant::now();
+    let samples: Vec<fixture::ProcessSample> = fixture::collect(&system, false)
+        .into_iter()
+        .map(|mut sample| {
+            sample.cmd = sample.cmd.iter().map(|arg| anonymizer.apply(arg)).collect();
+            sample.cwd = sample.cwd.map(|cwd| PathBuf::from(anonymizer.apply(&cwd.to_string_lossy())));
+            sample
+        })
+        .collect();
+    fixture::save(&bundle.join("fixture"), &samples)?;
+    writeln!(report, "\n== Timings ==")?;
+    writeln!(report, "Fixture of {} processes: {:?}", samples.len(), probe.elapsed())?;
+    writeln!(report, "Total: {:?}", started.elapsed())?;
+
+    fs::write(bundle.join("report.txt"), anonymizer.apply(&report))?;
+    Ok(())
+}
+
+/// Records which process a selector resolves to and what each backend finds for it.
+fn probe_process(report: &mut String, system: &System, identifier: &str, session: SessionType) -> Result<()> {
+    let process = match Selection::parse(identifier).and_then(|selection| selection.resolve_unique(system)) {
+        Ok(process) => process,
+        Err(err) => {
+            writeln!(report, "Selector did not resolve: {:#}", err)?;
+            return Ok(());
+        }
+    };
+    let pid = process.pid().as_u32();
+    writeln!(report, "Resolved to {} (PID: {})", process.name(), pid)?;
+    writeln!(report, "Process DISPLAY: {}", window::process_display(pid).unwrap_or_else(|| "(unset)".to_string()))?;
+
+    let backends = window::available_backends(session, None);
+    if backends.is_empty() {
+        writeln!(report, "No window backend is available in this session")?;
+    }
+    for backend in backends {
+        let probe = Instant::now();
+        match backend.find_window_by_pid(pid, None) {
+            Ok(found) => writeln!(
+                report,
+                "{}: found window {} (class {}, owned by PID {}) in {:?}",
+                backend.name(),
+                found.window_id,
+                found.class,
+                found.pid,
+                probe.elapsed()
+            )?,
+            Err(err) => writeln!(report, "{}: no window after {:?}: {:#}", backend.name(), probe.elapsed(), err)?,
+        }
+    }
+    Ok(())
+}
+
+fn pack(staging: &Path, name: &str, output: &Path) -> Result<()> {
+    let status = Command::new("tar")
+        .arg("-czf")
+        .arg(output)
+        .arg("-C")
+        .arg(staging)
+        .arg(name)
+        .status()
+        .context("Failed to run tar")?;
+    if !status.success() {
+        return Err(anyhow::anyhow!("tar failed to write {}", output.display()));
+    }
+    Ok(())
+}
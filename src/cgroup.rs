@@ -0,0 +1,61 @@
+//! What a process's cgroup path says about where it runs: the systemd unit that manages it and
+//! the container it is in. Shared by `explain` and the OpenTelemetry export.
+
+use std::fs;
+
+/// The contents of /proc/<pid>/cgroup, or an empty string when it is not readable.
+pub fn read(pid: u32) -> String {
+    fs::read_to_string(format!("/proc/{}/cgroup", pid)).unwrap_or_default()
+}
+
+pub struct Unit<'a> {
+    /// Such as `nginx.service` or `app-firefox-1234.scope`
+    pub name: &'a str,
+    /// Whether it belongs to a user's service manager rather than the system's
+    pub user: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Runtime {
+    Docker,
+    Podman,
+    Kubernetes,
+}
+
+pub struct Container<'a> {
+    pub runtime: Runtime,
+    /// The full container ID; Kubernetes pods are recognized without one
+    pub id: Option<&'a str>,
+}
+
+/// The systemd unit from the cgroup path: the last `.service` component, or a scope for
+/// processes started from a session or by an app launcher.
+pub fn systemd_unit(cgroup: &str) -> Option<Unit<'_>> {
+    let path = cgroup.lines().find(|line| line.starts_with("0::")).or(cgroup.lines().next())?;
+    let components: Vec<&str> = path.rsplit(':').next()?.split('/').collect();
+    let user = path.contains("/user@");
+    if let Some(service) = components.iter().rev().find(|c| c.ends_with(".service")) {
+        return Some(Unit { name: service, user });
+    }
+    components.iter().rev().find(|c| c.ends_with(".scope")).map(|scope| Unit { name: scope, user })
+}
+
+/// The container from cgroup paths such as `/system.slice/docker-<id>.scope`, `/docker/<id>`,
+/// `/machine.slice/libpod-<id>.scope`, or `/kubepods/...`.
+pub fn container(cgroup: &str) -> Option<Container<'_>> {
+    let paths = || cgroup.lines().filter_map(|line| line.rsplit(':').next());
+    for component in paths().flat_map(|path| path.split('/')) {
+        if let Some(id) = component.strip_prefix("docker-").and_then(|rest| rest.strip_suffix(".scope")) {
+            return Some(Container { runtime: Runtime::Docker, id: Some(id) });
+        }
+        if let Some(id) = component.strip_prefix("libpod-").and_then(|rest| rest.strip_suffix(".scope")) {
+            return Some(Container { runtime: Runtime::Podman, id: Some(id) });
+        }
+        if component.starts_with("kubepods") {
+            return Some(Container { runtime: Runtime::Kubernetes, id: None });
+        }
+    }
+    paths()
+        .find_map(|path| path.strip_prefix("/docker/"))
+        .map(|id| Container { runtime: Runtime::Docker, id: Some(id) })
+}
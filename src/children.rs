@@ -0,0 +1,260 @@
+use anyhow::Result;
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+use sysinfo::{System, ThreadKind};
+
+use crate::control::{self, Signal};
+use crate::error::CliError;
+use crate::output::{Diagram, DiagramFormat, format_memory};
+use crate::selector::Selection;
+
+/// How long each level of the tree gets to exit after SIGTERM before moving up to its parents.
+const TERMINATE_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// A process and all of its descendants.
+pub struct ProcessTree {
+    pub pid: u32,
+    pub name: String,
+    pub memory_bytes: u64,
+    pub cpu_percent: f32,
+    pub children: Vec<ProcessTree>,
+}
+
+impl ProcessTree {
+    pub fn build(system: &System, pid: u32) -> Option<ProcessTree> {
+        let mut children_of: HashMap<u32, Vec<u32>> = HashMap::new();
+        for process in system.processes().values() {
+            if process.thread_kind() == Some(ThreadKind::Userland) {
+                continue;
+            }
+            if let Some(parent) = process.parent() {
+                children_of.entry(parent.as_u32()).or_default().push(process.pid().as_u32());
+            }
+        }
+
+        build_node(system, &children_of, pid)
+    }
+
+    /// Number of processes in this subtree, including the root.
+    pub fn count(&self) -> usize {
+        1 + self.children.iter().map(ProcessTree::count).sum::<usize>()
+    }
+
+    pub fn total_memory(&self) -> u64 {
+        self.memory_bytes + self.children.iter().map(ProcessTree::total_memory).sum::<u64>()
+    }
+
+    pub fn total_cpu(&self) -> f32 {
+        self.cpu_percent + self.children.iter().map(ProcessTree::total_cpu).sum::<f32>()
+    }
+
+    /// The tree without processes outside `pids`, each dropped with its subtree.
+    pub fn retain(mut self, pids: &HashSet<u32>) -> ProcessTree {
+        self.children = self
+            .children
+            .into_iter()
+            .filter(|child| pids.contains(&child.pid))
+            .map(|child| child.retain(pids))
+            .collect();
+        self
+    }
+
+    pub fn to_diagram(&self) -> Diagram {
+        let mut diagram = Diagram::default();
+        self.add_to_diagram(&mut diagram);
+        diagram
+    }
+
+    fn add_to_diagram(&self, diagram: &mut Diagram) {
+        let mut label = vec![format!("{} ({})", self.name, self.pid), format_memory(self.memory_bytes)];
+        if !self.children.is_empty() {
+            label.push(format!("subtree {}", format_memory(self.total_memory())));
+        }
+        diagram.node(format!("p{}", self.pid), label);
+
+        for child in &self.children {
+            diagram.edge(format!("p{}", self.pid), format!("p{}", child.pid), Vec::new());
+            child.add_to_diagram(diagram);
+        }
+    }
+
+    /// PIDs grouped by depth, root first.
+    fn levels(&self) -> Vec<Vec<u32>> {
+        let mut levels: Vec<Vec<u32>> = Vec::new();
+        let mut current = vec![self];
+        while !current.is_empty() {
+            levels.push(current.iter().map(|node| node.pid).collect());
+            current = current.iter().flat_map(|node| &node.children).collect();
+        }
+        levels
+    }
+}
+
+fn build_node(system: &System, children_of: &HashMap<u32, Vec<u32>>, pid: u32) -> Option<ProcessTree> {
+    let process = system.process(sysinfo::Pid::from(pid as usize))?;
+
+    let mut children: Vec<ProcessTree> = children_of
+        .get(&pid)
+        .map(|pids| pids.iter().filter_map(|child| build_node(system, children_of, *child)).collect())
+        .unwrap_or_default();
+    children.sort_by_key(|child| child.pid);
+
+    Some(ProcessTree {
+        pid,
+        name: process.name().to_string(),
+        memory_bytes: process.memory(),
+        cpu_percent: process.cpu_usage(),
+        children,
+    })
+}
+
+/// Prints the descendant tree of a process with per-process and subtree totals (as text or a
+/// diagram), or with `kill_tree` terminates the whole subtree, deepest processes first. With
+/// `all`, prints the tree of every unrelated matching process instead of choosing one.
+pub async fn show_children(
+    identifier: &str,
+    diagram: Option<DiagramFormat>,
+    kill_tree: bool,
+    yes: bool,
+    all: bool,
+) -> Result<()> {
+    let mut system = System::new_all();
+    system.refresh_all();
+    // A second sample is needed for CPU usage
+    tokio::time::sleep(sysinfo::MINIMUM_CPU_UPDATE_INTERVAL).await;
+    system.refresh_processes();
+
+    let selection = Selection::parse(identifier)?;
+    if all {
+        let roots = selection.resolve_roots(&system);
+        if roots.is_empty() {
+            return Err(CliError::not_found("Process not found"));
+        }
+        for (i, root) in roots.iter().enumerate() {
+            let Some(tree) = ProcessTree::build(&system, root.pid().as_u32()) else {
+                continue;
+            };
+            if i > 0 {
+                println!();
+            }
+            print_tree(&tree);
+        }
+        return Ok(());
+    }
+
+    let process = selection.resolve_unique(&system)?;
+    let pid = process.pid().as_u32();
+    let tree = ProcessTree::build(&system, pid)
+        .ok_or_else(|| anyhow::anyhow!("Process {} exited while building its tree", pid))?;
+
+    if kill_tree {
+        return terminate_tree(&system, &tree, yes);
+    }
+
+    if let Some(format) = diagram {
+        print!("{}", tree.to_diagram().render(format, "process_tree", false));
+        return Ok(());
+    }
+
+    print_tree(&tree);
+    Ok(())
+}
+
+fn print_tree(tree: &ProcessTree) {
+    print_node(tree, "", "");
+    println!(
+        "\nTotal: {} processes, {}, {:.1}% CPU",
+        tree.count(),
+        format_memory(tree.total_memory()),
+        tree.total_cpu()
+    );
+}
+
+pub fn print_node(node: &ProcessTree, prefix: &str, child_prefix: &str) {
+    let mut line = format!(
+        "{}{} (PID: {})  {}  {:.1}% CPU",
+        prefix,
+        node.name,
+        node.pid,
+        format_memory(node.memory_bytes),
+        node.cpu_percent
+    );
+    if !node.children.is_empty() {
+        line.push_str(&format!(
+            "  [subtree: {} processes, {}, {:.1}% CPU]",
+            node.count(),
+            format_memory(node.total_memory()),
+            node.total_cpu()
+        ));
+    }
+    println!("{}", line);
+
+    for (i, child) in node.children.iter().enumerate() {
+        let last = i == node.children.len() - 1;
+        let (branch, continuation) = if last { ("└── ", "    ") } else { ("├── ", "│   ") };
+        print_node(
+            child,
+            &format!("{}{}", child_prefix, branch),
+            &format!("{}{}", child_prefix, continuation),
+        );
+    }
+}
+
+/// Sends SIGTERM level by level from the leaves up, giving each level time to exit so parents
+/// don't respawn or get orphaned children.
+fn terminate_tree(system: &System, tree: &ProcessTree, yes: bool) -> Result<()> {
+    let protected = control::own_ancestry(system);
+    let mut levels = tree.levels();
+    if levels.iter().flatten().any(|pid| protected.contains(pid)) {
+        return Err(anyhow::anyhow!("Refusing to terminate a tree containing top-helper or the processes running it"));
+    }
+
+    let pids: Vec<u32> = levels.iter().rev().flatten().copied().collect();
+    if pids.len() > 1 && !yes {
+        control::confirm(system, &pids, Signal::Terminate)?;
+    }
+
+    levels.reverse();
+    let mut survivors = Vec::new();
+    for level in levels {
+        for pid in &level {
+            // Already gone is fine: it may have exited along with its children
+            if control::send_signal(*pid, Signal::Terminate).is_ok() {
+                println!("{} {} (PID: {})", Signal::Terminate.verb(), process_name(system, *pid), pid);
+            }
+        }
+
+        let deadline = Instant::now() + TERMINATE_TIMEOUT;
+        while level.iter().any(|pid| is_running(*pid)) && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(50));
+        }
+        survivors.extend(level.into_iter().filter(|pid| is_running(*pid)));
+    }
+
+    if !survivors.is_empty() {
+        let list: Vec<String> = survivors
+            .iter()
+            .map(|pid| format!("{} ({})", pid, process_name(system, *pid)))
+            .collect();
+        return Err(anyhow::anyhow!(
+            "Still running after SIGTERM: {}; use `kill -9` if they must be stopped",
+            list.join(", ")
+        ));
+    }
+
+    Ok(())
+}
+
+fn process_name(system: &System, pid: u32) -> String {
+    system
+        .process(sysinfo::Pid::from(pid as usize))
+        .map(|p| p.name().to_string())
+        .unwrap_or_default()
+}
+
+/// Whether a process still exists and is not a zombie waiting to be reaped.
+fn is_running(pid: u32) -> bool {
+    procfs::process::Process::new(pid as i32)
+        .and_then(|process| process.stat())
+        .is_ok_and(|stat| stat.state != 'Z')
+}
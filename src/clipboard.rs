@@ -0,0 +1,125 @@
+//! Finds which process owns the clipboard and primary selection.
+//!
+//! On X11 (and XWayland) the selection owner is a window, which the X-Resource extension
+//! maps back to its client PID. Wayland deliberately hides the source client of a
+//! selection, so there the best we can do is point at the clipboard tools that are running.
+
+use anyhow::{Context, Result};
+use std::env;
+use sysinfo::System;
+use x11rb::protocol::xproto::{ConnectionExt as _, Window};
+
+use crate::error::CliError;
+use crate::session::SessionType;
+use crate::window;
+
+const SELECTIONS: &[&str] = &["CLIPBOARD", "PRIMARY"];
+
+/// Clipboard managers and helpers that hold on to selections after their source exits.
+const CLIPBOARD_TOOLS: &[&str] = &[
+    "wl-copy",
+    "wl-clip-persist",
+    "cliphist",
+    "clipman",
+    "copyq",
+    "greenclip",
+    "parcellite",
+    "clipit",
+    "diodon",
+    "gpaste-daemon",
+    "xfce4-clipman",
+    "klipper",
+    "xclip",
+    "xsel",
+];
+
+struct SelectionOwner {
+    selection: &'static str,
+    window: Option<Window>,
+    pid: Option<u32>,
+}
+
+pub fn show_clipboard_owner(display: Option<&str>) -> Result<()> {
+    let session = SessionType::detect();
+    let display = display.map(str::to_string).or_else(|| env::var("DISPLAY").ok());
+
+    if display.is_none() && session != SessionType::Wayland {
+        return Err(CliError::backend_missing(format!(
+            "No X display or Wayland session to inspect the clipboard in ({} session)",
+            session.name()
+        )));
+    }
+
+    let mut system = System::new_all();
+    system.refresh_all();
+    let process_name = |pid: u32| {
+        system
+            .process(sysinfo::Pid::from(pid as usize))
+            .map(|p| p.name().to_string())
+            .unwrap_or_else(|| "unknown".to_string())
+    };
+
+    if let Some(display) = &display {
+        let owners = x11_selection_owners(display)?;
+        for owner in &owners {
+            let description = match (owner.window, owner.pid) {
+                (None, _) => "none".to_string(),
+                (Some(window), Some(pid)) => {
+                    let mut line = format!("{} (PID: {}) [window 0x{:x}]", process_name(pid), pid, window);
+                    // Under XWayland, selections owned by native Wayland clients are proxied by Xwayland itself
+                    if session == SessionType::Wayland && process_name(pid) == "Xwayland" {
+                        line.push_str(" - held on behalf of a Wayland client");
+                    }
+                    line
+                }
+                (Some(window), None) => format!("unknown process [window 0x{:x}]", window),
+            };
+            println!("{:<10} {}", format!("{}:", owner.selection), description);
+        }
+    }
+
+    if session == SessionType::Wayland {
+        let tools: Vec<String> = system
+            .processes()
+            .values()
+            .filter(|p| CLIPBOARD_TOOLS.contains(&p.name()))
+            .map(|p| format!("{} (PID: {})", p.name(), p.pid()))
+            .collect();
+
+        println!("\nWayland does not reveal which client owns a selection.");
+        if tools.is_empty() {
+            println!("No clipboard managers or wl-copy processes are running; the owner is the application you copied from.");
+        } else {
+            println!("Clipboard tools that may be holding it:");
+            for tool in tools {
+                println!("  {}", tool);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn x11_selection_owners(display: &str) -> Result<Vec<SelectionOwner>> {
+    let (conn, _) = x11rb::connect(Some(display))
+        .with_context(|| format!("Failed to connect to X display {}", display))?;
+
+    let mut owners = Vec::new();
+    for selection in SELECTIONS {
+        let atom = conn.intern_atom(false, selection.as_bytes())?.reply()?.atom;
+        let owner = conn.get_selection_owner(atom)?.reply()?.owner;
+
+        if owner == x11rb::NONE {
+            owners.push(SelectionOwner { selection, window: None, pid: None });
+            continue;
+        }
+
+        // Selection owner windows are usually unmapped helpers without _NET_WM_PID,
+        // so ask the server for the owning client's PID first
+        let pid = window::x11_client_pid(&conn, owner)
+            .or_else(|| window::get_window_pid(&owner.to_string(), Some(display)).ok());
+        owners.push(SelectionOwner { selection, window: Some(owner), pid });
+    }
+
+    Ok(owners)
+}
@@ -0,0 +1,272 @@
+use anyhow::{Context, Result};
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+use sysinfo::System;
+use tabled::{Table, Tabled, settings::{Width, location::ByColumnName}};
+
+use crate::gpu;
+use crate::window;
+
+/// Process names of display servers and compositors whose load is caused by their clients.
+const COMPOSITORS: &[&str] = &[
+    "Xorg",
+    "Xwayland",
+    "gnome-shell",
+    "kwin_wayland",
+    "kwin_x11",
+    "mutter",
+    "sway",
+    "Hyprland",
+    "weston",
+    "picom",
+    "compiz",
+    "marco",
+    "xfwm4",
+];
+
+/// How often the damage sampler drains the X server's events.
+const DAMAGE_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+#[derive(Tabled)]
+struct ClientActivity {
+    #[tabled(rename = "PID")]
+    pid: u32,
+
+    #[tabled(rename = "Name")]
+    name: String,
+
+    #[tabled(rename = "CPU %")]
+    cpu_percent: String,
+
+    #[tabled(rename = "Redrawn (Mpx)")]
+    redrawn: String,
+
+    #[tabled(rename = "GPU busy (ms)")]
+    gpu_busy_ms: String,
+
+    #[tabled(rename = "Share %")]
+    share: String,
+
+    #[tabled(rename = "Window")]
+    window: String,
+}
+
+/// What the X server saw a client redraw in its windows during the sample.
+#[derive(Debug, Clone, Copy, Default)]
+struct Damage {
+    /// Damage events, about one per redrawn rectangle
+    updates: u64,
+    pixels: u64,
+}
+
+struct Client {
+    pid: u32,
+    cpu: f32,
+    gpu_ms: Option<f64>,
+    damage: Option<Damage>,
+}
+
+/// Samples compositor and client activity over an interval and ranks the clients most
+/// likely responsible for compositor load. On X11 (and for Xwayland clients) the ranking uses
+/// what each client redrew, from the DAMAGE extension, which is what a compositor has to
+/// recomposite. Wayland compositors expose no per-client damage or frame counts, so native
+/// Wayland clients are ranked by their own GPU busy time, or their CPU time without GPU
+/// statistics.
+pub async fn show_compositor_attribution(interval_secs: u64) -> Result<()> {
+    let mut system = System::new_all();
+    system.refresh_all();
+
+    let compositors: Vec<u32> = system
+        .processes()
+        .values()
+        .filter(|p| COMPOSITORS.contains(&p.name()))
+        .map(|p| p.pid().as_u32())
+        .collect();
+
+    if compositors.is_empty() {
+        println!("No known compositor or display server is running");
+        return Ok(());
+    }
+
+    // Window titles by PID when a window backend is available; otherwise consider every GPU client
+    let mut titles: HashMap<u32, String> = HashMap::new();
+    for w in window::list_all_windows(None).unwrap_or_default() {
+        titles.entry(w.pid).or_insert(format!("{} - {}", w.class, w.title));
+    }
+
+    let compositor_set: HashSet<u32> = compositors.iter().copied().collect();
+    let candidates: Vec<u32> = system
+        .processes()
+        .keys()
+        .map(|pid| pid.as_u32())
+        .filter(|pid| !compositor_set.contains(pid))
+        .filter(|pid| titles.contains_key(pid) || gpu::gpu_busy_ns(*pid).is_some())
+        .collect();
+
+    let gpu_before: HashMap<u32, u64> = compositors
+        .iter()
+        .chain(&candidates)
+        .filter_map(|pid| gpu::gpu_busy_ns(*pid).map(|ns| (*pid, ns)))
+        .collect();
+
+    println!("Sampling compositor activity for {}s...", interval_secs);
+    let interval = Duration::from_secs(interval_secs);
+    let damage = tokio::task::spawn_blocking(move || sample_x11_damage(interval));
+    tokio::time::sleep(interval).await;
+    system.refresh_processes();
+    let damage = match damage.await? {
+        Ok(damage) => damage,
+        Err(err) => {
+            // Without DISPLAY there are no X11 clients to watch, so only a failure on X is worth a warning
+            if std::env::var_os("DISPLAY").is_some() {
+                eprintln!("Warning: Could not watch X11 damage, ranking by GPU and CPU time: {:#}", err);
+            }
+            HashMap::new()
+        }
+    };
+
+    let gpu_delta_ms = |pid: u32| -> Option<f64> {
+        let before = gpu_before.get(&pid)?;
+        let after = gpu::gpu_busy_ns(pid)?;
+        Some(after.saturating_sub(*before) as f64 / 1_000_000.0)
+    };
+
+    println!();
+    for pid in &compositors {
+        if let Some(process) = system.process(sysinfo::Pid::from(*pid as usize)) {
+            let gpu = gpu_delta_ms(*pid)
+                .map(|ms| format!(", GPU busy {:.0} ms", ms))
+                .unwrap_or_default();
+            println!("{} (PID: {}): CPU {:.1}%{}", process.name(), pid, process.cpu_usage(), gpu);
+        }
+    }
+
+    // Rank clients by what they redrew when X11 damage is available, then by GPU activity, then CPU
+    let mut pids: Vec<u32> = candidates.clone();
+    pids.extend(damage.keys().filter(|pid| !compositor_set.contains(pid) && !candidates.contains(pid)));
+    let mut clients: Vec<Client> = pids
+        .iter()
+        .filter_map(|pid| {
+            let process = system.process(sysinfo::Pid::from(*pid as usize))?;
+            Some(Client {
+                pid: *pid,
+                cpu: process.cpu_usage(),
+                gpu_ms: gpu_delta_ms(*pid),
+                damage: damage.get(pid).copied(),
+            })
+        })
+        .filter(|client| {
+            client.cpu > 0.0
+                || client.gpu_ms.is_some_and(|ms| ms > 0.0)
+                || client.damage.is_some_and(|damage| damage.pixels > 0)
+        })
+        .collect();
+
+    let total_pixels: u64 = clients.iter().filter_map(|client| client.damage).map(|damage| damage.pixels).sum();
+    let total_gpu: f64 = clients.iter().filter_map(|client| client.gpu_ms).sum();
+    let total_cpu: f32 = clients.iter().map(|client| client.cpu).sum();
+    let basis = if total_pixels > 0 {
+        "area redrawn"
+    } else if total_gpu > 0.0 {
+        "GPU busy time"
+    } else {
+        "CPU time"
+    };
+    let score = |client: &Client| -> f64 {
+        if total_pixels > 0 {
+            client.damage.map_or(0.0, |damage| damage.pixels as f64 / total_pixels as f64)
+        } else if total_gpu > 0.0 {
+            client.gpu_ms.unwrap_or(0.0) / total_gpu
+        } else if total_cpu > 0.0 {
+            (client.cpu / total_cpu) as f64
+        } else {
+            0.0
+        }
+    };
+    clients.sort_by(|a, b| score(b).total_cmp(&score(a)));
+
+    if clients.is_empty() {
+        println!("\nNo client activity observed during the sample");
+        return Ok(());
+    }
+
+    let rows: Vec<ClientActivity> = clients
+        .iter()
+        .take(15)
+        .map(|client| ClientActivity {
+            pid: client.pid,
+            name: system
+                .process(sysinfo::Pid::from(client.pid as usize))
+                .map(|p| p.name().to_string())
+                .unwrap_or_default(),
+            cpu_percent: format!("{:.1}", client.cpu),
+            redrawn: client
+                .damage
+                .map(|damage| format!("{:.1} ({} updates)", damage.pixels as f64 / 1e6, damage.updates))
+                .unwrap_or_else(|| "-".to_string()),
+            gpu_busy_ms: client.gpu_ms.map(|ms| format!("{:.0}", ms)).unwrap_or_else(|| "-".to_string()),
+            share: format!("{:.0}", score(client) * 100.0),
+            window: titles.get(&client.pid).cloned().unwrap_or_else(|| "-".to_string()),
+        })
+        .collect();
+
+    println!("\nMost active clients by {} (likely causes of compositor load):", basis);
+    let mut table = Table::new(rows);
+    table.modify(ByColumnName::new("Window"), Width::truncate(50).suffix("..."));
+    println!("{}", table);
+
+    Ok(())
+}
+
+/// Watches every client window in the root window's `_NET_CLIENT_LIST` for `duration` with the
+/// X DAMAGE extension, and sums what each window's client redrew, by PID. Windows mapped during
+/// the sample are not watched.
+fn sample_x11_damage(duration: Duration) -> Result<HashMap<u32, Damage>> {
+    use x11rb::connection::{Connection, RequestConnection as _};
+    use x11rb::protocol::Event;
+    use x11rb::protocol::damage::{self as xdamage, ConnectionExt as _, ReportLevel};
+    use x11rb::protocol::xproto::{AtomEnum, ConnectionExt as _};
+
+    let deadline = Instant::now() + duration;
+    let (conn, screen_num) = x11rb::connect(None).context("Failed to connect to the X server")?;
+    if conn.extension_information(xdamage::X11_EXTENSION_NAME)?.is_none() {
+        return Err(anyhow::anyhow!("The X server has no DAMAGE extension"));
+    }
+    conn.damage_query_version(1, 1)?.reply()?;
+
+    let root = conn.setup().roots[screen_num].root;
+    let client_list = conn.intern_atom(false, b"_NET_CLIENT_LIST")?.reply()?.atom;
+    let windows: Vec<u32> = conn
+        .get_property(false, root, client_list, AtomEnum::WINDOW, 0, 4096)?
+        .reply()?
+        .value32()
+        .map(|values| values.collect())
+        .unwrap_or_default();
+
+    // Damage objects go away with the connection, so they need no cleanup
+    let mut owners: HashMap<u32, u32> = HashMap::new();
+    for window in windows {
+        let Some(pid) = window::x11_client_pid(&conn, window) else {
+            continue;
+        };
+        let damage = conn.generate_id()?;
+        conn.damage_create(damage, window, ReportLevel::RAW_RECTANGLES)?;
+        owners.insert(damage, pid);
+    }
+    conn.flush()?;
+
+    let mut redrawn: HashMap<u32, Damage> = HashMap::new();
+    while Instant::now() < deadline {
+        while let Some(event) = conn.poll_for_event()? {
+            if let Event::DamageNotify(notify) = event
+                && let Some(pid) = owners.get(&notify.damage)
+            {
+                let damage = redrawn.entry(*pid).or_default();
+                damage.updates += 1;
+                damage.pixels += notify.area.width as u64 * notify.area.height as u64;
+            }
+        }
+        std::thread::sleep(DAMAGE_POLL_INTERVAL);
+    }
+    Ok(redrawn)
+}
@@ -0,0 +1,168 @@
+//! User configuration from `~/.config/top-helper/config.toml`.
+//!
+//! `[profile.NAME]` sections hold per-machine defaults, so one config file can be shared across
+//! machines through dotfiles. The profile is the one `--profile` names, or else the first (by name)
+//! whose `hostnames` match this machine's hostname.
+
+use anyhow::{Context, Result};
+use regex::RegexBuilder;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use sysinfo::System;
+
+use crate::alerts::AlertRule;
+use crate::devtools::DevtoolRule;
+use crate::error::CliError;
+use crate::fleet::HostConfig;
+use crate::hooks::HookRule;
+use crate::output::MemoryUnits;
+use crate::paths;
+use crate::plugins::{self, PluginColumn};
+use crate::policy::PolicyRule;
+use crate::process::ListColumn;
+use crate::redact::RedactConfig;
+use crate::report::ReportConfig;
+use crate::sink::SinkConfig;
+use crate::window::Backend;
+
+/// The profile named with `--profile`, which overrides selection by hostname.
+static REQUESTED_PROFILE: OnceLock<String> = OnceLock::new();
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Named output destinations shared by reports and alerts
+    pub sinks: BTreeMap<String, SinkConfig>,
+    /// What to hide in JSON, porcelain, and exported output
+    pub redact: RedactConfig,
+    /// Threshold alerts evaluated by `monitor`
+    #[serde(rename = "alert")]
+    pub alerts: Vec<AlertRule>,
+    /// Extra process classification rules for `devtools`, tried before the built-in ones
+    #[serde(rename = "devtool")]
+    pub devtools: Vec<DevtoolRule>,
+    /// Commands `monitor` runs on process events
+    #[serde(rename = "hook")]
+    pub hooks: Vec<HookRule>,
+    /// Actions `enforce` takes on processes that break a rule
+    #[serde(rename = "policy")]
+    pub policies: Vec<PolicyRule>,
+    /// Where the daemon sends the daily report
+    pub report: ReportConfig,
+    /// Machines polled by `fleet`
+    #[serde(rename = "host")]
+    pub hosts: Vec<HostConfig>,
+    /// Custom `list` columns: the column name and the command that fills it
+    #[serde(rename = "column")]
+    pub columns: BTreeMap<String, String>,
+    /// Per-machine defaults by profile name
+    #[serde(rename = "profile")]
+    pub profiles: BTreeMap<String, Profile>,
+    /// The name of the profile in effect, if any
+    #[serde(skip)]
+    pub active_profile: Option<String>,
+}
+
+/// Defaults for one machine or context. Options given on the command line still win.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Profile {
+    /// Hostnames that select this profile when `--profile` isn't given; `*` matches any run of
+    /// characters, as in "build-*"
+    pub hostnames: Vec<String>,
+    /// Window backend to try first, when it's available
+    pub window_backend: Option<Backend>,
+    /// Default for `--units`
+    pub units: Option<MemoryUnits>,
+    /// Optional `list` columns to show by default
+    pub columns: Vec<ListColumn>,
+    /// What `list --high-memory` counts as high, in MB
+    pub high_memory_mb: Option<u64>,
+    /// Alert rules for `monitor` in addition to the top-level ones
+    #[serde(rename = "alert")]
+    pub alerts: Vec<AlertRule>,
+}
+
+impl Profile {
+    fn matches_host(&self, hostname: &str) -> bool {
+        self.hostnames.iter().any(|glob| {
+            let pattern = format!("^{}$", regex::escape(glob).replace(r"\*", ".*"));
+            RegexBuilder::new(&pattern).case_insensitive(true).build().is_ok_and(|regex| regex.is_match(hostname))
+        })
+    }
+}
+
+/// Picks the profile by name instead of by hostname.
+pub fn request_profile(name: &str) {
+    REQUESTED_PROFILE.set(name.to_string()).ok();
+}
+
+impl Config {
+    pub fn path() -> Option<PathBuf> {
+        paths::config_home().map(|dir| dir.join("top-helper").join("config.toml"))
+    }
+
+    /// Loads the config file, or the defaults if there is none, with the active profile's
+    /// alert rules added to the top-level ones.
+    pub fn load() -> Result<Self> {
+        let mut config = Config::load_file()?;
+        config.active_profile = config.select_profile()?;
+        if let Some(profile) = config.profile() {
+            let alerts = profile.alerts.clone();
+            config.alerts.extend(alerts);
+        }
+        Ok(config)
+    }
+
+    fn load_file() -> Result<Self> {
+        let Some(path) = Config::path() else {
+            return Ok(Config::default());
+        };
+
+        match fs::read_to_string(&path) {
+            Ok(content) => toml::from_str(&content).with_context(|| format!("Invalid config file {}", path.display())),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(err) => Err(anyhow::Error::new(err).context(format!("Failed to read {}", path.display()))),
+        }
+    }
+
+    fn select_profile(&self) -> Result<Option<String>> {
+        if let Some(name) = REQUESTED_PROFILE.get() {
+            if !self.profiles.contains_key(name) {
+                let known: Vec<&str> = self.profiles.keys().map(String::as_str).collect();
+                return Err(CliError::not_found(if known.is_empty() {
+                    format!("No profile named '{}'; the config file defines none", name)
+                } else {
+                    format!("No profile named '{}' (known: {})", name, known.join(", "))
+                }));
+            }
+            return Ok(Some(name.clone()));
+        }
+
+        let Some(hostname) = System::host_name() else {
+            return Ok(None);
+        };
+        Ok(self.profiles.iter().find(|(_, profile)| profile.matches_host(&hostname)).map(|(name, _)| name.clone()))
+    }
+
+    /// The custom `list` columns, in name order. A command that still uses a `{pid}`-style
+    /// placeholder is an error, since those are no longer filled in.
+    pub fn plugin_columns(&self) -> Result<Vec<PluginColumn>> {
+        self.columns
+            .iter()
+            .map(|(name, command)| {
+                plugins::reject_placeholders(command, &plugins::KEYS)
+                    .with_context(|| format!("Invalid column '{}'", name))?;
+                Ok(PluginColumn { name: name.clone(), command: command.clone() })
+            })
+            .collect()
+    }
+
+    /// The settings of the profile in effect, if any.
+    pub fn profile(&self) -> Option<&Profile> {
+        self.profiles.get(self.active_profile.as_deref()?)
+    }
+}
@@ -0,0 +1,179 @@
+use anyhow::Result;
+use std::io::{self, BufRead, Write};
+use sysinfo::{System, ThreadKind};
+
+use crate::error::CliError;
+use crate::output;
+use crate::selector::Selection;
+
+#[derive(Debug, Clone, Copy)]
+pub enum Signal {
+    Stop,
+    Continue,
+    Terminate,
+    Kill,
+    Hangup,
+}
+
+impl Signal {
+    fn number(&self) -> libc::c_int {
+        match self {
+            Signal::Stop => libc::SIGSTOP,
+            Signal::Continue => libc::SIGCONT,
+            Signal::Terminate => libc::SIGTERM,
+            Signal::Kill => libc::SIGKILL,
+            Signal::Hangup => libc::SIGHUP,
+        }
+    }
+
+    pub fn verb(&self) -> &'static str {
+        match self {
+            Signal::Stop => "Paused",
+            Signal::Continue => "Resumed",
+            Signal::Terminate => "Terminated",
+            Signal::Kill => "Killed",
+            Signal::Hangup => "Closed",
+        }
+    }
+}
+
+/// Sends SIGSTOP or SIGCONT to the selected processes, and with `tree` to all their descendants.
+/// Matching more than one process asks for confirmation unless `yes` is given.
+pub fn signal_processes(identifier: &str, signal: Signal, tree: bool, yes: bool) -> Result<()> {
+    let mut system = System::new_all();
+    system.refresh_all();
+
+    let matched = Selection::parse(identifier)?.resolve(&system);
+    if matched.is_empty() {
+        return Err(CliError::not_found(format!("Process not found: {}", identifier)));
+    }
+
+    signal_matched(&system, matched, signal, tree, yes)
+}
+
+/// Signals already-selected processes, with the same protections and confirmation as
+/// `signal_processes`.
+pub fn signal_matched(system: &System, matched: Vec<u32>, signal: Signal, tree: bool, yes: bool) -> Result<()> {
+    let protected = own_ancestry(system);
+
+    // Stopping any thread stops its whole process, so signal processes only
+    let mut pids: Vec<u32> = matched
+        .into_iter()
+        .filter(|pid| !protected.contains(pid) && !is_thread(system, *pid))
+        .collect();
+    if pids.is_empty() {
+        return Err(anyhow::anyhow!("Refusing to signal top-helper itself or the processes running it"));
+    }
+
+    if tree {
+        for pid in pids.clone() {
+            for child in descendants(system, pid) {
+                if !pids.contains(&child) && !protected.contains(&child) {
+                    pids.push(child);
+                }
+            }
+        }
+    }
+
+    if pids.len() > 1 && !yes {
+        confirm(system, &pids, signal)?;
+    }
+
+    for pid in &pids {
+        send_signal(*pid, signal)?;
+        let name = system
+            .process(sysinfo::Pid::from(*pid as usize))
+            .map(|p| p.name().to_string())
+            .unwrap_or_default();
+        println!("{} {} (PID: {})", signal.verb(), name, pid);
+    }
+
+    Ok(())
+}
+
+pub fn confirm(system: &System, pids: &[u32], signal: Signal) -> Result<()> {
+    let action = match signal {
+        Signal::Stop => "pause",
+        Signal::Continue => "resume",
+        Signal::Terminate => "terminate",
+        Signal::Kill => "kill",
+        Signal::Hangup => "close",
+    };
+
+    if !output::can_prompt() {
+        return Err(CliError::multiple_matches(format!(
+            "{} processes match; pass --yes to {} all of them",
+            pids.len(),
+            action
+        )));
+    }
+
+    println!("This will {} {} processes:", action, pids.len());
+    for pid in pids {
+        if let Some(process) = system.process(sysinfo::Pid::from(*pid as usize)) {
+            println!("  {} ({})", pid, process.name());
+        }
+    }
+    print!("Continue? [y/N] ");
+    io::stdout().flush()?;
+
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    if matches!(answer.trim(), "y" | "Y" | "yes") {
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!("Aborted"))
+    }
+}
+
+pub fn send_signal(pid: u32, signal: Signal) -> Result<()> {
+    // SAFETY: kill has no memory-safety preconditions
+    if unsafe { libc::kill(pid as libc::pid_t, signal.number()) } == 0 {
+        return Ok(());
+    }
+
+    let err = io::Error::last_os_error();
+    match err.raw_os_error() {
+        Some(libc::ESRCH) => Err(CliError::not_found(format!("Process {} has exited", pid))),
+        _ => Err(anyhow::Error::new(err).context(format!("Failed to signal process {}", pid))),
+    }
+}
+
+/// All descendants of a process, parents before their children.
+pub fn descendants(system: &System, pid: u32) -> Vec<u32> {
+    let mut result = Vec::new();
+    let mut queue = vec![pid];
+
+    while let Some(parent) = queue.pop() {
+        for process in system.processes().values() {
+            if process.parent().is_some_and(|p| p.as_u32() == parent)
+                && process.thread_kind() != Some(ThreadKind::Userland)
+            {
+                let child = process.pid().as_u32();
+                result.push(child);
+                queue.push(child);
+            }
+        }
+    }
+
+    result
+}
+
+pub fn is_thread(system: &System, pid: u32) -> bool {
+    system
+        .process(sysinfo::Pid::from(pid as usize))
+        .is_some_and(|p| p.thread_kind() == Some(ThreadKind::Userland))
+}
+
+/// This process and its ancestors (the shell or terminal running us), which must never be stopped.
+pub fn own_ancestry(system: &System) -> Vec<u32> {
+    let mut pids = Vec::new();
+    let mut current = Some(sysinfo::Pid::from_u32(std::process::id()));
+
+    while let Some(pid) = current {
+        pids.push(pid.as_u32());
+        current = system.process(pid).and_then(|p| p.parent());
+    }
+
+    pids
+}
@@ -0,0 +1,217 @@
+use anyhow::Result;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+use sysinfo::System;
+use tabled::{Table, Tabled};
+
+use crate::output;
+use crate::pressure;
+use crate::smoothing::Smoother;
+
+#[derive(Tabled)]
+struct CoreRow {
+    #[tabled(rename = "CPU")]
+    cpu: String,
+
+    #[tabled(rename = "Core")]
+    core: String,
+
+    #[tabled(rename = "Usage")]
+    usage: String,
+
+    #[tabled(rename = "Frequency")]
+    frequency: String,
+
+    #[tabled(rename = "Temp")]
+    temperature: String,
+}
+
+/// What /proc/cpuinfo says about one logical CPU.
+#[derive(Default)]
+struct CpuInfo {
+    package: Option<u32>,
+    core: Option<u32>,
+    mhz: Option<f64>,
+}
+
+/// Shows per-core utilization over `interval`, with frequencies and temperatures, once or
+/// (with `watch`) redrawn until interrupted, with usage smoothed across refreshes by `smoothing`.
+pub async fn show_cpu(interval: Duration, watch: bool, smoothing: f64, show_pressure: bool) -> Result<()> {
+    let mut system = System::new();
+    system.refresh_cpu();
+    let interval = interval.max(sysinfo::MINIMUM_CPU_UPDATE_INTERVAL);
+    // Keyed by CPU index, with None for the overall usage
+    let mut usage = Smoother::new(smoothing);
+
+    loop {
+        tokio::time::sleep(interval).await;
+        system.refresh_cpu();
+
+        let text = render(&system, &mut usage, show_pressure);
+        if watch {
+            output::redraw(&text);
+        } else {
+            print!("{}", text);
+            return Ok(());
+        }
+    }
+}
+
+fn render(system: &System, usage: &mut Smoother<Option<usize>>, show_pressure: bool) -> String {
+    let info = read_cpuinfo();
+    let core_temperatures = read_core_temperatures();
+
+    let rows: Vec<CoreRow> = system
+        .cpus()
+        .iter()
+        .enumerate()
+        .map(|(index, cpu)| {
+            let info = info.get(&index);
+            let core = info.and_then(|i| i.core);
+            let mhz = info.and_then(|i| i.mhz).unwrap_or(cpu.frequency() as f64);
+            let temperature = core.and_then(|core| {
+                core_temperatures.get(&(info.and_then(|i| i.package).unwrap_or(0), core))
+            });
+            let percent = usage.update(Some(index), cpu.cpu_usage() as f64) as f32;
+            CoreRow {
+                cpu: format!("cpu{}", index),
+                core: core.map(|c| c.to_string()).unwrap_or_else(|| "-".to_string()),
+                usage: format!("{:>5.1}% {}", percent, bar(percent)),
+                frequency: if mhz > 0.0 { format!("{:.0} MHz", mhz) } else { "-".to_string() },
+                temperature: temperature.map(|t| format!("{:.1}°C", t)).unwrap_or_else(|| "-".to_string()),
+            }
+        })
+        .collect();
+
+    let load = System::load_average();
+    let mut text = format!(
+        "Overall: {:.1}% across {} CPUs, load average {:.2} {:.2} {:.2}\n\n",
+        usage.update(None, system.global_cpu_info().cpu_usage() as f64),
+        system.cpus().len(),
+        load.one,
+        load.five,
+        load.fifteen
+    );
+    text.push_str(&format!("{}\n", Table::new(rows)));
+
+    let zones = read_thermal_zones();
+    if !zones.is_empty() {
+        text.push_str("\nThermal zones:\n");
+        for (name, celsius) in zones {
+            text.push_str(&format!("  {:<20} {:.1}°C\n", name, celsius));
+        }
+    }
+
+    if show_pressure {
+        text.push('\n');
+        text.push_str(&pressure::format("CPU", pressure::read("cpu")));
+    }
+
+    text
+}
+
+/// A 10-character usage bar.
+fn bar(percent: f32) -> String {
+    let filled = ((percent / 10.0).round() as usize).min(10);
+    format!("{}{}", "#".repeat(filled), ".".repeat(10 - filled))
+}
+
+/// Package, core, and current clock of each logical CPU, keyed by processor number.
+fn read_cpuinfo() -> HashMap<usize, CpuInfo> {
+    let Ok(content) = fs::read_to_string("/proc/cpuinfo") else {
+        return HashMap::new();
+    };
+
+    let mut cpus = HashMap::new();
+    for block in content.split("\n\n") {
+        let mut processor = None;
+        let mut info = CpuInfo::default();
+        for line in block.lines() {
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+            match key.trim() {
+                "processor" => processor = value.parse().ok(),
+                "physical id" => info.package = value.parse().ok(),
+                "core id" => info.core = value.parse().ok(),
+                "cpu MHz" => info.mhz = value.parse().ok(),
+                _ => {}
+            }
+        }
+        if let Some(processor) = processor {
+            cpus.insert(processor, info);
+        }
+    }
+    cpus
+}
+
+/// Per-core temperatures from the coretemp hwmon driver ("Core N" sensors), keyed by package
+/// and core id. Other drivers (e.g. k10temp) only report the package, which shows up under
+/// the thermal zones instead.
+fn read_core_temperatures() -> HashMap<(u32, u32), f64> {
+    let mut temperatures = HashMap::new();
+    let Ok(entries) = fs::read_dir("/sys/class/hwmon") else {
+        return temperatures;
+    };
+
+    for entry in entries.flatten() {
+        let dir = entry.path();
+        if fs::read_to_string(dir.join("name")).map(|n| n.trim() != "coretemp").unwrap_or(true) {
+            continue;
+        }
+        let Ok(files) = fs::read_dir(&dir) else {
+            continue;
+        };
+
+        // Each package has its own coretemp device, labelled "Package id N" next to "Core N"
+        let mut package = 0;
+        let mut cores = Vec::new();
+        for file in files.flatten() {
+            let name = file.file_name().to_string_lossy().to_string();
+            let Some(sensor) = name.strip_suffix("_label") else {
+                continue;
+            };
+            let label = fs::read_to_string(file.path()).unwrap_or_default();
+            let label = label.trim();
+            if let Some(id) = label.strip_prefix("Package id ").and_then(|id| id.parse().ok()) {
+                package = id;
+            } else if let Some(core) = label.strip_prefix("Core ").and_then(|c| c.parse().ok())
+                && let Some(celsius) = read_millidegrees(&dir.join(format!("{}_input", sensor)))
+            {
+                cores.push((core, celsius));
+            }
+        }
+        for (core, celsius) in cores {
+            temperatures.insert((package, core), celsius);
+        }
+    }
+    temperatures
+}
+
+/// Name and temperature of each kernel thermal zone, such as x86_pkg_temp or acpitz.
+fn read_thermal_zones() -> Vec<(String, f64)> {
+    let Ok(entries) = fs::read_dir("/sys/class/thermal") else {
+        return Vec::new();
+    };
+
+    let mut zones: Vec<(String, String, f64)> = entries
+        .flatten()
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with("thermal_zone"))
+        .filter_map(|entry| {
+            let dir = entry.path();
+            let name = fs::read_to_string(dir.join("type")).ok()?.trim().to_string();
+            let celsius = read_millidegrees(&dir.join("temp"))?;
+            Some((entry.file_name().to_string_lossy().to_string(), name, celsius))
+        })
+        .collect();
+    zones.sort_by(|a, b| a.0.len().cmp(&b.0.len()).then(a.0.cmp(&b.0)));
+    zones.into_iter().map(|(_, name, celsius)| (name, celsius)).collect()
+}
+
+fn read_millidegrees(path: &Path) -> Option<f64> {
+    let value: f64 = fs::read_to_string(path).ok()?.trim().parse().ok()?;
+    Some(value / 1000.0)
+}
@@ -0,0 +1,444 @@
+//! `daemon`: keeps history recording running in the background, along with `monitor` when alert
+//! rules or hooks are configured, focus recording when there is a window backend, and
+//! notification recording when there is a session bus, and sends the daily report.
+//!
+//! `daemon install` registers `top-helper daemon run` to start with the session: as a systemd
+//! user service where a user manager is running, as an XDG autostart entry elsewhere. `start`,
+//! `stop`, and `status` then go through `systemctl --user` for the service, and through the pid
+//! file `daemon run` keeps in the state directory for the autostart entry.
+
+use anyhow::{Context, Result};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+use sysinfo::System;
+use tokio::signal::unix::{SignalKind, signal};
+
+use crate::alerts;
+use crate::config::Config;
+use crate::control::{self, Signal};
+use crate::dbus;
+use crate::error::CliError;
+use crate::focus;
+use crate::history;
+use crate::notifications;
+use crate::output::{self, MonitorFormat};
+use crate::paths;
+use crate::report;
+use crate::window;
+
+const UNIT_NAME: &str = "top-helper.service";
+const DESKTOP_NAME: &str = "top-helper-daemon.desktop";
+
+/// The same intervals as `record`, `monitor`, and `focus-history --watch` use by default.
+const RECORD_INTERVAL: Duration = Duration::from_secs(60);
+const MONITOR_INTERVAL: Duration = Duration::from_secs(10);
+const FOCUS_INTERVAL: Duration = Duration::from_millis(500);
+
+const REPORT_PERIOD_SECS: i64 = 24 * 60 * 60;
+/// Sleeps are cut short to look at the clock again, since they don't count time suspended.
+const REPORT_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How the daemon was registered to start with the session.
+enum Installation {
+    Systemd(PathBuf),
+    Autostart(PathBuf),
+}
+
+impl Installation {
+    fn find() -> Result<Option<Installation>> {
+        let unit = unit_path()?;
+        if unit.exists() {
+            return Ok(Some(Installation::Systemd(unit)));
+        }
+        let desktop = desktop_path()?;
+        Ok(desktop.exists().then_some(Installation::Autostart(desktop)))
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            Installation::Systemd(path) => format!("a systemd user service ({})", path.display()),
+            Installation::Autostart(path) => format!("an XDG autostart entry ({})", path.display()),
+        }
+    }
+}
+
+fn config_dir() -> Result<PathBuf> {
+    paths::config_home().context("Cannot determine the user config directory")
+}
+
+fn unit_path() -> Result<PathBuf> {
+    Ok(config_dir()?.join("systemd/user").join(UNIT_NAME))
+}
+
+fn desktop_path() -> Result<PathBuf> {
+    Ok(config_dir()?.join("autostart").join(DESKTOP_NAME))
+}
+
+fn pid_path() -> Result<PathBuf> {
+    Ok(paths::state_dir()?.join("daemon.pid"))
+}
+
+fn log_path() -> Result<PathBuf> {
+    Ok(paths::state_dir()?.join("daemon.log"))
+}
+
+/// Whether a systemd user manager is running for this session.
+fn systemd_available() -> bool {
+    Command::new("systemctl")
+        .args(["--user", "show-environment"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+fn systemctl(args: &[&str]) -> Result<()> {
+    let status = Command::new("systemctl").arg("--user").args(args).status().map_err(|err| {
+        if err.kind() == ErrorKind::NotFound {
+            CliError::backend_missing("systemctl not found")
+        } else {
+            anyhow::Error::new(err).context("Failed to run systemctl")
+        }
+    })?;
+    if !status.success() {
+        return Err(anyhow::anyhow!("systemctl --user {} failed", args.join(" ")));
+    }
+    Ok(())
+}
+
+/// A property of the service, as `systemctl --user show` prints it.
+fn unit_property(property: &str) -> Option<String> {
+    let output = Command::new("systemctl")
+        .args(["--user", "show", "--property", property, "--value", UNIT_NAME])
+        .stderr(Stdio::null())
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// The command line that starts the daemon, quoted for both a unit's `ExecStart=` and a desktop
+/// entry's `Exec=`, which share the double-quote and `%%` conventions.
+fn exec_line() -> Result<String> {
+    let exe = std::env::current_exe().context("Failed to find the top-helper binary")?;
+    let exe = exe.display().to_string().replace('%', "%%");
+    let exe = if exe.contains(|c: char| c.is_whitespace() || c == '"' || c == '\\') {
+        format!("\"{}\"", exe.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        exe
+    };
+    Ok(format!("{} daemon run", exe))
+}
+
+fn unit_file(exec: &str) -> String {
+    format!(
+        "[Unit]\n\
+         Description=top-helper process history, alerts, and window focus recording\n\
+         \n\
+         [Service]\n\
+         ExecStart={}\n\
+         Restart=on-failure\n\
+         RestartSec=10\n\
+         \n\
+         [Install]\n\
+         WantedBy=default.target\n",
+        exec
+    )
+}
+
+fn desktop_entry(exec: &str) -> String {
+    format!(
+        "[Desktop Entry]\n\
+         Type=Application\n\
+         Name=top-helper daemon\n\
+         Comment=Process history, alerts, and window focus recording\n\
+         Exec={}\n\
+         Terminal=false\n\
+         NoDisplay=true\n\
+         X-GNOME-Autostart-enabled=true\n",
+        exec
+    )
+}
+
+fn write_file(path: &Path, content: &str) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    fs::write(path, content).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Registers the daemon to start with the session and starts it now. Uses a systemd user service
+/// unless `autostart` asks for an XDG autostart entry or there is no user manager.
+pub fn install(autostart: bool) -> Result<()> {
+    let exec = exec_line()?;
+    let use_systemd = !autostart && systemd_available();
+
+    // Both at once would run two daemons at login
+    if let Some(existing) = Installation::find()? {
+        let same_kind = matches!(existing, Installation::Systemd(_)) == use_systemd;
+        if !same_kind {
+            return Err(anyhow::anyhow!(
+                "Already installed as {}; run `top-helper daemon uninstall` first",
+                existing.describe()
+            ));
+        }
+    }
+
+    if use_systemd {
+        let path = unit_path()?;
+        write_file(&path, &unit_file(&exec))?;
+        systemctl(&["daemon-reload"])?;
+        systemctl(&["enable", "--now", UNIT_NAME])?;
+        println!("Installed {} and started it", path.display());
+        println!("Logs: journalctl --user -u {}", UNIT_NAME);
+    } else {
+        let path = desktop_path()?;
+        write_file(&path, &desktop_entry(&exec))?;
+        println!("Installed {}; the daemon starts at your next login", path.display());
+        start_detached()?;
+    }
+    Ok(())
+}
+
+/// Stops the daemon and removes whatever `install` registered.
+pub fn uninstall() -> Result<()> {
+    match Installation::find()? {
+        Some(Installation::Systemd(path)) => {
+            // The user manager may be gone (say, over SSH); the unit file can still go
+            if systemd_available() {
+                systemctl(&["disable", "--now", UNIT_NAME])?;
+            }
+            fs::remove_file(&path).with_context(|| format!("Failed to remove {}", path.display()))?;
+            if systemd_available() {
+                systemctl(&["daemon-reload"])?;
+            }
+            println!("Removed {}", path.display());
+        }
+        Some(Installation::Autostart(path)) => {
+            stop_detached()?;
+            fs::remove_file(&path).with_context(|| format!("Failed to remove {}", path.display()))?;
+            println!("Removed {}", path.display());
+        }
+        None => println!("The daemon is not installed"),
+    }
+    Ok(())
+}
+
+fn require_installation() -> Result<Installation> {
+    Installation::find()?
+        .ok_or_else(|| CliError::not_found("The daemon is not installed; run `top-helper daemon install` first"))
+}
+
+pub fn start() -> Result<()> {
+    match require_installation()? {
+        Installation::Systemd(_) => {
+            systemctl(&["start", UNIT_NAME])?;
+            println!("Started {}", UNIT_NAME);
+            Ok(())
+        }
+        Installation::Autostart(_) => start_detached(),
+    }
+}
+
+pub fn stop() -> Result<()> {
+    match require_installation()? {
+        Installation::Systemd(_) => {
+            systemctl(&["stop", UNIT_NAME])?;
+            println!("Stopped {}", UNIT_NAME);
+            Ok(())
+        }
+        Installation::Autostart(_) => stop_detached(),
+    }
+}
+
+/// Starts `daemon run` in its own process group, so it outlives the terminal, logging to the
+/// state directory.
+fn start_detached() -> Result<()> {
+    if let Some(pid) = running_pid()? {
+        println!("The daemon is already running (PID {})", pid);
+        return Ok(());
+    }
+
+    let log = log_path()?;
+    let file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&log)
+        .with_context(|| format!("Failed to open {}", log.display()))?;
+    let exe = std::env::current_exe().context("Failed to find the top-helper binary")?;
+    let child = {
+        use std::os::unix::process::CommandExt;
+        Command::new(exe)
+            .args(["daemon", "run"])
+            .stdin(Stdio::null())
+            .stdout(file.try_clone()?)
+            .stderr(file)
+            .process_group(0)
+            .spawn()
+            .context("Failed to start the daemon")?
+    };
+    println!("Started the daemon (PID {}), logging to {}", child.id(), log.display());
+    Ok(())
+}
+
+fn stop_detached() -> Result<()> {
+    let Some(pid) = running_pid()? else {
+        println!("The daemon is not running");
+        return Ok(());
+    };
+    control::send_signal(pid, Signal::Terminate)?;
+    // It removes its pid file on the way out
+    for _ in 0..50 {
+        if running_pid()?.is_none() {
+            println!("Stopped the daemon (PID {})", pid);
+            return Ok(());
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    Err(anyhow::anyhow!("The daemon (PID {}) did not exit within 5s", pid))
+}
+
+/// The PID in the pid file, if that process is still a running `daemon run`; a stale file from a
+/// daemon that was killed outright is ignored.
+fn running_pid() -> Result<Option<u32>> {
+    let content = match fs::read_to_string(pid_path()?) {
+        Ok(content) => content,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(anyhow::Error::new(err).context("Failed to read the daemon's pid file")),
+    };
+    let Ok(pid) = content.trim().parse::<u32>() else {
+        return Ok(None);
+    };
+    let Ok(cmdline) = fs::read(format!("/proc/{}/cmdline", pid)) else {
+        return Ok(None);
+    };
+    let args: Vec<&[u8]> = cmdline.split(|&b| b == 0).collect();
+    let is_daemon = args.windows(2).any(|pair| pair == [b"daemon".as_slice(), b"run".as_slice()]);
+    Ok(is_daemon.then_some(pid))
+}
+
+/// Shows how the daemon is installed, whether it's running, and what it costs.
+pub fn status() -> Result<()> {
+    let installation = Installation::find()?;
+    let pid = match &installation {
+        Some(Installation::Systemd(path)) => {
+            let enabled = unit_property("UnitFileState").unwrap_or_else(|| "unknown".to_string());
+            println!("Installed: systemd user service ({}), {}", path.display(), enabled);
+            unit_property("MainPID").and_then(|pid| pid.parse::<u32>().ok()).filter(|&pid| pid != 0)
+        }
+        Some(installation @ Installation::Autostart(_)) => {
+            println!("Installed: {}", installation.describe());
+            running_pid()?
+        }
+        None => {
+            println!("Installed: no (run `top-helper daemon install`)");
+            running_pid()?
+        }
+    };
+
+    let mut system = System::new();
+    system.refresh_processes();
+    match pid.and_then(|pid| system.process(sysinfo::Pid::from(pid as usize))) {
+        Some(process) => println!(
+            "Running: yes, PID {}, for {}, {}",
+            process.pid(),
+            output::format_duration(process.run_time() * 1000),
+            output::format_memory(process.memory())
+        ),
+        None => println!("Running: no"),
+    }
+
+    match installation {
+        Some(Installation::Systemd(_)) => println!("Logs: journalctl --user -u {}", UNIT_NAME),
+        _ => println!("Logs: {}", log_path()?.display()),
+    }
+    Ok(())
+}
+
+/// The daemon itself: records history, and monitors alerts and records window focus when there
+/// is something to do, until interrupted or terminated.
+pub async fn run(display: Option<&str>) -> Result<()> {
+    if let Some(pid) = running_pid()? {
+        return Err(anyhow::anyhow!("The daemon is already running (PID {})", pid));
+    }
+    let pid_file = pid_path()?;
+    fs::write(&pid_file, format!("{}\n", std::process::id()))
+        .with_context(|| format!("Failed to write {}", pid_file.display()))?;
+
+    let config = Config::load()?;
+    let monitor_alerts = !config.alerts.is_empty() || !config.hooks.is_empty();
+    if !monitor_alerts {
+        println!("No alert rules or hooks configured; not monitoring alerts");
+    }
+    let record_focus = window::focused_window(display).is_ok();
+    if !record_focus {
+        println!("No window backend available; not recording window focus");
+    }
+    let record_notifications = dbus::session().await.is_ok();
+    if !record_notifications {
+        println!("No session bus available; not recording notifications");
+    }
+
+    let mut terminate = signal(SignalKind::terminate()).context("Failed to listen for SIGTERM")?;
+    let result = tokio::select! {
+        result = history::record(None, RECORD_INTERVAL, false) => result,
+        result = daily_reports(&config.report.sinks) => result,
+        result = async {
+            if monitor_alerts { alerts::monitor(MONITOR_INTERVAL, 0.0, MonitorFormat::Text, None).await } else { std::future::pending().await }
+        } => result,
+        result = async {
+            if record_focus { focus::watch_focus(FOCUS_INTERVAL, display).await } else { std::future::pending().await }
+        } => result,
+        result = async {
+            // A bus that doesn't allow monitoring is no reason to stop recording everything else
+            if record_notifications && let Err(err) = notifications::watch_notifications().await {
+                eprintln!("Warning: stopped recording notifications: {:#}", err);
+            }
+            std::future::pending().await
+        } => result,
+        result = tokio::signal::ctrl_c() => result.context("Failed to wait for Ctrl+C"),
+        _ = terminate.recv() => Ok(()),
+    };
+
+    let _ = fs::remove_file(&pid_file);
+    result
+}
+
+/// Sends the daily report to `sinks` every 24 hours, the first a day after the daemon first ran.
+/// The time of the next one is kept in the state directory, so restarting the daemon neither
+/// skips nor repeats a report.
+async fn daily_reports(sinks: &[String]) -> Result<()> {
+    let path = paths::state_dir()?.join("next-report");
+    let now = chrono::Utc::now().timestamp();
+    let mut due = fs::read_to_string(&path)
+        .ok()
+        .and_then(|text| text.trim().parse::<i64>().ok())
+        .unwrap_or(now + REPORT_PERIOD_SECS);
+
+    loop {
+        fs::write(&path, format!("{}\n", due)).with_context(|| format!("Failed to write {}", path.display()))?;
+        loop {
+            let left = due - chrono::Utc::now().timestamp();
+            if left <= 0 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_secs(left as u64).min(REPORT_CHECK_INTERVAL)).await;
+        }
+
+        let report_sinks = sinks.to_vec();
+        let result = tokio::task::spawn_blocking(move || report::daily_report(&report_sinks)).await;
+        if let Err(err) = result.map_err(anyhow::Error::from).and_then(|result| result) {
+            eprintln!("Warning: the daily report could not be sent: {:#}", err);
+        }
+
+        // After the machine was off for longer than a day, the report just sent covers it
+        let now = chrono::Utc::now().timestamp();
+        due += REPORT_PERIOD_SECS;
+        if due <= now {
+            due = now + REPORT_PERIOD_SECS;
+        }
+    }
+}
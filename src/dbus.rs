@@ -0,0 +1,48 @@
+//! Session bus access through zbus, for the few calls top-helper makes as a client.
+
+use anyhow::{Context, Result};
+use std::future::Future;
+use zbus::Connection;
+use zbus::names::BusName;
+use zbus::zvariant::OwnedValue;
+
+/// Connects to the session bus.
+pub async fn session() -> Result<Connection> {
+    Connection::session().await.context("Cannot connect to the D-Bus session bus")
+}
+
+/// Runs bus calls from synchronous code. zbus's blocking API drives a runtime of its own, which
+/// cannot be entered from within ours, so inside ours the calls run on it instead.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    match tokio::runtime::Handle::try_current() {
+        Ok(handle) => tokio::task::block_in_place(|| handle.block_on(future)),
+        Err(_) => zbus::block_on(future),
+    }
+}
+
+/// Reads a property of an object, or None if it cannot be read or has another type.
+pub async fn property<T: TryFrom<OwnedValue>>(
+    connection: &Connection,
+    destination: &str,
+    path: &str,
+    interface: &str,
+    name: &str,
+) -> Option<T> {
+    let proxy = zbus::fdo::PropertiesProxy::builder(connection)
+        .destination(destination.to_string())
+        .ok()?
+        .path(path.to_string())
+        .ok()?
+        .build()
+        .await
+        .ok()?;
+    let interface = zbus::names::InterfaceName::try_from(interface).ok()?;
+    T::try_from(proxy.get(interface, name).await.ok()?).ok()
+}
+
+/// Looks up the PID behind a bus name such as `:1.42` or `org.kde.StatusNotifierWatcher`.
+pub async fn connection_pid(connection: &Connection, name: &str) -> Option<u32> {
+    let name = BusName::try_from(name).ok()?;
+    let proxy = zbus::fdo::DBusProxy::new(connection).await.ok()?;
+    proxy.get_connection_unix_process_id(name).await.ok()
+}
@@ -0,0 +1,101 @@
+//! `dbus-serve`: top-helper as a session bus service, so launchers, extensions, and other
+//! programs can switch to a process's window or look a process up without spawning the CLI.
+//!
+//! The service is `org.tophelper.Helper` at `/org/tophelper/Helper`. Every method takes and
+//! returns strings, the results being the same JSON `switch --format json` and `info` would
+//! give, so callers in any language only need a JSON parser:
+//!
+//! - `SwitchToProcess(selector)`: switch to the process's window
+//! - `ListWindows()`: every window top-helper can see
+//! - `GetProcessInfo(selector)`: details of one process
+
+use anyhow::{Context, Result};
+use std::sync::Mutex;
+use sysinfo::System;
+use zbus::fdo;
+
+use crate::gpu;
+use crate::process;
+use crate::redact;
+use crate::selector::Selection;
+use crate::window::{self, WindowChoice};
+
+pub const BUS_NAME: &str = "org.tophelper.Helper";
+pub const OBJECT_PATH: &str = "/org/tophelper/Helper";
+
+struct Helper {
+    system: Mutex<System>,
+}
+
+impl Helper {
+    /// Runs `f` on a freshly refreshed process list; a service lives long enough for any
+    /// snapshot to go stale.
+    fn with_system<T>(&self, f: impl FnOnce(&System) -> Result<T>) -> fdo::Result<T> {
+        let mut system = self.system.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        system.refresh_processes();
+        f(&system).map_err(|err| fdo::Error::Failed(format!("{:#}", err)))
+    }
+}
+
+fn to_json(value: &impl serde::Serialize) -> Result<String> {
+    serde_json::to_string(value).context("Failed to encode the reply")
+}
+
+#[zbus::interface(name = "org.tophelper.Helper")]
+impl Helper {
+    /// Switches to the window of the process `selector` picks, as `switch` does, and returns the
+    /// resolution. A window that was found but couldn't be activated is an error.
+    fn switch_to_process(&self, selector: &str) -> fdo::Result<String> {
+        self.with_system(|system| {
+            let process = Selection::parse(selector)?.resolve_unique(system)?;
+            let mut plan = window::find_switch_target(system, process, None)?.plan(&WindowChoice::default(), false)?;
+            plan.perform().context("Failed to switch window")?;
+            to_json(&plan.resolution)
+        })
+    }
+
+    /// Every window on the session's display, as an array of objects.
+    fn list_windows(&self) -> fdo::Result<String> {
+        self.with_system(|_| {
+            let windows: Vec<serde_json::Value> = window::list_all_windows(None)?
+                .iter()
+                .map(|window| {
+                    serde_json::json!({
+                        "window_id": window.window_id,
+                        "pid": window.pid,
+                        "class": window.class,
+                        "title": redact::text(&window.title),
+                        "backend": window.backend.name(),
+                    })
+                })
+                .collect();
+            to_json(&windows)
+        })
+    }
+
+    /// What `info` shows about the process `selector` picks.
+    async fn get_process_info(&self, selector: &str) -> fdo::Result<String> {
+        let mut info = self.with_system(|system| {
+            let process = Selection::parse(selector)?.resolve_unique(system)?;
+            process::get_detailed_process_info(system, process.pid().as_u32(), process, false, false, false)
+        })?;
+        info.gpu = gpu::measure(&[info.pid]).await.remove(&info.pid);
+        to_json(&info.json()).map_err(|err| fdo::Error::Failed(format!("{:#}", err)))
+    }
+}
+
+/// Claims the bus name and answers calls until interrupted.
+pub async fn serve() -> Result<()> {
+    let helper = Helper { system: Mutex::new(System::new_all()) };
+    let _connection = zbus::connection::Builder::session()
+        .context("Failed to connect to the session bus")?
+        .name(BUS_NAME)?
+        .serve_at(OBJECT_PATH, helper)?
+        .build()
+        .await
+        .with_context(|| format!("Failed to register {} on the session bus (is another instance running?)", BUS_NAME))?;
+
+    eprintln!("Serving {} at {} on the session bus", BUS_NAME, OBJECT_PATH);
+    tokio::signal::ctrl_c().await.context("Failed to wait for Ctrl+C")?;
+    Ok(())
+}
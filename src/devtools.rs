@@ -0,0 +1,272 @@
+//! `devtools`: development processes by category (language servers, bundlers and watchers, dev
+//! servers, test runners, containers, databases), with what each category costs in total.
+//!
+//! Processes are classified by a rules table: a rule matches on the process name, or on a regex
+//! over the leading words of the command line by basename (`node vite --port 3000` for
+//! `/usr/bin/node /app/node_modules/.bin/vite --port 3000`). `[[devtool]]` rules from the config
+//! file are tried before the built-in ones, so they can add categories or reclassify a process.
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::Deserialize;
+use sysinfo::System;
+use tabled::{Table, Tabled, settings::{Width, location::ByColumnName}};
+
+use crate::config::Config;
+use crate::output;
+
+/// How many leading command-line arguments rules look at. Later arguments are usually file
+/// names and free-form text that would match by accident.
+const MATCHED_ARGS: usize = 4;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DevtoolRule {
+    pub category: String,
+    /// Process names that belong to the category
+    #[serde(default)]
+    pub names: Vec<String>,
+    /// Regex over the leading command-line words, e.g. "^node .*vite( |$)"
+    pub pattern: Option<String>,
+}
+
+/// Built-in rules as (category, process names, command-line words). Words match whole,
+/// space-separated words, and multi-word entries such as "cargo test" match in sequence.
+const BUILTIN_RULES: &[(&str, &[&str], &[&str])] = &[
+    (
+        "language servers",
+        &[
+            "rust-analyzer",
+            "gopls",
+            "clangd",
+            "pyright",
+            "pylsp",
+            "jdtls",
+            "lua-language-server",
+            "typescript-language-server",
+            "solargraph",
+            "zls",
+            "taplo",
+            "marksman",
+        ],
+        &["tsserver.js", "pyright-langserver", "vscode-eslint-language-server", "vscode-json-language-server"],
+    ),
+    (
+        "test runners",
+        &["pytest", "jest", "vitest", "cargo-nextest", "rspec", "phpunit"],
+        &["pytest", "jest", "vitest", "mocha", "cargo test", "cargo nextest", "go test", "rspec", "playwright test"],
+    ),
+    (
+        "dev servers",
+        &["uvicorn", "gunicorn", "hugo", "live-server"],
+        &[
+            "vite",
+            "next dev",
+            "nuxt dev",
+            "webpack-dev-server",
+            "webpack serve",
+            "manage.py runserver",
+            "flask run",
+            "rails server",
+            "hugo server",
+            "jekyll serve",
+            "http.server",
+            "npm run dev",
+            "yarn dev",
+            "pnpm dev",
+            "trunk serve",
+        ],
+    ),
+    (
+        "bundlers/watchers",
+        &["esbuild", "watchman", "nodemon", "watchexec", "entr", "cargo-watch", "turbo"],
+        &["webpack", "rollup", "parcel", "esbuild", "tsc --watch", "tsc -w", "nodemon", "cargo watch", "tailwindcss"],
+    ),
+    (
+        "containers",
+        &[
+            "dockerd",
+            "containerd",
+            "docker-proxy",
+            "podman",
+            "conmon",
+            "buildkitd",
+            "runc",
+            "crun",
+            "colima",
+            "limactl",
+            "k3s",
+            "kubelet",
+        ],
+        &["containerd-shim-runc-v2", "containerd-shim"],
+    ),
+    (
+        "databases",
+        &[
+            "postgres",
+            "mysqld",
+            "mariadbd",
+            "mongod",
+            "redis-server",
+            "valkey-server",
+            "memcached",
+            "clickhouse-server",
+            "etcd",
+            "influxd",
+        ],
+        &["org.elasticsearch.bootstrap.Elasticsearch", "org.opensearch.bootstrap.OpenSearch", "neo4j"],
+    ),
+];
+
+/// A rule ready to match.
+struct Rule {
+    category: String,
+    names: Vec<String>,
+    pattern: Option<Regex>,
+}
+
+impl Rule {
+    fn matches(&self, name: &str, words: &str) -> bool {
+        self.names.iter().any(|n| n == name) || self.pattern.as_ref().is_some_and(|pattern| pattern.is_match(words))
+    }
+}
+
+#[derive(Tabled)]
+struct DevtoolProcess {
+    #[tabled(rename = "PID")]
+    pid: u32,
+
+    #[tabled(rename = "Name")]
+    name: String,
+
+    #[tabled(rename = "Memory")]
+    memory: String,
+
+    #[tabled(rename = "CPU %")]
+    cpu_percent: String,
+
+    #[tabled(rename = "Command")]
+    command: String,
+}
+
+struct Category {
+    name: String,
+    processes: Vec<DevtoolProcess>,
+    memory_bytes: u64,
+    cpu_percent: f32,
+}
+
+/// Prints each category that has running processes, heaviest first, with its totals and
+/// (unless `summary`) its processes.
+pub async fn show_devtools(summary: bool) -> Result<()> {
+    let rules = rules(&Config::load()?.devtools)?;
+
+    let mut system = System::new_all();
+    system.refresh_all();
+    // A second sample is needed for CPU usage
+    tokio::time::sleep(sysinfo::MINIMUM_CPU_UPDATE_INTERVAL).await;
+    system.refresh_processes();
+
+    let own = std::process::id();
+    let mut categories: Vec<Category> = Vec::new();
+    for process in system.processes().values() {
+        if process.thread_kind().is_some() || process.pid().as_u32() == own {
+            continue;
+        }
+        let words = leading_words(process.cmd());
+        let Some(rule) = rules.iter().find(|rule| rule.matches(process.name(), &words)) else {
+            continue;
+        };
+
+        let index = match categories.iter().position(|category| category.name == rule.category) {
+            Some(index) => index,
+            None => {
+                categories.push(Category {
+                    name: rule.category.clone(),
+                    processes: Vec::new(),
+                    memory_bytes: 0,
+                    cpu_percent: 0.0,
+                });
+                categories.len() - 1
+            }
+        };
+        let category = &mut categories[index];
+        category.memory_bytes += process.memory();
+        category.cpu_percent += process.cpu_usage();
+        category.processes.push(DevtoolProcess {
+            pid: process.pid().as_u32(),
+            name: process.name().to_string(),
+            memory: output::format_memory(process.memory()),
+            cpu_percent: format!("{:.1}", process.cpu_usage()),
+            command: if process.cmd().is_empty() { process.name().to_string() } else { process.cmd().join(" ") },
+        });
+    }
+
+    if categories.is_empty() {
+        println!("No development tools are running");
+        return Ok(());
+    }
+
+    categories.sort_by_key(|category| std::cmp::Reverse(category.memory_bytes));
+    for mut category in categories {
+        println!(
+            "{}: {} processes, {}, {:.1}% CPU",
+            capitalize(&category.name),
+            category.processes.len(),
+            output::format_memory(category.memory_bytes),
+            category.cpu_percent,
+        );
+        if !summary {
+            category.processes.sort_by_key(|process| process.pid);
+            let mut table = Table::new(category.processes);
+            table.modify(ByColumnName::new("Command"), Width::truncate(60).suffix("..."));
+            println!("{}\n", table);
+        }
+    }
+
+    Ok(())
+}
+
+/// The configured rules followed by the built-in ones.
+fn rules(configured: &[DevtoolRule]) -> Result<Vec<Rule>> {
+    let mut rules = Vec::new();
+    for rule in configured {
+        let pattern = rule
+            .pattern
+            .as_deref()
+            .map(Regex::new)
+            .transpose()
+            .with_context(|| format!("Invalid pattern in devtool rule for '{}'", rule.category))?;
+        rules.push(Rule { category: rule.category.clone(), names: rule.names.clone(), pattern });
+    }
+
+    for (category, names, words) in BUILTIN_RULES {
+        let alternatives: Vec<String> = words.iter().map(|word| regex::escape(word)).collect();
+        rules.push(Rule {
+            category: category.to_string(),
+            names: names.iter().map(|name| name.to_string()).collect(),
+            pattern: Some(Regex::new(&format!("(?:^| )(?:{})(?: |$)", alternatives.join("|")))?),
+        });
+    }
+    Ok(rules)
+}
+
+/// The first few command-line words, each by its basename: `/usr/bin/node
+/// /app/node_modules/.bin/vite --port 3000` is "node vite --port 3000". Arguments with spaces
+/// in them, such as a `sh -c` script, are left out.
+fn leading_words(cmd: &[String]) -> String {
+    cmd.iter()
+        .take(MATCHED_ARGS)
+        .filter(|arg| !arg.contains(char::is_whitespace))
+        .map(|arg| arg.rsplit('/').next().unwrap_or(arg))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
@@ -0,0 +1,308 @@
+//! `disks`: mounted filesystems with space and inode usage, and which processes are doing I/O
+//! on each.
+//!
+//! /proc/<pid>/io counts a process's storage I/O but not which device it went to, so the I/O
+//! is attributed to the filesystems of the files the process has open (or its working
+//! directory when it has none), split evenly when there are several.
+
+use anyhow::{Context, Result};
+use procfs::process::{FDTarget, Process};
+use std::collections::{BTreeMap, HashMap};
+use std::ffi::CString;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use sysinfo::System;
+use tabled::{Table, Tabled};
+
+use crate::output::{self, format_memory};
+use crate::smoothing::Smoother;
+
+/// Processes shown per filesystem in the I/O section.
+const TOP_PROCESSES: usize = 3;
+
+#[derive(Tabled)]
+struct DiskRow {
+    #[tabled(rename = "Mount")]
+    mount_point: String,
+
+    #[tabled(rename = "Device")]
+    device: String,
+
+    #[tabled(rename = "Type")]
+    fs_type: String,
+
+    #[tabled(rename = "Size")]
+    size: String,
+
+    #[tabled(rename = "Used")]
+    used: String,
+
+    #[tabled(rename = "Avail")]
+    available: String,
+
+    #[tabled(rename = "Use%")]
+    used_percent: String,
+
+    #[tabled(rename = "Inodes Use%")]
+    inodes_percent: String,
+}
+
+struct Mount {
+    mount_point: PathBuf,
+    device: String,
+    fs_type: String,
+    /// `major:minor`, the st_dev of files on it
+    majmin: String,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum Direction {
+    Read,
+    Write,
+}
+
+struct Usage {
+    size: u64,
+    available: u64,
+    free: u64,
+    inodes: u64,
+    inodes_free: u64,
+}
+
+/// Lists filesystems and the processes with the most I/O on each over `interval`, once or (with
+/// `watch`) redrawn until interrupted, with I/O rates smoothed across refreshes by `smoothing`.
+pub async fn show_disks(interval: Duration, all: bool, watch: bool, smoothing: f64) -> Result<()> {
+    let mut system = System::new();
+    // Bytes per second read and written, keyed by device and PID
+    let mut rates = Smoother::new(smoothing);
+
+    loop {
+        let mounts = real_mounts(all)?;
+        let io = io_by_mount(&mounts, interval).await;
+        system.refresh_processes();
+
+        let text = render(&mounts, &io, interval, &system, &mut rates);
+        if watch {
+            output::redraw(&text);
+        } else {
+            print!("{}", text);
+            return Ok(());
+        }
+    }
+}
+
+fn render(
+    mounts: &[(Mount, Usage)],
+    io: &HashMap<String, BTreeMap<u32, (u64, u64)>>,
+    interval: Duration,
+    system: &System,
+    rates: &mut Smoother<(String, u32, Direction)>,
+) -> String {
+    let mut rows = Vec::new();
+    for (mount, usage) in mounts {
+        let used = usage.size.saturating_sub(usage.free);
+        // Like df, the percentage is of the space available to unprivileged users
+        let usable = used + usage.available;
+        let inodes_used = usage.inodes.saturating_sub(usage.inodes_free);
+        rows.push(DiskRow {
+            mount_point: mount.mount_point.display().to_string(),
+            device: mount.device.clone(),
+            fs_type: mount.fs_type.clone(),
+            size: format_memory(usage.size),
+            used: format_memory(used),
+            available: format_memory(usage.available),
+            used_percent: if usable > 0 { format!("{:.0}%", used as f64 * 100.0 / usable as f64) } else { "-".to_string() },
+            inodes_percent: if usage.inodes > 0 {
+                format!("{:.0}%", inodes_used as f64 * 100.0 / usage.inodes as f64)
+            } else {
+                "-".to_string()
+            },
+        });
+    }
+    let mut text = format!("{}\n", Table::new(rows));
+
+    // Processes that have gone quiet still decay towards zero rather than vanishing at once
+    let secs = interval.as_secs_f64();
+    let mut samples: HashMap<(String, u32, Direction), f64> = rates.keys().map(|key| (key.clone(), 0.0)).collect();
+    for (majmin, processes) in io {
+        for (pid, (read, write)) in processes {
+            samples.insert((majmin.clone(), *pid, Direction::Read), *read as f64 / secs);
+            samples.insert((majmin.clone(), *pid, Direction::Write), *write as f64 / secs);
+        }
+    }
+    let mut by_mount: HashMap<String, HashMap<u32, (f64, f64)>> = HashMap::new();
+    for ((majmin, pid, direction), sample) in samples {
+        let rate = rates.update((majmin.clone(), pid, direction), sample);
+        let entry = by_mount.entry(majmin).or_default().entry(pid).or_default();
+        match direction {
+            Direction::Read => entry.0 = rate,
+            Direction::Write => entry.1 = rate,
+        }
+    }
+    rates.retain(|_, rate| rate >= 1.0);
+    by_mount.retain(|_, processes| {
+        processes.retain(|_, (read, write)| *read + *write >= 1.0);
+        !processes.is_empty()
+    });
+
+    if by_mount.is_empty() {
+        text.push_str(&format!("\nNo disk I/O by readable processes in the last {:?}\n", interval));
+        return text;
+    }
+
+    text.push_str(&format!("\nTop I/O over the last {:?}:\n", interval));
+    for (mount, _) in mounts {
+        let Some(processes) = by_mount.get(&mount.majmin) else {
+            continue;
+        };
+        let mut processes: Vec<(&u32, &(f64, f64))> = processes.iter().collect();
+        processes.sort_by(|(_, a), (_, b)| (b.0 + b.1).total_cmp(&(a.0 + a.1)));
+
+        text.push_str(&format!("  {}\n", mount.mount_point.display()));
+        for (pid, (read, write)) in processes.into_iter().take(TOP_PROCESSES) {
+            let name = system
+                .process(sysinfo::Pid::from(*pid as usize))
+                .map(|p| p.name().to_string())
+                .unwrap_or_else(|| "(exited)".to_string());
+            text.push_str(&format!(
+                "    {} (PID: {}): {}/s read, {}/s written\n",
+                name,
+                pid,
+                format_memory(*read as u64),
+                format_memory(*write as u64)
+            ));
+        }
+    }
+
+    text
+}
+
+/// Mounted filesystems that store data, each device once. Pseudo filesystems (proc, sysfs,
+/// cgroup) report no blocks and are skipped, as are read-only squashfs images such as snaps
+/// unless `all` is set.
+fn real_mounts(all: bool) -> Result<Vec<(Mount, Usage)>> {
+    let mountinfo = Process::myself()
+        .and_then(|process| process.mountinfo())
+        .context("Failed to read mount table")?;
+
+    let mut seen = std::collections::HashSet::new();
+    let mut mounts = Vec::new();
+    for info in mountinfo.0 {
+        if !all && info.fs_type == "squashfs" {
+            continue;
+        }
+        let Some(usage) = statvfs(&info.mount_point) else {
+            continue;
+        };
+        if usage.size == 0 || !seen.insert(info.majmin.clone()) {
+            continue;
+        }
+        mounts.push((
+            Mount {
+                mount_point: info.mount_point,
+                device: info.mount_source.unwrap_or_else(|| "-".to_string()),
+                fs_type: info.fs_type,
+                majmin: info.majmin,
+            },
+            usage,
+        ));
+    }
+    Ok(mounts)
+}
+
+fn statvfs(path: &Path) -> Option<Usage> {
+    let path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    // SAFETY: statvfs is plain data that the call fills in
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    // SAFETY: path is a valid NUL-terminated string and stat is valid for writes
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    let block = stat.f_frsize as u64;
+    Some(Usage {
+        size: stat.f_blocks as u64 * block,
+        available: stat.f_bavail as u64 * block,
+        free: stat.f_bfree as u64 * block,
+        inodes: stat.f_files as u64,
+        inodes_free: stat.f_ffree as u64,
+    })
+}
+
+/// Bytes read and written by each process over the interval, grouped by the `major:minor`
+/// of the filesystem they are attributed to.
+async fn io_by_mount(mounts: &[(Mount, Usage)], interval: Duration) -> HashMap<String, BTreeMap<u32, (u64, u64)>> {
+    let before = io_counters();
+    let started = Instant::now();
+    tokio::time::sleep(interval.saturating_sub(started.elapsed())).await;
+    let after = io_counters();
+
+    let known: std::collections::HashSet<&str> = mounts.iter().map(|(mount, _)| mount.majmin.as_str()).collect();
+    let mut by_mount: HashMap<String, BTreeMap<u32, (u64, u64)>> = HashMap::new();
+    for (pid, (read, write)) in after {
+        let (read_before, write_before) = before.get(&pid).copied().unwrap_or((read, write));
+        let (read, write) = (read.saturating_sub(read_before), write.saturating_sub(write_before));
+        if read + write == 0 {
+            continue;
+        }
+
+        let devices: Vec<String> = process_devices(pid).into_iter().filter(|d| known.contains(d.as_str())).collect();
+        let share = devices.len().max(1) as u64;
+        for device in devices {
+            let entry = by_mount.entry(device).or_default().entry(pid).or_default();
+            entry.0 += read / share;
+            entry.1 += write / share;
+        }
+    }
+    by_mount
+}
+
+/// Storage bytes read and written so far by every process whose /proc/<pid>/io is readable.
+fn io_counters() -> HashMap<u32, (u64, u64)> {
+    let Ok(processes) = procfs::process::all_processes() else {
+        return HashMap::new();
+    };
+    processes
+        .flatten()
+        .filter_map(|process| {
+            let io = process.io().ok()?;
+            Some((process.pid() as u32, (io.read_bytes, io.write_bytes)))
+        })
+        .collect()
+}
+
+/// The `major:minor` devices of a process's open files and directories, or of its working
+/// directory if it has none open.
+fn process_devices(pid: u32) -> Vec<String> {
+    let Ok(process) = Process::new(pid as i32) else {
+        return Vec::new();
+    };
+    // Device nodes, sockets, and pipes say nothing about where the I/O went
+    let majmin = |path: &Path| {
+        std::fs::metadata(path).ok().filter(|metadata| metadata.is_file() || metadata.is_dir()).map(|metadata| {
+            let dev = metadata.dev();
+            format!("{}:{}", libc::major(dev), libc::minor(dev))
+        })
+    };
+
+    let mut devices: Vec<String> = process
+        .fd()
+        .map(|fds| {
+            fds.flatten()
+                .filter_map(|fd| match fd.target {
+                    FDTarget::Path(path) => majmin(&path),
+                    _ => None,
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    if devices.is_empty()
+        && let Ok(cwd) = process.cwd()
+    {
+        devices.extend(majmin(&cwd));
+    }
+    devices.sort();
+    devices.dedup();
+    devices
+}
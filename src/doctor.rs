@@ -0,0 +1,332 @@
+//! `doctor`: which of top-helper's features work on this machine, which are degraded, and what
+//! to install or change to enable the rest.
+//!
+//! Everything optional is checked: the window backends and the tools they run, access to other
+//! users' processes in /proc, the session bus, systemd and cgroups, and the tools behind
+//! individual commands (`stack`, `trace`, `tabs`, `gpu`, ...). Nothing is changed.
+
+use anyhow::Result;
+use std::env;
+use std::fs;
+use std::path::Path;
+use sysinfo::System;
+use tabled::{Table, Tabled, settings::{Width, location::ByColumnName}};
+
+use crate::hyprland;
+use crate::session::SessionType;
+use crate::sway;
+use crate::window::{self, Backend, command_exists};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Ok,
+    Degraded,
+    Missing,
+}
+
+impl Status {
+    fn label(&self) -> &'static str {
+        match self {
+            Status::Ok => "ok",
+            Status::Degraded => "degraded",
+            Status::Missing => "missing",
+        }
+    }
+}
+
+struct Check {
+    feature: &'static str,
+    status: Status,
+    detail: String,
+    /// How to get the feature working, for anything not ok
+    fix: Option<String>,
+}
+
+impl Check {
+    fn ok(feature: &'static str, detail: impl Into<String>) -> Self {
+        Check { feature, status: Status::Ok, detail: detail.into(), fix: None }
+    }
+
+    fn degraded(feature: &'static str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Check { feature, status: Status::Degraded, detail: detail.into(), fix: Some(fix.into()) }
+    }
+
+    fn missing(feature: &'static str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Check { feature, status: Status::Missing, detail: detail.into(), fix: Some(fix.into()) }
+    }
+}
+
+#[derive(Tabled)]
+struct CheckRow {
+    #[tabled(rename = "Feature")]
+    feature: &'static str,
+
+    #[tabled(rename = "Status")]
+    status: &'static str,
+
+    #[tabled(rename = "Details")]
+    detail: String,
+}
+
+pub fn run_doctor(display: Option<&str>) -> Result<()> {
+    let session = SessionType::detect();
+    let mut checks = vec![session_check(session)];
+    checks.extend(window_checks(session, display));
+    checks.push(proc_check());
+    checks.extend(bus_checks());
+    checks.extend(systemd_checks());
+    checks.extend(tool_checks());
+
+    let rows = checks.iter().map(|check| CheckRow {
+        feature: check.feature,
+        status: check.status.label(),
+        detail: check.detail.clone(),
+    });
+    let mut table = Table::new(rows);
+    table.modify(ByColumnName::new("Details"), Width::wrap(70).keep_words());
+    println!("{}", table);
+
+    let fixes: Vec<&Check> = checks.iter().filter(|check| check.fix.is_some()).collect();
+    if fixes.is_empty() {
+        println!("\nEverything is available.");
+        return Ok(());
+    }
+    println!("\nTo enable what is degraded or missing:");
+    for check in fixes {
+        println!("  {}: {}", check.feature, check.fix.as_deref().unwrap_or_default());
+    }
+    Ok(())
+}
+
+fn session_check(session: SessionType) -> Check {
+    let compositor = if hyprland::is_running() {
+        " under Hyprland"
+    } else if sway::is_running() {
+        " under Sway/i3"
+    } else {
+        ""
+    };
+    let variables: Vec<String> = ["XDG_SESSION_TYPE", "DISPLAY", "WAYLAND_DISPLAY"]
+        .iter()
+        .filter_map(|name| Some(format!("{}={}", name, env::var(name).ok()?)))
+        .collect();
+    let detail = format!(
+        "{}{} ({})",
+        session.name(),
+        compositor,
+        if variables.is_empty() { "no session variables set".to_string() } else { variables.join(", ") }
+    );
+    match session {
+        SessionType::X11 | SessionType::Wayland => Check::ok("Session", detail),
+        SessionType::Tty | SessionType::Unknown => Check::degraded(
+            "Session",
+            detail,
+            "window commands need a graphical session; from outside one, pass --display :0 for X11",
+        ),
+    }
+}
+
+/// Window lookup (`switch`, `info`, `windows`) and activation, per backend.
+fn window_checks(session: SessionType, display: Option<&str>) -> Vec<Check> {
+    let mut checks = Vec::new();
+    let available = window::available_backends(session, display);
+
+    let usable: Vec<String> = Backend::all()
+        .iter()
+        .filter(|backend| backend.supports_session(session, display))
+        .map(|backend| match backend.required_tool() {
+            Some(tool) if !command_exists(tool) => format!("{} (needs {})", backend.name(), tool),
+            _ => backend.name().to_string(),
+        })
+        .collect();
+    checks.push(match available.first() {
+        Some(first) => Check::ok(
+            "Window lookup",
+            format!("using {}; usable here: {}", first.name(), usable.join(", ")),
+        ),
+        None if session.has_x11_display() || display.is_some() => Check::missing(
+            "Window lookup",
+            "no X11 window tool is installed",
+            "install xdotool (preferred) or wmctrl",
+        ),
+        None if session == SessionType::Wayland => Check::missing(
+            "Window lookup",
+            "only Hyprland and Sway/i3 are supported natively on Wayland, and DISPLAY is not set for XWayland",
+            "run Hyprland or Sway, or set DISPLAY so XWayland windows can be found with xdotool",
+        ),
+        None => Check::missing(
+            "Window lookup",
+            format!("no window backend works in a {} session", session.name()),
+            "run from a graphical session, or pass --display :0",
+        ),
+    });
+
+    if available.first() == Some(&Backend::Xdotool) && !command_exists("xprop") {
+        checks.push(Check::degraded(
+            "Window classes",
+            "xprop is missing, so xdotool windows show their class as Unknown",
+            "install xprop (x11-utils)",
+        ));
+    }
+    checks
+}
+
+/// How much of other users' processes /proc lets us see.
+fn proc_check() -> Check {
+    let hidepid = fs::read_to_string("/proc/mounts").ok().and_then(|mounts| {
+        mounts
+            .lines()
+            .find(|line| line.split_whitespace().nth(1) == Some("/proc"))
+            .and_then(|line| line.split_whitespace().nth(3)?.split(',').find(|option| option.starts_with("hidepid=")))
+            .map(str::to_string)
+    });
+    // SAFETY: geteuid has no preconditions
+    let uid = unsafe { libc::geteuid() };
+
+    let mut system = System::new();
+    system.refresh_processes();
+    let others: Vec<&sysinfo::Process> = system
+        .processes()
+        .values()
+        .filter(|p| p.thread_kind().is_none() && p.user_id().is_some_and(|user| **user != uid))
+        .collect();
+    let unreadable = others.iter().filter(|p| fs::read_link(format!("/proc/{}/cwd", p.pid())).is_err()).count();
+
+    match (hidepid.filter(|option| option != "hidepid=0" && option != "hidepid=off"), unreadable) {
+        (Some(option), _) => Check::degraded(
+            "/proc access",
+            format!("/proc is mounted with {}, which hides other users' processes", option),
+            "run as root, or remount /proc without hidepid",
+        ),
+        (None, 0) => Check::ok("/proc access", format!("full access, including {} processes of other users", others.len())),
+        (None, unreadable) => Check::degraded(
+            "/proc access",
+            format!(
+                "working directory, environment, and open files of {} of {} other users' processes are unreadable",
+                unreadable,
+                others.len()
+            ),
+            "run with sudo to see them in info, who-has, projects, and cwd: selectors",
+        ),
+    }
+}
+
+fn bus_checks() -> Vec<Check> {
+    let bus = env::var_os("DBUS_SESSION_BUS_ADDRESS").is_some()
+        || env::var_os("XDG_RUNTIME_DIR").is_some_and(|dir| Path::new(&dir).join("bus").exists());
+    let mut checks = Vec::new();
+    if !bus {
+        checks.push(Check::missing(
+            "Session bus",
+            "no D-Bus session bus found",
+            "run from within a desktop session, or set DBUS_SESSION_BUS_ADDRESS",
+        ));
+    } else {
+        checks.push(Check::ok("Session bus", "tray apps in agents, notifications --watch, desktop notifications"));
+    }
+    checks.push(match (bus, command_exists("notify-send")) {
+        (true, _) => Check::ok("Desktop notifications", "over D-Bus, with switch/kill buttons"),
+        (false, true) => Check::degraded(
+            "Desktop notifications",
+            "notify-send only; buttons need notify-send 0.7.9 or later",
+            "run from within a desktop session for notifications over D-Bus",
+        ),
+        (false, false) => Check::missing(
+            "Desktop notifications",
+            "no session bus and notify-send is not installed, so the desktop sink cannot deliver",
+            "run from within a desktop session, or install libnotify-bin (notify-send)",
+        ),
+    });
+    checks
+}
+
+fn systemd_checks() -> Vec<Check> {
+    let mut checks = Vec::new();
+    // Same check as sd_booted(3)
+    let booted = Path::new("/run/systemd/system").exists();
+    let cgroup2 = Path::new("/sys/fs/cgroup/cgroup.controllers").exists();
+    checks.push(match (booted, cgroup2) {
+        (true, _) => Check::ok("Resource limits", "systemd transient scopes"),
+        (false, true) => Check::degraded(
+            "Resource limits",
+            "no systemd; limit creates cgroups directly, which requires root",
+            "run limit and throttling policies as root",
+        ),
+        (false, false) => Check::missing(
+            "Resource limits",
+            "neither systemd nor a cgroup v2 hierarchy is available",
+            "boot with cgroup v2 (systemd.unified_cgroup_hierarchy=1)",
+        ),
+    });
+    checks.push(if booted && command_exists("systemctl") {
+        Check::ok("User services", "daemon install and agents use systemd --user")
+    } else {
+        Check::degraded(
+            "User services",
+            "no systemd; daemon install falls back to an XDG autostart entry and agents lists no services",
+            "run a systemd user manager to get user services",
+        )
+    });
+    checks.push(if booted && command_exists("systemd-analyze") {
+        Check::ok("Boot timing", "systemd-analyze")
+    } else {
+        Check::degraded("Boot timing", "boot can only list agents without systemd-analyze", "install systemd")
+    });
+    checks
+}
+
+/// Tools behind single commands.
+fn tool_checks() -> Vec<Check> {
+    let mut checks = vec![
+        tools_check("Terminal tabs", &["tmux"], "tmux panes in tabs, switch, and idle-shells", "install tmux"),
+        alternatives_check(
+            "Native stacks",
+            &["eu-stack", "gdb"],
+            "stack --native",
+            "install elfutils (eu-stack) or gdb",
+        ),
+        alternatives_check(
+            "Syscall tracing",
+            &["strace", "perf"],
+            "trace with call counts and latencies (it samples /proc otherwise)",
+            "install strace or perf",
+        ),
+        tools_check("Unix socket peers", &["ss"], "Unix socket edges in graph", "install iproute2 (ss)"),
+        tools_check("Fleet", &["ssh"], "fleet polls hosts over SSH", "install an SSH client"),
+        alternatives_check(
+            "Test displays",
+            &["Xvfb", "sway"],
+            "--test-env x11 (Xvfb) and --test-env sway",
+            "install xvfb or sway",
+        ),
+    ];
+    // Other GPUs report through /proc, NVIDIA's proprietary driver only through nvidia-smi
+    if Path::new("/proc/driver/nvidia").exists() {
+        checks.push(tools_check("NVIDIA GPUs", &["nvidia-smi"], "gpu usage of NVIDIA cards", "install nvidia-utils"));
+    }
+    checks
+}
+
+/// Ok when all of `tools` are installed, degraded when some are, missing when none are.
+fn tools_check(feature: &'static str, tools: &[&str], purpose: &str, fix: &str) -> Check {
+    let (found, missing): (Vec<&str>, Vec<&str>) = tools.iter().partition(|tool| command_exists(tool));
+    match (found.is_empty(), missing.is_empty()) {
+        (false, true) => Check::ok(feature, format!("{} ({})", purpose, found.join(", "))),
+        (false, false) => Check::degraded(
+            feature,
+            format!("{} ({}; {} not installed)", purpose, found.join(", "), missing.join(", ")),
+            fix,
+        ),
+        (true, _) => Check::missing(feature, format!("{} ({} not installed)", purpose, missing.join(", ")), fix),
+    }
+}
+
+/// Ok when any of `tools` is installed, since each can stand in for the others.
+fn alternatives_check(feature: &'static str, tools: &[&str], purpose: &str, fix: &str) -> Check {
+    let found: Vec<&str> = tools.iter().copied().filter(|tool| command_exists(tool)).collect();
+    if found.is_empty() {
+        Check::missing(feature, format!("{} ({} not installed)", purpose, tools.join(" or ")), fix)
+    } else {
+        Check::ok(feature, format!("{} ({})", purpose, found.join(", ")))
+    }
+}
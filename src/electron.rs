@@ -0,0 +1,125 @@
+//! Electron apps: the real application behind processes that show up as `electron` or as a
+//! Chromium helper.
+//!
+//! Helpers (`--type=renderer`, `--type=gpu-process`, ...) belong to the app's main process, found
+//! by walking up the parent chain. The main process names the app in one of several ways,
+//! checked in order:
+//!
+//! - `APPIMAGE` in its environment, for apps run from an AppImage
+//! - an `--app=` argument or an `.asar` archive on its command line, as when a distribution
+//!   runs a shared `electron` binary on the app's resources
+//! - a `resources/app.asar` next to its executable, as bundled apps like Slack ship
+
+use std::path::{Component, Path};
+use sysinfo::System;
+
+/// Executable names that say nothing about which app is running.
+const GENERIC_NAMES: &[&str] = &["electron", "chrome", "chromium", "chrome_crashpad_handler"];
+
+#[derive(Debug, Clone)]
+pub struct ElectronApp {
+    /// Application name, e.g. "Slack" or "obsidian"
+    pub name: String,
+    /// The app's main process, which owns its windows
+    pub main_pid: u32,
+}
+
+/// The Electron app `pid` belongs to, if it is one and its name says something the process
+/// name doesn't.
+pub fn detect(system: &System, pid: u32) -> Option<ElectronApp> {
+    let process = system.process(sysinfo::Pid::from(pid as usize))?;
+    if !might_be_electron(process.name(), process.cmd()) {
+        return None;
+    }
+
+    let mut main = process;
+    while main.cmd().iter().any(|arg| arg.starts_with("--type=")) {
+        main = system.process(main.parent()?)?;
+    }
+
+    let name = appimage_name(main.environ())
+        .or_else(|| main.cmd().iter().skip(1).find_map(|arg| argument_name(arg)))
+        .or_else(|| bundled_name(main.exe()?))?;
+    if name == process.name() {
+        return None;
+    }
+    Some(ElectronApp { name, main_pid: main.pid().as_u32() })
+}
+
+/// Cheap check before any parent walking or filesystem access: Chromium helpers and processes
+/// with a generic name.
+fn might_be_electron(name: &str, cmd: &[String]) -> bool {
+    is_generic(name) || cmd.iter().any(|arg| arg.starts_with("--type="))
+}
+
+fn is_generic(name: &str) -> bool {
+    // Versioned distribution packages, e.g. electron25
+    let name = name.trim_end_matches(|c: char| c.is_ascii_digit());
+    GENERIC_NAMES.contains(&name)
+}
+
+/// `Obsidian-1.4.16.AppImage` is Obsidian.
+fn appimage_name(environ: &[String]) -> Option<String> {
+    let path = environ.iter().find_map(|var| var.strip_prefix("APPIMAGE="))?;
+    let file = Path::new(path).file_stem()?.to_string_lossy();
+    let name = file.split(['-', '_']).next().unwrap_or(&file);
+    Some(name.to_string()).filter(|name| !name.is_empty())
+}
+
+/// The app from `--app=/usr/lib/slack/app.asar` or a bare `/usr/lib/obsidian/app.asar`.
+/// Chrome's `--app=https://...` windows are web pages, not Electron apps.
+fn argument_name(arg: &str) -> Option<String> {
+    let path = match arg.strip_prefix("--app=") {
+        Some(path) if !path.contains("://") => path,
+        Some(_) => return None,
+        None if arg.ends_with(".asar") || arg.contains(".asar/") => arg,
+        None => return None,
+    };
+    name_from_path(Path::new(path))
+}
+
+/// The app whose executable sits next to `resources/app.asar`: its own executable name, or its
+/// directory when the executable is generic.
+fn bundled_name(exe: &Path) -> Option<String> {
+    let dir = exe.parent()?;
+    let resources = dir.join("resources");
+    if !resources.join("app.asar").exists() && !resources.join("app").is_dir() {
+        return None;
+    }
+    let name = exe.file_name()?.to_string_lossy();
+    if is_generic(&name) {
+        name_from_path(&resources)
+    } else {
+        Some(name.into_owned())
+    }
+}
+
+/// The directory naming the app, skipping the archive and `resources` parts:
+/// `/opt/Slack/resources/app.asar` and `/usr/lib/slack/app.asar` are both the slack directory.
+fn name_from_path(path: &Path) -> Option<String> {
+    let mut parts: Vec<String> = path
+        .components()
+        .filter_map(|component| match component {
+            Component::Normal(part) => Some(part.to_string_lossy().into_owned()),
+            _ => None,
+        })
+        .collect();
+    // Nothing inside the archive says more about the app than the archive itself
+    if let Some(archive) = parts.iter().position(|part| part.ends_with(".asar")) {
+        parts.truncate(archive + 1);
+    }
+    while let Some(last) = parts.last() {
+        let generic = matches!(last.as_str(), "app" | "app.asar" | "resources");
+        if !generic && !last.ends_with(".asar") {
+            break;
+        }
+        // A standalone archive like /opt/apps/notes.asar names the app itself
+        if let Some(stem) = last.strip_suffix(".asar")
+            && stem != "app"
+        {
+            return Some(stem.to_string());
+        }
+        parts.pop();
+    }
+    parts.pop().filter(|name| !is_generic(name))
+}
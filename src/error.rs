@@ -0,0 +1,104 @@
+use std::fmt;
+use std::io;
+
+/// Failure categories that map to distinct process exit codes, so scripts can branch on outcomes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    NotFound,
+    BackendMissing,
+    Permission,
+    MultipleMatches,
+}
+
+impl ErrorKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ErrorKind::NotFound => "not_found",
+            ErrorKind::BackendMissing => "backend_missing",
+            ErrorKind::Permission => "permission",
+            ErrorKind::MultipleMatches => "multiple_matches",
+        }
+    }
+
+    /// Code 2 is left to clap, which exits with it on invalid usage.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            ErrorKind::NotFound => 6,
+            ErrorKind::BackendMissing => 3,
+            ErrorKind::Permission => 4,
+            ErrorKind::MultipleMatches => 5,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct CliError {
+    pub kind: ErrorKind,
+    pub message: String,
+}
+
+impl CliError {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        CliError { kind, message: message.into() }
+    }
+
+    pub fn not_found(message: impl Into<String>) -> anyhow::Error {
+        CliError::new(ErrorKind::NotFound, message).into()
+    }
+
+    pub fn backend_missing(message: impl Into<String>) -> anyhow::Error {
+        CliError::new(ErrorKind::BackendMissing, message).into()
+    }
+
+    pub fn permission(message: impl Into<String>) -> anyhow::Error {
+        CliError::new(ErrorKind::Permission, message).into()
+    }
+
+    pub fn multiple_matches(message: impl Into<String>) -> anyhow::Error {
+        CliError::new(ErrorKind::MultipleMatches, message).into()
+    }
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for CliError {}
+
+/// The category of an error: the first categorized error in its chain, or a
+/// permission failure anywhere in the chain.
+pub fn error_kind(err: &anyhow::Error) -> Option<ErrorKind> {
+    for cause in err.chain() {
+        if let Some(cli_error) = cause.downcast_ref::<CliError>() {
+            return Some(cli_error.kind);
+        }
+        if let Some(io_error) = cause.downcast_ref::<io::Error>()
+            && io_error.kind() == io::ErrorKind::PermissionDenied
+        {
+            return Some(ErrorKind::Permission);
+        }
+        if let Some(procfs::ProcError::PermissionDenied(_)) = cause.downcast_ref::<procfs::ProcError>() {
+            return Some(ErrorKind::Permission);
+        }
+    }
+
+    None
+}
+
+/// Exit code for an error; uncategorized errors exit with 1.
+pub fn exit_code(err: &anyhow::Error) -> u8 {
+    error_kind(err).map(|kind| kind.exit_code()).unwrap_or(1)
+}
+
+pub fn json_error(err: &anyhow::Error, code: u8) -> String {
+    serde_json::json!({
+        "error": {
+            "kind": error_kind(err).map(|kind| kind.name()).unwrap_or("error"),
+            "code": code,
+            "message": format!("{:#}", err),
+        }
+    })
+    .to_string()
+}
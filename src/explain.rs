@@ -0,0 +1,257 @@
+//! `explain`: a plain-language account of one process, combining what `info`, `tree`, `tabs`,
+//! `switch`, and the tmux lookup each know about it: who started it, where it runs, what it is
+//! doing right now, and how to get to it.
+
+use anyhow::Result;
+use procfs::process::{FDTarget, Process};
+use std::collections::HashSet;
+use std::fs;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use sysinfo::{System, Users};
+
+use crate::cgroup::{self, Container, Runtime};
+use crate::electron;
+use crate::names;
+use crate::output::{self, format_memory};
+use crate::project;
+use crate::selector::Selection;
+use crate::tabs;
+use crate::tty;
+use crate::window;
+
+/// How long CPU and I/O rates are measured over.
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Ancestors named in the ancestry chain before it is cut short.
+const MAX_ANCESTORS: usize = 8;
+
+/// Connections named in the activity paragraph; the rest are only counted.
+const MAX_PEERS: usize = 3;
+
+/// Prints the explanation of the process `identifier` selects, one paragraph per question.
+pub async fn explain_process(identifier: &str) -> Result<()> {
+    let selection = Selection::parse(identifier)?;
+    let mut system = System::new_all();
+    system.refresh_all();
+    let pid = selection.resolve_unique(&system)?.pid().as_u32();
+
+    // CPU usage and I/O rates are measured between two refreshes
+    tokio::time::sleep(SAMPLE_INTERVAL).await;
+    system.refresh_processes();
+    let Some(process) = system.process(sysinfo::Pid::from(pid as usize)) else {
+        return Err(anyhow::anyhow!("PID {} exited while it was being sampled", pid));
+    };
+
+    let paragraphs = [
+        origin(&system, process),
+        location(process),
+        activity(process),
+        access(&system, process),
+    ];
+    println!("{}", paragraphs.join("\n\n"));
+    Ok(())
+}
+
+/// Who started the process, when, and as whom.
+fn origin(system: &System, process: &sysinfo::Process) -> String {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let age = output::format_duration(now.saturating_sub(process.start_time()) * 1000);
+    let mut text = format!("{} was started {} ago", describe(system, process), age);
+
+    let mut ancestors = Vec::new();
+    let mut current = process;
+    while let Some(parent) = current.parent().and_then(|pid| system.process(pid)) {
+        ancestors.push(parent);
+        current = parent;
+    }
+    match ancestors.first() {
+        Some(parent) => text.push_str(&format!(" by {}", describe(system, parent))),
+        None => text.push_str(" by the kernel"),
+    }
+
+    let users = Users::new_with_refreshed_list();
+    if let Some(user) = process.user_id().and_then(|uid| users.get_user_by_id(uid)) {
+        text.push_str(&format!(", and runs as {}", user.name()));
+    }
+    text.push('.');
+
+    if ancestors.len() > 1 {
+        let mut chain: Vec<String> =
+            ancestors.iter().take(MAX_ANCESTORS).map(|p| format!("{} ({})", p.name(), p.pid())).collect();
+        if ancestors.len() > MAX_ANCESTORS {
+            chain.push("...".to_string());
+        }
+        text.push_str(&format!(" Its ancestry is {}.", chain.join(" ← ")));
+    }
+    text
+}
+
+/// Where the process works: directory, project, systemd unit, and container.
+fn location(process: &sysinfo::Process) -> String {
+    let pid = process.pid().as_u32();
+    let mut sentences = Vec::new();
+
+    if let Some(cwd) = process.cwd() {
+        let mut sentence = format!("It works in {}", project::display_path(cwd));
+        if let Some((root, is_git)) = project::project_root(cwd) {
+            let kind = if is_git { "git repository" } else { "project" };
+            if root == cwd {
+                sentence.push_str(&format!(", the root of a {}", kind));
+            } else {
+                sentence.push_str(&format!(", inside the {} {}", kind, project::display_path(&root)));
+            }
+        }
+        sentences.push(sentence + ".");
+    }
+
+    let cgroup = cgroup::read(pid);
+    match cgroup::container(&cgroup) {
+        Some(container) => sentences.push(format!("It runs in {}.", describe_container(&container))),
+        None if in_other_pid_namespace(pid) => sentences.push("It runs in its own PID namespace.".to_string()),
+        None => {}
+    }
+    if let Some(unit) = cgroup::systemd_unit(&cgroup) {
+        let owner = if unit.name.ends_with(".scope") {
+            "the scope"
+        } else if unit.user {
+            "the user service"
+        } else {
+            "the service"
+        };
+        sentences.push(format!("systemd manages it as part of {} {}.", owner, unit.name));
+    }
+
+    if sentences.is_empty() {
+        "Its working directory is not readable.".to_string()
+    } else {
+        sentences.join(" ")
+    }
+}
+
+/// What the process is doing right now: CPU, memory, disk, and network.
+fn activity(process: &sysinfo::Process) -> String {
+    let pid = process.pid().as_u32();
+    let io = process.disk_usage();
+    let per_second = |bytes: u64| format_memory((bytes as f64 / SAMPLE_INTERVAL.as_secs_f64()) as u64);
+    let mut text = format!(
+        "Right now it is {} and uses {:.1}% CPU and {} of memory, reading {}/s and writing {}/s.",
+        process.status().to_string().to_lowercase(),
+        process.cpu_usage(),
+        output::format_memory(process.memory()),
+        per_second(io.read_bytes),
+        per_second(io.written_bytes),
+    );
+
+    let network = network(pid);
+    if !network.listening.is_empty() {
+        let ports: Vec<String> = network.listening.iter().map(|port| port.to_string()).collect();
+        text.push_str(&format!(" It listens on TCP port{} {}.", if ports.len() == 1 { "" } else { "s" }, ports.join(", ")));
+    }
+    if !network.peers.is_empty() {
+        let mut peers: Vec<String> = network.peers.iter().take(MAX_PEERS).cloned().collect();
+        if network.peers.len() > MAX_PEERS {
+            peers.push(format!("{} more", network.peers.len() - MAX_PEERS));
+        }
+        text.push_str(&format!(
+            " It has {} open TCP connection{}, to {}.",
+            network.peers.len(),
+            if network.peers.len() == 1 { "" } else { "s" },
+            peers.join(", ")
+        ));
+    }
+    text
+}
+
+/// How to get to the process: its window, terminal tab, or tmux pane.
+fn access(system: &System, process: &sysinfo::Process) -> String {
+    let pid = process.pid().as_u32();
+    let display = window::process_display(pid);
+    let own_window = window::find_window_by_pid(pid, display.as_deref()).ok();
+    let app_window = || {
+        let app = electron::detect(system, pid)?;
+        window::find_window_by_pid(app.main_pid, display.as_deref()).ok()
+    };
+
+    let mut sentences = Vec::new();
+    if let Some(window) = own_window.or_else(app_window) {
+        sentences.push(format!("Its window is \"{}\" ({}).", window.title, window.class));
+    }
+    if let Some(pane) = tty::find_tmux_pane(pid) {
+        sentences.push(format!("It runs in tmux pane {}.", pane.target));
+    } else if let Some(tab) = tabs::find_tab(system, pid) {
+        sentences.push(format!("It runs in {} tab {}.", tab.emulator, tab.index));
+    } else if let Some(tty) = tty::process_tty(pid) {
+        sentences.push(format!("It is attached to /dev/{}.", tty));
+    }
+
+    if sentences.is_empty() {
+        return format!(
+            "It has no window or terminal, so it runs in the background. `top-helper info {}` shows its details.",
+            pid
+        );
+    }
+    sentences.push(format!("`top-helper switch {}` brings it to the front.", pid));
+    sentences.join(" ")
+}
+
+/// "Slack (PID 1200)", by the app or script name where there is one, like `list` shows it.
+fn describe(system: &System, process: &sysinfo::Process) -> String {
+    let pid = process.pid().as_u32();
+    let name = electron::detect(system, pid)
+        .map(|app| app.name)
+        .or_else(|| names::display_name(process.cmd()))
+        .unwrap_or_else(|| process.name().to_string());
+    format!("{} (PID {})", name, pid)
+}
+
+/// A container description, such as "Docker container 0123456789ab".
+fn describe_container(container: &Container) -> String {
+    let short = container.id.map(|id| id.chars().take(12).collect::<String>()).unwrap_or_default();
+    match container.runtime {
+        Runtime::Docker => format!("Docker container {}", short),
+        Runtime::Podman => format!("Podman container {}", short),
+        Runtime::Kubernetes => "a Kubernetes pod".to_string(),
+    }
+}
+
+fn in_other_pid_namespace(pid: u32) -> bool {
+    match (fs::read_link(format!("/proc/{}/ns/pid", pid)), fs::read_link("/proc/self/ns/pid")) {
+        (Ok(theirs), Ok(ours)) => theirs != ours,
+        _ => false,
+    }
+}
+
+#[derive(Default)]
+struct Network {
+    listening: Vec<u16>,
+    /// Remote addresses of established connections
+    peers: Vec<String>,
+}
+
+/// TCP sockets the process holds, from its open file descriptors and /proc/net/tcp{,6}.
+fn network(pid: u32) -> Network {
+    let Ok(fds) = Process::new(pid as i32).and_then(|process| process.fd()) else {
+        return Network::default();
+    };
+    let inodes: HashSet<u64> = fds
+        .flatten()
+        .filter_map(|fd| match fd.target {
+            FDTarget::Socket(inode) => Some(inode),
+            _ => None,
+        })
+        .collect();
+
+    let mut network = Network::default();
+    for entries in [procfs::net::tcp(), procfs::net::tcp6()].into_iter().flatten() {
+        for entry in entries.iter().filter(|entry| inodes.contains(&entry.inode)) {
+            match entry.state {
+                procfs::net::TcpState::Listen => network.listening.push(entry.local_address.port()),
+                procfs::net::TcpState::Established => network.peers.push(entry.remote_address.to_string()),
+                _ => {}
+            }
+        }
+    }
+    network.listening.sort_unstable();
+    network.listening.dedup();
+    network
+}
@@ -0,0 +1,57 @@
+//! Page fault counts and rates, for spotting processes that thrash against swap or page in heavily
+//! on demand. Minor faults are served from memory (a page touched for the first time, or found in
+//! the page cache); major faults had to wait for the disk or swap. The counts are the whole thread
+//! group's, from /proc/<pid>/stat.
+
+use procfs::process::Process;
+use std::collections::HashMap;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// Faults since the process started.
+#[derive(Debug, Clone, Copy)]
+pub struct FaultCounts {
+    pub minor: u64,
+    pub major: u64,
+}
+
+/// Faults per second over a sampling interval.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FaultRates {
+    pub minor: f64,
+    pub major: f64,
+}
+
+impl fmt::Display for FaultRates {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.0} ({:.0} major)", self.minor + self.major, self.major)
+    }
+}
+
+pub fn fault_counts(pid: u32) -> Option<FaultCounts> {
+    let stat = Process::new(pid as i32).ok()?.stat().ok()?;
+    Some(FaultCounts { minor: stat.minflt, major: stat.majflt })
+}
+
+/// Samples the fault counts of `pids` over `interval`. Processes that exited during it are left
+/// out.
+pub async fn measure(pids: &[u32], interval: Duration) -> HashMap<u32, FaultRates> {
+    let before: HashMap<u32, FaultCounts> = pids.iter().filter_map(|&pid| Some((pid, fault_counts(pid)?))).collect();
+    let started = Instant::now();
+    tokio::time::sleep(interval).await;
+    let elapsed = started.elapsed().as_secs_f64();
+
+    before
+        .into_iter()
+        .filter_map(|(pid, before)| {
+            let after = fault_counts(pid)?;
+            Some((
+                pid,
+                FaultRates {
+                    minor: after.minor.saturating_sub(before.minor) as f64 / elapsed,
+                    major: after.major.saturating_sub(before.major) as f64 / elapsed,
+                },
+            ))
+        })
+        .collect()
+}
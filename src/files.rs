@@ -0,0 +1,146 @@
+//! `files`: the files a process has open, and with `--watch`, the files it opens and closes as
+//! it runs, found by diffing /proc/<pid>/fd between polls.
+//!
+//! Polling misses files opened and closed again within one interval, so a config file that is
+//! read in one go may not show up; `strace -e trace=openat` sees every open() instead.
+
+use anyhow::{Context, Result};
+use procfs::process::{FDTarget, Process};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use sysinfo::System;
+use tabled::{Table, Tabled, settings::{Width, location::ByColumnName}};
+
+use crate::error::CliError;
+use crate::selector::{self, Selection};
+
+#[derive(Tabled)]
+struct FileRow {
+    #[tabled(rename = "FD")]
+    fd: i32,
+
+    #[tabled(rename = "Path")]
+    path: String,
+}
+
+/// Which open files are shown.
+pub struct FileFilter {
+    /// Only paths under one of these; all paths when empty
+    pub include: Vec<PathBuf>,
+    /// Never paths under these
+    pub exclude: Vec<PathBuf>,
+    /// Also sockets, pipes, and anonymous inodes, which have no path
+    pub all: bool,
+}
+
+impl FileFilter {
+    pub fn new(include: &[String], exclude: &[String], all: bool) -> Self {
+        FileFilter {
+            include: include.iter().map(|path| selector::resolve_path(path)).collect(),
+            exclude: exclude.iter().map(|path| selector::resolve_path(path)).collect(),
+            all,
+        }
+    }
+
+    fn matches(&self, target: &str) -> bool {
+        let path = Path::new(target);
+        if !path.is_absolute() {
+            // socket:[1234], pipe:[5678], anon_inode:[eventfd], ...
+            return self.all && self.include.is_empty();
+        }
+        (self.include.is_empty() || self.include.iter().any(|prefix| path.starts_with(prefix)))
+            && !self.exclude.iter().any(|prefix| path.starts_with(prefix))
+    }
+}
+
+/// Lists the files the process `identifier` selects has open, or with `watch`, prints each file
+/// it opens or closes until it exits.
+pub async fn show_files(identifier: &str, filter: &FileFilter, watch: Option<Duration>) -> Result<()> {
+    let selection = Selection::parse(identifier)?;
+    let mut system = System::new_all();
+    system.refresh_all();
+    let process = selection.resolve_unique(&system)?;
+    let pid = process.pid().as_u32();
+    let handle = Process::new(pid as i32).with_context(|| format!("PID {} is gone", pid))?;
+
+    let Some(mut previous) = open_files(&handle, filter)? else {
+        return Err(CliError::not_found(format!("PID {} exited", pid)));
+    };
+
+    let Some(interval) = watch else {
+        if previous.is_empty() {
+            println!("PID {} ({}) has no matching files open", pid, process.name());
+            return Ok(());
+        }
+        let rows: Vec<FileRow> = previous.into_iter().map(|(fd, path)| FileRow { fd, path }).collect();
+        let mut table = Table::new(rows);
+        table.modify(ByColumnName::new("Path"), Width::truncate(90).suffix("..."));
+        println!("{}", table);
+        return Ok(());
+    };
+
+    println!(
+        "Watching PID {} ({}), {} matching files open; Ctrl-C to stop",
+        pid,
+        process.name(),
+        previous.len()
+    );
+    loop {
+        tokio::time::sleep(interval).await;
+        let Some(current) = open_files(&handle, filter)? else {
+            println!("{}  PID {} exited", timestamp(), pid);
+            return Ok(());
+        };
+
+        // A descriptor number reused for another file counts as a close and an open
+        for (fd, path) in &previous {
+            if current.get(fd) != Some(path) {
+                println!("{}  close  fd {:<4} {}", timestamp(), fd, path);
+            }
+        }
+        for (fd, path) in &current {
+            if previous.get(fd) != Some(path) {
+                println!("{}  open   fd {:<4} {}", timestamp(), fd, path);
+            }
+        }
+        previous = current;
+    }
+}
+
+/// The process's open descriptors that pass the filter, by descriptor number, or None once it
+/// has exited.
+fn open_files(process: &Process, filter: &FileFilter) -> Result<Option<BTreeMap<i32, String>>> {
+    let fds = match process.fd() {
+        Ok(fds) => fds,
+        Err(procfs::ProcError::NotFound(_)) => return Ok(None),
+        Err(procfs::ProcError::PermissionDenied(_)) => {
+            return Err(CliError::permission(format!("The open files of PID {} are not readable", process.pid)));
+        }
+        Err(err) => return Err(err.into()),
+    };
+    if !process.is_alive() {
+        return Ok(None);
+    }
+
+    let files = fds
+        .flatten()
+        .filter_map(|fd| {
+            let target = match fd.target {
+                FDTarget::Path(path) => path.display().to_string(),
+                FDTarget::Socket(inode) => format!("socket:[{}]", inode),
+                FDTarget::Pipe(inode) => format!("pipe:[{}]", inode),
+                FDTarget::AnonInode(kind) => format!("anon_inode:{}", kind),
+                FDTarget::MemFD(name) => format!("memfd:{}", name),
+                FDTarget::Net(inode) => format!("net:[{}]", inode),
+                FDTarget::Other(kind, inode) => format!("{}:[{}]", kind, inode),
+            };
+            filter.matches(&target).then_some((fd.fd, target))
+        })
+        .collect();
+    Ok(Some(files))
+}
+
+fn timestamp() -> String {
+    chrono::Local::now().format("%H:%M:%S%.3f").to_string()
+}
@@ -0,0 +1,161 @@
+//! Recorded process snapshots, so `list` output can be reproduced without the original machine.
+//!
+//! A fixture is a directory with one JSON file per process (`<pid>.json`) holding what the
+//! process collector reads from /proc. `top-helper save-fixture DIR` records one, and the hidden
+//! global `--fixture DIR` option makes `list` read it instead of the live system. The files are
+//! plain JSON so a bug report can trim a fixture down to the processes that matter.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use sysinfo::{System, ThreadKind};
+
+use crate::electron;
+use crate::redact;
+use crate::sandbox::{self, SandboxedApp};
+use crate::tty;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessSample {
+    pub pid: u32,
+    pub parent_pid: Option<u32>,
+    pub name: String,
+    #[serde(default)]
+    pub cmd: Vec<String>,
+    pub cwd: Option<PathBuf>,
+    pub memory_bytes: u64,
+    #[serde(default)]
+    pub cpu_percent: f32,
+    #[serde(default)]
+    pub environ: HashMap<String, String>,
+    /// Unix time the process started
+    #[serde(default)]
+    pub start_time: u64,
+    /// Bytes read and written since the process started
+    #[serde(default)]
+    pub io_bytes: u64,
+    #[serde(default)]
+    pub threads: usize,
+    /// Controlling terminal, e.g. "pts/3"
+    #[serde(default)]
+    pub tty: Option<String>,
+    /// Electron app name, for processes that only show up as `electron` or a Chromium helper
+    #[serde(default)]
+    pub app: Option<String>,
+    /// The Flatpak or Snap app the process runs confined in
+    #[serde(default)]
+    pub sandbox: Option<SandboxedApp>,
+    /// A thread of `parent_pid` rather than a process of its own; threads are listed alongside
+    /// processes with their owner's command line and memory
+    #[serde(default)]
+    pub thread: bool,
+}
+
+impl ProcessSample {
+    /// Kernel threads are kthreadd (PID 2) and its children. They have no command line, which
+    /// tells them apart from a user process that kthreadd somehow ended up parenting.
+    pub fn is_kernel_thread(&self) -> bool {
+        self.pid == 2 || (self.parent_pid == Some(2) && self.cmd.is_empty())
+    }
+}
+
+/// Samples every process on the live system. Environments are only read when asked for, as
+/// that is one more /proc read per process.
+pub fn collect(system: &System, with_environ: bool) -> Vec<ProcessSample> {
+    system
+        .processes()
+        .values()
+        .map(|process| {
+            let pid = process.pid().as_u32();
+            ProcessSample {
+                pid,
+                parent_pid: process.parent().map(|p| p.as_u32()),
+                name: process.name().to_string(),
+                cmd: process.cmd().to_vec(),
+                cwd: process.cwd().map(Path::to_path_buf),
+                memory_bytes: process.memory(),
+                cpu_percent: process.cpu_usage(),
+                environ: if with_environ { read_environ(pid) } else { HashMap::new() },
+                start_time: process.start_time(),
+                io_bytes: {
+                    let io = process.disk_usage();
+                    io.total_read_bytes + io.total_written_bytes
+                },
+                // The task list includes the main thread; without one, count just that
+                threads: process.tasks().map(|tasks| tasks.len()).unwrap_or(0).max(1),
+                tty: tty::process_tty(pid),
+                app: electron::detect(system, pid).map(|app| app.name),
+                sandbox: sandbox::detect(pid),
+                thread: process.thread_kind() == Some(ThreadKind::Userland),
+            }
+        })
+        .collect()
+}
+
+fn read_environ(pid: u32) -> HashMap<String, String> {
+    procfs::process::Process::new(pid as i32)
+        .and_then(|p| p.environ())
+        .map(|environ| {
+            environ
+                .into_iter()
+                .map(|(key, value)| (key.to_string_lossy().into_owned(), value.to_string_lossy().into_owned()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Reads every `<pid>.json` in the fixture directory, ordered by PID.
+pub fn load(dir: &Path) -> Result<Vec<ProcessSample>> {
+    let entries = fs::read_dir(dir).with_context(|| format!("Failed to read fixture {}", dir.display()))?;
+
+    let mut samples = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        if path.extension().is_none_or(|ext| ext != "json") {
+            continue;
+        }
+        let content = fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let sample: ProcessSample =
+            serde_json::from_str(&content).with_context(|| format!("Invalid fixture file {}", path.display()))?;
+        samples.push(sample);
+    }
+
+    if samples.is_empty() {
+        return Err(anyhow::anyhow!("Fixture {} contains no process files", dir.display()));
+    }
+    samples.sort_by_key(|sample| sample.pid);
+    Ok(samples)
+}
+
+/// Writes the samples as a fixture directory, creating it if needed. Secrets in command lines
+/// and environments are redacted unless redaction is off.
+pub fn save(dir: &Path, samples: &[ProcessSample]) -> Result<()> {
+    fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    for sample in samples {
+        let sample = ProcessSample {
+            cmd: redact::command(&sample.cmd),
+            environ: sample.environ.iter().map(|(key, value)| (key.clone(), redact::env_value(key, value))).collect(),
+            ..sample.clone()
+        };
+        let path = dir.join(format!("{}.json", sample.pid));
+        fs::write(&path, serde_json::to_string_pretty(&sample)? + "\n")
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+    }
+    Ok(())
+}
+
+/// `save-fixture`: records the live process table, with CPU usage measured over a short interval.
+pub async fn save_fixture(dir: &Path) -> Result<()> {
+    let mut system = System::new_all();
+    system.refresh_all();
+    // A second sample is needed for CPU usage
+    tokio::time::sleep(sysinfo::MINIMUM_CPU_UPDATE_INTERVAL).await;
+    system.refresh_processes();
+
+    let samples = collect(&system, true);
+    save(dir, &samples)?;
+    println!("Saved {} processes to {}", samples.len(), dir.display());
+    Ok(())
+}
@@ -0,0 +1,181 @@
+//! Window focus history, for `switch` to prefer the window of a process that was used last and
+//! for `switch --cycle` to step through a process's windows.
+//!
+//! `focus-history --watch` polls the focused window (`_NET_ACTIVE_WINDOW` on X11, the compositor
+//! on Hyprland and Sway) and appends each change to a log; `switch` appends the windows it
+//! activates itself, so the history builds up even without the watcher.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tabled::{Table, Tabled, settings::{Width, location::ByColumnName}};
+
+use crate::output::format_age;
+use crate::paths;
+use crate::window::{self, Backend, WindowInfo};
+
+/// Once the log grows past this many entries it is cut down to the newer half.
+const MAX_ENTRIES: usize = 2000;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct FocusRecord {
+    /// Milliseconds since the epoch
+    timestamp: u64,
+    window: String,
+    pid: u32,
+    class: String,
+    title: String,
+}
+
+#[derive(Tabled)]
+struct FocusRow {
+    #[tabled(rename = "When")]
+    when: String,
+
+    #[tabled(rename = "PID")]
+    pid: u32,
+
+    #[tabled(rename = "Class")]
+    class: String,
+
+    #[tabled(rename = "Title")]
+    title: String,
+}
+
+fn log_path() -> Result<PathBuf> {
+    Ok(paths::state_dir()?.join("focus-history.jsonl"))
+}
+
+/// Identifies a window across backends and ID spellings: X11 windows by their numeric ID,
+/// whichever tool reported it.
+fn key(window: &WindowInfo) -> String {
+    match window.backend {
+        Backend::Hyprland => format!("hyprland:{}", window.window_id),
+        Backend::Sway => format!("sway:{}", window.window_id),
+        Backend::Xdotool | Backend::Wmctrl => match window::x11_window_id(&window.window_id) {
+            Some(id) => format!("x11:{}", id),
+            None => format!("x11:{}", window.window_id),
+        },
+    }
+}
+
+/// Appends `window` to the focus history.
+pub fn record(window: &WindowInfo) -> Result<()> {
+    let log = log_path()?;
+    let record = FocusRecord {
+        timestamp: now_ms(),
+        window: key(window),
+        pid: window.pid,
+        class: window.class.clone(),
+        title: window.title.clone(),
+    };
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&log)
+        .with_context(|| format!("Failed to open {}", log.display()))?;
+    writeln!(file, "{}", serde_json::to_string(&record)?)?;
+    drop(file);
+
+    let records = read_records()?;
+    if records.len() > MAX_ENTRIES {
+        let kept: Vec<String> = records[records.len() - MAX_ENTRIES / 2..]
+            .iter()
+            .filter_map(|record| serde_json::to_string(record).ok())
+            .collect();
+        fs::write(&log, kept.join("\n") + "\n").with_context(|| format!("Failed to write {}", log.display()))?;
+    }
+    Ok(())
+}
+
+fn read_records() -> Result<Vec<FocusRecord>> {
+    let log = log_path()?;
+    let content = match fs::read_to_string(&log) {
+        Ok(content) => content,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(err) => return Err(anyhow::Error::new(err).context(format!("Failed to read {}", log.display()))),
+    };
+    Ok(content.lines().filter_map(|line| serde_json::from_str(line).ok()).collect())
+}
+
+/// When each of `windows` last had focus, in milliseconds since the epoch, by index into
+/// `windows`; windows never seen focused are missing.
+pub fn last_focused(windows: &[WindowInfo]) -> HashMap<usize, u64> {
+    let Ok(records) = read_records() else {
+        return HashMap::new();
+    };
+    let mut last: HashMap<&str, u64> = HashMap::new();
+    for record in &records {
+        let entry = last.entry(record.window.as_str()).or_default();
+        *entry = (*entry).max(record.timestamp);
+    }
+    windows
+        .iter()
+        .enumerate()
+        .filter_map(|(i, window)| Some((i, *last.get(key(window).as_str())?)))
+        .collect()
+}
+
+/// When the oldest entry still in the history was recorded, in milliseconds since the epoch.
+pub fn history_start() -> Option<u64> {
+    read_records().ok()?.first().map(|record| record.timestamp)
+}
+
+/// Whether `a` and `b` are the same window, however their IDs were spelled.
+pub fn same_window(a: &WindowInfo, b: &WindowInfo) -> bool {
+    key(a) == key(b)
+}
+
+/// Polls the focused window and records every change until interrupted.
+pub async fn watch_focus(interval: Duration, display: Option<&str>) -> Result<()> {
+    let log = log_path()?;
+    println!("Recording window focus (logging to {})...", log.display());
+
+    let mut last: Option<String> = None;
+    loop {
+        // A failed poll (the compositor restarting, say) is retried on the next tick
+        if let Ok(Some(window)) = window::focused_window(display)
+            && last.as_deref() != Some(key(&window).as_str())
+        {
+            println!("{} (PID: {}): {}", window.class, window.pid, window.title);
+            record(&window)?;
+            last = Some(key(&window));
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
+
+/// Shows the most recent focus changes, newest first.
+pub fn show_focus_history(limit: usize) -> Result<()> {
+    let records = read_records()?;
+    if records.is_empty() {
+        println!("No focus history recorded yet. Run `top-helper focus-history --watch` to start recording.");
+        return Ok(());
+    }
+
+    let now = now_ms();
+    let rows: Vec<FocusRow> = records
+        .iter()
+        .rev()
+        .take(limit)
+        .map(|record| FocusRow {
+            when: format_age(now.saturating_sub(record.timestamp) / 1000),
+            pid: record.pid,
+            class: record.class.clone(),
+            title: record.title.clone(),
+        })
+        .collect();
+
+    let mut table = Table::new(rows);
+    table.modify(ByColumnName::new("Title"), Width::truncate(60).suffix("..."));
+    println!("{}", table);
+    Ok(())
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0)
+}
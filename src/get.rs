@@ -0,0 +1,109 @@
+//! `get`: one raw field of a process, printed without formatting for shell scripts.
+
+use anyhow::Result;
+use clap::ValueEnum;
+use std::time::{SystemTime, UNIX_EPOCH};
+use sysinfo::System;
+
+use crate::error::CliError;
+use crate::redact;
+use crate::selector::Selection;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+#[value(rename_all = "snake_case")]
+pub enum Field {
+    Pid,
+    Ppid,
+    Name,
+    Exe,
+    Cwd,
+    /// Command line, with secrets redacted
+    Cmd,
+    Uid,
+    /// Run state, e.g. Sleeping or Running
+    State,
+    /// Unix time the process started
+    StartTime,
+    RuntimeSecs,
+    /// Resident memory, summed over all matching processes
+    MemoryBytes,
+    /// Resident memory in whole MB, summed over all matching processes
+    MemoryMb,
+    /// Virtual memory, summed over all matching processes
+    VirtualBytes,
+    /// CPU usage in percent of one core, summed over all matching processes
+    Cpu,
+}
+
+impl Field {
+    /// Whether the field adds up over a multi-process application, rather than needing one process.
+    fn is_total(&self) -> bool {
+        matches!(self, Field::MemoryBytes | Field::MemoryMb | Field::VirtualBytes | Field::Cpu)
+    }
+}
+
+/// Prints `field` of the process `identifier` selects. Totals cover every matching process;
+/// other fields describe the single process the selector resolves to.
+pub async fn get_field(identifier: &str, field: Field) -> Result<()> {
+    let selection = Selection::parse(identifier)?;
+    let mut system = System::new_all();
+    system.refresh_all();
+    if field == Field::Cpu {
+        // CPU usage is measured between two refreshes
+        tokio::time::sleep(sysinfo::MINIMUM_CPU_UPDATE_INTERVAL).await;
+        system.refresh_processes();
+    }
+
+    let value = if field.is_total() {
+        total(field, &selection, &system)?
+    } else {
+        single(field, selection.resolve_unique(&system)?)?
+    };
+    println!("{}", value);
+    Ok(())
+}
+
+fn total(field: Field, selection: &Selection, system: &System) -> Result<String> {
+    let processes: Vec<&sysinfo::Process> = selection
+        .resolve(system)
+        .iter()
+        .filter_map(|pid| system.process(sysinfo::Pid::from(*pid as usize)))
+        .collect();
+    if processes.is_empty() {
+        return Err(CliError::not_found("Process not found"));
+    }
+    let memory: u64 = processes.iter().map(|p| p.memory()).sum();
+    Ok(match field {
+        Field::MemoryMb => (memory / 1024 / 1024).to_string(),
+        Field::VirtualBytes => processes.iter().map(|p| p.virtual_memory()).sum::<u64>().to_string(),
+        Field::Cpu => format!("{:.1}", processes.iter().map(|p| p.cpu_usage()).sum::<f32>()),
+        _ => memory.to_string(),
+    })
+}
+
+fn single(field: Field, process: &sysinfo::Process) -> Result<String> {
+    let unreadable = || CliError::permission(format!("{} of PID {} is not readable", field_name(field), process.pid()));
+    let path = |path: Option<&std::path::Path>| path.map(|path| path.display().to_string()).ok_or_else(unreadable);
+    Ok(match field {
+        Field::Ppid => process
+            .parent()
+            .map(|parent| parent.to_string())
+            .ok_or_else(|| CliError::not_found(format!("PID {} has no parent", process.pid())))?,
+        Field::Name => process.name().to_string(),
+        Field::Exe => path(process.exe())?,
+        Field::Cwd => path(process.cwd())?,
+        Field::Cmd => redact::command(process.cmd()).join(" "),
+        Field::Uid => process.user_id().map(|uid| uid.to_string()).ok_or_else(unreadable)?,
+        Field::State => process.status().to_string(),
+        Field::StartTime => process.start_time().to_string(),
+        Field::RuntimeSecs => {
+            let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+            now.saturating_sub(process.start_time()).to_string()
+        }
+        _ => process.pid().to_string(),
+    })
+}
+
+fn field_name(field: Field) -> String {
+    field.to_possible_value().map(|value| value.get_name().to_string()).unwrap_or_default()
+}
@@ -0,0 +1,294 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::process::Command;
+use std::time::Duration;
+
+/// How long engine busy time is sampled to tell what a GPU client is doing now.
+pub const SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GpuProcessType {
+    Graphics,
+    Compute,
+    Video,
+    Idle,
+}
+
+impl fmt::Display for GpuProcessType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            GpuProcessType::Graphics => "graphics",
+            GpuProcessType::Compute => "compute",
+            GpuProcessType::Video => "video",
+            GpuProcessType::Idle => "idle",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GpuUsage {
+    pub kind: GpuProcessType,
+    pub driver: String,
+    pub memory_bytes: Option<u64>,
+}
+
+impl fmt::Display for GpuUsage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.memory_bytes {
+            Some(bytes) => write!(f, "{} ({}, {:.0} MB)", self.kind, self.driver, bytes as f64 / 1024.0 / 1024.0),
+            None => write!(f, "{} ({})", self.kind, self.driver),
+        }
+    }
+}
+
+/// Busy time per engine class, accumulated over all DRM clients a process holds.
+#[derive(Default)]
+struct EngineTotals {
+    driver: String,
+    render_ns: u64,
+    compute_ns: u64,
+    video_ns: u64,
+    resident_bytes: u64,
+    memory_bytes: u64,
+}
+
+impl EngineTotals {
+    /// The engine class that was busiest since `before`, or idle when none ran. Totals since the
+    /// client opened the GPU would keep a process that rendered a splash screen classed as
+    /// graphics however long it computes afterwards.
+    fn classify_since(&self, before: &EngineTotals) -> GpuProcessType {
+        let busiest = [
+            (self.render_ns.saturating_sub(before.render_ns), GpuProcessType::Graphics),
+            (self.compute_ns.saturating_sub(before.compute_ns), GpuProcessType::Compute),
+            (self.video_ns.saturating_sub(before.video_ns), GpuProcessType::Video),
+        ]
+        .into_iter()
+        .max_by_key(|(ns, _)| *ns);
+
+        match busiest {
+            Some((ns, kind)) if ns > 0 => kind,
+            _ => GpuProcessType::Idle,
+        }
+    }
+
+    /// Older drivers only report `drm-memory-*`; newer ones report `drm-resident-*` as well.
+    fn memory(&self) -> Option<u64> {
+        Some(if self.resident_bytes > 0 { self.resident_bytes } else { self.memory_bytes }).filter(|b| *b > 0)
+    }
+}
+
+/// GPU usage of `pids` and of every process on an NVIDIA GPU, keyed by PID. DRM clients are
+/// classified by their engine usage over [`SAMPLE_INTERVAL`], which is only waited for when one
+/// of `pids` holds a client.
+pub async fn measure(pids: &[u32]) -> HashMap<u32, GpuUsage> {
+    let nvidia = tokio::task::spawn_blocking(nvidia_apps);
+    let before: HashMap<u32, EngineTotals> =
+        pids.iter().filter_map(|&pid| Some((pid, read_drm_fdinfo(pid)?))).collect();
+    if !before.is_empty() {
+        tokio::time::sleep(SAMPLE_INTERVAL).await;
+    }
+
+    let mut usage = nvidia.await.unwrap_or_default();
+    for (pid, before) in before {
+        let Some(after) = read_drm_fdinfo(pid) else {
+            continue;
+        };
+        usage.insert(
+            pid,
+            GpuUsage {
+                kind: after.classify_since(&before),
+                driver: after.driver.clone(),
+                memory_bytes: after.memory(),
+            },
+        );
+    }
+
+    usage
+}
+
+/// Total busy time across all GPU engines for a process, for sampling activity over an interval.
+pub fn gpu_busy_ns(pid: u32) -> Option<u64> {
+    read_drm_fdinfo(pid).map(|totals| totals.render_ns + totals.compute_ns + totals.video_ns)
+}
+
+/// Reads the DRM client statistics the kernel exposes in /proc/<pid>/fdinfo (amdgpu, i915, xe, msm, ...).
+fn read_drm_fdinfo(pid: u32) -> Option<EngineTotals> {
+    let entries = fs::read_dir(format!("/proc/{}/fdinfo", pid)).ok()?;
+
+    let mut totals = EngineTotals::default();
+    let mut seen_clients = HashSet::new();
+
+    for entry in entries.flatten() {
+        let Ok(content) = fs::read_to_string(entry.path()) else {
+            continue;
+        };
+        if !content.contains("drm-driver:") {
+            continue;
+        }
+
+        // The same client is visible through every duplicated fd, count it once
+        let client_id = field(&content, "drm-client-id").unwrap_or_default().to_string();
+        if !seen_clients.insert(client_id) {
+            continue;
+        }
+
+        if let Some(driver) = field(&content, "drm-driver") {
+            totals.driver = driver.to_string();
+        }
+
+        for line in content.lines() {
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let amount = parse_amount(value);
+
+            if let Some(engine) = key.strip_prefix("drm-engine-") {
+                match engine_class(engine) {
+                    Some(GpuProcessType::Graphics) => totals.render_ns += amount,
+                    Some(GpuProcessType::Compute) => totals.compute_ns += amount,
+                    Some(GpuProcessType::Video) => totals.video_ns += amount,
+                    _ => {}
+                }
+            } else if key.starts_with("drm-resident-") {
+                totals.resident_bytes += amount;
+            } else if key.starts_with("drm-memory-") {
+                totals.memory_bytes += amount;
+            }
+        }
+    }
+
+    if seen_clients.is_empty() {
+        None
+    } else {
+        Some(totals)
+    }
+}
+
+fn engine_class(engine: &str) -> Option<GpuProcessType> {
+    match engine {
+        "render" | "gfx" | "rcs" | "gpu" => Some(GpuProcessType::Graphics),
+        "compute" | "ccs" => Some(GpuProcessType::Compute),
+        "vcs" | "vecs" | "jpeg" => Some(GpuProcessType::Video),
+        e if e.starts_with("video") || e.starts_with("dec") || e.starts_with("enc") || e.starts_with("vcn") => {
+            Some(GpuProcessType::Video)
+        }
+        _ => None,
+    }
+}
+
+fn field<'a>(content: &'a str, key: &str) -> Option<&'a str> {
+    content
+        .lines()
+        .find_map(|line| line.strip_prefix(key)?.strip_prefix(':'))
+        .map(str::trim)
+}
+
+/// Parses values like "123456 ns", "2048 KiB", or "4 MiB" into a plain number (ns or bytes).
+fn parse_amount(value: &str) -> u64 {
+    let mut parts = value.split_whitespace();
+    let number: u64 = parts.next().and_then(|n| n.parse().ok()).unwrap_or(0);
+    match parts.next() {
+        Some("KiB") => number * 1024,
+        Some("MiB") => number * 1024 * 1024,
+        Some("GiB") => number * 1024 * 1024 * 1024,
+        _ => number,
+    }
+}
+
+/// Processes on an NVIDIA GPU, which the proprietary driver reports through `nvidia-smi` rather
+/// than DRM fdinfo. `pmon` covers graphics as well as compute contexts; GPUs without process
+/// monitoring fall back to the compute processes alone.
+fn nvidia_apps() -> HashMap<u32, GpuUsage> {
+    nvidia_pmon().unwrap_or_else(nvidia_compute_apps)
+}
+
+/// One sample of `nvidia-smi pmon`, each process classified by what was busiest in it: the video
+/// encoder or decoder, or else the SMs, counted as compute or graphics by the context's type
+/// (`C`, `G`, or `C+G`).
+fn nvidia_pmon() -> Option<HashMap<u32, GpuUsage>> {
+    let output = Command::new("nvidia-smi").args(["pmon", "-c", "1", "-s", "um"]).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut lines = stdout.lines();
+    // The first line names the columns, which vary between driver versions
+    let header: Vec<&str> = lines.next()?.strip_prefix('#')?.split_whitespace().collect();
+    let column = |name: &str| header.iter().position(|column| *column == name);
+    let (pid_column, type_column) = (column("pid")?, column("type")?);
+    let (sm_column, enc_column, dec_column, fb_column) = (column("sm"), column("enc"), column("dec"), column("fb"));
+
+    let mut apps: HashMap<u32, GpuUsage> = HashMap::new();
+    for line in lines.filter(|line| !line.starts_with('#')) {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        // GPUs without processes get a row of dashes
+        let Some(pid) = fields.get(pid_column).and_then(|pid| pid.parse::<u32>().ok()) else {
+            continue;
+        };
+        let number = |column: Option<usize>| column.and_then(|i| fields.get(i)).and_then(|value| value.parse::<u64>().ok());
+        let sm = number(sm_column).unwrap_or(0);
+        let video = number(enc_column).unwrap_or(0).max(number(dec_column).unwrap_or(0));
+        let kind = if sm == 0 && video == 0 {
+            GpuProcessType::Idle
+        } else if video > sm {
+            GpuProcessType::Video
+        } else if fields.get(type_column).is_some_and(|kind| kind.starts_with('C')) {
+            GpuProcessType::Compute
+        } else {
+            GpuProcessType::Graphics
+        };
+        let memory_bytes = number(fb_column).map(|mib| mib * 1024 * 1024);
+
+        // A process on several GPUs has a row for each
+        let usage = apps.entry(pid).or_insert(GpuUsage {
+            kind: GpuProcessType::Idle,
+            driver: "nvidia".to_string(),
+            memory_bytes: None,
+        });
+        if usage.kind == GpuProcessType::Idle {
+            usage.kind = kind;
+        }
+        if let Some(bytes) = memory_bytes {
+            *usage.memory_bytes.get_or_insert(0) += bytes;
+        }
+    }
+
+    Some(apps)
+}
+
+/// Compute processes reported by the NVIDIA driver, for GPUs that `pmon` does not support.
+fn nvidia_compute_apps() -> HashMap<u32, GpuUsage> {
+    let mut apps = HashMap::new();
+
+    let Ok(output) = Command::new("nvidia-smi")
+        .args(["--query-compute-apps=pid,used_memory", "--format=csv,noheader,nounits"])
+        .output()
+    else {
+        return apps;
+    };
+
+    if !output.status.success() {
+        return apps;
+    }
+
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let mut parts = line.split(',').map(str::trim);
+        if let (Some(pid), Some(memory_mib)) = (parts.next(), parts.next())
+            && let Ok(pid) = pid.parse::<u32>()
+        {
+            apps.insert(
+                pid,
+                GpuUsage {
+                    kind: GpuProcessType::Compute,
+                    driver: "nvidia".to_string(),
+                    memory_bytes: memory_mib.parse::<u64>().ok().map(|mib| mib * 1024 * 1024),
+                },
+            );
+        }
+    }
+
+    apps
+}
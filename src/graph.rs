@@ -0,0 +1,238 @@
+//! A best-effort "talks to" graph between local processes, built from established
+//! localhost TCP connections and connected Unix sockets.
+//!
+//! Each socket is attributed to the process holding it open (from /proc/<pid>/fd). The side of a
+//! connection that owns the listening port or the bound socket path is taken to be the server.
+
+use anyhow::Result;
+use procfs::net::TcpState;
+use procfs::process::FDTarget;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::net::{IpAddr, SocketAddr};
+use std::process::Command;
+use sysinfo::System;
+use tabled::{Table, Tabled, settings::{Width, location::ByColumnName}};
+
+use crate::output::{Diagram, DiagramFormat};
+
+pub struct TalkGraph {
+    pub names: HashMap<u32, String>,
+    /// (client PID, server PID) -> the ports and socket paths they talk over
+    pub edges: BTreeMap<(u32, u32), BTreeSet<String>>,
+    /// Connections with an endpoint whose owner could not be seen (usually another user's process)
+    pub unattributed: usize,
+    pub unix_available: bool,
+}
+
+#[derive(Tabled)]
+struct EdgeRow {
+    #[tabled(rename = "Client")]
+    client: String,
+
+    #[tabled(rename = "Client PID")]
+    client_pid: u32,
+
+    #[tabled(rename = "Server")]
+    server: String,
+
+    #[tabled(rename = "Server PID")]
+    server_pid: u32,
+
+    #[tabled(rename = "Via")]
+    via: String,
+}
+
+pub fn show_graph(diagram: Option<DiagramFormat>) -> Result<()> {
+    let graph = build_graph()?;
+
+    if let Some(format) = diagram {
+        print!("{}", graph.to_diagram().render(format, "talks_to", true));
+        return Ok(());
+    }
+
+    if graph.edges.is_empty() {
+        println!("No connections between local processes found");
+    } else {
+        let mut rows: Vec<EdgeRow> = graph
+            .edges
+            .iter()
+            .map(|((client, server), via)| EdgeRow {
+                client: graph.name(*client),
+                client_pid: *client,
+                server: graph.name(*server),
+                server_pid: *server,
+                via: via.iter().cloned().collect::<Vec<_>>().join(", "),
+            })
+            .collect();
+        rows.sort_by(|a, b| (&a.server, a.server_pid, &a.client).cmp(&(&b.server, b.server_pid, &b.client)));
+
+        let mut table = Table::new(rows);
+        table.modify(ByColumnName::new("Via"), Width::truncate(60).suffix("..."));
+        println!("{}", table);
+    }
+
+    if !graph.unix_available {
+        println!("\nNote: `ss` (iproute2) was not found, so Unix socket connections are not shown");
+    }
+    if graph.unattributed > 0 {
+        println!(
+            "\nNote: {} connections involve processes that could not be inspected; run as root to include them",
+            graph.unattributed
+        );
+    }
+
+    Ok(())
+}
+
+impl TalkGraph {
+    pub fn name(&self, pid: u32) -> String {
+        self.names.get(&pid).cloned().unwrap_or_else(|| "?".to_string())
+    }
+
+    pub fn to_diagram(&self) -> Diagram {
+        let mut diagram = Diagram::default();
+
+        let nodes: BTreeSet<u32> = self.edges.keys().flat_map(|(client, server)| [*client, *server]).collect();
+        for pid in nodes {
+            diagram.node(format!("p{}", pid), vec![self.name(pid), pid.to_string()]);
+        }
+        for ((client, server), via) in &self.edges {
+            diagram.edge(format!("p{}", client), format!("p{}", server), via.iter().cloned().collect());
+        }
+
+        diagram
+    }
+}
+
+pub fn build_graph() -> Result<TalkGraph> {
+    let mut system = System::new_all();
+    system.refresh_all();
+
+    let owners = socket_owners();
+    let mut graph = TalkGraph {
+        names: system
+            .processes()
+            .iter()
+            .map(|(pid, process)| (pid.as_u32(), process.name().to_string()))
+            .collect(),
+        edges: BTreeMap::new(),
+        unattributed: 0,
+        unix_available: true,
+    };
+
+    let mut add_edge = |client_inode: u64, server_inode: u64, via: String| {
+        match (owners.get(&client_inode), owners.get(&server_inode)) {
+            (Some(client), Some(server)) if client != server => {
+                graph.edges.entry((*client, *server)).or_default().insert(via);
+            }
+            (Some(_), Some(_)) => {}
+            _ => graph.unattributed += 1,
+        }
+    };
+
+    for (client_inode, server_inode, port) in tcp_connections() {
+        add_edge(client_inode, server_inode, format!("tcp:{}", port));
+    }
+
+    match unix_connections() {
+        Some(connections) => {
+            for (client_inode, server_inode, path) in connections {
+                add_edge(client_inode, server_inode, format!("unix:{}", path));
+            }
+        }
+        None => graph.unix_available = false,
+    }
+
+    Ok(graph)
+}
+
+/// Socket inode -> the process holding it. A socket inherited across fork is held by several
+/// processes; the lowest PID (usually the parent that created it) is used.
+fn socket_owners() -> HashMap<u64, u32> {
+    let mut owners: HashMap<u64, u32> = HashMap::new();
+
+    if let Ok(all) = procfs::process::all_processes() {
+        for process in all.flatten() {
+            let Ok(fds) = process.fd() else {
+                continue;
+            };
+            for fd in fds.flatten() {
+                if let FDTarget::Socket(inode) = fd.target {
+                    let owner = owners.entry(inode).or_insert(process.pid as u32);
+                    *owner = (*owner).min(process.pid as u32);
+                }
+            }
+        }
+    }
+
+    owners
+}
+
+/// Established TCP connections with both ends on this machine, as (client inode, server inode, port).
+fn tcp_connections() -> Vec<(u64, u64, u16)> {
+    let mut entries = procfs::net::tcp().unwrap_or_default();
+    entries.extend(procfs::net::tcp6().unwrap_or_default());
+
+    let listening: HashSet<u16> = entries
+        .iter()
+        .filter(|e| e.state == TcpState::Listen)
+        .map(|e| e.local_address.port())
+        .collect();
+
+    let established: HashMap<(SocketAddr, SocketAddr), u64> = entries
+        .iter()
+        .filter(|e| e.state == TcpState::Established)
+        .map(|e| ((normalize(e.local_address), normalize(e.remote_address)), e.inode))
+        .collect();
+
+    let mut connections = Vec::new();
+    for ((local, remote), inode) in &established {
+        // Only the client end: its peer holds the listening port and its own port is ephemeral
+        if !listening.contains(&remote.port()) || listening.contains(&local.port()) {
+            continue;
+        }
+        if let Some(peer_inode) = established.get(&(*remote, *local)) {
+            connections.push((*inode, *peer_inode, remote.port()));
+        }
+    }
+
+    connections
+}
+
+/// Folds IPv4-mapped IPv6 addresses into IPv4 so both ends of a dual-stack connection match.
+fn normalize(address: SocketAddr) -> SocketAddr {
+    match address.ip() {
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(v4) => SocketAddr::new(IpAddr::V4(v4), address.port()),
+            None => address,
+        },
+        IpAddr::V4(_) => address,
+    }
+}
+
+/// Connected Unix stream sockets to a bound path, as (client inode, server inode, path).
+/// /proc/net/unix has no peer information, so this comes from `ss`, which asks the kernel
+/// over sock_diag.
+fn unix_connections() -> Option<Vec<(u64, u64, String)>> {
+    let output = Command::new("ss").args(["-x", "-n", "-H"]).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let connections = stdout
+        .lines()
+        .filter_map(|line| {
+            // Netid, State, Recv-Q, Send-Q, local address, local inode, peer address, peer inode
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() < 8 || fields[1] != "ESTAB" || fields[4] == "*" {
+                return None;
+            }
+            let server_inode = fields[5].parse().ok()?;
+            let client_inode = fields[7].parse().ok()?;
+            Some((client_inode, server_inode, fields[4].to_string()))
+        })
+        .collect();
+
+    Some(connections)
+}
@@ -0,0 +1,305 @@
+//! Long-term process history, sampled by `record` into a SQLite database.
+
+use anyhow::{Context, Result};
+use rusqlite::{Connection, params};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use sysinfo::System;
+
+use crate::error::CliError;
+use crate::paths;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS samples (
+        timestamp INTEGER NOT NULL,
+        pid INTEGER NOT NULL,
+        start_time INTEGER NOT NULL,
+        name TEXT NOT NULL,
+        memory_bytes INTEGER NOT NULL,
+        cpu_percent REAL NOT NULL,
+        cpu_time_ms INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS samples_timestamp ON samples (timestamp);
+    CREATE TABLE IF NOT EXISTS alerts (
+        timestamp INTEGER NOT NULL,
+        rule TEXT NOT NULL,
+        event TEXT NOT NULL,
+        message TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS alerts_timestamp ON alerts (timestamp);
+";
+
+/// How long a write waits for another writer, such as `record` and `monitor` both running in the
+/// daemon.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub fn default_path() -> Result<PathBuf> {
+    Ok(paths::data_dir()?.join("history.db"))
+}
+
+pub struct History {
+    conn: Connection,
+}
+
+/// Memory samples of one process instance over time.
+pub struct MemorySeries {
+    pub pid: u32,
+    pub name: String,
+    /// Seconds and resident memory in bytes
+    pub points: Vec<(i64, u64)>,
+}
+
+/// An alert that fired or resolved, as `monitor` recorded it.
+pub struct AlertRecord {
+    pub timestamp: i64,
+    pub rule: String,
+    /// "firing" or "resolved"
+    pub event: String,
+    pub message: String,
+}
+
+/// One recorded sample of one process.
+pub struct Sample {
+    pub timestamp: i64,
+    pub pid: u32,
+    pub name: String,
+    pub memory_bytes: u64,
+    pub cpu_percent: f32,
+}
+
+impl History {
+    pub fn open(path: &Path) -> Result<Self> {
+        let conn = Connection::open(path).with_context(|| format!("Failed to open history database {}", path.display()))?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        conn.execute_batch(SCHEMA).context("Failed to initialize history database")?;
+        Ok(History { conn })
+    }
+
+    /// Opens a database that `record` has already written, rather than creating an empty one.
+    pub fn open_existing(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Err(CliError::not_found(format!(
+                "No history recorded yet at {}; run `top-helper record` to start collecting it",
+                path.display()
+            )));
+        }
+        Self::open(path)
+    }
+
+    /// Stores one sample of every process. Returns the number of processes recorded.
+    pub fn record(&mut self, system: &System, timestamp: i64) -> Result<usize> {
+        let ticks_per_second = procfs::ticks_per_second().max(1);
+        let tx = self.conn.transaction()?;
+        let mut count = 0;
+
+        {
+            let mut insert = tx.prepare_cached(
+                "INSERT INTO samples (timestamp, pid, start_time, name, memory_bytes, cpu_percent, cpu_time_ms)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            )?;
+
+            for process in system.processes().values().filter(|p| p.thread_kind().is_none()) {
+                let pid = process.pid().as_u32();
+                let cpu_time_ms = procfs::process::Process::new(pid as i32)
+                    .and_then(|p| p.stat())
+                    .map(|stat| (stat.utime + stat.stime) * 1000 / ticks_per_second)
+                    .unwrap_or(0);
+
+                insert.execute(params![
+                    timestamp,
+                    pid,
+                    process.start_time() as i64,
+                    process.name(),
+                    process.memory() as i64,
+                    process.cpu_usage(),
+                    cpu_time_ms as i64,
+                ])?;
+                count += 1;
+            }
+        }
+
+        tx.commit()?;
+        Ok(count)
+    }
+
+    /// Stores an alert event for the daily report.
+    pub fn record_alert(&self, timestamp: i64, rule: &str, event: &str, message: &str) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO alerts (timestamp, rule, event, message) VALUES (?1, ?2, ?3, ?4)",
+            params![timestamp, rule, event, message],
+        )?;
+        Ok(())
+    }
+
+    /// Alert events in `[since, until]`, oldest first.
+    pub fn alerts(&self, since: i64, until: i64) -> Result<Vec<AlertRecord>> {
+        let mut statement = self.conn.prepare(
+            "SELECT timestamp, rule, event, message FROM alerts
+             WHERE timestamp BETWEEN ?1 AND ?2 ORDER BY timestamp",
+        )?;
+        let rows = statement.query_map(params![since, until], |row| {
+            Ok(AlertRecord { timestamp: row.get(0)?, rule: row.get(1)?, event: row.get(2)?, message: row.get(3)? })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Number of samples taken and the time of the first and last one in `[since, until]`.
+    pub fn coverage(&self, since: i64, until: i64) -> Result<(i64, Option<i64>, Option<i64>)> {
+        Ok(self.conn.query_row(
+            "SELECT COUNT(DISTINCT timestamp), MIN(timestamp), MAX(timestamp) FROM samples
+             WHERE timestamp BETWEEN ?1 AND ?2",
+            params![since, until],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )?)
+    }
+
+    pub fn has_samples_before(&self, timestamp: i64) -> Result<bool> {
+        Ok(self.conn.query_row(
+            "SELECT EXISTS (SELECT 1 FROM samples WHERE timestamp < ?1)",
+            params![timestamp],
+            |row| row.get(0),
+        )?)
+    }
+
+    /// Highest memory seen per process name.
+    pub fn peak_memory(&self, since: i64, until: i64, limit: usize) -> Result<Vec<(String, u64)>> {
+        self.name_totals(
+            "SELECT name, MAX(memory_bytes) AS value FROM samples
+             WHERE timestamp BETWEEN ?1 AND ?2
+             GROUP BY name ORDER BY value DESC LIMIT ?3",
+            since,
+            until,
+            limit,
+        )
+    }
+
+    /// CPU time used per process name, in milliseconds, summed over the process instances
+    /// seen during the period. Processes sampled only once contribute nothing. A process that
+    /// exec'd during the period counts entirely under its latest name, since its CPU time
+    /// carries over the exec.
+    pub fn cpu_time(&self, since: i64, until: i64, limit: usize) -> Result<Vec<(String, u64)>> {
+        self.name_totals(
+            "SELECT name, SUM(used) AS value FROM (
+                 SELECT latest.name AS name, MAX(cpu_time_ms) - MIN(cpu_time_ms) AS used FROM samples
+                 JOIN (
+                     SELECT pid, start_time, name, MAX(timestamp) FROM samples
+                     WHERE timestamp BETWEEN ?1 AND ?2
+                     GROUP BY pid, start_time
+                 ) AS latest USING (pid, start_time)
+                 WHERE timestamp BETWEEN ?1 AND ?2
+                 GROUP BY pid, start_time
+             )
+             GROUP BY name HAVING value > 0 ORDER BY value DESC LIMIT ?3",
+            since,
+            until,
+            limit,
+        )
+    }
+
+    /// Process names first seen during the period, with the time they first appeared.
+    pub fn new_processes(&self, since: i64, until: i64, limit: usize) -> Result<Vec<(String, u64)>> {
+        self.name_totals(
+            "SELECT name, MIN(timestamp) AS value FROM samples
+             WHERE timestamp BETWEEN ?1 AND ?2
+               AND name NOT IN (SELECT DISTINCT name FROM samples WHERE timestamp < ?1)
+             GROUP BY name ORDER BY value LIMIT ?3",
+            since,
+            until,
+            limit,
+        )
+    }
+
+    /// Samples taken at or after `since` that satisfy `condition`, an SQL expression over the
+    /// `samples` columns with `?` placeholders for `values`. Newest first.
+    pub fn samples(&self, condition: &str, values: &[rusqlite::types::Value], since: i64, limit: usize) -> Result<Vec<Sample>> {
+        let sql = format!(
+            "SELECT timestamp, pid, name, memory_bytes, cpu_percent FROM samples
+             WHERE timestamp >= ? AND ({})
+             ORDER BY timestamp DESC, memory_bytes DESC LIMIT ?",
+            condition
+        );
+        let mut all_values = vec![since.into()];
+        all_values.extend_from_slice(values);
+        all_values.push((limit as i64).into());
+
+        let mut statement = self.conn.prepare(&sql)?;
+        let rows = statement.query_map(rusqlite::params_from_iter(all_values), |row| {
+            Ok(Sample {
+                timestamp: row.get(0)?,
+                pid: row.get(1)?,
+                name: row.get(2)?,
+                memory_bytes: row.get::<_, i64>(3)? as u64,
+                cpu_percent: row.get::<_, f64>(4)? as f32,
+            })
+        })?;
+
+        Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+    }
+
+    /// Memory of every process instance sampled at or after `since`, oldest sample first, named
+    /// after its latest sample in case it exec'd.
+    pub fn memory_series(&self, since: i64) -> Result<Vec<MemorySeries>> {
+        let mut statement = self.conn.prepare(
+            "SELECT pid, start_time, name, timestamp, memory_bytes FROM samples
+             WHERE timestamp >= ?1
+             ORDER BY pid, start_time, timestamp",
+        )?;
+        let mut rows = statement.query(params![since])?;
+
+        let mut series: Vec<MemorySeries> = Vec::new();
+        let mut current = None;
+        while let Some(row) = rows.next()? {
+            let instance: (u32, i64) = (row.get(0)?, row.get(1)?);
+            if current != Some(instance) {
+                current = Some(instance);
+                series.push(MemorySeries { pid: instance.0, name: String::new(), points: Vec::new() });
+            }
+            if let Some(last) = series.last_mut() {
+                last.name = row.get(2)?;
+                last.points.push((row.get(3)?, row.get::<_, i64>(4)? as u64));
+            }
+        }
+        Ok(series)
+    }
+
+    fn name_totals(&self, sql: &str, since: i64, until: i64, limit: usize) -> Result<Vec<(String, u64)>> {
+        let mut statement = self.conn.prepare(sql)?;
+        let rows = statement.query_map(params![since, until, limit as i64], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? as u64))
+        })?;
+
+        Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+    }
+}
+
+/// Samples all processes into the history database at `db` (default [`default_path`]) every
+/// `interval`, or once with `once`.
+pub async fn record(db: Option<&Path>, interval: Duration, once: bool) -> Result<()> {
+    let path = match db {
+        Some(path) => path.to_path_buf(),
+        None => default_path()?,
+    };
+    if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent).with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    let mut history = History::open(&path)?;
+    let mut system = System::new_all();
+    system.refresh_all();
+
+    if once {
+        // CPU usage needs two refreshes to be meaningful
+        tokio::time::sleep(sysinfo::MINIMUM_CPU_UPDATE_INTERVAL).await;
+        system.refresh_processes();
+        let count = history.record(&system, chrono::Utc::now().timestamp())?;
+        println!("Recorded {} processes to {}", count, path.display());
+        return Ok(());
+    }
+
+    println!("Recording process samples every {}s to {}", interval.as_secs(), path.display());
+    let mut ticker = tokio::time::interval(interval);
+    loop {
+        ticker.tick().await;
+        system.refresh_processes();
+        history.record(&system, chrono::Utc::now().timestamp())?;
+    }
+}
@@ -0,0 +1,141 @@
+//! Which processes hold a file or directory: open descriptors, working directories, and
+//! (optionally) memory mappings, read from /proc like fuser and lsof.
+
+use anyhow::Result;
+use procfs::process::{FDTarget, MMapPath};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use sysinfo::System;
+use tabled::{Table, Tabled, settings::{Width, location::ByColumnName}};
+
+use crate::control::{self, Signal};
+use crate::error::CliError;
+use crate::selector;
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Access {
+    Cwd,
+    Fd(i32),
+    Mapped,
+}
+
+impl fmt::Display for Access {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Access::Cwd => write!(f, "cwd"),
+            Access::Fd(fd) => write!(f, "fd {}", fd),
+            Access::Mapped => write!(f, "mapped"),
+        }
+    }
+}
+
+#[derive(Tabled)]
+struct HolderRow {
+    #[tabled(rename = "PID")]
+    pid: u32,
+
+    #[tabled(rename = "Name")]
+    name: String,
+
+    #[tabled(rename = "Access")]
+    access: String,
+
+    #[tabled(rename = "Path")]
+    path: String,
+}
+
+/// Every process that has `path` (or, for a directory, anything inside it) open, as its
+/// working directory, or with `include_maps` mapped into memory. Keyed by PID.
+pub fn holders(path: &Path, include_maps: bool) -> BTreeMap<u32, Vec<(Access, PathBuf)>> {
+    let mut holders: BTreeMap<u32, Vec<(Access, PathBuf)>> = BTreeMap::new();
+    let Ok(all) = procfs::process::all_processes() else {
+        return holders;
+    };
+
+    for process in all.flatten() {
+        let pid = process.pid as u32;
+        let mut found = Vec::new();
+
+        if let Ok(cwd) = process.cwd()
+            && cwd.starts_with(path)
+        {
+            found.push((Access::Cwd, cwd));
+        }
+
+        if let Ok(fds) = process.fd() {
+            for fd in fds.flatten() {
+                if let FDTarget::Path(target) = fd.target
+                    && target.starts_with(path)
+                {
+                    found.push((Access::Fd(fd.fd), target));
+                }
+            }
+        }
+
+        if include_maps && let Ok(maps) = process.maps() {
+            for map in maps {
+                if let MMapPath::Path(target) = map.pathname
+                    && target.starts_with(path)
+                    && !found.iter().any(|(access, p)| *access == Access::Mapped && *p == target)
+                {
+                    found.push((Access::Mapped, target));
+                }
+            }
+        }
+
+        if !found.is_empty() {
+            found.sort();
+            holders.insert(pid, found);
+        }
+    }
+
+    holders
+}
+
+/// Lists the processes holding a path, optionally terminating them.
+pub fn show_holders(path: &str, include_maps: bool, kill: bool, yes: bool) -> Result<()> {
+    let resolved = selector::resolve_path(path);
+    if !resolved.exists() {
+        return Err(CliError::not_found(format!("No such file or directory: {}", path)));
+    }
+
+    let found = holders(&resolved, include_maps);
+    if found.is_empty() {
+        return Err(CliError::not_found(format!("No process holds {}", resolved.display())));
+    }
+
+    let mut system = System::new_all();
+    system.refresh_all();
+
+    if kill {
+        return control::signal_matched(&system, found.into_keys().collect(), Signal::Terminate, false, yes);
+    }
+
+    let rows: Vec<HolderRow> = found
+        .iter()
+        .flat_map(|(pid, accesses)| {
+            let name = system
+                .process(sysinfo::Pid::from(*pid as usize))
+                .map(|p| p.name().to_string())
+                .unwrap_or_default();
+            accesses.iter().map(move |(access, target)| HolderRow {
+                pid: *pid,
+                name: name.clone(),
+                access: access.to_string(),
+                path: target.display().to_string(),
+            })
+        })
+        .collect();
+
+    let mut table = Table::new(rows);
+    table.modify(ByColumnName::new("Path"), Width::truncate(70).suffix("..."));
+    println!("{}", table);
+    println!(
+        "\nSelect these processes elsewhere with file:{}, e.g. `top-helper info --all-matches file:{}`",
+        resolved.display(),
+        resolved.display()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,215 @@
+//! Commands run on process events, from the `[[hook]]` rules in the config file. `monitor`
+//! (and so the daemon) evaluates them alongside the alert rules.
+//!
+//! ```toml
+//! [[hook]]
+//! on = "new_process"        # "new_process", "process_exit", or "high_memory"
+//! process = "name:chrome"   # Only processes this selector matches; omit for all
+//! run = "logger \"chrome started as $TOP_HELPER_PID\""
+//!
+//! [[hook]]
+//! on = "high_memory"
+//! above = 3000              # MB
+//! run = "notify-send \"$TOP_HELPER_NAME uses $TOP_HELPER_MEM MB\""
+//! ```
+//!
+//! The process's values are passed to the command only in its environment, as `TOP_HELPER_PID`,
+//! `TOP_HELPER_NAME`, and so on, never spliced into the command itself.
+//!
+//! Events come from comparing the process table between evaluations, so a process that starts
+//! and exits between two of them goes unnoticed. `high_memory` fires when a process crosses the
+//! threshold, and only fires for it again once it has dropped back below.
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::process::Stdio;
+use sysinfo::System;
+use tokio::process::Command;
+
+use crate::identity::ProcessKey;
+use crate::plugins;
+use crate::selector::{ProcessIndex, Selection};
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HookRule {
+    pub on: HookEvent,
+    /// Process selector; without one the hook applies to every process
+    pub process: Option<String>,
+    /// Memory threshold in MB, for `high_memory`
+    pub above: Option<f64>,
+    /// Shell command, with `TOP_HELPER_PID`, `_PPID`, `_NAME`, `_MEM`, `_CPU`, `_CWD`, and `_EVENT`
+    /// in its environment
+    pub run: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HookEvent {
+    NewProcess,
+    ProcessExit,
+    HighMemory,
+}
+
+/// The names of the values a hook's command is given, as in [`Subject::values`].
+const VALUES: [&str; 7] = ["pid", "ppid", "name", "mem", "cpu", "cwd", "event"];
+
+impl HookEvent {
+    fn name(&self) -> &'static str {
+        match self {
+            HookEvent::NewProcess => "new_process",
+            HookEvent::ProcessExit => "process_exit",
+            HookEvent::HighMemory => "high_memory",
+        }
+    }
+}
+
+/// What a hook's command is told about the process, as of the evaluation that last saw it.
+#[derive(Debug, Clone)]
+struct Subject {
+    pid: u32,
+    ppid: Option<u32>,
+    name: String,
+    memory_mb: f64,
+    cpu_percent: f32,
+    cwd: Option<String>,
+}
+
+impl Subject {
+    fn of(process: &sysinfo::Process) -> Self {
+        Subject {
+            pid: process.pid().as_u32(),
+            ppid: process.parent().map(|pid| pid.as_u32()),
+            name: process.name().to_string(),
+            memory_mb: process.memory() as f64 / 1024.0 / 1024.0,
+            cpu_percent: process.cpu_usage(),
+            cwd: process.cwd().map(|cwd| cwd.display().to_string()),
+        }
+    }
+
+    fn values(&self, event: HookEvent) -> [(&'static str, String); 7] {
+        [
+            ("pid", self.pid.to_string()),
+            ("ppid", self.ppid.map(|ppid| ppid.to_string()).unwrap_or_default()),
+            ("name", self.name.clone()),
+            ("mem", format!("{:.0}", self.memory_mb)),
+            ("cpu", format!("{:.1}", self.cpu_percent)),
+            ("cwd", self.cwd.clone().unwrap_or_default()),
+            ("event", event.name().to_string()),
+        ]
+    }
+}
+
+struct Hook {
+    rule: HookRule,
+    selection: Option<Selection>,
+    /// Processes the rule matched at the previous evaluation, for `process_exit`
+    matched: HashMap<ProcessKey, Subject>,
+    /// Processes currently above the threshold, for `high_memory`
+    over: HashSet<ProcessKey>,
+}
+
+/// The configured hooks with what they saw at the previous evaluation.
+pub struct Hooks {
+    hooks: Vec<Hook>,
+    /// Every process at the previous evaluation, for `new_process`; None before the first
+    seen: Option<HashSet<ProcessKey>>,
+}
+
+impl Hooks {
+    /// Checks the rules: selectors must parse, `high_memory` needs a threshold, and commands must
+    /// not use the `{name}` placeholders, which are no longer filled in.
+    pub fn new(rules: Vec<HookRule>) -> Result<Self> {
+        let hooks = rules
+            .into_iter()
+            .map(|rule| {
+                if rule.on == HookEvent::HighMemory && rule.above.is_none() {
+                    return Err(anyhow::anyhow!("high_memory hook '{}' needs `above` (MB)", rule.run));
+                }
+                plugins::reject_placeholders(&rule.run, &VALUES).context("Invalid hook")?;
+                let selection = rule.process.as_deref().map(Selection::parse).transpose()?;
+                Ok(Hook { rule, selection, matched: HashMap::new(), over: HashSet::new() })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Hooks { hooks, seen: None })
+    }
+
+    pub fn len(&self) -> usize {
+        self.hooks.len()
+    }
+
+    /// Compares `system` with the previous evaluation and runs the hooks of the events found.
+    /// The first evaluation only records the baseline.
+    pub fn evaluate(&mut self, system: &System, index: &mut ProcessIndex) {
+        let processes: HashMap<ProcessKey, &sysinfo::Process> = system
+            .processes()
+            .values()
+            .filter(|process| process.thread_kind().is_none())
+            .map(|process| (ProcessKey::of(process), process))
+            .collect();
+
+        for hook in &mut self.hooks {
+            let pids: HashSet<u32> = match &hook.selection {
+                Some(selection) => selection.resolve_in(index).into_iter().collect(),
+                None => processes.keys().map(|key| key.pid).collect(),
+            };
+            let matched: HashMap<ProcessKey, Subject> = processes
+                .iter()
+                .filter(|(key, _)| pids.contains(&key.pid))
+                .map(|(key, process)| (*key, Subject::of(process)))
+                .collect();
+
+            match hook.rule.on {
+                HookEvent::NewProcess => {
+                    if let Some(seen) = &self.seen {
+                        for (key, subject) in &matched {
+                            if !seen.contains(key) {
+                                run_hook(&hook.rule, subject);
+                            }
+                        }
+                    }
+                }
+                HookEvent::ProcessExit => {
+                    for (key, subject) in &hook.matched {
+                        if !processes.contains_key(key) {
+                            run_hook(&hook.rule, subject);
+                        }
+                    }
+                }
+                HookEvent::HighMemory => {
+                    let above = hook.rule.above.unwrap_or(f64::INFINITY);
+                    let over: HashSet<ProcessKey> =
+                        matched.iter().filter(|(_, subject)| subject.memory_mb > above).map(|(key, _)| *key).collect();
+                    for key in over.difference(&hook.over) {
+                        run_hook(&hook.rule, &matched[key]);
+                    }
+                    hook.over = over;
+                }
+            }
+            hook.matched = matched;
+        }
+
+        self.seen = Some(processes.into_keys().collect());
+    }
+}
+
+/// Starts the hook's command without waiting for it, so a slow one doesn't hold up monitoring.
+fn run_hook(rule: &HookRule, subject: &Subject) {
+    let mut child = Command::new("sh");
+    child.args(["-c", &rule.run]).stdin(Stdio::null());
+    for (key, value) in &subject.values(rule.on) {
+        child.env(format!("TOP_HELPER_{}", key.to_uppercase()), value);
+    }
+
+    let event = rule.on.name();
+    let (pid, name) = (subject.pid, subject.name.clone());
+    tokio::spawn(async move {
+        let result = child.status().await.context("Failed to run the hook");
+        match result {
+            Ok(status) if status.success() => {}
+            Ok(status) => eprintln!("Warning: {} hook for {} ({}) exited with {}", event, name, pid, status),
+            Err(err) => eprintln!("Warning: {} hook for {} ({}): {:#}", event, name, pid, err),
+        }
+    });
+}
@@ -0,0 +1,128 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::env;
+use std::io::{Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+
+use crate::window::{Backend, Geometry, WindowInfo, WindowPlacement};
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HyprClient {
+    address: String,
+    pid: i64,
+    class: String,
+    title: String,
+    at: Option<[i32; 2]>,
+    size: Option<[u32; 2]>,
+    workspace: Option<HyprWorkspace>,
+    /// 0 for the focused window, then in order of how recently each was focused
+    #[serde(rename = "focusHistoryID")]
+    focus_history_id: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct HyprWorkspace {
+    name: String,
+}
+
+impl HyprClient {
+    fn into_window(self) -> WindowInfo {
+        WindowInfo {
+            window_id: self.address,
+            title: self.title,
+            class: self.class,
+            pid: self.pid as u32,
+            backend: Backend::Hyprland,
+            display: None,
+        }
+    }
+}
+
+pub fn is_running() -> bool {
+    env::var_os("HYPRLAND_INSTANCE_SIGNATURE").is_some()
+}
+
+/// Lists all client windows, equivalent to `hyprctl clients -j`.
+pub fn list_windows() -> Result<Vec<WindowInfo>> {
+    Ok(clients()?.into_iter().filter(|c| c.pid > 0).map(HyprClient::into_window).collect())
+}
+
+/// The window that has focus, if any does.
+pub fn focused_window() -> Result<Option<WindowInfo>> {
+    Ok(clients()?
+        .into_iter()
+        .find(|c| c.pid > 0 && c.focus_history_id == Some(0))
+        .map(HyprClient::into_window))
+}
+
+/// Workspace, position, and focus of the window at `address`.
+pub fn placement(address: &str) -> Result<WindowPlacement> {
+    let client = clients()?
+        .into_iter()
+        .find(|c| c.address == address)
+        .with_context(|| format!("Hyprland has no window {}", address))?;
+
+    Ok(WindowPlacement {
+        workspace: client.workspace.map(|workspace| workspace.name),
+        geometry: client.at.zip(client.size).map(|([x, y], [width, height])| Geometry { x, y, width, height }),
+        focused: client.focus_history_id.map(|id| id == 0),
+    })
+}
+
+fn clients() -> Result<Vec<HyprClient>> {
+    let response = request("j/clients")?;
+    serde_json::from_str(&response).context("Failed to parse Hyprland clients")
+}
+
+pub fn focus_window(address: &str) -> Result<()> {
+    let response = request(&format!("dispatch focuswindow address:{}", address))?;
+    if response.trim() != "ok" {
+        return Err(anyhow::anyhow!("Hyprland refused to focus window {}: {}", address, response.trim()));
+    }
+
+    Ok(())
+}
+
+/// Moves the window to the special workspace "minimized", as Hyprland has no minimized state.
+pub fn minimize_window(address: &str) -> Result<()> {
+    let response = request(&format!("dispatch movetoworkspacesilent special:minimized,address:{}", address))?;
+    if response.trim() != "ok" {
+        return Err(anyhow::anyhow!("Hyprland refused to minimize window {}: {}", address, response.trim()));
+    }
+
+    Ok(())
+}
+
+fn request(command: &str) -> Result<String> {
+    let path = socket_path()?;
+    let mut stream = UnixStream::connect(&path)
+        .with_context(|| format!("Failed to connect to Hyprland socket {}", path.display()))?;
+
+    stream
+        .write_all(command.as_bytes())
+        .context("Failed to send Hyprland request")?;
+
+    let mut response = String::new();
+    stream
+        .read_to_string(&mut response)
+        .context("Failed to read Hyprland response")?;
+
+    Ok(response)
+}
+
+fn socket_path() -> Result<PathBuf> {
+    let signature = env::var("HYPRLAND_INSTANCE_SIGNATURE")
+        .context("HYPRLAND_INSTANCE_SIGNATURE is not set")?;
+
+    // Hyprland >= 0.40 keeps its sockets under XDG_RUNTIME_DIR, older versions under /tmp
+    if let Some(runtime_dir) = env::var_os("XDG_RUNTIME_DIR") {
+        let path = PathBuf::from(runtime_dir).join("hypr").join(&signature).join(".socket.sock");
+        if path.exists() {
+            return Ok(path);
+        }
+    }
+
+    Ok(PathBuf::from("/tmp/hypr").join(&signature).join(".socket.sock"))
+}
@@ -0,0 +1,50 @@
+//! Process identity that survives exec(): the PID and start time stay the same when a process
+//! execs, while its name and command line change. A wrapper script that execs into the real
+//! program is therefore still the same process, not an exit followed by a start.
+
+use std::collections::HashSet;
+use sysinfo::System;
+
+/// Identifies one process instance; unlike a PID alone, it is never reused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ProcessKey {
+    pub pid: u32,
+    pub start_time: u64,
+}
+
+impl ProcessKey {
+    pub fn of(process: &sysinfo::Process) -> Self {
+        ProcessKey { pid: process.pid().as_u32(), start_time: process.start_time() }
+    }
+
+    /// The process this key refers to, if it is still running.
+    pub fn find<'a>(&self, system: &'a System) -> Option<&'a sysinfo::Process> {
+        system
+            .process(sysinfo::Pid::from(self.pid as usize))
+            .filter(|process| process.start_time() == self.start_time)
+    }
+}
+
+/// Processes that matched a selector at some point, followed for as long as they run even
+/// after an exec stops them from matching it.
+#[derive(Default)]
+pub struct Followed {
+    keys: HashSet<ProcessKey>,
+}
+
+impl Followed {
+    /// Adds the processes matching now and returns the PIDs of every followed process that is
+    /// still running.
+    pub fn update(&mut self, system: &System, matched: &[u32]) -> Vec<u32> {
+        self.keys.retain(|key| key.find(system).is_some());
+        for pid in matched {
+            if let Some(process) = system.process(sysinfo::Pid::from(*pid as usize)) {
+                self.keys.insert(ProcessKey::of(process));
+            }
+        }
+
+        let mut pids: Vec<u32> = self.keys.iter().map(|key| key.pid).collect();
+        pids.sort_unstable();
+        pids
+    }
+}
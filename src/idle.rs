@@ -0,0 +1,289 @@
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::os::unix::fs::MetadataExt;
+use std::time::{SystemTime, UNIX_EPOCH};
+use sysinfo::System;
+use tabled::{Table, Tabled, settings::{Width, location::ByColumnName}};
+
+use crate::control::{self, Signal};
+use crate::focus;
+use crate::output::{self, OutputFormat};
+use crate::tty;
+use crate::window::{self, WindowChoice, WindowInfo};
+
+/// Foreground jobs using more CPU than this are working, not abandoned, however quiet their terminal.
+const BUSY_CPU_PERCENT: f32 = 1.0;
+
+#[derive(Tabled)]
+struct IdleTerminal {
+    #[tabled(rename = "TTY")]
+    tty: String,
+
+    #[tabled(rename = "Terminal")]
+    location: String,
+
+    #[tabled(rename = "Shell PID")]
+    shell_pid: u32,
+
+    #[tabled(rename = "Idle")]
+    idle: String,
+
+    #[tabled(rename = "Running")]
+    running: String,
+
+    #[tabled(rename = "Working Dir")]
+    working_dir: String,
+}
+
+/// Lists terminals with no input or output for at least `hours` whose foreground is idle, and
+/// with `close` hangs them up, which is what closing the terminal window does.
+pub async fn show_idle_shells(hours: f64, close: bool, yes: bool) -> Result<()> {
+    let mut system = System::new_all();
+    system.refresh_all();
+    // A second sample is needed for CPU usage
+    tokio::time::sleep(sysinfo::MINIMUM_CPU_UPDATE_INTERVAL).await;
+    system.refresh_processes();
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() as i64;
+    let threshold = (hours * 3600.0) as i64;
+    let own = control::own_ancestry(&system);
+    let panes = tty::list_tmux_panes().unwrap_or_default();
+    let process = |pid: u32| system.process(sysinfo::Pid::from(pid as usize));
+
+    let mut idle: Vec<(i64, IdleTerminal)> = Vec::new();
+    for job in tty::foreground_jobs() {
+        if own.contains(&job.shell_pid) {
+            continue;
+        }
+        // The kernel stamps a terminal's atime on input and mtime on output
+        let Ok(metadata) = fs::metadata(format!("/dev/{}", job.tty)) else {
+            continue;
+        };
+        let idle_secs = now - metadata.atime().max(metadata.mtime());
+        if idle_secs < threshold {
+            continue;
+        }
+
+        let Some(shell) = process(job.shell_pid) else {
+            continue;
+        };
+        let foreground = job.foreground_pid.and_then(process);
+        if foreground.is_some_and(|p| p.cpu_usage() > BUSY_CPU_PERCENT) {
+            continue;
+        }
+        let active = foreground.unwrap_or(shell);
+
+        idle.push((
+            idle_secs,
+            IdleTerminal {
+                location: tty::terminal_location(&system, &job, &panes),
+                tty: job.tty,
+                shell_pid: job.shell_pid,
+                idle: output::format_duration(idle_secs as u64 * 1000),
+                running: match foreground {
+                    Some(p) if !p.cmd().is_empty() => p.cmd().join(" "),
+                    Some(p) => p.name().to_string(),
+                    None => "(prompt)".to_string(),
+                },
+                working_dir: active.cwd().map(|cwd| cwd.display().to_string()).unwrap_or_else(|| "N/A".to_string()),
+            },
+        ));
+    }
+
+    if idle.is_empty() {
+        println!("No terminals idle for {} hours or more", hours);
+        return Ok(());
+    }
+
+    // Longest idle first
+    idle.sort_by_key(|(secs, _)| std::cmp::Reverse(*secs));
+    let shells: Vec<u32> = idle.iter().map(|(_, terminal)| terminal.shell_pid).collect();
+
+    if close {
+        return control::signal_matched(&system, shells, Signal::Hangup, false, yes);
+    }
+
+    let mut table = Table::new(idle.into_iter().map(|(_, terminal)| terminal));
+    table
+        .modify(ByColumnName::new("Running"), Width::truncate(40).suffix("..."))
+        .modify(ByColumnName::new("Working Dir"), Width::truncate(30).suffix("..."));
+    println!("{}", table);
+    println!(
+        "\nJump to one with `top-helper switch pid:<Shell PID>`, or close them all with `top-helper idle-shells --hours {} --close`",
+        hours
+    );
+
+    Ok(())
+}
+
+#[derive(Tabled)]
+struct IdleAppRow {
+    #[tabled(rename = "#")]
+    number: usize,
+
+    #[tabled(rename = "PID")]
+    pid: u32,
+
+    #[tabled(rename = "App")]
+    name: String,
+
+    #[tabled(rename = "Windows")]
+    windows: usize,
+
+    #[tabled(rename = "Memory")]
+    memory: String,
+
+    #[tabled(rename = "Last Focused")]
+    last_focused: String,
+
+    #[tabled(rename = "Title")]
+    title: String,
+}
+
+/// A GUI application that has gone unused, with the windows it owns.
+struct IdleApp {
+    pid: u32,
+    name: String,
+    windows: Vec<WindowInfo>,
+    /// Its own memory and that of its descendants, such as a browser's renderers
+    memory_bytes: u64,
+    /// When one of its windows last had focus; None if never since the history began
+    last_focused: Option<u64>,
+}
+
+/// Lists applications none of whose windows have had focus for at least `hours` and that use at
+/// least `min_memory_mb` (with their descendants), biggest first. On a terminal, then offers to
+/// switch to, minimize, or terminate them one at a time.
+pub async fn show_idle_apps(hours: f64, min_memory_mb: u64, display: Option<&str>) -> Result<()> {
+    let Some(history_start) = focus::history_start() else {
+        println!(
+            "No focus history recorded yet. Run `top-helper daemon install` or `top-helper focus-history --watch` \
+             to start recording."
+        );
+        return Ok(());
+    };
+
+    let mut system = System::new_all();
+    system.refresh_all();
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64;
+    let threshold = (hours * 3_600_000.0) as u64;
+    if now.saturating_sub(history_start) < threshold {
+        println!(
+            "Focus history only goes back {}; it takes {} hours to tell what has been idle that long.",
+            output::format_duration(now.saturating_sub(history_start)),
+            hours
+        );
+        return Ok(());
+    }
+
+    let windows = window::list_all_windows(display)?;
+    let last_focused = focus::last_focused(&windows);
+    let focused = window::focused_window(display).ok().flatten();
+    let own = control::own_ancestry(&system);
+
+    let mut apps: BTreeMap<u32, IdleApp> = BTreeMap::new();
+    for (i, window) in windows.into_iter().enumerate() {
+        let Some(process) = system.process(sysinfo::Pid::from(window.pid as usize)) else {
+            continue;
+        };
+        let app = apps.entry(window.pid).or_insert_with(|| IdleApp {
+            pid: window.pid,
+            name: process.name().to_string(),
+            windows: Vec::new(),
+            memory_bytes: process.memory()
+                + control::descendants(&system, window.pid)
+                    .into_iter()
+                    .filter_map(|pid| system.process(sysinfo::Pid::from(pid as usize)))
+                    .map(|p| p.memory())
+                    .sum::<u64>(),
+            last_focused: None,
+        });
+        app.last_focused = app.last_focused.max(last_focused.get(&i).copied());
+        app.windows.push(window);
+    }
+
+    let mut idle: Vec<IdleApp> = apps
+        .into_values()
+        .filter(|app| !own.contains(&app.pid))
+        // Whatever is in front of the user right now is in use, whatever the history says
+        .filter(|app| {
+            !focused.as_ref().is_some_and(|focused| app.windows.iter().any(|w| focus::same_window(w, focused)))
+        })
+        .filter(|app| now.saturating_sub(app.last_focused.unwrap_or(history_start)) >= threshold)
+        .filter(|app| app.memory_bytes >= min_memory_mb * 1024 * 1024)
+        .collect();
+    if idle.is_empty() {
+        println!("No applications using {} MB or more have been idle for {} hours or more", min_memory_mb, hours);
+        return Ok(());
+    }
+    idle.sort_by_key(|app| std::cmp::Reverse(app.memory_bytes));
+
+    let rows = idle.iter().enumerate().map(|(i, app)| IdleAppRow {
+        number: i + 1,
+        pid: app.pid,
+        name: app.name.clone(),
+        windows: app.windows.len(),
+        memory: output::format_memory(app.memory_bytes),
+        last_focused: match app.last_focused {
+            Some(last) => format!("{} ago", output::format_duration(now.saturating_sub(last))),
+            None => format!("over {} ago", output::format_duration(now.saturating_sub(history_start))),
+        },
+        title: app.windows.first().map(|w| w.title.clone()).unwrap_or_default(),
+    });
+    let mut table = Table::new(rows);
+    table.modify(ByColumnName::new("Title"), Width::truncate(40).suffix("..."));
+    println!("{}", table);
+
+    if !output::can_prompt() {
+        println!("\nSwitch to one with `top-helper switch pid:<PID>`; run `idle` in a terminal to minimize or close");
+        return Ok(());
+    }
+    act_on_idle_apps(&system, &idle, display).await
+}
+
+/// Asks what to do with the listed apps until the user is done: `s2` switches to the second
+/// one, `m2` minimizes its windows, and `k2` terminates it.
+async fn act_on_idle_apps(system: &System, apps: &[IdleApp], display: Option<&str>) -> Result<()> {
+    loop {
+        print!("\n[s]witch, [m]inimize, or [k]ill by number (e.g. m1), or Enter to quit: ");
+        io::stdout().flush()?;
+        let mut answer = String::new();
+        if io::stdin().lock().read_line(&mut answer)? == 0 {
+            return Ok(());
+        }
+        let answer = answer.trim();
+        if answer.is_empty() || answer == "q" {
+            return Ok(());
+        }
+
+        let (action, number) = answer.split_at(1);
+        let Some(app) = number.trim().parse::<usize>().ok().and_then(|n| apps.get(n.wrapping_sub(1))) else {
+            println!("Expected an action and a number from 1 to {}, such as m1", apps.len());
+            continue;
+        };
+        let result = match action {
+            "s" => {
+                let selector = format!("pid:{}", app.pid);
+                let choice = WindowChoice::default();
+                let format = OutputFormat::Text;
+                window::switch_to_process_window(Some(&selector), None, false, format, display, &choice).await
+            }
+            "m" => app
+                .windows
+                .iter()
+                .try_for_each(|window| window.backend.minimize(window))
+                .map(|()| println!("Minimized {} window(s) of {} (PID: {})", app.windows.len(), app.name, app.pid)),
+            // Picking the app is the confirmation
+            "k" => control::signal_matched(system, vec![app.pid], Signal::Terminate, false, true),
+            _ => {
+                println!("Unknown action '{}'; use s, m, or k", action);
+                continue;
+            }
+        };
+        if let Err(err) = result {
+            eprintln!("Error: {:#}", err);
+        }
+    }
+}
@@ -0,0 +1,246 @@
+//! Diagnoses "my keyboard shortcuts stopped working" by finding active input grabs.
+//!
+//! Neither X11 nor evdev lets another client list grabs directly, so both checks probe:
+//! an X grab attempt that fails with `AlreadyGrabbed`, or an `EVIOCGRAB` that fails with
+//! `EBUSY`. A successful probe grab is released immediately.
+
+use anyhow::{Context, Result};
+use procfs::process::FDTarget;
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::os::fd::AsRawFd;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use sysinfo::System;
+use tabled::{Table, Tabled};
+use x11rb::CURRENT_TIME;
+use x11rb::connection::Connection;
+use x11rb::protocol::xproto::{ConnectionExt as _, EventMask, GrabMode, GrabStatus};
+
+use crate::window;
+
+/// `_IOW('E', 0x90, int)` from linux/input.h
+const EVIOCGRAB: libc::c_ulong = 0x4004_4590;
+
+#[derive(Tabled)]
+struct InputDeviceRow {
+    #[tabled(rename = "Device")]
+    device: String,
+
+    #[tabled(rename = "Name")]
+    name: String,
+
+    #[tabled(rename = "Grab")]
+    grab: String,
+
+    #[tabled(rename = "Opened By")]
+    holders: String,
+}
+
+enum EvdevGrab {
+    Grabbed,
+    Free,
+    Unknown(String),
+}
+
+pub fn show_input_grabs(display: Option<&str>) -> Result<()> {
+    let mut system = System::new_all();
+    system.refresh_all();
+    let describe = |pid: u32| {
+        system
+            .process(sysinfo::Pid::from(pid as usize))
+            .map(|p| format!("{} ({})", p.name(), pid))
+            .unwrap_or_else(|| pid.to_string())
+    };
+
+    let display = display.map(str::to_string).or_else(|| env::var("DISPLAY").ok());
+    if let Some(display) = &display {
+        println!("X11 display {}:", display);
+        match x11_grabs(display) {
+            Ok((keyboard, pointer, focus_pid)) => {
+                println!("  Keyboard: {}", keyboard);
+                println!("  Pointer:  {}", pointer);
+                if keyboard != "free" || pointer != "free" {
+                    // X does not say who holds a grab; the focused client is the usual suspect
+                    match focus_pid {
+                        Some(pid) => println!("  Focused client (likely grab holder): {}", describe(pid)),
+                        None => println!("  The grab holder could not be identified"),
+                    }
+                }
+            }
+            Err(err) => println!("  Could not query grabs: {:#}", err),
+        }
+        println!();
+    }
+
+    let devices = input_devices();
+    if devices.is_empty() {
+        println!("No evdev input devices found in /dev/input");
+        return Ok(());
+    }
+
+    let holders = device_holders(&devices);
+    let own_pid = std::process::id();
+    let mut rows = Vec::new();
+    let mut permission_denied = false;
+
+    for device in &devices {
+        let grab = match probe_evdev_grab(device) {
+            EvdevGrab::Grabbed => "grabbed".to_string(),
+            EvdevGrab::Free => "free".to_string(),
+            EvdevGrab::Unknown(reason) => {
+                permission_denied |= reason == "permission denied";
+                format!("unknown ({})", reason)
+            }
+        };
+
+        let opened_by: Vec<String> = holders
+            .get(device)
+            .into_iter()
+            .flatten()
+            .filter(|pid| **pid != own_pid)
+            .map(|pid| describe(*pid))
+            .collect();
+
+        rows.push(InputDeviceRow {
+            device: device.display().to_string(),
+            name: device_name(device).unwrap_or_default(),
+            grab,
+            holders: if opened_by.is_empty() { "-".to_string() } else { opened_by.join(", ") },
+        });
+    }
+
+    println!("{}", Table::new(rows));
+    if permission_denied {
+        println!("\nSome devices could not be probed; run as root or as a member of the 'input' group.");
+    }
+
+    Ok(())
+}
+
+/// Probes the keyboard and pointer grab state and returns it along with the PID of the focused client.
+fn x11_grabs(display: &str) -> Result<(String, String, Option<u32>)> {
+    let (conn, screen_num) = x11rb::connect(Some(display))
+        .with_context(|| format!("Failed to connect to X display {}", display))?;
+    let root = conn.setup().roots[screen_num].root;
+
+    let keyboard = conn
+        .grab_keyboard(false, root, CURRENT_TIME, GrabMode::ASYNC, GrabMode::ASYNC)?
+        .reply()?
+        .status;
+    if keyboard == GrabStatus::SUCCESS {
+        conn.ungrab_keyboard(CURRENT_TIME)?;
+    }
+
+    let pointer = conn
+        .grab_pointer(
+            false,
+            root,
+            EventMask::NO_EVENT,
+            GrabMode::ASYNC,
+            GrabMode::ASYNC,
+            x11rb::NONE,
+            x11rb::NONE,
+            CURRENT_TIME,
+        )?
+        .reply()?
+        .status;
+    if pointer == GrabStatus::SUCCESS {
+        conn.ungrab_pointer(CURRENT_TIME)?;
+    }
+    conn.flush()?;
+
+    let focus = conn.get_input_focus()?.reply()?.focus;
+    let focus_pid = window::x11_client_pid(&conn, focus);
+
+    Ok((grab_status_name(keyboard), grab_status_name(pointer), focus_pid))
+}
+
+fn grab_status_name(status: GrabStatus) -> String {
+    match status {
+        GrabStatus::SUCCESS => "free",
+        GrabStatus::ALREADY_GRABBED => "grabbed by another client",
+        GrabStatus::FROZEN => "frozen by another client's grab",
+        GrabStatus::NOT_VIEWABLE => "not viewable",
+        _ => "unknown",
+    }
+    .to_string()
+}
+
+fn input_devices() -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir("/dev/input") else {
+        return Vec::new();
+    };
+
+    let mut devices: Vec<PathBuf> = entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.file_name().is_some_and(|n| n.to_string_lossy().starts_with("event")))
+        .collect();
+    devices.sort_by_key(|p| {
+        p.file_name()
+            .and_then(|n| n.to_string_lossy().trim_start_matches("event").parse::<u32>().ok())
+            .unwrap_or(u32::MAX)
+    });
+    devices
+}
+
+fn device_name(device: &Path) -> Option<String> {
+    let event = device.file_name()?.to_string_lossy().to_string();
+    let name = fs::read_to_string(format!("/sys/class/input/{}/device/name", event)).ok()?;
+    Some(name.trim().to_string())
+}
+
+/// Processes holding each device open, from their /proc/<pid>/fd links.
+fn device_holders(devices: &[PathBuf]) -> HashMap<PathBuf, Vec<u32>> {
+    let mut holders: HashMap<PathBuf, Vec<u32>> = HashMap::new();
+
+    if let Ok(all) = procfs::process::all_processes() {
+        for process in all.flatten() {
+            let Ok(fds) = process.fd() else {
+                continue;
+            };
+            for fd in fds.flatten() {
+                if let FDTarget::Path(path) = fd.target
+                    && devices.contains(&path)
+                {
+                    let pids = holders.entry(path).or_default();
+                    if !pids.contains(&(process.pid as u32)) {
+                        pids.push(process.pid as u32);
+                    }
+                }
+            }
+        }
+    }
+
+    holders
+}
+
+fn probe_evdev_grab(device: &Path) -> EvdevGrab {
+    let file = match fs::OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_NONBLOCK)
+        .open(device)
+    {
+        Ok(file) => file,
+        Err(err) if err.kind() == std::io::ErrorKind::PermissionDenied => {
+            return EvdevGrab::Unknown("permission denied".to_string());
+        }
+        Err(err) => return EvdevGrab::Unknown(err.to_string()),
+    };
+
+    // SAFETY: the fd is valid while `file` is alive and EVIOCGRAB takes an int argument
+    let result = unsafe { libc::ioctl(file.as_raw_fd(), EVIOCGRAB, 1 as libc::c_int) };
+    if result == 0 {
+        // SAFETY: as above; releases the grab we just took
+        unsafe { libc::ioctl(file.as_raw_fd(), EVIOCGRAB, 0 as libc::c_int) };
+        return EvdevGrab::Free;
+    }
+
+    let err = std::io::Error::last_os_error();
+    match err.raw_os_error() {
+        Some(libc::EBUSY) => EvdevGrab::Grabbed,
+        _ => EvdevGrab::Unknown(err.to_string()),
+    }
+}
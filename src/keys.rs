@@ -0,0 +1,105 @@
+//! Single keypresses for interactive `--watch` modes, through crossterm. While a [`RawMode`]
+//! guard lives, the terminal neither echoes nor buffers lines, so each key arrives as it is
+//! pressed. Raw mode also turns Ctrl-C into a key, which arrives as [`Key::Interrupt`].
+
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::terminal;
+use std::io::{self, IsTerminal};
+use std::time::Duration;
+use tokio::sync::mpsc::{self, UnboundedReceiver};
+
+/// How often the reading thread checks whether its receiver is gone.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    /// A printable character, also when typed with Alt
+    Char(char),
+    Enter,
+    Backspace,
+    Escape,
+    Up,
+    Down,
+    /// Ctrl-C
+    Interrupt,
+}
+
+/// Restores the terminal's settings when dropped.
+pub struct RawMode(());
+
+impl RawMode {
+    /// Switches the terminal to raw mode, or returns None when stdin is not a terminal.
+    pub fn enable() -> Option<Self> {
+        if !io::stdin().is_terminal() {
+            return None;
+        }
+        terminal::enable_raw_mode().ok()?;
+        Some(RawMode(()))
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        terminal::disable_raw_mode().ok();
+    }
+}
+
+/// Leaves raw mode until dropped, for printing text that is not laid out for it. Does nothing
+/// outside raw mode.
+pub struct Cooked {
+    was_raw: bool,
+}
+
+impl Cooked {
+    pub fn enter() -> Self {
+        let was_raw = terminal::is_raw_mode_enabled().unwrap_or(false);
+        if was_raw {
+            terminal::disable_raw_mode().ok();
+        }
+        Cooked { was_raw }
+    }
+}
+
+impl Drop for Cooked {
+    fn drop(&mut self) {
+        if self.was_raw {
+            terminal::enable_raw_mode().ok();
+        }
+    }
+}
+
+/// Reads keys from the terminal on a thread of its own until the receiver is dropped. The
+/// terminal must be in raw mode.
+pub fn read_keys() -> UnboundedReceiver<Key> {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    std::thread::spawn(move || -> io::Result<()> {
+        while !sender.is_closed() {
+            if !event::poll(POLL_INTERVAL)? {
+                continue;
+            }
+            let Event::Key(press) = event::read()? else {
+                continue;
+            };
+            if press.kind == KeyEventKind::Release {
+                continue;
+            }
+            let control = press.modifiers.contains(KeyModifiers::CONTROL);
+            let key = match press.code {
+                KeyCode::Char('c') if control => Key::Interrupt,
+                KeyCode::Char(_) if control => continue,
+                KeyCode::Char(c) if !c.is_control() => Key::Char(c),
+                KeyCode::Enter => Key::Enter,
+                KeyCode::Backspace => Key::Backspace,
+                KeyCode::Esc => Key::Escape,
+                KeyCode::Up => Key::Up,
+                KeyCode::Down => Key::Down,
+                _ => continue,
+            };
+            if sender.send(key).is_err() {
+                break;
+            }
+        }
+        Ok(())
+    });
+    receiver
+}
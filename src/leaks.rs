@@ -0,0 +1,177 @@
+//! `leaks`: processes whose memory keeps growing, found by fitting a trend line to samples of
+//! their resident memory, taken live or read back from the `record` history.
+
+use anyhow::Result;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use sysinfo::System;
+use tabled::{Table, Tabled};
+
+use crate::history::{self, History, MemorySeries};
+use crate::output::{self, format_memory};
+
+/// Fewer samples than this can't tell a trend from a one-off allocation.
+const MIN_SAMPLES: usize = 3;
+
+/// Share of steps between samples in which memory must not shrink. Allocators hand some memory
+/// back now and then, so a leak isn't required to grow at every single step.
+const MIN_STEADY_SHARE: f64 = 0.8;
+
+#[derive(Tabled)]
+struct LeakRow {
+    #[tabled(rename = "PID")]
+    pid: u32,
+
+    #[tabled(rename = "Name")]
+    name: String,
+
+    #[tabled(rename = "Growth Rate")]
+    rate: String,
+
+    #[tabled(rename = "Grew")]
+    growth: String,
+
+    #[tabled(rename = "Memory")]
+    memory: String,
+
+    #[tabled(rename = "Steady")]
+    steady: String,
+}
+
+struct Trend {
+    /// Slope of the least-squares line through the samples
+    mb_per_hour: f64,
+    /// Share of steps in which memory did not shrink
+    steady_share: f64,
+}
+
+/// Reports processes whose memory grew by at least `min_rate` MB per hour, steadily, over
+/// `window`: sampled every `interval` from now, or (with `from_history`) read from the
+/// history database.
+pub async fn find_leaks(interval: Duration, window: Duration, min_rate: f64, from_history: bool) -> Result<()> {
+    let series = if from_history {
+        let history = History::open_existing(&history::default_path()?)?;
+        history.memory_series(chrono::Utc::now().timestamp() - window.as_secs() as i64)?
+    } else {
+        sample(interval, window).await?
+    };
+
+    let mut leaks: Vec<(f64, LeakRow)> = series
+        .into_iter()
+        .filter_map(|series| {
+            let trend = trend(&series.points)?;
+            if trend.mb_per_hour < min_rate || trend.steady_share < MIN_STEADY_SHARE {
+                return None;
+            }
+            let (first, last) = (series.points.first()?, series.points.last()?);
+            if last.1 <= first.1 {
+                return None;
+            }
+            Some((
+                trend.mb_per_hour,
+                LeakRow {
+                    pid: series.pid,
+                    name: series.name,
+                    rate: format!("{:.1} MB/hour", trend.mb_per_hour),
+                    growth: format!(
+                        "{} in {}",
+                        format_memory(last.1 - first.1),
+                        output::format_duration((last.0 - first.0) as u64 * 1000)
+                    ),
+                    memory: format!("{} -> {}", format_memory(first.1), format_memory(last.1)),
+                    steady: format!("{:.0}%", trend.steady_share * 100.0),
+                },
+            ))
+        })
+        .collect();
+
+    if leaks.is_empty() {
+        println!("No process grew steadily by {} MB/hour or more", min_rate);
+        return Ok(());
+    }
+
+    leaks.sort_by(|a, b| b.0.total_cmp(&a.0));
+    println!("{}", Table::new(leaks.into_iter().map(|(_, row)| row)));
+    println!("\nSteady is the share of samples in which memory did not shrink. Growth that levels off is usually a cache warming up, not a leak.");
+
+    Ok(())
+}
+
+/// Samples the memory of every process every `interval` until `window` has passed or Ctrl-C
+/// is pressed, keyed by PID and start time so a reused PID starts a new series.
+async fn sample(interval: Duration, window: Duration) -> Result<Vec<MemorySeries>> {
+    let interval = interval.max(Duration::from_secs(1));
+    let samples = (window.as_secs_f64() / interval.as_secs_f64()) as usize + 1;
+    if samples < MIN_SAMPLES {
+        return Err(anyhow::anyhow!(
+            "A {}s window only fits {} samples at a {}s interval; at least {} are needed",
+            window.as_secs(),
+            samples,
+            interval.as_secs(),
+            MIN_SAMPLES
+        ));
+    }
+
+    println!(
+        "Sampling memory every {}s for {} ({} samples); press Ctrl-C to stop early and report",
+        interval.as_secs(),
+        output::format_duration(window.as_millis() as u64),
+        samples
+    );
+
+    let mut system = System::new();
+    let mut series: HashMap<(u32, u64), MemorySeries> = HashMap::new();
+    let started = Instant::now();
+    let mut ticker = tokio::time::interval(interval);
+    for _ in 0..samples {
+        tokio::select! {
+            _ = ticker.tick() => {}
+            _ = tokio::signal::ctrl_c() => {
+                println!();
+                break;
+            }
+        }
+        system.refresh_processes();
+        let elapsed = started.elapsed().as_secs() as i64;
+        for process in system.processes().values().filter(|p| p.thread_kind().is_none()) {
+            let pid = process.pid().as_u32();
+            if pid == std::process::id() {
+                continue;
+            }
+            let entry = series
+                .entry((pid, process.start_time()))
+                .or_insert_with(|| MemorySeries { pid, name: String::new(), points: Vec::new() });
+            // A forked child keeps its parent's name until it execs
+            entry.name = process.name().to_string();
+            entry.points.push((elapsed, process.memory()));
+        }
+    }
+
+    Ok(series.into_values().collect())
+}
+
+fn trend(points: &[(i64, u64)]) -> Option<Trend> {
+    if points.len() < MIN_SAMPLES {
+        return None;
+    }
+
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|(x, _)| *x as f64).sum::<f64>() / n;
+    let mean_y = points.iter().map(|(_, y)| *y as f64).sum::<f64>() / n;
+    let (mut covariance, mut variance) = (0.0, 0.0);
+    for (x, y) in points {
+        let dx = *x as f64 - mean_x;
+        covariance += dx * (*y as f64 - mean_y);
+        variance += dx * dx;
+    }
+    if variance == 0.0 {
+        return None;
+    }
+    let bytes_per_second = covariance / variance;
+
+    let steady = points.windows(2).filter(|pair| pair[1].1 >= pair[0].1).count();
+    Some(Trend {
+        mb_per_hour: bytes_per_second * 3600.0 / 1024.0 / 1024.0,
+        steady_share: steady as f64 / (points.len() - 1) as f64,
+    })
+}
@@ -0,0 +1,5 @@
+//! The top-helper command-line tool lives in the binary; this library exposes the pieces that
+//! are useful to other crates, currently the headless display harness used by its end-to-end
+//! tests.
+
+pub mod testenv;
@@ -0,0 +1,260 @@
+//! Throttles running processes by moving them into a cgroup with memory and CPU limits.
+//!
+//! With systemd, the processes are adopted into a transient scope unit (the same mechanism as
+//! `systemd-run --scope`, but for processes that are already running). Without it, a cgroup v2
+//! group is created directly under /sys/fs/cgroup, which requires root.
+
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::Path;
+use sysinfo::System;
+use zbus::zvariant::{OwnedObjectPath, Value};
+use zbus::{Connection, Proxy};
+
+use crate::cgroup;
+use crate::control;
+use crate::dbus;
+use crate::error::CliError;
+use crate::selector::Selection;
+
+/// A `--cpu` limit such as `50%` (half a core) or `200%` (two cores).
+#[derive(Debug, Clone, Copy)]
+pub struct CpuLimit {
+    percent: u32,
+}
+
+impl std::str::FromStr for CpuLimit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim_end_matches('%').parse::<u32>() {
+            Ok(percent) if percent > 0 => Ok(CpuLimit { percent }),
+            _ => Err(format!("invalid CPU limit '{}' (expected a percentage such as 50%)", s)),
+        }
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for CpuLimit {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// A `--memory` limit such as `512M` or `2G`, in bytes.
+#[derive(Debug, Clone, Copy)]
+pub struct MemoryLimit {
+    bytes: u64,
+}
+
+impl MemoryLimit {
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+}
+
+impl std::str::FromStr for MemoryLimit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let upper = s.trim().to_uppercase();
+        let upper = upper.trim_end_matches('B').trim_end_matches('I');
+        let (number, multiplier) = match upper.chars().last() {
+            Some('K') => (&upper[..upper.len() - 1], 1u64 << 10),
+            Some('M') => (&upper[..upper.len() - 1], 1 << 20),
+            Some('G') => (&upper[..upper.len() - 1], 1 << 30),
+            Some('T') => (&upper[..upper.len() - 1], 1 << 40),
+            _ => (upper, 1),
+        };
+
+        match number.trim().parse::<f64>() {
+            Ok(value) if value > 0.0 => Ok(MemoryLimit { bytes: (value * multiplier as f64) as u64 }),
+            _ => Err(format!("invalid memory limit '{}' (expected a size such as 512M or 2G)", s)),
+        }
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for MemoryLimit {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+pub fn limit_process(identifier: &str, memory: Option<MemoryLimit>, cpu: Option<CpuLimit>) -> Result<()> {
+    if memory.is_none() && cpu.is_none() {
+        return Err(anyhow::anyhow!("Nothing to limit; pass --memory and/or --cpu"));
+    }
+
+    let mut system = System::new_all();
+    system.refresh_all();
+
+    let process = Selection::parse(identifier)?.resolve_unique(&system)?;
+    let pid = process.pid().as_u32();
+    let (descendants, location) = limit_tree(&system, pid, memory, cpu)?;
+
+    println!(
+        "Limited {} (PID: {}) and {} descendants to {} in {}",
+        process.name(),
+        pid,
+        descendants,
+        describe(memory, cpu),
+        location
+    );
+    Ok(())
+}
+
+/// Moves a process and its descendants under the limits, returning how many descendants there
+/// were and where the limits live.
+pub fn limit_tree(
+    system: &System,
+    pid: u32,
+    memory: Option<MemoryLimit>,
+    cpu: Option<CpuLimit>,
+) -> Result<(usize, String)> {
+    let mut pids = vec![pid];
+    pids.extend(control::descendants(system, pid));
+
+    // Same check as sd_booted(3)
+    let location = if Path::new("/run/systemd/system").exists() {
+        limit_with_systemd(pid, &pids, memory, cpu)?
+    } else {
+        limit_with_cgroup(pid, &pids, memory, cpu)?
+    };
+    Ok((pids.len() - 1, location))
+}
+
+/// Whether the process is in a scope or group made by `limit`.
+pub fn is_limited(pid: u32) -> bool {
+    cgroup::read(pid).contains("/top-helper-limit-")
+}
+
+/// The limits in words, e.g. "memory 512.0 MB, CPU 50%".
+pub fn describe(memory: Option<MemoryLimit>, cpu: Option<CpuLimit>) -> String {
+    let mut limits = Vec::new();
+    if let Some(memory) = memory {
+        limits.push(format!("memory {:.1} MB", memory.bytes as f64 / 1024.0 / 1024.0));
+    }
+    if let Some(cpu) = cpu {
+        limits.push(format!("CPU {}%", cpu.percent));
+    }
+    limits.join(", ")
+}
+
+fn is_root() -> bool {
+    // SAFETY: geteuid has no preconditions
+    unsafe { libc::geteuid() == 0 }
+}
+
+/// Adopts the processes into a transient scope via systemd's StartTransientUnit. A scope left
+/// from an earlier limit on the same PID still exists, so it takes the processes and the new
+/// limits instead.
+fn limit_with_systemd(pid: u32, pids: &[u32], memory: Option<MemoryLimit>, cpu: Option<CpuLimit>) -> Result<String> {
+    let unit = format!("top-helper-limit-{}.scope", pid);
+    let system = is_root();
+    dbus::block_on(start_scope(&unit, system, pids, memory, cpu)).map_err(|err| match err {
+        zbus::Error::MethodError(name, message, _)
+            if name.as_str() == "org.freedesktop.DBus.Error.AccessDenied"
+                || name.as_str() == "org.freedesktop.DBus.Error.InteractiveAuthorizationRequired" =>
+        {
+            CliError::permission(format!(
+                "systemd refused to adopt PID {} into {}: {}",
+                pid,
+                unit,
+                message.unwrap_or_default()
+            ))
+        }
+        err => anyhow::Error::new(err).context(format!("Failed to create {}", unit)),
+    })?;
+
+    let bus = if system { "--system" } else { "--user" };
+    Ok(format!(
+        "{} (lift with: systemctl {} set-property --runtime {} MemoryMax=infinity CPUQuota=)",
+        unit, bus, unit
+    ))
+}
+
+async fn start_scope(
+    unit: &str,
+    system: bool,
+    pids: &[u32],
+    memory: Option<MemoryLimit>,
+    cpu: Option<CpuLimit>,
+) -> zbus::Result<()> {
+    let connection = if system { Connection::system().await? } else { Connection::session().await? };
+    let manager = Proxy::new(
+        &connection,
+        "org.freedesktop.systemd1",
+        "/org/freedesktop/systemd1",
+        "org.freedesktop.systemd1.Manager",
+    )
+    .await?;
+
+    let limits = || {
+        let mut limits: Vec<(&str, Value)> = Vec::new();
+        if let Some(memory) = memory {
+            limits.push(("MemoryMax", Value::U64(memory.bytes)));
+        }
+        if let Some(cpu) = cpu {
+            // CPU time allowed per second of wall time, in microseconds
+            limits.push(("CPUQuotaPerSecUSec", Value::U64(cpu.percent as u64 * 10_000)));
+        }
+        limits
+    };
+    let mut properties = vec![("PIDs", Value::from(pids.to_vec()))];
+    properties.extend(limits());
+    let auxiliary: Vec<(&str, Vec<(&str, Value)>)> = Vec::new();
+
+    let started = manager
+        .call::<_, _, OwnedObjectPath>("StartTransientUnit", &(unit, "fail", properties, auxiliary))
+        .await;
+    match started {
+        Ok(_) => Ok(()),
+        Err(zbus::Error::MethodError(name, _, _)) if name.as_str() == "org.freedesktop.systemd1.UnitExists" => {
+            manager.call::<_, _, ()>("AttachProcessesToUnit", &(unit, "", pids)).await?;
+            manager.call::<_, _, ()>("SetUnitProperties", &(unit, true, limits())).await
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// Creates a cgroup v2 group directly and moves the processes into it.
+fn limit_with_cgroup(pid: u32, pids: &[u32], memory: Option<MemoryLimit>, cpu: Option<CpuLimit>) -> Result<String> {
+    let root = Path::new("/sys/fs/cgroup");
+    if !root.join("cgroup.controllers").exists() {
+        return Err(CliError::backend_missing("Neither systemd nor a cgroup v2 hierarchy is available"));
+    }
+    if !is_root() {
+        return Err(CliError::permission("Creating cgroups without systemd requires root"));
+    }
+
+    // The controllers only reach the new group once they are enabled for the root's children
+    let mut controllers = Vec::new();
+    if memory.is_some() {
+        controllers.push("+memory");
+    }
+    if cpu.is_some() {
+        controllers.push("+cpu");
+    }
+    if !controllers.is_empty() {
+        fs::write(root.join("cgroup.subtree_control"), controllers.join(" "))
+            .with_context(|| format!("Failed to enable the {} controllers", controllers.join(" ").replace('+', "")))?;
+    }
+
+    let group = root.join(format!("top-helper-limit-{}", pid));
+    fs::create_dir_all(&group).with_context(|| format!("Failed to create {}", group.display()))?;
+
+    if let Some(memory) = memory {
+        fs::write(group.join("memory.max"), memory.bytes.to_string()).context("Failed to set memory.max")?;
+    }
+    if let Some(cpu) = cpu {
+        let period = 100_000u64;
+        let quota = cpu.percent as u64 * period / 100;
+        fs::write(group.join("cpu.max"), format!("{} {}", quota, period)).context("Failed to set cpu.max")?;
+    }
+
+    for pid in pids {
+        // Processes that exited in the meantime are skipped
+        let _ = fs::write(group.join("cgroup.procs"), pid.to_string());
+    }
+
+    Ok(group.display().to_string())
+}
@@ -0,0 +1,33 @@
+//! Diagnostics for `-v` (debug) and `-vv` (trace): which window backends were tried, which tool
+//! calls failed and why, and what each lookup fell back to. Logs go to stderr, or to the file
+//! given with `--log-file`, so they never mix with the output of a command.
+
+use anyhow::{Context, Result};
+use std::fs::OpenOptions;
+use std::path::Path;
+use std::sync::Mutex;
+use tracing::Level;
+
+/// Installs the log subscriber. Nothing is logged at verbosity 0 unless there is a log file,
+/// which then gets debug messages.
+pub fn init(verbosity: u8, log_file: Option<&Path>) -> Result<()> {
+    let level = match (verbosity, log_file) {
+        (0, None) => return Ok(()),
+        (0 | 1, _) => Level::DEBUG,
+        _ => Level::TRACE,
+    };
+    let builder = tracing_subscriber::fmt().with_max_level(level).with_target(false);
+
+    match log_file {
+        Some(path) => {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .with_context(|| format!("Failed to open {}", path.display()))?;
+            builder.with_ansi(false).with_writer(Mutex::new(file)).init();
+        }
+        None => builder.with_writer(std::io::stderr).init(),
+    }
+    Ok(())
+}
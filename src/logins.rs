@@ -0,0 +1,254 @@
+//! `sessions`: who is logged in, from where, and what each login session is running.
+//!
+//! Sessions come from systemd-logind (`loginctl`), whose session scopes (`session-<id>.scope`)
+//! hold exactly the session's processes. Without logind, /run/utmp is read instead, and a
+//! session's processes are the descendants of the process that logged in.
+
+use anyhow::Result;
+use std::collections::HashSet;
+use std::fs;
+use std::process::Command;
+use sysinfo::System;
+
+use crate::children::{self, ProcessTree};
+use crate::control::{self, Signal};
+use crate::error::CliError;
+use crate::output;
+
+/// Where the login database lives; /var/run is a symlink to /run on current systems.
+const UTMP_PATH: &str = "/run/utmp";
+
+/// Size of a glibc `struct utmp` on 64-bit Linux, and the offsets of the fields used.
+const UTMP_RECORD: usize = 384;
+const UT_PID: usize = 4;
+const UT_LINE: (usize, usize) = (8, 32);
+const UT_USER: (usize, usize) = (44, 32);
+const UT_HOST: (usize, usize) = (76, 256);
+
+/// `ut_type` of a logged-in user.
+const USER_PROCESS: i16 = 7;
+
+#[derive(Debug, Clone)]
+pub struct LoginSession {
+    pub id: String,
+    pub user: String,
+    /// e.g. "pts/0" or "tty2"
+    pub tty: Option<String>,
+    /// The client's address, for remote sessions
+    pub remote_host: Option<String>,
+    pub remote: bool,
+    /// PAM service the session was opened by, e.g. "sshd" or "gdm-password"
+    pub service: Option<String>,
+    /// x11, wayland, tty, ...
+    pub kind: Option<String>,
+    pub leader: Option<u32>,
+}
+
+impl LoginSession {
+    /// "SSH from 192.168.1.20", "local wayland session", ...
+    fn origin(&self) -> String {
+        if self.remote {
+            let how = match self.service.as_deref() {
+                Some(service) if service.contains("ssh") => "SSH".to_string(),
+                Some(service) => format!("remote ({})", service),
+                None => "remote".to_string(),
+            };
+            return match &self.remote_host {
+                Some(host) => format!("{} from {}", how, host),
+                None => how,
+            };
+        }
+        match self.kind.as_deref() {
+            Some(kind) if kind != "unspecified" => format!("local {} session", kind),
+            _ => "local session".to_string(),
+        }
+    }
+}
+
+/// Lists login sessions with their process trees, or with `kill` terminates every process in the
+/// session with that ID.
+pub async fn show_sessions(kill: Option<&str>, yes: bool) -> Result<()> {
+    let mut system = System::new_all();
+    system.refresh_all();
+    // A second sample is needed for CPU usage
+    tokio::time::sleep(sysinfo::MINIMUM_CPU_UPDATE_INTERVAL).await;
+    system.refresh_processes();
+
+    let (sessions, from_logind) = match logind_sessions() {
+        Some(sessions) => (sessions, true),
+        None => (utmp_sessions(&system), false),
+    };
+
+    if let Some(id) = kill {
+        let Some(session) = sessions.iter().find(|session| session.id == id) else {
+            return Err(CliError::not_found(format!("No login session {}", id)));
+        };
+        let pids = members(&system, session, from_logind);
+        if pids.is_empty() {
+            return Err(CliError::not_found(format!("Session {} has no processes left", id)));
+        }
+        return control::signal_matched(&system, pids, Signal::Terminate, false, yes);
+    }
+
+    if sessions.is_empty() {
+        println!("No login sessions found");
+        return Ok(());
+    }
+
+    for (i, session) in sessions.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        let trees = trees(&system, &members(&system, session, from_logind));
+        let count: usize = trees.iter().map(ProcessTree::count).sum();
+        let memory: u64 = trees.iter().map(ProcessTree::total_memory).sum();
+
+        let mut heading = format!("Session {}: {}, {}", session.id, session.user, session.origin());
+        if let Some(tty) = &session.tty {
+            heading.push_str(&format!(" on {}", tty));
+        }
+        let started = session
+            .leader
+            .and_then(|pid| system.process(sysinfo::Pid::from(pid as usize)))
+            .map(|leader| output::format_time(leader.start_time() as i64));
+        if let Some(started) = started {
+            heading.push_str(&format!(", since {}", started));
+        }
+        println!("{}; {} processes, {}", heading, count, output::format_memory(memory));
+        for tree in &trees {
+            children::print_node(tree, "  ", "  ");
+        }
+    }
+
+    println!("\nTerminate a session's processes with `top-helper sessions --kill <ID>`");
+    Ok(())
+}
+
+/// Sessions known to logind, or None when loginctl is missing or logind isn't running.
+fn logind_sessions() -> Option<Vec<LoginSession>> {
+    let output = Command::new("loginctl").args(["list-sessions", "--no-legend"]).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let ids: Vec<String> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.split_whitespace().next().map(str::to_string))
+        .collect();
+
+    let sessions = ids
+        .into_iter()
+        .filter_map(|id| {
+            let output = Command::new("loginctl")
+                .args(["show-session", &id])
+                .args(["-p", "Name", "-p", "TTY", "-p", "Remote", "-p", "RemoteHost"])
+                .args(["-p", "Service", "-p", "Type", "-p", "Leader"])
+                .output()
+                .ok()?;
+            let text = String::from_utf8_lossy(&output.stdout);
+            let property = |key: &str| {
+                text.lines()
+                    .find_map(|line| line.strip_prefix(key)?.strip_prefix('='))
+                    .filter(|value| !value.is_empty())
+                    .map(str::to_string)
+            };
+            Some(LoginSession {
+                user: property("Name").unwrap_or_else(|| "?".to_string()),
+                tty: property("TTY"),
+                remote_host: property("RemoteHost"),
+                remote: property("Remote").as_deref() == Some("yes"),
+                service: property("Service"),
+                kind: property("Type"),
+                leader: property("Leader").and_then(|pid| pid.parse().ok()).filter(|&pid| pid != 0),
+                id,
+            })
+        })
+        .collect();
+    Some(sessions)
+}
+
+/// Logged-in users from /run/utmp whose login process is still running, numbered by that PID.
+fn utmp_sessions(system: &System) -> Vec<LoginSession> {
+    let Ok(data) = fs::read(UTMP_PATH) else {
+        return Vec::new();
+    };
+    let text = |record: &[u8], (offset, len): (usize, usize)| {
+        let field = &record[offset..offset + len];
+        let end = field.iter().position(|&b| b == 0).unwrap_or(len);
+        Some(String::from_utf8_lossy(&field[..end]).into_owned()).filter(|value| !value.is_empty())
+    };
+
+    data.chunks_exact(UTMP_RECORD)
+        .filter(|record| i16::from_ne_bytes([record[0], record[1]]) == USER_PROCESS)
+        .filter_map(|record| {
+            let pid = u32::from_ne_bytes(record[UT_PID..UT_PID + 4].try_into().ok()?);
+            system.process(sysinfo::Pid::from(pid as usize))?;
+            let host = text(record, UT_HOST);
+            // Local X sessions record the display (":0") as their host
+            let remote = host.as_deref().is_some_and(|host| !host.starts_with(':'));
+            Some(LoginSession {
+                id: pid.to_string(),
+                user: text(record, UT_USER).unwrap_or_else(|| "?".to_string()),
+                tty: text(record, UT_LINE),
+                remote_host: host.filter(|_| remote),
+                remote,
+                // utmp doesn't record how the user logged in, but an SSH login runs under sshd
+                service: under_sshd(system, pid).then(|| "sshd".to_string()),
+                kind: None,
+                leader: Some(pid),
+            })
+        })
+        .collect()
+}
+
+fn under_sshd(system: &System, pid: u32) -> bool {
+    let mut current = system.process(sysinfo::Pid::from(pid as usize));
+    while let Some(process) = current {
+        if process.name().starts_with("sshd") {
+            return true;
+        }
+        current = process.parent().and_then(|parent| system.process(parent));
+    }
+    false
+}
+
+/// The processes of a session: those in its logind scope, or the leader's descendants.
+fn members(system: &System, session: &LoginSession, from_logind: bool) -> Vec<u32> {
+    if !from_logind {
+        let Some(leader) = session.leader else {
+            return Vec::new();
+        };
+        let mut pids = vec![leader];
+        pids.extend(control::descendants(system, leader));
+        return pids;
+    }
+
+    let scope = format!("/session-{}.scope", session.id);
+    let mut pids: Vec<u32> = system
+        .processes()
+        .values()
+        .filter(|process| process.thread_kind().is_none())
+        .map(|process| process.pid().as_u32())
+        .filter(|pid| {
+            fs::read_to_string(format!("/proc/{}/cgroup", pid))
+                .is_ok_and(|cgroup| cgroup.lines().any(|line| line.ends_with(&scope)))
+        })
+        .collect();
+    pids.sort_unstable();
+    pids
+}
+
+/// The members as trees: one per member whose parent is outside the session, each holding only
+/// members (a user service started from the session runs elsewhere).
+fn trees(system: &System, pids: &[u32]) -> Vec<ProcessTree> {
+    let members: HashSet<u32> = pids.iter().copied().collect();
+    pids.iter()
+        .filter(|pid| {
+            system
+                .process(sysinfo::Pid::from(**pid as usize))
+                .and_then(|process| process.parent())
+                .is_none_or(|parent| !members.contains(&parent.as_u32()))
+        })
+        .filter_map(|&pid| ProcessTree::build(system, pid))
+        .map(|tree| tree.retain(&members))
+        .collect()
+}
@@ -1,7 +1,101 @@
 use clap::{Parser, Subcommand};
 use anyhow::Result;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Duration;
 
+use config::Config;
+use control::Signal;
+use limit::{CpuLimit, MemoryLimit};
+use output::{
+    DiagramFormat, InfoFormat, ListFormat, MemoryUnits, MonitorFormat, OutputFormat, PorcelainVersion, StatusFormat,
+};
+use priority::IoPriority;
+use process::{ListColumn, SortField, SortKey};
+use sandbox::Packaging;
+use selector::{EnvFilter, NameMatch};
+use top_helper::testenv::{self, Backend, TestEnv};
+
+mod agents;
+mod alerts;
+mod bench;
+mod boot;
+mod browser;
+mod bugreport;
+mod cgroup;
+mod children;
+mod clipboard;
+mod compositor;
+mod config;
+mod control;
+mod cpu;
+mod daemon;
+mod dbus;
+mod dbus_service;
+mod devtools;
+mod doctor;
+mod disks;
+mod electron;
+mod error;
+mod explain;
+mod faults;
+mod files;
+mod fixture;
+mod focus;
+mod fleet;
+mod get;
+mod gpu;
+mod graph;
+mod history;
+mod hooks;
+mod identity;
+mod idle;
+mod holders;
+mod hyprland;
+mod input;
+mod keys;
+mod leaks;
+mod limit;
+mod logging;
+mod logins;
+mod memory;
+mod menu;
+mod names;
+mod namespaces;
+mod notifications;
+mod notify;
+mod oom;
+mod otlp;
+mod output;
+mod paths;
+mod power;
+mod plugins;
+mod policy;
+mod pressure;
+mod priority;
 mod process;
+mod project;
+mod query;
+mod redact;
+mod report;
+mod run;
+mod sandbox;
+mod sched;
+mod security;
+mod selector;
+mod sensors;
+mod session;
+mod sink;
+mod smoothing;
+mod snapshot;
+mod stack;
+mod status;
+mod sway;
+mod tabs;
+mod terminals;
+mod trace;
+mod tty;
+mod wait;
 mod window;
 
 #[derive(Parser)]
@@ -9,6 +103,55 @@ mod window;
 #[command(about = "A CLI tool to monitor system resources and track process contexts")]
 #[command(version)]
 struct Cli {
+    /// Print nothing; report the outcome only through the exit code
+    #[arg(short, long, global = true)]
+    quiet: bool,
+
+    /// Log what lookups try and why they fail to stderr; -vv for every tool call
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    /// Append the diagnostics to this file instead of stderr (at -v level unless more is given)
+    #[arg(long, global = true, value_name = "PATH")]
+    log_file: Option<PathBuf>,
+
+    /// Match process names as a whole, not as substrings, in selectors and `list --name`
+    #[arg(long, global = true, conflicts_with = "word")]
+    exact: bool,
+
+    /// Match process names on whole words, so `sh` matches `sh` and `sh-run` but not `bash` or `ssh`
+    #[arg(long, global = true)]
+    word: bool,
+
+    /// X display to use for window lookups (defaults to the target process's own DISPLAY)
+    #[arg(long, global = true)]
+    display: Option<String>,
+
+    /// Don't hide secrets (tokens, keys, URL query strings) in JSON, porcelain, and exported output
+    #[arg(long, global = true)]
+    no_redact: bool,
+
+    /// Units for memory sizes in list, info, projects, and agents output [default: mib, or the
+    /// profile's units]
+    #[arg(long, global = true, value_enum)]
+    units: Option<MemoryUnits>,
+
+    /// Config profile to use instead of the one matching this machine's hostname
+    #[arg(long, global = true, value_name = "NAME")]
+    profile: Option<String>,
+
+    /// Run the command against a throwaway headless display with dummy windows
+    #[arg(long, global = true, value_name = "BACKEND", num_args = 0..=1, require_equals = true, default_missing_value = "x11")]
+    test_env: Option<Backend>,
+
+    /// Number of dummy windows to open for --test-env
+    #[arg(long, global = true, value_name = "N", default_value_t = 3)]
+    test_windows: usize,
+
+    /// Read processes from a fixture recorded with `save-fixture` instead of the live system
+    #[arg(long, global = true, hide = true, value_name = "DIR")]
+    fixture: Option<PathBuf>,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -21,51 +164,1371 @@ enum Commands {
         #[arg(short, long)]
         name: Option<String>,
 
-        /// Show only high memory usage processes (>100MB)
+        /// Only show processes whose working directory is inside this path
+        #[arg(long)]
+        cwd: Option<String>,
+
+        /// Only show processes whose environment has KEY set, or KEY set to VALUE (repeatable)
+        #[arg(long, value_name = "KEY[=VALUE]")]
+        env: Vec<EnvFilter>,
+
+        /// Show only high memory usage processes (over 100 MB, or the profile's high_memory_mb)
         #[arg(long)]
         high_memory: bool,
 
-        /// Sort by memory usage (desc)
+        /// Also show kernel threads (children of kthreadd, such as kworker)
+        #[arg(long, overrides_with = "no_kernel_threads")]
+        kernel_threads: bool,
+
+        /// Hide kernel threads (the default)
+        #[arg(long, overrides_with = "kernel_threads")]
+        no_kernel_threads: bool,
+
+        /// Also show top-helper itself
+        #[arg(long)]
+        include_self: bool,
+
+        /// Only show processes whose controlling terminal is this one, e.g. pts/3
+        #[arg(long, value_name = "TTY")]
+        tty: Option<String>,
+
+        /// Show python, node, and java processes by the script, module, jar, or main class
+        /// they run, e.g. "manage.py (python3)"
+        #[arg(long)]
+        resolve_names: bool,
+
+        /// Only show Flatpak apps
+        #[arg(long, conflicts_with = "snap")]
+        flatpak: bool,
+
+        /// Only show Snap apps
         #[arg(long)]
+        snap: bool,
+
+        /// Sort by pid, name, mem, cpu, uptime, io, threads, fds, faults, or oom, optionally with
+        /// :asc or :desc (default: ascending for pid and name, descending otherwise)
+        #[arg(long, value_name = "FIELD[:asc|desc]")]
+        sort: Option<SortKey>,
+
+        /// Show only the first N processes, after sorting
+        #[arg(long, value_name = "N")]
+        limit: Option<usize>,
+
+        /// Skip the first N processes, after sorting
+        #[arg(long, value_name = "N", default_value = "0")]
+        offset: usize,
+
+        /// Show page N (from 1) of --limit processes each (default 50)
+        #[arg(long, value_name = "N", conflicts_with = "offset", value_parser = clap::value_parser!(u64).range(1..))]
+        page: Option<u64>,
+
+        /// Same as `--sort mem`
+        #[arg(long, conflicts_with_all = ["sort", "top_memory", "top_cpu", "sort_oom"])]
         sort_memory: bool,
 
-        /// Show top N processes by memory usage
-        #[arg(long, conflicts_with = "top_cpu")]
+        /// Same as `--sort mem --limit N`
+        #[arg(long, value_name = "N", conflicts_with_all = ["sort", "limit", "top_cpu", "sort_oom"])]
         top_memory: Option<usize>,
 
-        /// Show top N processes by CPU usage
-        #[arg(long, conflicts_with = "top_memory")]
+        /// Same as `--sort cpu --limit N`
+        #[arg(long, value_name = "N", conflicts_with_all = ["sort", "limit", "sort_oom"])]
         top_cpu: Option<usize>,
+
+        /// Show GPU usage type (graphics/compute/video) and memory per process
+        #[arg(long)]
+        gpu: bool,
+
+        /// Optional columns to show, comma-separated [default: the profile's columns]
+        #[arg(long, value_enum, value_delimiter = ',', value_name = "COLUMNS")]
+        columns: Vec<ListColumn>,
+
+        /// Same as `--sort oom`: the order the kernel's OOM killer picks victims
+        #[arg(long, conflicts_with = "sort")]
+        sort_oom: bool,
+
+        /// Same as `--sort threads`
+        #[arg(long, conflicts_with_all = ["sort", "sort_memory", "top_memory", "top_cpu", "sort_oom", "sort_fds"])]
+        sort_threads: bool,
+
+        /// Same as `--sort fds`
+        #[arg(long, conflicts_with_all = ["sort", "sort_memory", "top_memory", "top_cpu", "sort_oom"])]
+        sort_fds: bool,
+
+        /// Only show processes with at least N threads
+        #[arg(long, value_name = "N")]
+        min_threads: Option<usize>,
+
+        /// Only show processes with at least N open file descriptors
+        #[arg(long, value_name = "N")]
+        min_fds: Option<usize>,
+
+        /// Report memory as PSS, with each shared page divided among the processes sharing it,
+        /// instead of RSS, so forked and shared-library-heavy processes compare fairly
+        #[arg(long)]
+        pss: bool,
+
+        /// For each process shown without a shown ancestor, also show the memory of its whole
+        /// tree of descendants, shown or not (with --pss, shared pages count once)
+        #[arg(long)]
+        tree_memory: bool,
+
+        /// Estimate each process's energy impact over a one-second sample (see `power`)
+        #[arg(long)]
+        power: bool,
+
+        /// Show each terminal and the job running in its foreground instead of all processes
+        #[arg(long, conflicts_with_all = ["high_memory", "sort", "limit", "sort_memory", "top_memory", "top_cpu", "gpu", "columns", "sort_oom", "power", "porcelain", "sort_threads", "sort_fds", "min_threads", "min_fds", "pss", "tree_memory"])]
+        interactive: bool,
+
+        /// Stable tab-separated output for scripts (see README for the field order)
+        #[arg(long, value_name = "VERSION", num_args = 0..=1, require_equals = true, default_missing_value = "v1")]
+        porcelain: Option<PorcelainVersion>,
+
+        /// Output format; JSON includes the total count for paging
+        #[arg(long, value_enum, default_value = "text", conflicts_with_all = ["interactive", "porcelain"])]
+        format: ListFormat,
+
+        /// Keep redrawing until interrupted, or with --format ndjson, keep writing samples
+        #[arg(long, conflicts_with_all = ["interactive", "porcelain"])]
+        watch: bool,
+
+        /// Refresh interval in seconds for --watch
+        #[arg(long, default_value = "2")]
+        interval: u64,
+
+        /// While watching, highlight new processes, keep exited ones for one more refresh, and
+        /// show each process's memory change
+        #[arg(long, requires = "watch")]
+        diff: bool,
+
+        /// How much each redraw keeps of the previous CPU usage, from 0 (raw samples) to just
+        /// under 1 (very slow to change)
+        #[arg(long, default_value = "0.5", value_parser = smoothing::parse_factor, requires = "watch")]
+        smoothing: f64,
     },
 
-    /// Show detailed information about a specific process
+    /// Show detailed information about one or more processes
     Info {
-        /// Process ID or name
+        /// Process selectors (PID, name, or pid:/name:/exact:/re:/unit:/port:/window:/cwd:/file:, optionally after newest:/oldest:)
+        #[arg(required_unless_present = "cwd")]
+        processes: Vec<String>,
+
+        /// Only match processes whose working directory is inside this path
+        #[arg(long)]
+        cwd: Option<String>,
+
+        /// Show every process matching each selector instead of the likeliest one
+        #[arg(long, visible_alias = "all")]
+        all_matches: bool,
+
+        /// Render a comparison table instead of detail blocks
+        #[arg(long, conflicts_with = "porcelain")]
+        table: bool,
+
+        /// Stable tab-separated output for scripts (see README for the field order)
+        #[arg(long, value_name = "VERSION", num_args = 0..=1, require_equals = true, default_missing_value = "v1")]
+        porcelain: Option<PorcelainVersion>,
+
+        /// Output format; json, yaml, and toml list the processes under `processes`
+        #[arg(long, value_enum, default_value = "text", conflicts_with_all = ["table", "porcelain"])]
+        format: InfoFormat,
+
+        /// Also show user and group IDs, capabilities, seccomp, no_new_privs, and the
+        /// SELinux/AppArmor label
+        #[arg(long, conflicts_with_all = ["table", "porcelain"])]
+        security: bool,
+
+        /// Also show the process's namespaces and which other processes share each one
+        #[arg(long, conflicts_with_all = ["table", "porcelain"])]
+        namespaces: bool,
+
+        /// Also show the scheduling policy and priority, context switches, and time spent
+        /// waiting for a CPU
+        #[arg(long, conflicts_with_all = ["table", "porcelain"])]
+        sched: bool,
+
+        /// Also sample page faults for a second and show their rates
+        #[arg(long, conflicts_with_all = ["table", "porcelain"])]
+        faults: bool,
+    },
+
+    /// Print one field of a process as a raw value, for shell scripts
+    Get {
+        /// Process selector (PID, name, or pid:/name:/exact:/re:/unit:/port:/window:/cwd:/file:, optionally after newest:/oldest:)
         process: String,
+
+        /// Field to print
+        #[arg(value_enum)]
+        field: get::Field,
+    },
+
+    /// Explain in plain words who started a process, where it runs, what it is doing, and how to
+    /// get to it
+    Explain {
+        /// Process selector (PID, name, or pid:/name:/exact:/re:/unit:/port:/window:/cwd:/file:, optionally after newest:/oldest:)
+        process: String,
+    },
+
+    /// Show why a process is blocked or spinning: each thread's state, the kernel function it
+    /// waits in, and its CPU use
+    Stack {
+        /// Process selector (PID, name, or pid:/name:/exact:/re:/unit:/port:/window:/cwd:/file:, optionally after newest:/oldest:)
+        process: String,
+
+        /// Also print kernel stacks, grouped by identical stack (needs root)
+        #[arg(long)]
+        kernel: bool,
+
+        /// Also print user-space stacks with eu-stack or gdb (attaches with ptrace)
+        #[arg(long)]
+        native: bool,
+    },
+
+    /// Summarize which system calls a process spends its time in, with strace or perf when
+    /// available and by sampling /proc otherwise
+    Trace {
+        /// Process selector (PID, name, or pid:/name:/exact:/re:/unit:/port:/window:/cwd:/file:, optionally after newest:/oldest:)
+        process: String,
+
+        /// How long to trace, e.g. 5s or 1m
+        #[arg(long, default_value = "5s", value_parser = report::parse_duration)]
+        duration: Duration,
+
+        /// Tracing method
+        #[arg(long, value_enum, default_value = "auto")]
+        method: trace::TraceMethod,
+    },
+
+    /// Show the files a process has open, or with --watch, the files it opens and closes
+    Files {
+        /// Process selector (PID, name, or pid:/name:/exact:/re:/unit:/port:/window:/cwd:/file:, optionally after newest:/oldest:)
+        process: String,
+
+        /// Keep polling and print each file as it is opened or closed
+        #[arg(short, long)]
+        watch: bool,
+
+        /// Polling interval for --watch, e.g. 100ms or 1s
+        #[arg(long, default_value = "100ms", value_parser = report::parse_duration, requires = "watch")]
+        interval: Duration,
+
+        /// Only show files under this path (repeatable)
+        #[arg(long = "path", value_name = "PATH")]
+        paths: Vec<String>,
+
+        /// Never show files under this path, e.g. /usr/lib (repeatable)
+        #[arg(long, value_name = "PATH")]
+        exclude: Vec<String>,
+
+        /// Also show sockets, pipes, and other descriptors without a path
+        #[arg(long)]
+        all: bool,
+    },
+
+    /// Group processes by the namespaces they are in, leaving out the host's: containers,
+    /// sandboxes, and anything started with unshare
+    Namespaces {
+        /// Only these namespace types (repeatable)
+        #[arg(long = "type", value_enum, value_name = "TYPE")]
+        kinds: Vec<namespaces::Kind>,
+
+        /// Also list the host's namespaces
+        #[arg(long)]
+        all: bool,
+    },
+
+    /// List login sessions, local and SSH, with the client address and the processes each one
+    /// is running
+    Sessions {
+        /// Terminate every process in the session with this ID (SIGTERM)
+        #[arg(long, value_name = "ID")]
+        kill: Option<String>,
+
+        /// Don't ask for confirmation when several processes would be terminated
+        #[arg(short, long, requires = "kill")]
+        yes: bool,
+    },
+
+    /// Show the heaviest processes across several machines, polled over SSH
+    Fleet {
+        /// SSH destination to poll instead of the configured [[host]] entries (repeatable)
+        #[arg(long = "host", value_name = "[USER@]HOST")]
+        hosts: Vec<String>,
+
+        /// Order processes by memory or CPU usage
+        #[arg(long, value_enum, default_value = "mem")]
+        sort: fleet::FleetSort,
+
+        /// How many processes to show across all hosts
+        #[arg(long, default_value = "20")]
+        limit: usize,
+    },
+
+    /// Serve switch, window listing, and process info on the session bus as org.tophelper.Helper
+    DbusServe,
+
+    /// Show recent window focus changes, or record them with --watch
+    FocusHistory {
+        /// Poll the focused window and record each change, for `switch` to prefer recent windows
+        #[arg(long)]
+        watch: bool,
+
+        /// How often to poll with --watch, e.g. 500ms or 1s
+        #[arg(long, default_value = "500ms", value_parser = report::parse_duration, requires = "watch")]
+        interval: Duration,
+
+        /// Number of recent focus changes to show
+        #[arg(long, default_value = "20", conflicts_with = "watch")]
+        recent: usize,
+    },
+
+    /// Keep history recording, alert monitoring, and focus recording running in the background
+    Daemon {
+        #[command(subcommand)]
+        action: DaemonAction,
+    },
+
+    /// Show which processes have a file or directory open, mapped, or as their working directory
+    WhoHas {
+        /// File or directory
+        path: String,
+
+        /// Also check memory-mapped files (shared libraries, mmapped data)
+        #[arg(long)]
+        maps: bool,
+
+        /// Terminate the processes holding the path (SIGTERM)
+        #[arg(long)]
+        kill: bool,
+
+        /// Don't ask for confirmation when several processes would be terminated
+        #[arg(short, long, requires = "kill")]
+        yes: bool,
+    },
+
+    /// Show a process's full descendant tree with memory and CPU totals
+    Children {
+        /// Process selector (PID, name, or pid:/name:/exact:/re:/unit:/port:/window:/cwd:/file:, optionally after newest:/oldest:)
+        process: String,
+
+        /// Print the tree as a diagram instead of text
+        #[arg(long, value_name = "FORMAT", conflicts_with = "kill_tree")]
+        output: Option<DiagramFormat>,
+
+        /// Terminate the whole subtree with SIGTERM, children before their parents
+        #[arg(long)]
+        kill_tree: bool,
+
+        /// Don't ask for confirmation before terminating the tree
+        #[arg(short, long, requires = "kill_tree")]
+        yes: bool,
+
+        /// Show the tree of every unrelated matching process instead of the likeliest one
+        #[arg(long, conflicts_with_all = ["output", "kill_tree"])]
+        all: bool,
+    },
+
+    /// Wait for a process to exit, then notify or run a command
+    Wait {
+        /// Process selector (PID, name, or pid:/name:/exact:/re:/unit:/port:/window:/cwd:/file:, optionally after newest:/oldest:)
+        process: String,
+
+        /// Send a desktop notification when it exits (same as `--sink desktop`)
+        #[arg(long)]
+        notify: bool,
+
+        /// Also deliver the exit summary to these sinks (repeatable)
+        #[arg(long = "sink", value_name = "NAME")]
+        sinks: Vec<String>,
+
+        /// Shell command to run afterwards, with TOP_HELPER_PID, TOP_HELPER_NAME,
+        /// TOP_HELPER_EXIT_CODE, TOP_HELPER_SIGNAL, and TOP_HELPER_RUNTIME_SECS set
+        #[arg(long, value_name = "CMD")]
+        exec: Option<String>,
+    },
+
+    /// Run a command and summarize its resource usage when it exits (like `time -v`)
+    Run {
+        /// Show live memory, CPU, and process count while it runs
+        #[arg(long)]
+        watch: bool,
+
+        /// Summary format
+        #[arg(long, value_enum, default_value = "text")]
+        format: OutputFormat,
+
+        /// Write the summary to a file instead of stderr
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+
+        /// The command to run, after `--`
+        #[arg(last = true, required = true, value_name = "COMMAND")]
+        command: Vec<String>,
+    },
+
+    /// Benchmark a shell command, or compare two, over several runs
+    Bench {
+        /// Number of runs per command
+        #[arg(long, default_value = "5")]
+        runs: usize,
+
+        /// Let the commands print to the terminal instead of discarding their output
+        #[arg(long)]
+        show_output: bool,
+
+        /// One or two shell commands, after `--`
+        #[arg(last = true, required = true, num_args = 1..=2, value_name = "COMMAND")]
+        commands: Vec<String>,
+    },
+
+    /// Group processes by controlling terminal, to see what runs in each tab
+    Terminals,
+
+    /// List terminal emulator tabs with their shell, foreground job, and working directory
+    Tabs,
+
+    /// Find terminals that have been idle for hours, to switch to or close
+    IdleShells {
+        /// Minimum time without input or output on the terminal
+        #[arg(long, default_value = "4")]
+        hours: f64,
+
+        /// Close the idle terminals' shells (SIGHUP, as when the window is closed)
+        #[arg(long)]
+        close: bool,
+
+        /// Don't ask for confirmation before closing several shells
+        #[arg(short, long, requires = "close")]
+        yes: bool,
+    },
+
+    /// Find GUI applications whose windows haven't had focus for hours but still use memory, to
+    /// switch to, minimize, or close (needs focus history from the daemon or `focus-history --watch`)
+    Idle {
+        /// Minimum time since any of the application's windows had focus
+        #[arg(long, default_value = "4")]
+        hours: f64,
+
+        /// Only applications using at least this much memory with their child processes, in MB
+        #[arg(long, value_name = "MB", default_value = "200")]
+        min_memory: u64,
+    },
+
+    /// Show per-core CPU utilization, frequencies, and temperatures
+    Cpu {
+        /// Sampling interval in seconds
+        #[arg(long, default_value = "1")]
+        interval: u64,
+
+        /// Keep redrawing until interrupted
+        #[arg(long)]
+        watch: bool,
+
+        /// How much each redraw keeps of the previous values, from 0 (raw samples) to just
+        /// under 1 (very slow to change)
+        #[arg(long, default_value = "0.5", value_parser = smoothing::parse_factor, requires = "watch")]
+        smoothing: f64,
+
+        /// Also show CPU pressure stall information (PSI)
+        #[arg(long)]
+        pressure: bool,
+    },
+
+    /// Print a one-line CPU and memory summary for status bars (Waybar, Polybar, i3blocks)
+    Status {
+        /// Output format
+        #[arg(long, value_enum, default_value = "text")]
+        format: StatusFormat,
+
+        /// Number of top CPU and memory consumers to list in the tooltip
+        #[arg(long, default_value = "5")]
+        top: usize,
+    },
+
+    /// Show mounted filesystems with space and inode usage, and the processes doing I/O on each
+    Disks {
+        /// How long to measure process I/O for, in seconds
+        #[arg(long, default_value = "1")]
+        interval: u64,
+
+        /// Also show read-only squashfs images (e.g. snaps)
+        #[arg(long)]
+        all: bool,
+
+        /// Keep measuring and redrawing until interrupted
+        #[arg(long)]
+        watch: bool,
+
+        /// How much each redraw keeps of the previous I/O rates, from 0 (raw samples) to just
+        /// under 1 (very slow to change)
+        #[arg(long, default_value = "0.5", value_parser = smoothing::parse_factor, requires = "watch")]
+        smoothing: f64,
+    },
+
+    /// Show temperatures and fan speeds from the hardware sensors
+    Sensors {
+        /// Keep redrawing until interrupted
+        #[arg(long)]
+        watch: bool,
+
+        /// Refresh interval in seconds for --watch
+        #[arg(long, default_value = "2")]
+        interval: u64,
+    },
+
+    /// Rank processes by estimated energy impact to find battery drainers
+    Power {
+        /// Sampling interval in seconds
+        #[arg(long, default_value = "2")]
+        interval: u64,
+
+        /// Number of processes to show
+        #[arg(long, default_value = "15")]
+        top: usize,
+    },
+
+    /// Show the system memory breakdown, memory pressure, and the biggest swap users
+    Memory {
+        /// Suggest which processes to stop to free this much memory (e.g. 500M or 2G)
+        #[arg(long, value_name = "SIZE")]
+        free: Option<MemoryLimit>,
+    },
+
+    /// Explain a process's virtual, resident, proportional, unique, and swapped memory
+    ExplainMemory {
+        /// Process selector (PID, name, or pid:/name:/exact:/re:/unit:/port:/window:/cwd:/file:, optionally after newest:/oldest:)
+        process: String,
+    },
+
+    /// Save the current process set, or show what changed since a saved one
+    Snapshot {
+        #[command(subcommand)]
+        action: SnapshotAction,
+    },
+
+    /// Find processes whose memory keeps growing, sorted by how fast
+    Leaks {
+        /// Seconds between samples
+        #[arg(long, default_value = "60")]
+        interval: u64,
+
+        /// How long to watch (e.g. 30m, 1h), or with --history how far back to look
+        #[arg(long, default_value = "1h", value_parser = report::parse_duration)]
+        window: Duration,
+
+        /// Growth rate in MB per hour from which a process is reported
+        #[arg(long, default_value = "10")]
+        min_rate: f64,
+
+        /// Analyze the samples taken by `record` instead of sampling now
+        #[arg(long, conflicts_with = "interval")]
+        history: bool,
     },
 
     /// Switch to the window containing the specified process
     Switch {
-        /// Process ID or name
+        /// Process selector (PID, name, or pid:/name:/exact:/re:/unit:/port:/window:/cwd:/file:, optionally after newest:/oldest:)
+        #[arg(required_unless_present = "cwd")]
+        process: Option<String>,
+
+        /// Only match processes whose working directory is inside this path
+        #[arg(long)]
+        cwd: Option<String>,
+
+        /// Resolve the window and print the action without switching
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Output format
+        #[arg(long, value_enum, default_value = "text")]
+        format: OutputFormat,
+
+        /// Which of the process's windows to switch to, counting from 1 (after --title)
+        #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+        window_index: Option<u32>,
+
+        /// Only consider windows whose title contains this (case-insensitive)
+        #[arg(long)]
+        title: Option<String>,
+
+        /// Go to the process's next window, one further on each run
+        #[arg(long, conflicts_with = "window_index")]
+        cycle: bool,
+    },
+
+    /// Print windows and terminal tabs for rofi or dmenu, or act on the line picked from them
+    Menu {
+        /// Add rofi row options (window icons) to each line
+        #[arg(long)]
+        rofi: bool,
+
+        /// A line picked from the menu, to act on instead of printing the menu
+        #[arg(long, value_name = "LINE")]
+        select: Option<String>,
+
+        /// What to do with the picked process
+        #[arg(long, value_enum, default_value = "switch", requires = "select")]
+        action: menu::MenuAction,
+    },
+
+    /// Freeze processes with SIGSTOP without killing them
+    Pause {
+        /// Process selector (PID, name, or pid:/name:/exact:/re:/unit:/port:/window:/cwd:/file:, optionally after newest:/oldest:)
         process: String,
+
+        /// Also pause all descendants of the matched processes
+        #[arg(long)]
+        tree: bool,
+
+        /// Don't ask for confirmation when several processes match
+        #[arg(short, long)]
+        yes: bool,
+    },
+
+    /// Resume processes stopped with `pause` (SIGCONT)
+    Resume {
+        /// Process selector (PID, name, or pid:/name:/exact:/re:/unit:/port:/window:/cwd:/file:, optionally after newest:/oldest:)
+        process: String,
+
+        /// Also resume all descendants of the matched processes
+        #[arg(long)]
+        tree: bool,
+
+        /// Don't ask for confirmation when several processes match
+        #[arg(short, long)]
+        yes: bool,
+    },
+
+    /// Show or change the CPU nice value and I/O priority of processes
+    Priority {
+        /// Process selector (PID, name, or pid:/name:/exact:/re:/unit:/port:/window:/cwd:/file:, optionally after newest:/oldest:)
+        process: String,
+
+        /// Set the nice value (-20 highest priority to 19 lowest)
+        #[arg(long, allow_hyphen_values = true, value_parser = clap::value_parser!(i32).range(-20..=19))]
+        nice: Option<i32>,
+
+        /// Set the I/O class: idle, best-effort[:0-7], or realtime[:0-7]
+        #[arg(long, value_name = "CLASS[:LEVEL]")]
+        ionice: Option<IoPriority>,
+
+        /// Also apply to all descendants of the matched processes
+        #[arg(long)]
+        tree: bool,
+    },
+
+    /// Throttle a running process and its descendants with cgroup memory/CPU limits
+    Limit {
+        /// Process selector (PID, name, or pid:/name:/exact:/re:/unit:/port:/window:/cwd:/file:, optionally after newest:/oldest:)
+        process: String,
+
+        /// Maximum memory, e.g. 512M or 2G
+        #[arg(long)]
+        memory: Option<MemoryLimit>,
+
+        /// Maximum CPU as a percentage of one core, e.g. 50% or 200%
+        #[arg(long)]
+        cpu: Option<CpuLimit>,
+    },
+
+    /// Set oom_score_adj to protect processes from (or offer them to) the OOM killer
+    OomAdjust {
+        /// Process selector (PID, name, or pid:/name:/exact:/re:/unit:/port:/window:/cwd:/file:, optionally after newest:/oldest:)
+        process: String,
+
+        /// Adjustment from -1000 (never kill) to 1000 (kill first)
+        #[arg(allow_hyphen_values = true, value_parser = clap::value_parser!(i32).range(-1000..=1000))]
+        value: i32,
+    },
+
+    /// Show which process owns the clipboard and primary selection
+    ClipboardOwner,
+
+    /// Find which processes hold keyboard/pointer grabs (X11) or exclusive evdev grabs
+    InputGrabs,
+
+    /// Show which processes sent recent desktop notifications
+    Notifications {
+        /// Monitor the session bus and record notifications as they arrive
+        #[arg(long)]
+        watch: bool,
+
+        /// Number of recent notifications (or senders) to show
+        #[arg(long, default_value = "20", num_args = 0..=1, default_missing_value = "20")]
+        recent: usize,
+
+        /// Count notifications per sending process to find spammy apps
+        #[arg(long, conflicts_with = "watch")]
+        by_process: bool,
+    },
+
+    /// List background agents (autostart entries, user services, tray apps) and what they cost
+    Agents {
+        /// Stop an autostart entry or user service from starting with the session
+        #[arg(long, value_name = "NAME", conflicts_with = "enable")]
+        disable: Option<String>,
+
+        /// Re-enable a disabled autostart entry or user service
+        #[arg(long, value_name = "NAME")]
+        enable: Option<String>,
+    },
+
+    /// Show what slows down boot and login, and which autostarted agents are still heavy
+    Boot {
+        /// Number of slowest units to show
+        #[arg(long, default_value = "10")]
+        top: usize,
+
+        /// Memory from which an agent counts as heavy, in MB
+        #[arg(long, value_name = "MB", default_value = "100")]
+        min_memory: u64,
+
+        /// Stop an autostart entry or user service from starting with the session, as
+        /// `agents --disable` does
+        #[arg(long, value_name = "NAME")]
+        disable: Option<String>,
+    },
+
+    /// Sample all processes into the history database
+    Record {
+        /// Seconds between samples
+        #[arg(long, default_value = "60")]
+        interval: u64,
+
+        /// Take a single sample and exit (e.g. from cron or a systemd timer)
+        #[arg(long)]
+        once: bool,
+
+        /// History database to write (default ~/.local/share/top-helper/history.db)
+        #[arg(long, value_name = "PATH")]
+        db: Option<PathBuf>,
+    },
+
+    /// Search the history database for samples matching a condition
+    Query {
+        /// Comparisons joined by `and`, e.g. "name=chrome and mem>2000"; fields are name, pid,
+        /// mem (MB), and cpu (%), and `~` matches part of a name
+        #[arg(long = "where", value_name = "EXPR")]
+        condition: Option<query::Condition>,
+
+        /// Only search samples from this long ago until now (e.g. 30m, 1h, 2d)
+        #[arg(long, value_parser = report::parse_duration)]
+        since: Option<Duration>,
+
+        /// Maximum number of samples to show
+        #[arg(long, default_value = "100")]
+        limit: usize,
+
+        /// History database to read (default ~/.local/share/top-helper/history.db)
+        #[arg(long, value_name = "PATH")]
+        db: Option<PathBuf>,
+    },
+
+    /// Evaluate the [[alert]] rules from the config file and deliver alerts to their sinks, and run
+    /// the [[hook]] commands on process events
+    Monitor {
+        /// Seconds between evaluations
+        #[arg(long, default_value = "10")]
+        interval: u64,
+
+        /// How much each evaluation keeps of the previous values, from 0 (compare raw samples)
+        /// to just under 1, so brief spikes and dips count for less
+        #[arg(long, default_value = "0", value_parser = smoothing::parse_factor)]
+        smoothing: f64,
+
+        /// Output format; ndjson also writes every process at every evaluation
+        #[arg(long, value_enum, default_value = "text")]
+        format: MonitorFormat,
+
+        /// Also send every process's memory, CPU, and disk I/O at every evaluation to this
+        /// OpenTelemetry collector, e.g. http://collector:4317 (gRPC) or http://collector:4318 (HTTP)
+        #[arg(long, value_name = "URL")]
+        otlp_endpoint: Option<String>,
+
+        /// OTLP protocol of the collector's receiver; by default gRPC on port 4317 and HTTP otherwise
+        #[arg(long, value_enum, requires = "otlp_endpoint")]
+        otlp_protocol: Option<otlp::Protocol>,
+    },
+
+    /// Terminate, pause, or throttle processes that break the [[policy]] rules from the config file
+    Enforce {
+        /// How often to check, e.g. 30s; a rule's confirmations are this far apart
+        #[arg(long, default_value = "30s", value_parser = report::parse_duration)]
+        interval: Duration,
+
+        /// Only report and log what would be done
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Show the most recent actions from the audit log instead of enforcing
+        #[arg(long, value_name = "N", num_args = 0..=1, default_missing_value = "20", conflicts_with = "dry_run")]
+        log: Option<usize>,
+    },
+
+    /// Summarize recorded history
+    Report {
+        /// Summarize the last 24 hours
+        #[arg(long, required = true)]
+        daily: bool,
+
+        /// Where to send the report: a sink from the config file, `stdout`, `desktop`, or
+        /// `file:PATH` (repeatable; default stdout)
+        #[arg(long = "sink", value_name = "NAME")]
+        sinks: Vec<String>,
+    },
+
+    /// Group processes by the git repository or project their working directory belongs to
+    Projects {
+        /// Only show per-project totals, not the member processes
+        #[arg(long)]
+        summary: bool,
+    },
+
+    /// Group development processes (language servers, dev servers, test runners, databases,
+    /// ...) by category with per-category totals
+    Devtools {
+        /// Only show per-category totals, not the member processes
+        #[arg(long)]
+        summary: bool,
+    },
+
+    /// Attribute compositor/display server load to the client windows causing it
+    Compositor {
+        /// Sampling interval in seconds
+        #[arg(long, default_value = "2")]
+        interval: u64,
+    },
+
+    /// Show which local processes talk to each other over localhost TCP and Unix sockets
+    Graph {
+        /// Print the graph as a diagram instead of a table
+        #[arg(long, value_name = "FORMAT")]
+        output: Option<DiagramFormat>,
+
+        /// Shorthand for `--output dot`
+        #[arg(long, conflicts_with = "output")]
+        dot: bool,
+    },
+
+    /// List windows with their owning processes
+    Windows {
+        /// Enumerate windows on every local X display
+        #[arg(long, conflicts_with = "display")]
+        all_displays: bool,
+    },
+
+    /// Check which optional tools and permissions are available, and what to install or change to
+    /// enable degraded or missing features
+    Doctor,
+
+    /// Collect an anonymized diagnostics bundle to attach to an issue
+    BugReport {
+        /// Process whose window could not be found, to record each backend's lookup
+        #[arg(long)]
+        process: Option<String>,
+
+        /// Where to write the tarball (default: top-helper-bug-report-<time>.tar.gz)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Record the process table as a fixture directory for `--fixture`
+    #[command(hide = true)]
+    SaveFixture {
+        dir: PathBuf,
+    },
+
+    /// Open a dummy X11 window and keep it open (used by --test-env)
+    #[command(hide = true)]
+    TestWindow {
+        #[arg(long)]
+        title: String,
+
+        #[arg(long)]
+        class: String,
     },
+
+    /// Wait for a button press on a desktop notification and run its action (started by the
+    /// notification sink)
+    #[command(hide = true)]
+    NotificationActions {
+        /// The D-Bus notification whose buttons to wait for
+        #[arg(long, conflicts_with = "pressed")]
+        id: Option<u32>,
+
+        /// The key of the button already pressed, as notify-send --wait prints it
+        #[arg(long)]
+        pressed: Option<String>,
+
+        /// KIND:PID:START_TIME of each button
+        actions: Vec<notify::Action>,
+    },
+}
+
+#[derive(Subcommand)]
+enum SnapshotAction {
+    /// Save the current processes under a name, replacing any earlier snapshot of that name
+    Save {
+        name: String,
+    },
+
+    /// Show processes that are new, gone, exec'd, or grown by 10 MB or more since the snapshot
+    Diff {
+        name: String,
+    },
+
+    /// List saved snapshots
+    List,
+}
+
+#[derive(Subcommand)]
+enum DaemonAction {
+    /// Start the daemon with the session (a systemd user service, or an XDG autostart entry
+    /// without a user manager) and start it now
+    Install {
+        /// Use an XDG autostart entry even when a systemd user manager is running
+        #[arg(long)]
+        autostart: bool,
+    },
+
+    /// Stop the daemon and remove its service or autostart entry
+    Uninstall,
+
+    /// Start the installed daemon
+    Start,
+
+    /// Stop the running daemon
+    Stop,
+
+    /// Show how the daemon is installed and whether it is running
+    Status,
+
+    /// Run the daemon in the foreground; this is what the service and autostart entry start
+    Run,
 }
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() -> ExitCode {
     let cli = Cli::parse();
 
+    if cli.quiet {
+        output::silence_output();
+    }
+    // With --quiet, stderr already goes nowhere
+    if let Err(err) = logging::init(cli.verbose, cli.log_file.as_deref()) {
+        eprintln!("Warning: continuing without the log file: {:#}", err);
+    }
+    if cli.no_redact {
+        redact::disable();
+    }
+    match run(&cli).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            let code = error::exit_code(&err);
+            if !cli.quiet {
+                if matches!(cli.command, Commands::Switch { format: OutputFormat::Json, .. }) {
+                    eprintln!("{}", error::json_error(&err, code));
+                } else {
+                    eprintln!("Error: {:#}", err);
+                }
+            }
+            ExitCode::from(code)
+        }
+    }
+}
+
+async fn run(cli: &Cli) -> Result<()> {
+    if let Some(name) = &cli.profile {
+        config::request_profile(name);
+    }
+    let config = match Config::load() {
+        Ok(config) => config,
+        Err(err) if cli.profile.is_some() => return Err(err),
+        Err(err) => {
+            if !cli.quiet {
+                eprintln!("Warning: continuing without the config file: {:#}", err);
+            }
+            Config::default()
+        }
+    };
+    let profile = config.profile().cloned().unwrap_or_default();
+    output::set_memory_units(cli.units.or(profile.units).unwrap_or_default());
+    selector::set_name_match(match (cli.exact, cli.word) {
+        (true, _) => NameMatch::Exact,
+        (_, true) => NameMatch::Word,
+        _ => NameMatch::Substring,
+    });
+    if let Some(backend) = profile.window_backend {
+        window::prefer_backend(backend);
+    }
+
+    if let Some(backend) = cli.test_env {
+        let code = run_in_test_env(backend, cli.test_windows)?;
+        if code != 0 {
+            std::process::exit(code);
+        }
+        return Ok(());
+    }
+
     match &cli.command {
-        Commands::List { name, high_memory, sort_memory, top_memory, top_cpu } => {
-            process::list_processes(name.as_deref(), *high_memory, *sort_memory, *top_memory, *top_cpu).await?;
+        Commands::List {
+            name,
+            cwd,
+            env,
+            high_memory,
+            kernel_threads,
+            no_kernel_threads: _,
+            include_self,
+            tty,
+            resolve_names,
+            flatpak,
+            snap,
+            sort,
+            limit,
+            offset,
+            page,
+            sort_memory,
+            top_memory,
+            top_cpu,
+            gpu,
+            columns,
+            sort_oom,
+            sort_threads,
+            sort_fds,
+            min_threads,
+            min_fds,
+            pss,
+            tree_memory,
+            power,
+            interactive,
+            porcelain,
+            format,
+            watch,
+            interval,
+            diff,
+            smoothing,
+        } => {
+            let limit = limit.or(*top_memory).or(*top_cpu);
+            let (limit, offset) = match page {
+                Some(page) => {
+                    let size = limit.unwrap_or(process::DEFAULT_PAGE_SIZE);
+                    (Some(size), (*page as usize - 1) * size)
+                }
+                None => (limit, *offset),
+            };
+            let options = process::ListOptions {
+                name_filter: name.clone(),
+                cwd: cwd.as_deref().map(selector::resolve_path),
+                env: env.clone(),
+                high_memory: high_memory.then(|| profile.high_memory_mb.unwrap_or(process::HIGH_MEMORY_MB)),
+                min_threads: *min_threads,
+                min_fds: *min_fds,
+                pss: *pss,
+                tree_memory: *tree_memory,
+                kernel_threads: *kernel_threads,
+                include_self: *include_self,
+                tty: tty.clone(),
+                resolve_names: *resolve_names,
+                sandbox: if *flatpak {
+                    Some(Packaging::Flatpak)
+                } else if *snap {
+                    Some(Packaging::Snap)
+                } else {
+                    None
+                },
+                sort: sort
+                    .or(sort_memory.then(|| SortKey::new(SortField::Memory)))
+                    .or(top_memory.map(|_| SortKey::new(SortField::Memory)))
+                    .or(top_cpu.map(|_| SortKey::new(SortField::Cpu)))
+                    .or(sort_oom.then(|| SortKey::new(SortField::Oom)))
+                    .or(sort_threads.then(|| SortKey::new(SortField::Threads)))
+                    .or(sort_fds.then(|| SortKey::new(SortField::Fds))),
+                limit,
+                offset,
+                columns: {
+                    let mut columns = if columns.is_empty() { profile.columns.clone() } else { columns.clone() };
+                    columns.extend(gpu.then_some(ListColumn::Gpu));
+                    columns.extend(power.then_some(ListColumn::Power));
+                    columns
+                },
+                plugin_columns: config.plugin_columns()?,
+                interactive: *interactive,
+                porcelain: *porcelain,
+                format: *format,
+                fixture: cli.fixture.clone(),
+                watch: watch.then(|| Duration::from_secs(*interval)),
+                diff: *diff,
+                smoothing: *smoothing,
+                display: cli.display.clone(),
+            };
+            process::list_processes(&options).await?;
+        }
+        Commands::Get { process, field } => {
+            get::get_field(process, *field).await?;
+        }
+        Commands::Explain { process } => {
+            explain::explain_process(process).await?;
+        }
+        Commands::Stack { process, kernel, native } => {
+            stack::show_stack(process, *kernel, *native).await?;
+        }
+        Commands::Trace { process, duration, method } => {
+            trace::trace_process(process, *duration, *method).await?;
+        }
+        Commands::Info { processes, cwd, all_matches, table, porcelain, format, security, namespaces, sched, faults } => {
+            let options = process::InfoOptions {
+                cwd: cwd.clone(),
+                all_matches: *all_matches,
+                table: *table,
+                porcelain: *porcelain,
+                format: *format,
+                security: *security,
+                namespaces: *namespaces,
+                sched: *sched,
+                faults: *faults,
+            };
+            process::show_process_info(processes, &options).await?;
+        }
+        Commands::Files { process, watch, interval, paths, exclude, all } => {
+            let filter = files::FileFilter::new(paths, exclude, *all);
+            files::show_files(process, &filter, watch.then_some(*interval)).await?;
+        }
+        Commands::Namespaces { kinds, all } => {
+            namespaces::show_namespaces(kinds, *all)?;
+        }
+        Commands::Sessions { kill, yes } => {
+            logins::show_sessions(kill.as_deref(), *yes).await?;
+        }
+        Commands::Fleet { hosts, sort, limit } => {
+            fleet::show_fleet(hosts, *sort, *limit).await?;
+        }
+        Commands::DbusServe => {
+            dbus_service::serve().await?;
         }
-        Commands::Info { process } => {
-            process::show_process_info(process).await?;
+        Commands::FocusHistory { watch, interval, recent } => {
+            if *watch {
+                focus::watch_focus(*interval, cli.display.as_deref()).await?;
+            } else {
+                focus::show_focus_history(*recent)?;
+            }
         }
-        Commands::Switch { process } => {
-            window::switch_to_process_window(process).await?;
+        Commands::Daemon { action } => match action {
+            DaemonAction::Install { autostart } => daemon::install(*autostart)?,
+            DaemonAction::Uninstall => daemon::uninstall()?,
+            DaemonAction::Start => daemon::start()?,
+            DaemonAction::Stop => daemon::stop()?,
+            DaemonAction::Status => daemon::status()?,
+            DaemonAction::Run => daemon::run(cli.display.as_deref()).await?,
+        },
+        Commands::WhoHas { path, maps, kill, yes } => {
+            holders::show_holders(path, *maps, *kill, *yes)?;
+        }
+        Commands::Children { process, output, kill_tree, yes, all } => {
+            children::show_children(process, *output, *kill_tree, *yes, *all).await?;
+        }
+        Commands::Wait { process, notify, sinks, exec } => {
+            let mut sinks = sinks.clone();
+            if *notify {
+                sinks.push("desktop".to_string());
+            }
+            wait::wait_for_exit(process, &sinks, exec.as_deref())?;
+        }
+        Commands::Run { watch, format, output, command } => {
+            let code = run::run_command(command, *watch, *format, output.as_deref())?;
+            if code != 0 {
+                std::process::exit(code);
+            }
+        }
+        Commands::Bench { runs, show_output, commands } => {
+            bench::bench(commands, *runs, *show_output)?;
+        }
+        Commands::Tabs => {
+            tabs::show_tabs()?;
+        }
+        Commands::Terminals => {
+            terminals::show_terminals()?;
+        }
+        Commands::IdleShells { hours, close, yes } => {
+            idle::show_idle_shells(*hours, *close, *yes).await?;
+        }
+        Commands::Idle { hours, min_memory } => {
+            idle::show_idle_apps(*hours, *min_memory, cli.display.as_deref()).await?;
+        }
+        Commands::Cpu { interval, watch, smoothing, pressure } => {
+            cpu::show_cpu(Duration::from_secs(*interval), *watch, *smoothing, *pressure).await?;
+        }
+        Commands::Status { format, top } => {
+            status::show_status(*format, *top).await?;
+        }
+        Commands::Disks { interval, all, watch, smoothing } => {
+            disks::show_disks(Duration::from_secs(*interval), *all, *watch, *smoothing).await?;
+        }
+        Commands::Sensors { watch, interval } => {
+            sensors::show_sensors(Duration::from_secs(*interval), *watch).await?;
+        }
+        Commands::Power { interval, top } => {
+            power::show_power(Duration::from_secs(*interval), *top).await?;
+        }
+        Commands::Memory { free } => {
+            memory::show_memory(free.map(|size| size.bytes()))?;
+        }
+        Commands::ExplainMemory { process } => {
+            memory::explain_memory(process)?;
+        }
+        Commands::Snapshot { action } => match action {
+            SnapshotAction::Save { name } => snapshot::save(name)?,
+            SnapshotAction::Diff { name } => snapshot::diff(name)?,
+            SnapshotAction::List => snapshot::list()?,
+        },
+        Commands::Leaks { interval, window, min_rate, history } => {
+            leaks::find_leaks(Duration::from_secs(*interval), *window, *min_rate, *history).await?;
+        }
+        Commands::Switch { process, cwd, dry_run, format, window_index, title, cycle } => {
+            let choice = window::WindowChoice {
+                index: window_index.map(|index| index as usize),
+                title: title.clone(),
+                prompt: !*cycle,
+                cycle: *cycle,
+            };
+            window::switch_to_process_window(
+                process.as_deref(),
+                cwd.as_deref(),
+                *dry_run,
+                *format,
+                cli.display.as_deref(),
+                &choice,
+            )
+            .await?;
+        }
+        Commands::Menu { rofi, select, action } => match select {
+            Some(line) => menu::select(line, *action, cli.display.as_deref()).await?,
+            None => menu::show_menu(cli.display.as_deref(), *rofi)?,
+        },
+        Commands::Pause { process, tree, yes } => {
+            control::signal_processes(process, Signal::Stop, *tree, *yes)?;
+        }
+        Commands::Resume { process, tree, yes } => {
+            control::signal_processes(process, Signal::Continue, *tree, *yes)?;
+        }
+        Commands::Priority { process, nice, ionice, tree } => {
+            priority::manage_priority(process, *nice, *ionice, *tree)?;
+        }
+        Commands::Limit { process, memory, cpu } => {
+            limit::limit_process(process, *memory, *cpu)?;
+        }
+        Commands::OomAdjust { process, value } => {
+            oom::adjust_oom_score(process, *value)?;
+        }
+        Commands::ClipboardOwner => {
+            clipboard::show_clipboard_owner(cli.display.as_deref())?;
+        }
+        Commands::InputGrabs => {
+            input::show_input_grabs(cli.display.as_deref())?;
+        }
+        Commands::Notifications { watch, recent, by_process } => {
+            if *watch {
+                notifications::watch_notifications().await?;
+            } else {
+                notifications::show_recent_notifications(*recent, *by_process)?;
+            }
+        }
+        Commands::Agents { disable, enable } => match (disable, enable) {
+            (Some(name), _) => agents::set_agent_enabled(name, false)?,
+            (None, Some(name)) => agents::set_agent_enabled(name, true)?,
+            (None, None) => agents::show_agents()?,
+        },
+        Commands::Boot { top, min_memory, disable } => match disable {
+            Some(name) => agents::set_agent_enabled(name, false)?,
+            None => boot::show_boot_report(*top, *min_memory)?,
+        },
+        Commands::Record { interval, once, db } => {
+            history::record(db.as_deref(), Duration::from_secs(*interval), *once).await?;
+        }
+        Commands::Query { condition, since, limit, db } => {
+            query::show_query(db.as_deref(), &condition.clone().unwrap_or_default(), *since, *limit)?;
+        }
+        Commands::Monitor { interval, smoothing, format, otlp_endpoint, otlp_protocol } => {
+            let exporter = otlp_endpoint.as_deref().map(|endpoint| otlp::Exporter::new(endpoint, *otlp_protocol)).transpose()?;
+            alerts::monitor(Duration::from_secs(*interval), *smoothing, *format, exporter).await?;
+        }
+        Commands::Enforce { interval, dry_run, log } => match log {
+            Some(limit) => policy::show_audit_log(*limit)?,
+            None => policy::enforce(*interval, *dry_run).await?,
+        },
+        Commands::Report { daily: _, sinks } => {
+            report::daily_report(sinks)?;
+        }
+        Commands::Projects { summary } => {
+            project::show_projects(*summary).await?;
+        }
+        Commands::Devtools { summary } => {
+            devtools::show_devtools(*summary).await?;
+        }
+        Commands::Compositor { interval } => {
+            compositor::show_compositor_attribution(*interval).await?;
+        }
+        Commands::Graph { output, dot } => {
+            graph::show_graph(if *dot { Some(DiagramFormat::Dot) } else { *output })?;
+        }
+        Commands::Windows { all_displays } => {
+            window::list_windows(cli.display.as_deref(), *all_displays).await?;
+        }
+        Commands::Doctor => {
+            doctor::run_doctor(cli.display.as_deref())?;
+        }
+        Commands::BugReport { process, output } => {
+            bugreport::bug_report(output.as_deref(), process.as_deref())?;
+        }
+        Commands::SaveFixture { dir } => {
+            fixture::save_fixture(dir).await?;
+        }
+        Commands::TestWindow { title, class } => {
+            testenv::run_test_window(title, class)?;
+        }
+        Commands::NotificationActions { id, pressed, actions } => {
+            notify::handle_actions(*id, pressed.as_deref(), actions).await?;
         }
     }
 
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Starts a headless display with dummy windows and runs this same command line against it,
+/// returning its exit code once the display has been torn down again.
+fn run_in_test_env(backend: Backend, windows: usize) -> Result<i32> {
+    let mut env = TestEnv::start(backend)?;
+    let mut pids = Vec::new();
+    for i in 1..=windows {
+        pids.push(env.spawn_window(&format!("top-helper test window {}", i), "TopHelperTest")?.to_string());
+    }
+    let display = env
+        .vars()
+        .iter()
+        .find(|(key, _)| key == "DISPLAY" || key == "WAYLAND_DISPLAY")
+        .map(|(key, value)| format!("{}={}", key, value))
+        .unwrap_or_default();
+    eprintln!("[top-helper] test environment on {} with windows from PIDs {}", display, pids.join(", "));
+
+    // Pass the command line on without the harness's own flags and their values
+    let mut args = Vec::new();
+    let mut skip_value = false;
+    for arg in std::env::args_os().skip(1) {
+        let text = arg.to_string_lossy();
+        if std::mem::take(&mut skip_value)
+            || text == "--test-env"
+            || text.starts_with("--test-env=")
+            || text.starts_with("--test-windows=")
+        {
+            continue;
+        }
+        if text == "--test-windows" {
+            skip_value = true;
+            continue;
+        }
+        args.push(arg);
+    }
+
+    let status = env.command(std::env::current_exe()?).args(args).status()?;
+    drop(env);
+    Ok(status.code().unwrap_or(1))
+}
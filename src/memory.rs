@@ -0,0 +1,344 @@
+use anyhow::{Context, Result};
+use procfs::{Current, Meminfo};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use sysinfo::System;
+use tabled::{Table, Tabled, settings::{Width, location::ByColumnName}};
+
+use crate::control;
+use crate::error::CliError;
+use crate::pressure;
+use crate::output::format_memory;
+use crate::selector::Selection;
+
+/// How many swap users to show.
+const TOP_SWAP_USERS: usize = 10;
+
+#[derive(Tabled)]
+struct SwapUser {
+    #[tabled(rename = "PID")]
+    pid: u32,
+
+    #[tabled(rename = "Name")]
+    name: String,
+
+    #[tabled(rename = "Swap")]
+    swap: String,
+
+    #[tabled(rename = "RSS")]
+    rss: String,
+}
+
+#[derive(Tabled)]
+struct Candidate {
+    #[tabled(rename = "PID")]
+    pid: u32,
+
+    #[tabled(rename = "Name")]
+    name: String,
+
+    #[tabled(rename = "RSS + Swap")]
+    footprint: String,
+
+    #[tabled(rename = "Freed so far")]
+    cumulative: String,
+
+    #[tabled(rename = "Command")]
+    command: String,
+}
+
+/// Resident and swapped-out memory of one process, in bytes.
+struct Footprint {
+    pid: u32,
+    name: String,
+    command: String,
+    rss: u64,
+    swap: u64,
+}
+
+/// Shows where system memory goes, memory pressure, the biggest swap users, and with
+/// `free_bytes`, which processes to stop to get that much back.
+pub fn show_memory(free_bytes: Option<u64>) -> Result<()> {
+    let meminfo = Meminfo::current().context("Failed to read /proc/meminfo")?;
+    let mut system = System::new_all();
+    system.refresh_all();
+
+    let available = meminfo.mem_available.unwrap_or(meminfo.mem_free + meminfo.cached + meminfo.buffers);
+    let used = meminfo.mem_total.saturating_sub(available);
+    let percent = |part: u64, total: u64| if total > 0 { part as f64 * 100.0 / total as f64 } else { 0.0 };
+
+    println!("Memory:");
+    println!("  Total:      {:>12}", format_memory(meminfo.mem_total));
+    println!("  Used:       {:>12}  ({:.0}%)", format_memory(used), percent(used, meminfo.mem_total));
+    println!("  Available:  {:>12}  (free plus reclaimable cache)", format_memory(available));
+    println!("  Free:       {:>12}", format_memory(meminfo.mem_free));
+    println!("  Cached:     {:>12}", format_memory(meminfo.cached + meminfo.buffers));
+    if let Some(shmem) = meminfo.shmem {
+        println!("  Shared:     {:>12}  (tmpfs and shared memory, not reclaimable)", format_memory(shmem));
+    }
+
+    println!("\nSwap:");
+    if meminfo.swap_total == 0 {
+        println!("  No swap configured");
+    } else {
+        let swap_used = meminfo.swap_total.saturating_sub(meminfo.swap_free);
+        println!("  Total:      {:>12}", format_memory(meminfo.swap_total));
+        println!("  Used:       {:>12}  ({:.0}%)", format_memory(swap_used), percent(swap_used, meminfo.swap_total));
+        println!("  Cached:     {:>12}  (also still in RAM)", format_memory(meminfo.swap_cached));
+    }
+
+    println!();
+    print!("{}", pressure::format("Memory", pressure::read("memory")));
+
+    let own = control::own_ancestry(&system);
+    let mut footprints: Vec<Footprint> = system
+        .processes()
+        .values()
+        .filter(|p| !control::is_thread(&system, p.pid().as_u32()))
+        .map(|p| {
+            let pid = p.pid().as_u32();
+            Footprint {
+                pid,
+                name: p.name().to_string(),
+                command: if p.cmd().is_empty() { p.name().to_string() } else { p.cmd().join(" ") },
+                rss: p.memory(),
+                swap: vm_swap(pid).unwrap_or(0),
+            }
+        })
+        .collect();
+
+    footprints.sort_by_key(|f| std::cmp::Reverse(f.swap));
+    let swap_users: Vec<SwapUser> = footprints
+        .iter()
+        .filter(|f| f.swap > 0)
+        .take(TOP_SWAP_USERS)
+        .map(|f| SwapUser { pid: f.pid, name: f.name.clone(), swap: format_memory(f.swap), rss: format_memory(f.rss) })
+        .collect();
+    if !swap_users.is_empty() {
+        println!("\nTop swap users:");
+        println!("{}", Table::new(swap_users));
+    }
+
+    let Some(target) = free_bytes else {
+        return Ok(());
+    };
+
+    // Largest first, so the fewest processes are needed; never suggest init or this shell
+    footprints.sort_by_key(|f| std::cmp::Reverse(f.rss + f.swap));
+    let mut freed = 0u64;
+    let mut candidates = Vec::new();
+    for footprint in footprints.into_iter().filter(|f| f.pid != 1 && !own.contains(&f.pid) && f.rss + f.swap > 0) {
+        if freed >= target {
+            break;
+        }
+        freed += footprint.rss + footprint.swap;
+        candidates.push(Candidate {
+            pid: footprint.pid,
+            name: footprint.name,
+            footprint: format_memory(footprint.rss + footprint.swap),
+            cumulative: format_memory(freed),
+            command: footprint.command,
+        });
+    }
+
+    println!("\nTo free {}, stop:", format_memory(target));
+    let pids: Vec<String> = candidates.iter().map(|c| c.pid.to_string()).collect();
+    let mut table = Table::new(candidates);
+    table.modify(ByColumnName::new("Command"), Width::truncate(50).suffix("..."));
+    println!("{}", table);
+    if freed < target {
+        println!("Even stopping all of these frees only about {}", format_memory(freed));
+    }
+    println!(
+        "\nEstimates count each process's resident and swapped memory; memory shared between processes is only \
+         freed once all of them exit, so the real gain can be lower. Stop them with `kill {}`",
+        pids.join(" ")
+    );
+
+    Ok(())
+}
+
+/// Swapped-out memory of a process (VmSwap in /proc/<pid>/status), in bytes.
+fn vm_swap(pid: u32) -> Option<u64> {
+    let status = procfs::process::Process::new(pid as i32).ok()?.status().ok()?;
+    status.vmswap.map(|kb| kb * 1024)
+}
+
+/// Totals over a process's memory mappings from /proc/<pid>/smaps, in bytes.
+#[derive(Default)]
+struct Breakdown {
+    virtual_size: u64,
+    rss: u64,
+    pss: u64,
+    /// Private pages, the USS: freed when the process exits
+    private: u64,
+    swap: u64,
+    /// Private pages not backed by a file: heap, stacks, and anonymous mappings
+    anonymous: u64,
+    /// Shared resident memory per mapped file (or kind of mapping), e.g. `libxul.so`
+    shared_by_mapping: HashMap<String, u64>,
+}
+
+/// Explains the difference between the virtual, resident, proportional, unique, and swapped
+/// memory of one process, with its own numbers and what they mean for it.
+pub fn explain_memory(identifier: &str) -> Result<()> {
+    let mut system = System::new_all();
+    system.refresh_all();
+    let process = Selection::parse(identifier)?.resolve_unique(&system)?;
+    let pid = process.pid().as_u32();
+
+    let breakdown = match read_breakdown(pid) {
+        Ok(breakdown) => breakdown,
+        Err(err) if err.kind() == std::io::ErrorKind::PermissionDenied => {
+            return Err(CliError::permission(format!(
+                "Not allowed to read the memory maps of {} (PID: {}); run as its owner or root",
+                process.name(),
+                pid
+            )));
+        }
+        Err(err) => return Err(err).with_context(|| format!("Failed to read /proc/{}/smaps", pid)),
+    };
+    let shared = breakdown.rss.saturating_sub(breakdown.private);
+
+    println!("{} (PID: {})\n", process.name(), pid);
+    let lines = [
+        ("Virtual", breakdown.virtual_size, "Address space reserved; untouched parts cost nothing"),
+        ("Resident (RSS)", breakdown.rss, "Pages in RAM now, counting pages shared with other processes in full"),
+        ("Proportional (PSS)", breakdown.pss, "RSS with each shared page divided among the processes sharing it"),
+        ("Unique (USS)", breakdown.private, "Pages only this process uses, freed when it exits"),
+        ("Swap", breakdown.swap, "Pages moved out to swap, read back in when touched"),
+    ];
+    for (label, bytes, meaning) in lines {
+        println!("  {:<20} {:>12}  {}", label, format_memory(bytes), meaning);
+    }
+
+    let mut shared_mappings: Vec<(&String, &u64)> = breakdown.shared_by_mapping.iter().filter(|(_, bytes)| **bytes > 0).collect();
+    shared_mappings.sort_by_key(|(_, bytes)| std::cmp::Reverse(**bytes));
+    if !shared_mappings.is_empty() {
+        println!("\nLargest shared mappings:");
+        for (name, bytes) in shared_mappings.iter().take(5) {
+            println!("  {:<40} {:>12}", name, format_memory(**bytes));
+        }
+    }
+
+    let mut explanation = if breakdown.rss == 0 {
+        "Nothing of it is in RAM right now.".to_string()
+    } else if shared > breakdown.private {
+        let top: Vec<&str> = shared_mappings.iter().take(3).map(|(name, _)| name.as_str()).collect();
+        format!(
+            "{} RSS but only {} unique; most of the rest is shared{}, which stays in memory as long as other \
+             processes use it. Its fair share of the total is the PSS, {}.",
+            format_memory(breakdown.rss),
+            format_memory(breakdown.private),
+            if top.is_empty() { String::new() } else { format!(" ({})", top.join(", ")) },
+            format_memory(breakdown.pss)
+        )
+    } else {
+        format!(
+            "Most of its {} RSS is its own: {} unique, of which {} is heap, stacks, and other anonymous memory. \
+             Stopping it would free about {}.",
+            format_memory(breakdown.rss),
+            format_memory(breakdown.private),
+            format_memory(breakdown.anonymous),
+            format_memory(breakdown.private)
+        )
+    };
+    if breakdown.swap > breakdown.rss / 10 && breakdown.swap > 0 {
+        explanation.push_str(&format!(
+            " Another {} is swapped out, so touching it again will be slow.",
+            format_memory(breakdown.swap)
+        ));
+    }
+    if breakdown.virtual_size > breakdown.rss.saturating_mul(4) {
+        explanation.push_str(&format!(
+            " The {} virtual size is mostly reserved address space and not a problem in itself.",
+            format_memory(breakdown.virtual_size)
+        ));
+    }
+    println!("\n{}", explanation);
+
+    Ok(())
+}
+
+/// Resident memory three ways, in bytes: counting shared pages in full (RSS), divided among the
+/// processes sharing them (PSS), and not at all (USS).
+#[derive(Debug, Clone, Copy)]
+pub struct SharedMemory {
+    pub rss: u64,
+    pub pss: u64,
+    pub uss: u64,
+}
+
+/// Reads /proc/<pid>/smaps_rollup, which the kernel sums for us; None when it is not readable,
+/// as other users' processes aren't without root.
+pub fn shared_memory(pid: u32) -> Option<SharedMemory> {
+    let content = fs::read_to_string(format!("/proc/{}/smaps_rollup", pid)).ok()?;
+    let mut memory = SharedMemory { rss: 0, pss: 0, uss: 0 };
+    for line in content.lines() {
+        let mut fields = line.split_whitespace();
+        let (Some(key), Some(kb)) = (fields.next(), fields.next().and_then(|value| value.parse::<u64>().ok())) else {
+            continue;
+        };
+        match key {
+            "Rss:" => memory.rss += kb * 1024,
+            "Pss:" => memory.pss += kb * 1024,
+            "Private_Clean:" | "Private_Dirty:" => memory.uss += kb * 1024,
+            _ => {}
+        }
+    }
+    Some(memory)
+}
+
+/// Sums the per-mapping counters of /proc/<pid>/smaps.
+fn read_breakdown(pid: u32) -> std::io::Result<Breakdown> {
+    let content = fs::read_to_string(format!("/proc/{}/smaps", pid))?;
+
+    let mut breakdown = Breakdown::default();
+    let mut mapping = String::new();
+    for line in content.lines() {
+        let mut fields = line.split_whitespace();
+        let Some(key) = fields.next() else {
+            continue;
+        };
+        let Some(key) = key.strip_suffix(':') else {
+            // A mapping header: address perms offset dev inode [path]
+            mapping = mapping_name(fields.nth(4).unwrap_or(""));
+            continue;
+        };
+        let Some(kb) = fields.next().and_then(|value| value.parse::<u64>().ok()) else {
+            continue;
+        };
+        let bytes = kb * 1024;
+        match key {
+            "Size" => breakdown.virtual_size += bytes,
+            "Rss" => breakdown.rss += bytes,
+            "Pss" => breakdown.pss += bytes,
+            "Private_Clean" | "Private_Dirty" => {
+                breakdown.private += bytes;
+                if mapping.starts_with('[') {
+                    breakdown.anonymous += bytes;
+                }
+            }
+            "Shared_Clean" | "Shared_Dirty" => *breakdown.shared_by_mapping.entry(mapping.clone()).or_default() += bytes,
+            "Swap" => breakdown.swap += bytes,
+            _ => {}
+        }
+    }
+    Ok(breakdown)
+}
+
+/// A readable name for a mapping's path column: the file name of mapped files and libraries,
+/// or a bracketed kind for memory not backed by a file.
+fn mapping_name(path: &str) -> String {
+    if path.is_empty() {
+        return "[anonymous]".to_string();
+    }
+    if path.starts_with('[') {
+        return path.to_string();
+    }
+    if path.starts_with("/memfd:") || path.starts_with("/dev/shm/") || path.starts_with("/SYSV") {
+        return "[shared memory]".to_string();
+    }
+    Path::new(path).file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_else(|| path.to_string())
+}
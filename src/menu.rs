@@ -0,0 +1,149 @@
+//! `menu`: windows and terminal tabs as lines for rofi or dmenu, and the action to take on the
+//! line the user picked, so a launcher bound to a hotkey can jump to (or kill) any of them.
+//!
+//! A line reads "name (PID) — window title — working directory". The PID and title are parsed
+//! back out of the picked line, the title to pick the right window of a process that has several.
+
+use anyhow::Result;
+use clap::ValueEnum;
+use std::env;
+use std::path::Path;
+use sysinfo::System;
+
+use crate::control::{self, Signal};
+use crate::error::CliError;
+use crate::output::OutputFormat;
+use crate::process;
+use crate::tabs;
+use crate::window::{self, WindowChoice};
+
+const SEPARATOR: &str = " — ";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum MenuAction {
+    Switch,
+    Kill,
+    Info,
+}
+
+struct Entry {
+    pid: u32,
+    name: String,
+    title: String,
+    cwd: Option<String>,
+    /// Icon name for rofi, which it looks up in the icon theme
+    icon: String,
+}
+
+impl Entry {
+    fn line(&self) -> String {
+        let title = self.title.replace(['\n', '\t'], " ");
+        let cwd = self.cwd.as_deref().unwrap_or("-");
+        format!("{} ({}){}{}{}{}", self.name, self.pid, SEPARATOR, title, SEPARATOR, cwd)
+    }
+}
+
+/// Prints one line per window, then per terminal tab not already listed through its window's
+/// process. With `rofi`, each line carries an icon in rofi's row-option syntax.
+pub fn show_menu(display: Option<&str>, rofi: bool) -> Result<()> {
+    let mut system = System::new_all();
+    system.refresh_all();
+
+    for entry in entries(&system, display)? {
+        if rofi {
+            println!("{}\0icon\x1f{}", entry.line(), entry.icon);
+        } else {
+            println!("{}", entry.line());
+        }
+    }
+    Ok(())
+}
+
+fn entries(system: &System, display: Option<&str>) -> Result<Vec<Entry>> {
+    let process = |pid: u32| system.process(sysinfo::Pid::from(pid as usize));
+    let cwd = |pid: u32| process(pid).and_then(|p| p.cwd()).map(home_relative);
+
+    let mut entries: Vec<Entry> = window::list_all_windows(display)?
+        .into_iter()
+        .map(|window| Entry {
+            pid: window.pid,
+            name: process(window.pid).map(|p| p.name().to_string()).unwrap_or_else(|| window.class.clone()),
+            cwd: cwd(window.pid),
+            icon: window.class.to_lowercase(),
+            title: window.title,
+        })
+        .collect();
+
+    for tab in tabs::list_tabs(system) {
+        // The job in the foreground, or the shell at its prompt
+        let pid = tab.foreground_pid.filter(|&pid| process(pid).is_some()).unwrap_or(tab.shell_pid);
+        if entries.iter().any(|entry| entry.pid == pid) {
+            continue;
+        }
+        let Some(active) = process(pid) else {
+            continue;
+        };
+        entries.push(Entry {
+            pid,
+            name: active.name().to_string(),
+            title: match &tab.title {
+                Some(title) => format!("{} tab {}: {}", tab.emulator, tab.index, title),
+                None => format!("{} tab {}", tab.emulator, tab.index),
+            },
+            cwd: cwd(pid),
+            icon: tab.emulator.clone(),
+        });
+    }
+    Ok(entries)
+}
+
+/// "~/projects/app" for a path under the home directory.
+fn home_relative(path: &Path) -> String {
+    match env::var_os("HOME").and_then(|home| path.strip_prefix(home).ok().map(Path::to_path_buf)) {
+        Some(rest) if rest.as_os_str().is_empty() => "~".to_string(),
+        Some(rest) => format!("~/{}", rest.display()),
+        None => path.display().to_string(),
+    }
+}
+
+/// Performs `action` on the process of a line printed by `show_menu`. An empty selection, which
+/// is what a launcher gives back when dismissed, does nothing.
+pub async fn select(line: &str, action: MenuAction, display: Option<&str>) -> Result<()> {
+    let line = line.trim_end_matches('\n');
+    if line.is_empty() {
+        return Ok(());
+    }
+    let (name, pid, title) = parse_line(line)
+        .ok_or_else(|| anyhow::anyhow!("Not a line printed by `top-helper menu`: {}", line))?;
+
+    // The PID may have been reused since the menu was shown
+    let mut system = System::new_all();
+    system.refresh_all();
+    match system.process(sysinfo::Pid::from(pid as usize)) {
+        Some(process) if process.name() == name => {}
+        _ => return Err(CliError::not_found(format!("{} ({}) is no longer running", name, pid))),
+    }
+
+    let selector = format!("pid:{}", pid);
+    match action {
+        MenuAction::Switch => {
+            // The line is for one window; a process with several has to be told which
+            let by_title = window::find_windows_by_pid(pid, display)
+                .is_ok_and(|windows| windows.len() > 1 && windows.iter().any(|window| window.title == title));
+            let choice = WindowChoice { title: by_title.then(|| title.to_string()), ..WindowChoice::default() };
+            window::switch_to_process_window(Some(&selector), None, false, OutputFormat::Text, display, &choice).await
+        }
+        // Picking the line is the confirmation
+        MenuAction::Kill => control::signal_matched(&system, vec![pid], Signal::Terminate, false, true),
+        MenuAction::Info => process::show_process_info(&[selector], &process::InfoOptions::default()).await,
+    }
+}
+
+/// The name, PID, and title from "name (PID) — title — cwd". Titles may contain the separator
+/// themselves, so the title is what lies between the first and the last one.
+fn parse_line(line: &str) -> Option<(&str, u32, &str)> {
+    let (head, rest) = line.split_once(SEPARATOR)?;
+    let title = rest.rsplit_once(SEPARATOR).map_or(rest, |(title, _cwd)| title);
+    let (name, pid) = head.rsplit_once(" (")?;
+    Some((name, pid.strip_suffix(')')?.parse().ok()?, title))
+}
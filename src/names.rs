@@ -0,0 +1,140 @@
+//! Effective names for interpreted processes: the script, module, jar, or main class a
+//! `python3`, `node`, or `java` process is running, taken from its command line.
+
+use std::path::Path;
+
+/// Python options that take a value as the next argument.
+const PYTHON_VALUE_OPTIONS: &[&str] = &["-W", "-X", "--check-hash-based-pycs"];
+
+/// Node options that take a value as the next argument (`--opt=value` forms need no special
+/// handling).
+const NODE_VALUE_OPTIONS: &[&str] =
+    &["-r", "--require", "--import", "--loader", "--experimental-loader", "--inspect-port", "--title", "--env-file"];
+
+/// Java options that take a value as the next argument.
+const JAVA_VALUE_OPTIONS: &[&str] = &[
+    "-cp",
+    "-classpath",
+    "--class-path",
+    "-p",
+    "--module-path",
+    "--upgrade-module-path",
+    "--add-modules",
+    "--add-opens",
+    "--add-exports",
+    "--add-reads",
+    "--patch-module",
+    "--limit-modules",
+    "--enable-native-access",
+];
+
+/// Scripts named like this say nothing on their own, so their directory is named with them.
+const GENERIC_SCRIPTS: &[&str] = &["index", "main", "__main__", "server", "app", "cli"];
+
+/// The effective name and the interpreter of an interpreted process, e.g. ("manage.py",
+/// "python3"), or None when the command line isn't one or runs inline code (`python3 -c`).
+fn resolve(cmd: &[String]) -> Option<(String, String)> {
+    let interpreter = Path::new(cmd.first()?).file_name()?.to_string_lossy().into_owned();
+    let args = &cmd[1..];
+    let name = match interpreter_kind(&interpreter)? {
+        Kind::Python => python_target(args),
+        Kind::Node => node_target(args),
+        Kind::Java => java_target(args),
+    }?;
+    Some((name, interpreter))
+}
+
+/// The name with its interpreter in parentheses, e.g. "manage.py (python3)".
+pub fn display_name(cmd: &[String]) -> Option<String> {
+    resolve(cmd).map(|(name, interpreter)| format!("{} ({})", name, interpreter))
+}
+
+enum Kind {
+    Python,
+    Node,
+    Java,
+}
+
+fn interpreter_kind(name: &str) -> Option<Kind> {
+    // Versioned binaries such as python3.12 or node20
+    let base = name.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+    match base {
+        "python" | "pypy" => Some(Kind::Python),
+        "node" | "nodejs" | "bun" => Some(Kind::Node),
+        "java" => Some(Kind::Java),
+        _ => None,
+    }
+}
+
+fn python_target(args: &[String]) -> Option<String> {
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-c" => return None,
+            "-m" => return args.next().cloned(),
+            _ if PYTHON_VALUE_OPTIONS.contains(&arg.as_str()) => {
+                args.next();
+            }
+            _ if arg.starts_with('-') => {}
+            script => return Some(script_name(script)),
+        }
+    }
+    None
+}
+
+fn node_target(args: &[String]) -> Option<String> {
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-e" | "--eval" | "-p" | "--print" => return None,
+            _ if NODE_VALUE_OPTIONS.contains(&arg.as_str()) => {
+                args.next();
+            }
+            _ if arg.starts_with('-') => {}
+            // `bun run dev` runs a package.json script
+            "run" => return args.next().cloned(),
+            script => return Some(script_name(script)),
+        }
+    }
+    None
+}
+
+fn java_target(args: &[String]) -> Option<String> {
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-jar" => return args.next().map(|jar| script_name(jar)),
+            // A module's main class, e.g. -m com.example.app/com.example.app.Main
+            "-m" | "--module" => {
+                let module = args.next()?;
+                return Some(class_name(module.rsplit('/').next().unwrap_or(module)));
+            }
+            _ if JAVA_VALUE_OPTIONS.contains(&arg.as_str()) => {
+                args.next();
+            }
+            _ if arg.starts_with('-') => {}
+            class => return Some(class_name(class)),
+        }
+    }
+    None
+}
+
+/// The script's file name, with its directory when the file name is generic:
+/// `/srv/api/index.js` is `api/index.js`.
+fn script_name(path: &str) -> String {
+    let path = Path::new(path);
+    let Some(file) = path.file_name().map(|file| file.to_string_lossy()) else {
+        return path.display().to_string();
+    };
+    let stem = path.file_stem().map(|stem| stem.to_string_lossy()).unwrap_or_default();
+    match path.parent().and_then(Path::file_name) {
+        Some(dir) if GENERIC_SCRIPTS.contains(&stem.as_ref()) => format!("{}/{}", dir.to_string_lossy(), file),
+        _ => file.into_owned(),
+    }
+}
+
+/// The simple name of a fully qualified class: org.gradle.launcher.daemon.bootstrap.GradleDaemon
+/// is GradleDaemon.
+fn class_name(class: &str) -> String {
+    class.rsplit('.').next().unwrap_or(class).to_string()
+}
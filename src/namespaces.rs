@@ -0,0 +1,216 @@
+//! Linux namespaces from /proc/<pid>/ns: which ones a process is in, which other processes share
+//! them (`info --namespaces`), and every namespace other than the host's with its members
+//! (`namespaces`), for telling containers and sandboxes apart.
+
+use anyhow::Result;
+use clap::ValueEnum;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use sysinfo::System;
+use tabled::{Table, Tabled, settings::{Width, location::ByColumnName}};
+
+/// Members named per namespace before the rest are only counted.
+const MAX_MEMBERS: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, ValueEnum)]
+pub enum Kind {
+    Pid,
+    Net,
+    Mnt,
+    User,
+    Uts,
+    Ipc,
+    Cgroup,
+    Time,
+}
+
+impl Kind {
+    const ALL: [Kind; 8] = [Kind::Pid, Kind::Net, Kind::Mnt, Kind::User, Kind::Uts, Kind::Ipc, Kind::Cgroup, Kind::Time];
+
+    /// The file name under /proc/<pid>/ns
+    fn name(&self) -> &'static str {
+        match self {
+            Kind::Pid => "pid",
+            Kind::Net => "net",
+            Kind::Mnt => "mnt",
+            Kind::User => "user",
+            Kind::Uts => "uts",
+            Kind::Ipc => "ipc",
+            Kind::Cgroup => "cgroup",
+            Kind::Time => "time",
+        }
+    }
+}
+
+/// One namespace of a process, for `info --namespaces`.
+#[derive(Debug, Clone)]
+pub struct Membership {
+    pub kind: Kind,
+    pub inode: u64,
+    /// Whether it is the namespace PID 1 is in
+    pub host: bool,
+    /// The other processes in it
+    pub shared_with: Vec<u32>,
+}
+
+impl Membership {
+    pub fn json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "kind": self.kind.name(),
+            "inode": self.inode,
+            "host": self.host,
+            "shared_with": self.shared_with,
+        })
+    }
+}
+
+/// The namespace inodes of `pid` by kind; kinds the kernel lacks or that aren't readable are
+/// missing.
+fn namespaces_of(pid: u32) -> BTreeMap<Kind, u64> {
+    Kind::ALL
+        .iter()
+        .filter_map(|&kind| {
+            // The link reads e.g. "net:[4026531840]"
+            let link = fs::read_link(format!("/proc/{}/ns/{}", pid, kind.name())).ok()?;
+            let inode = link.to_str()?.rsplit_once('[')?.1.strip_suffix(']')?.parse().ok()?;
+            Some((kind, inode))
+        })
+        .collect()
+}
+
+/// The host's namespaces, those of PID 1, or of this process when PID 1's aren't readable
+/// (inside a container without privileges, this process is in the same ones anyway).
+fn host_namespaces() -> BTreeMap<Kind, u64> {
+    let init = namespaces_of(1);
+    if init.is_empty() { namespaces_of(std::process::id()) } else { init }
+}
+
+/// Every readable process's namespaces, grouped: (kind, inode) to member PIDs.
+fn all_members(system: &System) -> HashMap<(Kind, u64), Vec<u32>> {
+    let mut members: HashMap<(Kind, u64), Vec<u32>> = HashMap::new();
+    for process in system.processes().values() {
+        if process.thread_kind().is_some() {
+            continue;
+        }
+        let pid = process.pid().as_u32();
+        for (kind, inode) in namespaces_of(pid) {
+            members.entry((kind, inode)).or_default().push(pid);
+        }
+    }
+    for pids in members.values_mut() {
+        pids.sort_unstable();
+    }
+    members
+}
+
+/// The namespaces of `pid` with who else is in each one.
+pub fn memberships(system: &System, pid: u32) -> Vec<Membership> {
+    let own = namespaces_of(pid);
+    if own.is_empty() {
+        return Vec::new();
+    }
+    let host = host_namespaces();
+    let members = all_members(system);
+    own.into_iter()
+        .map(|(kind, inode)| Membership {
+            kind,
+            inode,
+            host: host.get(&kind) == Some(&inode),
+            shared_with: members
+                .get(&(kind, inode))
+                .map(|pids| pids.iter().copied().filter(|&other| other != pid).collect())
+                .unwrap_or_default(),
+        })
+        .collect()
+}
+
+/// Prints the "Namespaces:" block of `info`.
+pub fn print_memberships(system: &System, memberships: &[Membership]) {
+    println!("\nNamespaces:");
+    if memberships.is_empty() {
+        println!("  (not readable)");
+        return;
+    }
+    for membership in memberships {
+        let mut line = format!("  {}: {}", membership.kind.name(), membership.inode);
+        if membership.host {
+            line.push_str(" (host)");
+        }
+        match membership.shared_with.len() {
+            0 => line.push_str(", no other process"),
+            // Naming everyone in the host namespace would be every process on the system
+            count if membership.host => line.push_str(&format!(", shared with {} other processes", count)),
+            _ => line.push_str(&format!(", shared with {}", member_names(system, &membership.shared_with))),
+        }
+        println!("{}", line);
+    }
+}
+
+#[derive(Tabled)]
+struct NamespaceRow {
+    #[tabled(rename = "Type")]
+    kind: &'static str,
+
+    #[tabled(rename = "Namespace")]
+    inode: u64,
+
+    #[tabled(rename = "Processes")]
+    count: usize,
+
+    #[tabled(rename = "Members")]
+    members: String,
+}
+
+/// Lists the namespaces processes are in, grouped, leaving out the host's unless `all`.
+pub fn show_namespaces(kinds: &[Kind], all: bool) -> Result<()> {
+    let mut system = System::new_all();
+    system.refresh_all();
+
+    let host = host_namespaces();
+    let mut groups: Vec<((Kind, u64), Vec<u32>)> = all_members(&system)
+        .into_iter()
+        .filter(|((kind, _), _)| kinds.is_empty() || kinds.contains(kind))
+        .filter(|((kind, inode), _)| all || host.get(kind) != Some(inode))
+        .collect();
+    if groups.is_empty() {
+        println!("Every process is in the host's namespaces; use --all to list those");
+        return Ok(());
+    }
+
+    // By kind, then the biggest group first
+    groups.sort_by_key(|((kind, inode), pids)| (*kind, std::cmp::Reverse(pids.len()), *inode));
+    let rows: Vec<NamespaceRow> = groups
+        .iter()
+        .map(|((kind, inode), pids)| NamespaceRow {
+            kind: kind.name(),
+            inode: *inode,
+            count: pids.len(),
+            members: format!(
+                "{}{}",
+                if host.get(kind) == Some(inode) { "(host) " } else { "" },
+                member_names(&system, pids)
+            ),
+        })
+        .collect();
+
+    let mut table = Table::new(rows);
+    table.modify(ByColumnName::new("Members"), Width::truncate(80).suffix("..."));
+    println!("{}", table);
+    Ok(())
+}
+
+/// "nginx (1201), nginx (1202), and 3 more"
+fn member_names(system: &System, pids: &[u32]) -> String {
+    let mut names: Vec<String> = pids
+        .iter()
+        .take(MAX_MEMBERS)
+        .map(|&pid| {
+            let name = system.process(sysinfo::Pid::from(pid as usize)).map(|p| p.name()).unwrap_or("?");
+            format!("{} ({})", name, pid)
+        })
+        .collect();
+    if pids.len() > MAX_MEMBERS {
+        names.push(format!("and {} more", pids.len() - MAX_MEMBERS));
+    }
+    names.join(", ")
+}
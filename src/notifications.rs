@@ -0,0 +1,225 @@
+//! Attributes desktop notifications to the processes that sent them.
+//!
+//! `notifications --watch` eavesdrops on `org.freedesktop.Notifications.Notify` calls on the
+//! session bus as a bus monitor, resolves each sender's bus name to a PID, and appends the
+//! result to a log that `notifications` (without `--watch`) reads back.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use sysinfo::System;
+use tabled::{Table, Tabled, settings::{Width, location::ByColumnName}};
+use zbus::export::futures_util::StreamExt;
+use zbus::message::Type as MessageType;
+use zbus::zvariant::OwnedValue;
+use zbus::{MatchRule, MessageStream};
+
+use crate::dbus;
+use crate::output::format_age;
+use crate::paths;
+
+/// The arguments of Notify: app name, ID to replace, icon, summary, body, actions, hints, and
+/// timeout.
+type NotifyArgs = (String, u32, String, String, String, Vec<String>, HashMap<String, OwnedValue>, i32);
+
+#[derive(Debug, Serialize, Deserialize)]
+struct NotificationRecord {
+    timestamp: u64,
+    pid: Option<u32>,
+    process: Option<String>,
+    app_name: String,
+    summary: String,
+    body: String,
+}
+
+#[derive(Tabled)]
+struct NotificationRow {
+    #[tabled(rename = "When")]
+    when: String,
+
+    #[tabled(rename = "PID")]
+    pid: String,
+
+    #[tabled(rename = "Process")]
+    process: String,
+
+    #[tabled(rename = "App")]
+    app_name: String,
+
+    #[tabled(rename = "Summary")]
+    summary: String,
+}
+
+#[derive(Tabled)]
+struct SenderRow {
+    #[tabled(rename = "Process")]
+    process: String,
+
+    #[tabled(rename = "App")]
+    app_name: String,
+
+    #[tabled(rename = "Notifications")]
+    count: usize,
+
+    #[tabled(rename = "Last")]
+    last: String,
+}
+
+fn log_path() -> Result<PathBuf> {
+    Ok(paths::state_dir()?.join("notifications.jsonl"))
+}
+
+/// Monitors the session bus and records every notification until interrupted.
+pub async fn watch_notifications() -> Result<()> {
+    // A monitor connection can only receive, so senders are looked up over a second one
+    let monitor = dbus::session().await?;
+    let lookup = dbus::session().await?;
+    let rule = MatchRule::builder()
+        .msg_type(MessageType::MethodCall)
+        .interface("org.freedesktop.Notifications")?
+        .member("Notify")?
+        .build();
+    // Called without a proxy, which would go on to send messages of its own, and the bus drops
+    // monitors that send anything
+    monitor
+        .call_method(
+            Some("org.freedesktop.DBus"),
+            "/org/freedesktop/DBus",
+            Some("org.freedesktop.DBus.Monitoring"),
+            "BecomeMonitor",
+            &(vec![rule.to_string()], 0u32),
+        )
+        .await
+        .context("The session bus does not allow monitoring")?;
+
+    let mut messages = MessageStream::from(monitor);
+    let log = log_path()?;
+    let mut system = System::new();
+
+    println!("Watching for notifications (logging to {})...", log.display());
+
+    while let Some(message) = messages.next().await {
+        let message = message?;
+        let header = message.header();
+        if header.message_type() != MessageType::MethodCall || header.member().is_none_or(|member| member != "Notify") {
+            continue;
+        }
+        let Ok((app_name, _replaces_id, _app_icon, summary, body, ..)) = message.body().deserialize::<NotifyArgs>() else {
+            continue;
+        };
+
+        // notify-send and similar tools disconnect as soon as the call returns, so the sender is
+        // resolved right away
+        let pid = match header.sender() {
+            Some(sender) => dbus::connection_pid(&lookup, sender).await,
+            None => None,
+        };
+        let record = NotificationRecord {
+            timestamp: now(),
+            pid,
+            process: pid.and_then(|pid| process_name(&mut system, pid)),
+            app_name,
+            summary,
+            body,
+        };
+        append(&record, &log)?;
+    }
+
+    Ok(())
+}
+
+/// Shows recently recorded notifications, or per-process totals with `by_process`.
+pub fn show_recent_notifications(limit: usize, by_process: bool) -> Result<()> {
+    let log = log_path()?;
+    let content = match fs::read_to_string(&log) {
+        Ok(content) => content,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(err) => return Err(anyhow::Error::new(err).context(format!("Failed to read {}", log.display()))),
+    };
+
+    let records: Vec<NotificationRecord> = content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect();
+
+    if records.is_empty() {
+        println!("No notifications recorded yet. Run `top-helper notifications --watch` to start recording.");
+        return Ok(());
+    }
+
+    let now = now();
+    if by_process {
+        let mut senders: HashMap<(String, String), (usize, u64)> = HashMap::new();
+        for record in &records {
+            let key = (record.process.clone().unwrap_or_else(|| "unknown".to_string()), record.app_name.clone());
+            let entry = senders.entry(key).or_insert((0, 0));
+            entry.0 += 1;
+            entry.1 = entry.1.max(record.timestamp);
+        }
+
+        let mut rows: Vec<SenderRow> = senders
+            .into_iter()
+            .map(|((process, app_name), (count, last))| SenderRow {
+                process,
+                app_name,
+                count,
+                last: format_age(now.saturating_sub(last)),
+            })
+            .collect();
+        rows.sort_by_key(|row| std::cmp::Reverse(row.count));
+        rows.truncate(limit);
+        println!("{}", Table::new(rows));
+    } else {
+        let rows: Vec<NotificationRow> = records
+            .iter()
+            .rev()
+            .take(limit)
+            .map(|record| NotificationRow {
+                when: format_age(now.saturating_sub(record.timestamp)),
+                pid: record.pid.map(|pid| pid.to_string()).unwrap_or_else(|| "-".to_string()),
+                process: record.process.clone().unwrap_or_else(|| "-".to_string()),
+                app_name: record.app_name.clone(),
+                summary: record.summary.clone(),
+            })
+            .collect();
+
+        let mut table = Table::new(rows);
+        table.modify(ByColumnName::new("Summary"), Width::truncate(60).suffix("..."));
+        println!("{}", table);
+    }
+
+    Ok(())
+}
+
+fn process_name(system: &mut System, pid: u32) -> Option<String> {
+    let pid = sysinfo::Pid::from(pid as usize);
+    system.refresh_process(pid);
+    system.process(pid).map(|p| p.name().to_string())
+}
+
+fn append(record: &NotificationRecord, log: &Path) -> Result<()> {
+    println!(
+        "{} (PID: {}) [{}]: {}",
+        record.process.as_deref().unwrap_or("unknown"),
+        record.pid.map(|pid| pid.to_string()).unwrap_or_else(|| "-".to_string()),
+        record.app_name,
+        record.summary
+    );
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(log)
+        .with_context(|| format!("Failed to open {}", log.display()))?;
+    writeln!(file, "{}", serde_json::to_string(record)?)?;
+
+    Ok(())
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
@@ -0,0 +1,291 @@
+//! Desktop notifications through the org.freedesktop.Notifications D-Bus service, falling back
+//! to `notify-send` when that fails.
+//!
+//! Notifications can carry action buttons that switch to or kill the process they are about.
+//! Clicks arrive after top-helper may have exited (e.g. after `wait`), so they are handled by a
+//! detached `top-helper notification-actions` that waits for the button press, checks that the
+//! process is still the one the notification was about, and then runs the action. Buttons
+//! pressed more than an hour later do nothing.
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::os::unix::process::CommandExt;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+use std::time::Duration;
+use sysinfo::System;
+use zbus::export::futures_util::StreamExt;
+use zbus::zvariant::Value;
+use zbus::{Connection, Proxy};
+
+use crate::control::{self, Signal};
+use crate::dbus;
+use crate::error::CliError;
+use crate::identity::ProcessKey;
+use crate::output::OutputFormat;
+use crate::sink::Urgency;
+use crate::window::{self, WindowChoice};
+
+const DEFAULT_ICON: &str = "utilities-system-monitor";
+
+/// How long the buttons of a notification keep working.
+const ACTION_TIMEOUT: Duration = Duration::from_secs(60 * 60);
+
+pub struct Notification<'a> {
+    pub summary: &'a str,
+    pub body: &'a str,
+    pub urgency: Urgency,
+    /// Icon name from the icon theme, or a path to an image
+    pub icon: Option<&'a str>,
+    pub actions: Vec<Action>,
+}
+
+/// A button on a notification about a running process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ActionKind {
+    /// Focus the process's window (`top-helper switch`)
+    Switch,
+    /// Send the process SIGTERM
+    Kill,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Action {
+    pub kind: ActionKind,
+    pub process: ProcessKey,
+}
+
+impl Action {
+    fn key(&self) -> &'static str {
+        match self.kind {
+            ActionKind::Switch => "switch",
+            ActionKind::Kill => "kill",
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self.kind {
+            ActionKind::Switch => "Switch to it",
+            ActionKind::Kill => "Kill it",
+        }
+    }
+
+    /// Carries out the action, unless the process has exited and its PID may have been reused.
+    async fn run(&self) -> Result<()> {
+        let mut system = System::new();
+        system.refresh_processes();
+        if self.process.find(&system).is_none() {
+            return Err(CliError::not_found(format!("Process {} has exited", self.process.pid)));
+        }
+
+        match self.kind {
+            ActionKind::Switch => {
+                let selector = format!("pid:{}", self.process.pid);
+                let choice = WindowChoice::default();
+                window::switch_to_process_window(Some(&selector), None, false, OutputFormat::Text, None, &choice).await
+            }
+            ActionKind::Kill => control::send_signal(self.process.pid, Signal::Terminate),
+        }
+    }
+}
+
+/// `KIND:PID:START_TIME`, as `notification-actions` takes its actions.
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.key(), self.process.pid, self.process.start_time)
+    }
+}
+
+impl FromStr for Action {
+    type Err = anyhow::Error;
+
+    fn from_str(spec: &str) -> Result<Self> {
+        let invalid = || anyhow::anyhow!("Invalid action '{}': expected KIND:PID:START_TIME", spec);
+        let mut parts = spec.split(':');
+        let kind = match parts.next() {
+            Some("switch") => ActionKind::Switch,
+            Some("kill") => ActionKind::Kill,
+            _ => return Err(invalid()),
+        };
+        let pid = parts.next().and_then(|pid| pid.parse().ok()).ok_or_else(invalid)?;
+        let start_time = parts.next().and_then(|time| time.parse().ok()).ok_or_else(invalid)?;
+        Ok(Action { kind, process: ProcessKey { pid, start_time } })
+    }
+}
+
+/// Shows a notification, over D-Bus if possible and with notify-send otherwise.
+pub fn send(notification: &Notification) -> Result<()> {
+    match dbus::block_on(send_dbus(notification)) {
+        Ok(id) => {
+            if !notification.actions.is_empty() {
+                let mut handler = Command::new(exe());
+                handler.args(["notification-actions", "--id", &id.to_string()]);
+                handler.args(notification.actions.iter().map(Action::to_string));
+                spawn_detached(handler)?;
+            }
+            Ok(())
+        }
+        Err(dbus_err) => send_notify_send(notification).map_err(|err| {
+            err.context(format!("No notification service reachable over D-Bus either ({:#})", dbus_err))
+        }),
+    }
+}
+
+/// `notification-actions`: waits for a button to be pressed on notification `id`, or takes the
+/// one already `pressed`, and runs its action.
+pub async fn handle_actions(id: Option<u32>, pressed: Option<&str>, actions: &[Action]) -> Result<()> {
+    let key = match (id, pressed) {
+        (_, Some(key)) => Some(key.to_string()),
+        (Some(id), None) => wait_for_action(id).await?,
+        (None, None) => None,
+    };
+    match key.and_then(|key| actions.iter().find(|action| action.key() == key)) {
+        Some(action) => action.run().await,
+        None => Ok(()),
+    }
+}
+
+async fn service(connection: &Connection) -> zbus::Result<Proxy<'static>> {
+    Proxy::new(
+        connection,
+        "org.freedesktop.Notifications",
+        "/org/freedesktop/Notifications",
+        "org.freedesktop.Notifications",
+    )
+    .await
+}
+
+/// Calls Notify and returns the new notification's ID.
+async fn send_dbus(notification: &Notification<'_>) -> Result<u32> {
+    let actions: Vec<&str> = notification.actions.iter().flat_map(|action| [action.key(), action.label()]).collect();
+    let urgency: u8 = match notification.urgency {
+        Urgency::Low => 0,
+        Urgency::Normal => 1,
+        Urgency::Critical => 2,
+    };
+    let hints = HashMap::from([("urgency", Value::U8(urgency))]);
+
+    let connection = dbus::session().await?;
+    let id = service(&connection)
+        .await?
+        .call(
+            "Notify",
+            &(
+                "top-helper",
+                0u32,
+                notification.icon.unwrap_or(DEFAULT_ICON),
+                notification.summary,
+                notification.body,
+                actions,
+                hints,
+                -1i32,
+            ),
+        )
+        .await
+        .context("The notification service refused the notification")?;
+    Ok(id)
+}
+
+/// The key of the button pressed on notification `id`, or None once it is closed or the buttons
+/// have timed out.
+async fn wait_for_action(id: u32) -> Result<Option<String>> {
+    let connection = dbus::session().await?;
+    let proxy = service(&connection).await?;
+    let mut invoked = proxy.receive_signal("ActionInvoked").await?;
+    let mut closed = proxy.receive_signal("NotificationClosed").await?;
+
+    let pressed = async {
+        loop {
+            tokio::select! {
+                Some(signal) = invoked.next() => {
+                    if let Ok((signal_id, key)) = signal.body().deserialize::<(u32, String)>()
+                        && signal_id == id
+                    {
+                        return Some(key);
+                    }
+                }
+                Some(signal) = closed.next() => {
+                    if signal.body().deserialize::<(u32, u32)>().is_ok_and(|(signal_id, _reason)| signal_id == id) {
+                        return None;
+                    }
+                }
+                else => return None,
+            }
+        }
+    };
+    Ok(tokio::time::timeout(ACTION_TIMEOUT, pressed).await.unwrap_or(None))
+}
+
+fn send_notify_send(notification: &Notification) -> Result<()> {
+    let mut args = vec![
+        "-u".to_string(),
+        notification.urgency.name().to_string(),
+        "-a".to_string(),
+        "top-helper".to_string(),
+        "-i".to_string(),
+        notification.icon.unwrap_or(DEFAULT_ICON).to_string(),
+    ];
+
+    if notification.actions.is_empty() {
+        args.extend([notification.summary.to_string(), notification.body.to_string()]);
+        let status = Command::new("notify-send")
+            .args(&args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .status()
+            .map_err(|err| match err.kind() {
+                std::io::ErrorKind::NotFound => CliError::backend_missing("notify-send is not installed"),
+                _ => anyhow::Error::new(err).context("Failed to run notify-send"),
+            })?;
+        if !status.success() {
+            return Err(anyhow::anyhow!("notify-send exited with {}", status));
+        }
+        return Ok(());
+    }
+
+    // notify-send 0.7.9 and later prints the key of the pressed button when run with --wait
+    args.push("--wait".to_string());
+    for action in &notification.actions {
+        args.push(format!("--action={}={}", action.key(), action.label()));
+    }
+    args.extend([notification.summary.to_string(), notification.body.to_string()]);
+
+    let quoted: Vec<String> = args.iter().map(|arg| shell_quote(arg)).collect();
+    let specs: Vec<String> = notification.actions.iter().map(|action| shell_quote(&action.to_string())).collect();
+    let script = format!(
+        "key=$(timeout {} notify-send {})\nexec {} notification-actions --pressed \"$key\" {}\n",
+        ACTION_TIMEOUT.as_secs(),
+        quoted.join(" "),
+        shell_quote(&exe().display().to_string()),
+        specs.join(" ")
+    );
+    let mut shell = Command::new("sh");
+    shell.args(["-c", &script]);
+    spawn_detached(shell)
+}
+
+fn exe() -> PathBuf {
+    std::env::current_exe().unwrap_or_else(|_| PathBuf::from("top-helper"))
+}
+
+/// Starts the action handler in its own process group, so it outlives top-helper and its
+/// terminal's Ctrl-C.
+fn spawn_detached(mut command: Command) -> Result<()> {
+    command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .process_group(0)
+        .spawn()
+        .context("Failed to start the notification action handler")?;
+    Ok(())
+}
+
+/// Quotes `text` as one word for `sh`.
+fn shell_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "'\\''"))
+}
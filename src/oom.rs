@@ -0,0 +1,79 @@
+use anyhow::Result;
+use std::fmt;
+use std::fs;
+use std::io;
+use sysinfo::System;
+
+use crate::control;
+use crate::error::CliError;
+use crate::selector::Selection;
+
+/// The kernel's OOM-killer badness for a process: the highest score is killed first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OomScore {
+    pub score: i32,
+    /// User adjustment from -1000 (never kill) to 1000 (kill first)
+    pub adj: i32,
+}
+
+impl fmt::Display for OomScore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.adj == 0 {
+            write!(f, "{}", self.score)
+        } else {
+            write!(f, "{} (adj {:+})", self.score, self.adj)
+        }
+    }
+}
+
+pub fn oom_score(pid: u32) -> Option<OomScore> {
+    Some(OomScore {
+        score: read_proc_number(pid, "oom_score")?,
+        adj: read_proc_number(pid, "oom_score_adj")?,
+    })
+}
+
+fn read_proc_number(pid: u32, file: &str) -> Option<i32> {
+    fs::read_to_string(format!("/proc/{}/{}", pid, file)).ok()?.trim().parse().ok()
+}
+
+/// Sets `oom_score_adj` for every process matching the selector.
+pub fn adjust_oom_score(identifier: &str, value: i32) -> Result<()> {
+    let mut system = System::new_all();
+    system.refresh_all();
+
+    let pids: Vec<u32> = Selection::parse(identifier)?
+        .resolve(&system)
+        .into_iter()
+        .filter(|pid| !control::is_thread(&system, *pid))
+        .collect();
+
+    if pids.is_empty() {
+        return Err(CliError::not_found(format!("Process not found: {}", identifier)));
+    }
+
+    for pid in pids {
+        match fs::write(format!("/proc/{}/oom_score_adj", pid), value.to_string()) {
+            Ok(()) => {}
+            Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+            Err(err) if err.kind() == io::ErrorKind::PermissionDenied => {
+                return Err(CliError::permission(format!(
+                    "Permission denied for PID {}: lowering oom_score_adj, or changing another user's process, requires CAP_SYS_RESOURCE",
+                    pid
+                )));
+            }
+            Err(err) => return Err(anyhow::Error::new(err).context(format!("Failed to adjust PID {}", pid))),
+        }
+
+        let name = system
+            .process(sysinfo::Pid::from(pid as usize))
+            .map(|p| p.name().to_string())
+            .unwrap_or_default();
+        match oom_score(pid) {
+            Some(score) => println!("{} (PID: {}): OOM score {}", name, pid, score),
+            None => println!("{} (PID: {}): oom_score_adj set to {}", name, pid, value),
+        }
+    }
+
+    Ok(())
+}
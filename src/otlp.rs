@@ -0,0 +1,221 @@
+//! OpenTelemetry export for `monitor --otlp-endpoint`: the memory, CPU, and disk I/O of every
+//! process, sent at each evaluation to a collector's OTLP receiver. Collectors take OTLP/gRPC on
+//! port 4317 and OTLP/HTTP (`/v1/metrics`) on port 4318 by default; the endpoint's port picks
+//! between them unless `--otlp-protocol` says otherwise. gRPC is spoken over plaintext HTTP/2 by
+//! tonic; OTLP/HTTP posts the protobuf encoding with `curl`, which also handles https.
+//!
+//! Data points carry the process's PID and name, and its systemd unit and container when it has
+//! them, as attributes.
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use opentelemetry_proto::tonic::collector::metrics::v1::ExportMetricsServiceRequest;
+use opentelemetry_proto::tonic::collector::metrics::v1::metrics_service_client::MetricsServiceClient;
+use opentelemetry_proto::tonic::common::v1::{AnyValue, InstrumentationScope, KeyValue, any_value};
+use opentelemetry_proto::tonic::metrics::v1::{
+    AggregationTemporality, Gauge, Metric, NumberDataPoint, ResourceMetrics, ScopeMetrics, Sum, metric,
+    number_data_point,
+};
+use opentelemetry_proto::tonic::resource::v1::Resource;
+use prost::Message;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use sysinfo::System;
+use tonic::transport::{Channel, Endpoint};
+
+use crate::cgroup;
+use crate::sink;
+
+/// Gives up on a collector that does not answer in time, so the next evaluation is not held up.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The collector's default OTLP/gRPC port.
+const GRPC_PORT: &str = "4317";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Protocol {
+    /// OTLP/gRPC, usually on port 4317
+    Grpc,
+    /// OTLP/HTTP with the protobuf encoding, usually on port 4318
+    Http,
+}
+
+pub enum Exporter {
+    Grpc(MetricsServiceClient<Channel>),
+    Http(String),
+}
+
+impl Exporter {
+    /// An exporter for the collector at `endpoint`, such as `http://collector:4317` or
+    /// `http://collector:4318`. Without a `protocol`, port 4317 means gRPC and any other means
+    /// OTLP/HTTP, whose `/v1/metrics` path is added unless it is already there. Connecting waits
+    /// for the first export, so the collector may start after top-helper.
+    pub fn new(endpoint: &str, protocol: Option<Protocol>) -> Result<Self> {
+        let endpoint = endpoint.trim_end_matches('/');
+        let Some(address) = endpoint.strip_prefix("http://").or_else(|| endpoint.strip_prefix("https://")) else {
+            return Err(anyhow::anyhow!("Invalid OTLP endpoint '{}': expected an http:// or https:// URL", endpoint));
+        };
+        let port = address.split('/').next().and_then(|host| host.rsplit_once(':')).map(|(_, port)| port);
+        let protocol = protocol.unwrap_or(if port == Some(GRPC_PORT) { Protocol::Grpc } else { Protocol::Http });
+
+        match protocol {
+            Protocol::Grpc => {
+                if endpoint.starts_with("https://") {
+                    return Err(anyhow::anyhow!(
+                        "OTLP/gRPC over TLS is not supported; use an http:// endpoint or --otlp-protocol http"
+                    ));
+                }
+                let channel = Endpoint::from_shared(endpoint.to_string())
+                    .with_context(|| format!("Invalid OTLP endpoint '{}'", endpoint))?
+                    .connect_timeout(TIMEOUT)
+                    .timeout(TIMEOUT)
+                    .connect_lazy();
+                Ok(Exporter::Grpc(MetricsServiceClient::new(channel)))
+            }
+            Protocol::Http if endpoint.ends_with("/v1/metrics") => Ok(Exporter::Http(endpoint.to_string())),
+            Protocol::Http => Ok(Exporter::Http(format!("{}/v1/metrics", endpoint))),
+        }
+    }
+
+    /// Sends the current values of every process. curl runs on a blocking thread, so neither
+    /// protocol holds up the runtime while the collector answers.
+    pub async fn export(&self, system: &System) -> Result<()> {
+        let request = metrics_request(system);
+        match self {
+            Exporter::Grpc(client) => {
+                client.clone().export(request).await.map_err(|status| {
+                    anyhow::anyhow!("The collector did not take the metrics: {:?}: {}", status.code(), status.message())
+                })?;
+                Ok(())
+            }
+            Exporter::Http(url) => {
+                let url = url.clone();
+                let body = request.encode_to_vec();
+                tokio::task::spawn_blocking(move || {
+                    let max_time = TIMEOUT.as_secs().to_string();
+                    sink::run(
+                        "curl",
+                        &[
+                            "-fsS",
+                            "--max-time",
+                            &max_time,
+                            "-X",
+                            "POST",
+                            "-H",
+                            "Content-Type: application/x-protobuf",
+                            "--data-binary",
+                            "@-",
+                            &url,
+                        ],
+                        Some(body),
+                    )
+                })
+                .await?
+            }
+        }
+    }
+}
+
+fn metrics_request(system: &System) -> ExportMetricsServiceRequest {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos() as u64;
+    let cpus = system.cpus().len().max(1) as f64;
+
+    let mut memory = Vec::new();
+    let mut cpu = Vec::new();
+    let mut io = Vec::new();
+    for process in system.processes().values().filter(|p| p.thread_kind().is_none()) {
+        let attributes = process_attributes(process);
+        memory.push(NumberDataPoint {
+            attributes: attributes.clone(),
+            time_unix_nano: now,
+            value: Some(number_data_point::Value::AsInt(process.memory() as i64)),
+            ..Default::default()
+        });
+        cpu.push(NumberDataPoint {
+            attributes: attributes.clone(),
+            time_unix_nano: now,
+            // A share of all CPUs, as the semantic conventions define utilization
+            value: Some(number_data_point::Value::AsDouble(process.cpu_usage() as f64 / 100.0 / cpus)),
+            ..Default::default()
+        });
+        let usage = process.disk_usage();
+        for (direction, bytes) in [("read", usage.total_read_bytes), ("write", usage.total_written_bytes)] {
+            let mut attributes = attributes.clone();
+            attributes.push(attribute("disk.io.direction", any_value::Value::StringValue(direction.to_string())));
+            io.push(NumberDataPoint {
+                attributes,
+                start_time_unix_nano: process.start_time() * 1_000_000_000,
+                time_unix_nano: now,
+                value: Some(number_data_point::Value::AsInt(bytes as i64)),
+                ..Default::default()
+            });
+        }
+    }
+
+    let mut resource = vec![attribute("service.name", any_value::Value::StringValue("top-helper".to_string()))];
+    if let Some(host) = System::host_name() {
+        resource.push(attribute("host.name", any_value::Value::StringValue(host)));
+    }
+
+    let metrics = vec![
+        Metric {
+            name: "process.memory.usage".to_string(),
+            description: "Resident set size".to_string(),
+            unit: "By".to_string(),
+            data: Some(metric::Data::Gauge(Gauge { data_points: memory })),
+            ..Default::default()
+        },
+        Metric {
+            name: "process.cpu.utilization".to_string(),
+            description: "CPU time used since the previous evaluation, as a share of all CPUs".to_string(),
+            unit: "1".to_string(),
+            data: Some(metric::Data::Gauge(Gauge { data_points: cpu })),
+            ..Default::default()
+        },
+        Metric {
+            name: "process.disk.io".to_string(),
+            description: "Bytes read from and written to disk since the process started".to_string(),
+            unit: "By".to_string(),
+            data: Some(metric::Data::Sum(Sum {
+                data_points: io,
+                aggregation_temporality: AggregationTemporality::Cumulative as i32,
+                is_monotonic: true,
+            })),
+            ..Default::default()
+        },
+    ];
+
+    ExportMetricsServiceRequest {
+        resource_metrics: vec![ResourceMetrics {
+            resource: Some(Resource { attributes: resource, dropped_attributes_count: 0 }),
+            scope_metrics: vec![ScopeMetrics {
+                scope: Some(InstrumentationScope {
+                    name: "top-helper".to_string(),
+                    version: env!("CARGO_PKG_VERSION").to_string(),
+                    ..Default::default()
+                }),
+                metrics,
+                schema_url: String::new(),
+            }],
+            schema_url: String::new(),
+        }],
+    }
+}
+
+fn process_attributes(process: &sysinfo::Process) -> Vec<KeyValue> {
+    let mut attributes = vec![
+        attribute("process.pid", any_value::Value::IntValue(process.pid().as_u32() as i64)),
+        attribute("process.executable.name", any_value::Value::StringValue(process.name().to_string())),
+    ];
+    let cgroup = cgroup::read(process.pid().as_u32());
+    if let Some(unit) = cgroup::systemd_unit(&cgroup) {
+        attributes.push(attribute("systemd.unit", any_value::Value::StringValue(unit.name.to_string())));
+    }
+    if let Some(id) = cgroup::container(&cgroup).and_then(|container| container.id) {
+        attributes.push(attribute("container.id", any_value::Value::StringValue(id.to_string())));
+    }
+    attributes
+}
+
+fn attribute(key: &str, value: any_value::Value) -> KeyValue {
+    KeyValue { key: key.to_string(), value: Some(AnyValue { value: Some(value) }) }
+}
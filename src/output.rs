@@ -0,0 +1,369 @@
+use chrono::{Local, TimeZone};
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::redact;
+
+static MEMORY_UNITS: OnceLock<MemoryUnits> = OnceLock::new();
+
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    Text,
+    Json,
+}
+
+/// Formats for `list`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum ListFormat {
+    #[default]
+    Text,
+    /// One document, with the total count for paging
+    Json,
+    /// One JSON object per process and line, stamped with the time of the sample; with --watch,
+    /// for every sample
+    Ndjson,
+}
+
+/// Formats for `monitor`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum MonitorFormat {
+    /// Only warnings; alerts go to their sinks
+    #[default]
+    Text,
+    /// Also every process at every evaluation, as with `list --watch --format ndjson`
+    Ndjson,
+}
+
+/// Diagram formats for `--output` on commands that produce trees or graphs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum DiagramFormat {
+    /// Graphviz DOT (render with `dot -Tsvg`)
+    Dot,
+    /// Mermaid flowchart (renders in Markdown on GitHub, GitLab, and most wikis)
+    Mermaid,
+}
+
+/// Units for memory columns, chosen with the global `--units` option.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MemoryUnits {
+    /// Scaled to fit, e.g. 1.2 GB or 340.0 MB
+    Human,
+    /// Megabytes with two decimals, e.g. 1234.56 MB
+    #[default]
+    Mib,
+    /// Whole kilobytes
+    Kib,
+    /// Exact bytes
+    Bytes,
+}
+
+pub fn set_memory_units(units: MemoryUnits) {
+    MEMORY_UNITS.set(units).ok();
+}
+
+/// Formats a memory size in the units chosen with `--units`.
+pub fn format_memory(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    match MEMORY_UNITS.get().copied().unwrap_or_default() {
+        MemoryUnits::Human => {
+            let mut value = bytes as f64;
+            let mut unit = 0;
+            while value >= 1024.0 && unit < UNITS.len() - 1 {
+                value /= 1024.0;
+                unit += 1;
+            }
+            if unit == 0 { format!("{} B", bytes) } else { format!("{:.1} {}", value, UNITS[unit]) }
+        }
+        MemoryUnits::Mib => format!("{:.2} MB", bytes as f64 / 1024.0 / 1024.0),
+        MemoryUnits::Kib => format!("{} KB", bytes / 1024),
+        MemoryUnits::Bytes => bytes.to_string(),
+    }
+}
+
+/// A local date and time to the minute, such as `2024-05-01 14:30`.
+pub fn format_time(timestamp: i64) -> String {
+    Local
+        .timestamp_opt(timestamp, 0)
+        .single()
+        .map(|time| time.format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_else(|| timestamp.to_string())
+}
+
+/// A duration in the largest units that keep it short: `4.2s`, `3m 20s`, or `2h 5m`.
+pub fn format_duration(ms: u64) -> String {
+    let seconds = ms / 1000;
+    match seconds {
+        0..60 => format!("{:.1}s", ms as f64 / 1000.0),
+        60..3600 => format!("{}m {}s", seconds / 60, seconds % 60),
+        _ => format!("{}h {}m", seconds / 3600, seconds % 3600 / 60),
+    }
+}
+
+/// How long ago something happened, in its largest whole unit: `45s ago` or `3d ago`.
+pub fn format_age(seconds: u64) -> String {
+    match seconds {
+        0..60 => format!("{}s ago", seconds),
+        60..3600 => format!("{}m ago", seconds / 60),
+        3600..86400 => format!("{}h ago", seconds / 3600),
+        _ => format!("{}d ago", seconds / 86400),
+    }
+}
+
+/// Formats for `info`. The structured ones all carry the same fields.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum InfoFormat {
+    #[default]
+    Text,
+    Json,
+    Yaml,
+    Toml,
+}
+
+impl InfoFormat {
+    /// Renders structured data in this format; None for text, which every command lays out itself.
+    pub fn render(&self, value: &serde_json::Value) -> anyhow::Result<Option<String>> {
+        Ok(Some(match self {
+            InfoFormat::Text => return Ok(None),
+            InfoFormat::Json => serde_json::to_string_pretty(value)? + "\n",
+            InfoFormat::Yaml => to_yaml(value),
+            // TOML has no null, so missing values are left out
+            InfoFormat::Toml => toml::to_string(&without_nulls(value.clone()))?,
+        }))
+    }
+}
+
+/// Block-style YAML for a JSON value. Strings are double-quoted the way JSON quotes them, which
+/// YAML reads the same, so no value can be mistaken for a number, a boolean, or a null.
+fn to_yaml(value: &serde_json::Value) -> String {
+    let mut out = String::new();
+    write_yaml(value, 0, &mut out);
+    out
+}
+
+fn write_yaml(value: &serde_json::Value, indent: usize, out: &mut String) {
+    use serde_json::Value;
+
+    let pad = " ".repeat(indent);
+    match value {
+        Value::Object(map) if !map.is_empty() => {
+            for (key, value) in map {
+                let key = yaml_key(key);
+                if is_yaml_block(value) {
+                    out.push_str(&format!("{}{}:\n", pad, key));
+                    write_yaml(value, indent + 2, out);
+                } else {
+                    out.push_str(&format!("{}{}: {}\n", pad, key, yaml_scalar(value)));
+                }
+            }
+        }
+        Value::Array(items) if !items.is_empty() => {
+            for item in items {
+                // Each item is written as if indented under the dash, which then replaces the
+                // padding of its first line
+                let mut block = String::new();
+                write_yaml(item, indent + 2, &mut block);
+                out.push_str(&format!("{}- {}", pad, &block[indent + 2..]));
+            }
+        }
+        scalar => out.push_str(&format!("{}{}\n", pad, yaml_scalar(scalar))),
+    }
+}
+
+/// A key as it is, when YAML reads it back as that same string, or JSON-quoted. Keys such as
+/// `null`, `yes`, or `123` would otherwise come back as a null, a boolean, or a number.
+fn yaml_key(key: &str) -> String {
+    const RESERVED: [&str; 9] = ["null", "true", "false", "yes", "no", "on", "off", "y", "n"];
+    let plain = key.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !RESERVED.contains(&key.to_ascii_lowercase().as_str());
+    if plain { key.to_string() } else { serde_json::Value::from(key).to_string() }
+}
+
+fn is_yaml_block(value: &serde_json::Value) -> bool {
+    match value {
+        serde_json::Value::Object(map) => !map.is_empty(),
+        serde_json::Value::Array(items) => !items.is_empty(),
+        _ => false,
+    }
+}
+
+fn yaml_scalar(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::Object(_) => "{}".to_string(),
+        serde_json::Value::Array(_) => "[]".to_string(),
+        // null, booleans, numbers, and JSON-quoted strings are all valid YAML as they are
+        other => other.to_string(),
+    }
+}
+
+fn without_nulls(value: serde_json::Value) -> serde_json::Value {
+    use serde_json::Value;
+
+    match value {
+        Value::Object(map) => {
+            Value::Object(map.into_iter().filter(|(_, v)| !v.is_null()).map(|(k, v)| (k, without_nulls(v))).collect())
+        }
+        Value::Array(items) => Value::Array(items.into_iter().filter(|v| !v.is_null()).map(without_nulls).collect()),
+        other => other,
+    }
+}
+
+/// Formats for `status`, by the status bar that polls it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum StatusFormat {
+    /// The summary line, then the top consumers
+    Text,
+    /// Waybar custom module JSON (`"return-type": "json"`): text, tooltip, class, percentage
+    Waybar,
+    /// i3blocks lines: full text, short text, and a color when usage is high
+    I3blocks,
+}
+
+/// Nodes and edges to render as a diagram. Labels are given as lines.
+#[derive(Default)]
+pub struct Diagram {
+    nodes: Vec<(String, Vec<String>)>,
+    edges: Vec<(String, String, Vec<String>)>,
+}
+
+impl Diagram {
+    pub fn node(&mut self, id: String, label: Vec<String>) {
+        self.nodes.push((id, label));
+    }
+
+    pub fn edge(&mut self, from: String, to: String, label: Vec<String>) {
+        self.edges.push((from, to, label));
+    }
+
+    /// Renders the diagram, laid out left to right or top to bottom.
+    pub fn render(&self, format: DiagramFormat, name: &str, left_to_right: bool) -> String {
+        let mut out = String::new();
+        match format {
+            DiagramFormat::Dot => {
+                let escape = |line: &String| line.replace('\\', "\\\\").replace('"', "\\\"");
+                let join = |lines: &[String]| lines.iter().map(escape).collect::<Vec<_>>().join("\\n");
+
+                out.push_str(&format!("digraph {} {{\n", name));
+                out.push_str(&format!("  rankdir={};\n  node [shape=box];\n", if left_to_right { "LR" } else { "TB" }));
+                for (id, label) in &self.nodes {
+                    out.push_str(&format!("  {} [label=\"{}\"];\n", id, join(label)));
+                }
+                for (from, to, label) in &self.edges {
+                    if label.is_empty() {
+                        out.push_str(&format!("  {} -> {};\n", from, to));
+                    } else {
+                        out.push_str(&format!("  {} -> {} [label=\"{}\"];\n", from, to, join(label)));
+                    }
+                }
+                out.push_str("}\n");
+            }
+            DiagramFormat::Mermaid => {
+                let escape = |line: &String| line.replace('"', "#quot;");
+                let join = |lines: &[String]| lines.iter().map(escape).collect::<Vec<_>>().join("<br/>");
+
+                out.push_str(&format!("flowchart {}\n", if left_to_right { "LR" } else { "TB" }));
+                for (id, label) in &self.nodes {
+                    out.push_str(&format!("  {}[\"{}\"]\n", id, join(label)));
+                }
+                for (from, to, label) in &self.edges {
+                    if label.is_empty() {
+                        out.push_str(&format!("  {} --> {}\n", from, to));
+                    } else {
+                        out.push_str(&format!("  {} -->|\"{}\"| {}\n", from, join(label), to));
+                    }
+                }
+            }
+        }
+        out
+    }
+}
+
+/// Version of the line-oriented `--porcelain` format.
+///
+/// The field order of a version never changes; new fields only ever appear in a new version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum PorcelainVersion {
+    V1,
+}
+
+/// One process in porcelain v1 form:
+/// `pid  ppid  name  rss_bytes  cpu_percent  cwd  command`, tab-separated.
+pub struct PorcelainProcess<'a> {
+    pub pid: u32,
+    pub parent_pid: Option<u32>,
+    pub name: &'a str,
+    pub memory_bytes: u64,
+    pub cpu_percent: f32,
+    pub working_dir: Option<&'a str>,
+    pub command: &'a [String],
+}
+
+impl PorcelainProcess<'_> {
+    pub fn line(&self, version: PorcelainVersion) -> String {
+        match version {
+            PorcelainVersion::V1 => porcelain_line(&[
+                self.pid.to_string(),
+                self.parent_pid.map(|p| p.to_string()).unwrap_or_else(|| "-".to_string()),
+                self.name.to_string(),
+                self.memory_bytes.to_string(),
+                format!("{:.1}", self.cpu_percent),
+                self.working_dir.unwrap_or("-").to_string(),
+                if self.command.is_empty() { "-".to_string() } else { redact::command(self.command).join(" ") },
+            ]),
+        }
+    }
+}
+
+/// Joins fields with tabs, escaping backslashes, tabs, and newlines so every record stays on one line.
+pub fn porcelain_line(fields: &[String]) -> String {
+    fields
+        .iter()
+        .map(|field| {
+            field
+                .replace('\\', "\\\\")
+                .replace('\t', "\\t")
+                .replace('\n', "\\n")
+        })
+        .collect::<Vec<_>>()
+        .join("\t")
+}
+
+/// Clears the terminal and prints `text` from the top, for `--watch` modes.
+pub fn redraw(text: &str) {
+    // Raw mode leaves the cursor in its column at a newline
+    if crossterm::terminal::is_raw_mode_enabled().unwrap_or(false) {
+        print!("\x1b[2J\x1b[H{}", text.replace('\n', "\r\n"));
+    } else {
+        print!("\x1b[2J\x1b[H{}", text);
+    }
+    std::io::Write::flush(&mut std::io::stdout()).ok();
+}
+
+/// Whether to color output: only on a terminal, and never with NO_COLOR set (no-color.org).
+pub fn use_color() -> bool {
+    std::io::IsTerminal::is_terminal(&std::io::stdout()) && std::env::var_os("NO_COLOR").is_none()
+}
+
+/// Whether the user can be asked something: stdin is a terminal, and `--quiet` has not hidden
+/// the question. Commands that would ask fail or pick a default otherwise.
+pub fn can_prompt() -> bool {
+    !QUIET.load(Ordering::Relaxed) && std::io::IsTerminal::is_terminal(&std::io::stdin())
+}
+
+/// Redirects stdout and stderr to /dev/null for `--quiet`, so no command needs to check the flag itself.
+pub fn silence_output() {
+    QUIET.store(true, Ordering::Relaxed);
+    if let Ok(devnull) = std::fs::OpenOptions::new().write(true).open("/dev/null") {
+        use std::os::fd::AsRawFd;
+        // SAFETY: all descriptors are valid for the duration of the calls
+        unsafe {
+            libc::dup2(devnull.as_raw_fd(), libc::STDOUT_FILENO);
+            libc::dup2(devnull.as_raw_fd(), libc::STDERR_FILENO);
+        }
+    }
+}
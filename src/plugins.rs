@@ -0,0 +1,130 @@
+//! Custom `list` columns defined in the config, so the table can be extended without changing
+//! top-helper:
+//!
+//! ```toml
+//! [column]
+//! container = "my-container-name \"$TOP_HELPER_PID\""
+//! "GPU temp" = "gpu-temp --pid \"$TOP_HELPER_PID\""
+//! ```
+//!
+//! Each command runs through `sh -c` once per listed row, after sorting and `--limit`, and the
+//! first line of its output fills the cell. The process's values are only passed in the
+//! environment, as `TOP_HELPER_PID`, `TOP_HELPER_PPID`, `TOP_HELPER_NAME`, and `TOP_HELPER_CWD`,
+//! never spliced into the command. The commands run in parallel, and one that takes longer than
+//! [`TIMEOUT`] is killed and shows as `timeout`.
+
+use anyhow::Result;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::process::Command;
+use tokio::sync::Semaphore;
+
+/// How long one command gets for one row.
+pub const TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Commands running at once, so a long list doesn't fork hundreds of shells together.
+const MAX_PARALLEL: usize = 16;
+
+#[derive(Debug, Clone)]
+pub struct PluginColumn {
+    pub name: String,
+    pub command: String,
+}
+
+/// The values a command's environment is filled from.
+pub struct Variables {
+    pub pid: u32,
+    pub ppid: Option<u32>,
+    pub name: String,
+    pub cwd: Option<String>,
+}
+
+/// The names of the values in [`Variables`], which commands may not use as `{key}` placeholders.
+pub const KEYS: [&str; 4] = ["pid", "ppid", "name", "cwd"];
+
+impl Variables {
+    fn pairs(&self) -> [(&'static str, String); 4] {
+        [
+            ("pid", self.pid.to_string()),
+            ("ppid", self.ppid.map(|ppid| ppid.to_string()).unwrap_or_default()),
+            ("name", self.name.clone()),
+            ("cwd", self.cwd.clone().unwrap_or_default()),
+        ]
+    }
+}
+
+/// Rejects a command that still uses one of the `{key}` placeholders of `keys`. Values are only
+/// passed in the environment: spliced into the command, a process name such as `x;reboot` would
+/// run as shell code as soon as the command put the placeholder inside quotes of its own.
+pub fn reject_placeholders(command: &str, keys: &[&str]) -> Result<()> {
+    match keys.iter().find(|key| command.contains(&format!("{{{}}}", key))) {
+        Some(key) => Err(anyhow::anyhow!(
+            "'{}' uses {{{}}}, which is not filled in; use \"$TOP_HELPER_{}\" instead",
+            command,
+            key,
+            key.to_uppercase()
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Runs every column's command for every row and returns the cells, one `Vec` per row in the
+/// order of `columns`.
+pub async fn column_values(columns: &[PluginColumn], rows: &[Variables]) -> Vec<Vec<String>> {
+    let permits = Arc::new(Semaphore::new(MAX_PARALLEL));
+    let mut tasks = Vec::new();
+    for row in rows {
+        let pairs = row.pairs();
+        for column in columns {
+            let command = column.command.clone();
+            let env = pairs.clone();
+            let permits = Arc::clone(&permits);
+            tasks.push(tokio::spawn(async move {
+                let _permit = permits.acquire_owned().await.ok();
+                run(&command, &env).await
+            }));
+        }
+    }
+
+    let mut cells = Vec::with_capacity(tasks.len());
+    for task in tasks {
+        cells.push(task.await.unwrap_or_else(|_| "-".to_string()));
+    }
+    if columns.is_empty() {
+        return rows.iter().map(|_| Vec::new()).collect();
+    }
+    cells.chunks(columns.len()).map(<[String]>::to_vec).collect()
+}
+
+/// The first line the command prints, `-` if it fails or prints nothing, or `timeout`.
+async fn run(command: &str, env: &[(&str, String)]) -> String {
+    let mut child = Command::new("sh");
+    // Its own process group, so a timeout kills whatever the shell started too
+    child.args(["-c", command]).stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::null()).process_group(0);
+    for (key, value) in env {
+        child.env(format!("TOP_HELPER_{}", key.to_uppercase()), value);
+    }
+    let Ok(child) = child.spawn() else {
+        return "-".to_string();
+    };
+    let group = child.id();
+
+    match tokio::time::timeout(TIMEOUT, child.wait_with_output()).await {
+        Err(_) => {
+            if let Some(group) = group {
+                // SAFETY: kill has no memory-safety preconditions
+                unsafe { libc::kill(-(group as libc::pid_t), libc::SIGKILL) };
+            }
+            "timeout".to_string()
+        }
+        Ok(Ok(output)) if output.status.success() => String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .next()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .unwrap_or("-")
+            .to_string(),
+        Ok(_) => "-".to_string(),
+    }
+}
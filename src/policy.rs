@@ -0,0 +1,368 @@
+//! Automatic actions from the `[[policy]]` rules in the config file, carried out by `enforce`.
+//!
+//! ```toml
+//! [[policy]]
+//! name = "runaway renderers"
+//! process = "name:chrome"
+//! cmdline = "--type=renderer"   # Only processes whose command line contains this
+//! metric = "memory"             # "memory" (MB) or "cpu" (%), per process
+//! above = 3000
+//! confirmations = 2             # Consecutive checks above the threshold before acting
+//! action = "terminate"          # "terminate", "kill", "pause", or "throttle"
+//! ```
+//!
+//! Unlike alerts, rules apply to each matching process on its own rather than to their sum.
+//! Checks are `enforce --interval` apart, so with the default of 30s the rule above acts on a
+//! renderer that stays above 3 GB for two checks 30s apart. `throttle` moves the process and its
+//! descendants into a cgroup with `memory_limit` and/or `cpu_limit`, as `limit` does.
+//!
+//! Every action, including those `--dry-run` only reports, is appended to an audit log.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Duration;
+use sysinfo::{ProcessStatus, System};
+use tabled::{Table, Tabled, settings::{Width, location::ByColumnName}};
+
+use crate::alerts::Metric;
+use crate::config::Config;
+use crate::control::{self, Signal};
+use crate::identity::ProcessKey;
+use crate::limit::{self, CpuLimit, MemoryLimit};
+use crate::output::format_age;
+use crate::paths;
+use crate::selector::{ProcessIndex, Selection};
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyRule {
+    pub name: String,
+    pub process: String,
+    /// Text the command line must contain, for telling apart processes with the same name
+    pub cmdline: Option<String>,
+    pub metric: Metric,
+    pub above: f64,
+    #[serde(default = "default_confirmations")]
+    pub confirmations: u32,
+    pub action: Action,
+    /// Memory limit for `throttle`, e.g. "2G"
+    pub memory_limit: Option<MemoryLimit>,
+    /// CPU limit for `throttle`, e.g. "50%"
+    pub cpu_limit: Option<CpuLimit>,
+}
+
+fn default_confirmations() -> u32 {
+    1
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+    /// SIGTERM
+    Terminate,
+    /// SIGKILL
+    Kill,
+    /// SIGSTOP
+    Pause,
+    /// Move into a cgroup with the rule's limits
+    Throttle,
+}
+
+impl Action {
+    fn name(&self) -> &'static str {
+        match self {
+            Action::Terminate => "terminate",
+            Action::Kill => "kill",
+            Action::Pause => "pause",
+            Action::Throttle => "throttle",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct AuditRecord {
+    /// Seconds since the epoch
+    timestamp: i64,
+    rule: String,
+    action: String,
+    pid: u32,
+    name: String,
+    /// The value that confirmed the action, in the rule's metric
+    value: f64,
+    dry_run: bool,
+    /// Why the action failed, if it did
+    error: Option<String>,
+}
+
+#[derive(Tabled)]
+struct AuditRow {
+    #[tabled(rename = "When")]
+    when: String,
+
+    #[tabled(rename = "Rule")]
+    rule: String,
+
+    #[tabled(rename = "Action")]
+    action: String,
+
+    #[tabled(rename = "PID")]
+    pid: u32,
+
+    #[tabled(rename = "Name")]
+    name: String,
+
+    #[tabled(rename = "Outcome")]
+    outcome: String,
+}
+
+fn log_path() -> Result<PathBuf> {
+    Ok(paths::state_dir()?.join("enforce-audit.jsonl"))
+}
+
+/// A policy rule with how many consecutive checks each process has been above its threshold.
+struct Policy {
+    rule: PolicyRule,
+    selection: Selection,
+    strikes: HashMap<ProcessKey, u32>,
+    /// Processes the rule paused or throttled, left alone for as long as they stay that way
+    held: HashSet<ProcessKey>,
+}
+
+impl Policy {
+    fn new(rule: PolicyRule) -> Result<Self> {
+        if rule.action == Action::Throttle && rule.memory_limit.is_none() && rule.cpu_limit.is_none() {
+            return Err(anyhow::anyhow!(
+                "Policy '{}' throttles but sets neither memory_limit nor cpu_limit",
+                rule.name
+            ));
+        }
+        let selection = Selection::parse(&rule.process).with_context(|| format!("Policy '{}'", rule.name))?;
+        Ok(Policy { rule, selection, strikes: HashMap::new(), held: HashSet::new() })
+    }
+
+    /// Counts a strike for every matching process above the threshold, forgetting processes
+    /// that dropped below it, and returns those confirmed often enough to act on with their value.
+    /// Processes the rule still holds paused or throttled are skipped; once resumed or moved out
+    /// of their limits, they count again.
+    fn check(&mut self, system: &System, index: &mut ProcessIndex) -> Vec<(ProcessKey, f64)> {
+        let action = self.rule.action;
+        self.held.retain(|key| still_held(action, system, key));
+        let mut strikes = HashMap::new();
+        let mut confirmed = Vec::new();
+        for pid in self.selection.resolve_in(index) {
+            let Some(process) = system.process(sysinfo::Pid::from(pid as usize)) else {
+                continue;
+            };
+            if process.thread_kind().is_some() {
+                continue;
+            }
+            if let Some(text) = &self.rule.cmdline
+                && !process.cmd().join(" ").contains(text.as_str())
+            {
+                continue;
+            }
+
+            let key = ProcessKey::of(process);
+            if self.held.contains(&key) {
+                continue;
+            }
+
+            let value = match self.rule.metric {
+                Metric::Memory => process.memory() as f64 / 1024.0 / 1024.0,
+                Metric::Cpu => process.cpu_usage() as f64,
+            };
+            if value <= self.rule.above {
+                continue;
+            }
+            let count = self.strikes.get(&key).copied().unwrap_or(0) + 1;
+            if count >= self.rule.confirmations {
+                confirmed.push((key, value));
+            } else {
+                strikes.insert(key, count);
+            }
+        }
+        // Acted-on processes start over, in case they survive the action
+        self.strikes = strikes;
+        confirmed
+    }
+
+    /// Carries out the rule's action on one process, returning what was done.
+    fn act(&self, system: &System, pid: u32, name: &str) -> Result<String> {
+        let signal = match self.rule.action {
+            Action::Terminate => Signal::Terminate,
+            Action::Kill => Signal::Kill,
+            Action::Pause => Signal::Stop,
+            Action::Throttle => {
+                let (descendants, location) =
+                    limit::limit_tree(system, pid, self.rule.memory_limit, self.rule.cpu_limit)?;
+                return Ok(format!(
+                    "Throttled {} (PID: {}) and {} descendants to {} in {}",
+                    name,
+                    pid,
+                    descendants,
+                    limit::describe(self.rule.memory_limit, self.rule.cpu_limit),
+                    location
+                ));
+            }
+        };
+        control::send_signal(pid, signal)?;
+        Ok(format!("{} {} (PID: {})", signal.verb(), name, pid))
+    }
+}
+
+/// Whether a process the rule paused or throttled still is.
+fn still_held(action: Action, system: &System, key: &ProcessKey) -> bool {
+    let Some(process) = key.find(system) else {
+        return false;
+    };
+    match action {
+        Action::Pause => process.status() == ProcessStatus::Stop,
+        Action::Throttle => limit::is_limited(key.pid),
+        Action::Terminate | Action::Kill => false,
+    }
+}
+
+/// Checks the configured policies every `interval` until interrupted and acts on the processes
+/// that break them; with `dry_run`, only reports what it would do.
+pub async fn enforce(interval: Duration, dry_run: bool) -> Result<()> {
+    let config = Config::load()?;
+    if config.policies.is_empty() {
+        return Err(anyhow::anyhow!(
+            "No policies configured; add [[policy]] sections to {}",
+            Config::path().map(|p| p.display().to_string()).unwrap_or_else(|| "the config file".to_string())
+        ));
+    }
+    let mut policies = config.policies.into_iter().map(Policy::new).collect::<Result<Vec<_>>>()?;
+
+    let mut system = System::new_all();
+    system.refresh_all();
+    println!(
+        "Enforcing {} policies every {}s{} (audit log: {})",
+        policies.len(),
+        interval.as_secs(),
+        if dry_run { " in dry-run mode" } else { "" },
+        log_path()?.display()
+    );
+
+    let mut ticker = tokio::time::interval(interval);
+    // The first tick completes immediately, before CPU usage can be measured
+    ticker.tick().await;
+    loop {
+        ticker.tick().await;
+        system.refresh_all();
+        let mut index = ProcessIndex::new(&system);
+        let protected = control::own_ancestry(&system);
+
+        for policy in &mut policies {
+            for (key, value) in policy.check(&system, &mut index) {
+                if protected.contains(&key.pid) {
+                    continue;
+                }
+                let Some(process) = key.find(&system) else {
+                    continue;
+                };
+                let name = process.name().to_string();
+                let result = if dry_run {
+                    Ok(format!("Would {} {} (PID: {})", policy.rule.action.name(), name, key.pid))
+                } else {
+                    policy.act(&system, key.pid, &name)
+                };
+                let error = match result {
+                    Ok(done) => {
+                        println!("[{}] {} at {}", policy.rule.name, done, format_value(policy.rule.metric, value));
+                        if !dry_run && matches!(policy.rule.action, Action::Pause | Action::Throttle) {
+                            policy.held.insert(key);
+                        }
+                        None
+                    }
+                    Err(err) => {
+                        eprintln!(
+                            "Warning: policy '{}' could not {} {} (PID: {}): {:#}",
+                            policy.rule.name,
+                            policy.rule.action.name(),
+                            name,
+                            key.pid,
+                            err
+                        );
+                        Some(format!("{:#}", err))
+                    }
+                };
+
+                let record = AuditRecord {
+                    timestamp: chrono::Utc::now().timestamp(),
+                    rule: policy.rule.name.clone(),
+                    action: policy.rule.action.name().to_string(),
+                    pid: key.pid,
+                    name,
+                    value,
+                    dry_run,
+                    error,
+                };
+                if let Err(err) = append_audit(&record) {
+                    eprintln!("Warning: could not write the audit log: {:#}", err);
+                }
+            }
+        }
+    }
+}
+
+fn format_value(metric: Metric, value: f64) -> String {
+    match metric {
+        Metric::Memory => format!("{:.0} MB", value),
+        Metric::Cpu => format!("{:.0}% CPU", value),
+    }
+}
+
+fn append_audit(record: &AuditRecord) -> Result<()> {
+    let log = log_path()?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&log)
+        .with_context(|| format!("Failed to open {}", log.display()))?;
+    writeln!(file, "{}", serde_json::to_string(record)?)?;
+    Ok(())
+}
+
+/// Shows the most recent entries of the audit log, newest first.
+pub fn show_audit_log(limit: usize) -> Result<()> {
+    let log = log_path()?;
+    let content = match fs::read_to_string(&log) {
+        Ok(content) => content,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(err) => return Err(anyhow::Error::new(err).context(format!("Failed to read {}", log.display()))),
+    };
+    let records: Vec<AuditRecord> = content.lines().filter_map(|line| serde_json::from_str(line).ok()).collect();
+    if records.is_empty() {
+        println!("No policy actions recorded yet.");
+        return Ok(());
+    }
+
+    let now = chrono::Utc::now().timestamp();
+    let rows: Vec<AuditRow> = records
+        .iter()
+        .rev()
+        .take(limit)
+        .map(|record| AuditRow {
+            when: format_age(now.saturating_sub(record.timestamp).max(0) as u64),
+            rule: record.rule.clone(),
+            action: record.action.clone(),
+            pid: record.pid,
+            name: record.name.clone(),
+            outcome: match (&record.error, record.dry_run) {
+                (Some(err), _) => format!("failed: {}", err),
+                (None, true) => "dry run".to_string(),
+                (None, false) => "done".to_string(),
+            },
+        })
+        .collect();
+
+    let mut table = Table::new(rows);
+    table.modify(ByColumnName::new("Outcome"), Width::truncate(60).suffix("..."));
+    println!("{}", table);
+    Ok(())
+}
@@ -0,0 +1,216 @@
+//! Per-process energy impact estimates for finding battery drainers.
+//!
+//! There is no per-process energy counter in Linux, so the impact of a process is its CPU time
+//! over a sampling interval, weighted by how fast the core it ran on was clocked (a core at
+//! half its maximum frequency uses well under half the power). When the RAPL package energy
+//! counters in /sys/class/powercap are readable (often root-only), the measured package power
+//! is split between processes in proportion to their impact.
+
+use anyhow::Result;
+use procfs::process::all_processes;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::time::{Duration, Instant};
+use sysinfo::System;
+use tabled::{Table, Tabled, settings::{Width, location::ByColumnName}};
+
+#[derive(Debug, Clone, Copy)]
+pub struct PowerImpact {
+    /// CPU cores kept busy at full clock speed, on average over the interval
+    pub cores: f64,
+    /// Share of the measured package power, when RAPL is readable
+    pub watts: Option<f64>,
+}
+
+impl fmt::Display for PowerImpact {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.watts {
+            Some(watts) => write!(f, "{:.2} W", watts),
+            None => write!(f, "{:.2}", self.cores),
+        }
+    }
+}
+
+/// Impact of every process that used CPU during the interval, plus the package power measured
+/// over it.
+pub struct PowerEstimate {
+    pub impacts: HashMap<u32, PowerImpact>,
+    pub package_watts: Option<f64>,
+}
+
+#[derive(Tabled)]
+struct PowerRow {
+    #[tabled(rename = "PID")]
+    pid: u32,
+
+    #[tabled(rename = "Name")]
+    name: String,
+
+    #[tabled(rename = "Impact")]
+    impact: String,
+
+    #[tabled(rename = "Share %")]
+    share: String,
+
+    #[tabled(rename = "Command")]
+    command: String,
+}
+
+/// Samples CPU time and clock speeds over `interval` and estimates each process's impact.
+pub async fn measure(interval: Duration) -> PowerEstimate {
+    let before = cpu_ticks();
+    let energy_before = package_energy_uj();
+    let started = Instant::now();
+    tokio::time::sleep(interval).await;
+    let after = cpu_ticks();
+    let energy_after = package_energy_uj();
+    let elapsed = started.elapsed().as_secs_f64();
+
+    let ticks_per_second = procfs::ticks_per_second() as f64;
+    let weights = frequency_weights();
+    let mut cores: HashMap<u32, f64> = HashMap::new();
+    for (pid, (ticks, processor)) in after {
+        // Processes that started during the interval count from zero
+        let delta = ticks.saturating_sub(before.get(&pid).map(|(t, _)| *t).unwrap_or(0));
+        if delta == 0 {
+            continue;
+        }
+        let weight = weights.get(&processor).copied().unwrap_or(1.0);
+        cores.insert(pid, delta as f64 / ticks_per_second / elapsed * weight);
+    }
+
+    let package_watts = match (energy_before, energy_after) {
+        (Some(before), Some(after)) if after >= before => Some((after - before) as f64 / 1_000_000.0 / elapsed),
+        _ => None,
+    };
+    let total: f64 = cores.values().sum();
+    let impacts = cores
+        .into_iter()
+        .map(|(pid, cores)| {
+            let watts = package_watts.filter(|_| total > 0.0).map(|watts| watts * cores / total);
+            (pid, PowerImpact { cores, watts })
+        })
+        .collect();
+
+    PowerEstimate { impacts, package_watts }
+}
+
+/// Ranks processes by estimated energy impact over `interval`.
+pub async fn show_power(interval: Duration, top: usize) -> Result<()> {
+    let estimate = measure(interval).await;
+    let mut system = System::new_all();
+    system.refresh_all();
+
+    let total: f64 = estimate.impacts.values().map(|impact| impact.cores).sum();
+    let mut impacts: Vec<(u32, PowerImpact)> = estimate.impacts.into_iter().collect();
+    impacts.sort_by(|a, b| b.1.cores.total_cmp(&a.1.cores));
+
+    let rows: Vec<PowerRow> = impacts
+        .into_iter()
+        .take(top)
+        .map(|(pid, impact)| {
+            let process = system.process(sysinfo::Pid::from(pid as usize));
+            PowerRow {
+                pid,
+                name: process.map(|p| p.name().to_string()).unwrap_or_else(|| "(exited)".to_string()),
+                impact: impact.to_string(),
+                share: format!("{:.1}", if total > 0.0 { impact.cores * 100.0 / total } else { 0.0 }),
+                command: process.map(|p| p.cmd().join(" ")).unwrap_or_default(),
+            }
+        })
+        .collect();
+
+    if rows.is_empty() {
+        println!("No process used any CPU during the last {:?}", interval);
+        return Ok(());
+    }
+
+    let mut table = Table::new(rows);
+    table.modify(ByColumnName::new("Command"), Width::truncate(50).suffix("..."));
+    println!("{}", table);
+
+    match estimate.package_watts {
+        Some(watts) => println!("\nCPU package power: {:.1} W (from RAPL), split by each process's share", watts),
+        None => println!(
+            "\nImpact is in CPU cores kept busy at full clock speed. RAPL energy counters are not available or not readable \
+             (try as root) so no wattage is shown."
+        ),
+    }
+    if let Some(watts) = battery_discharge_watts() {
+        println!("Battery: discharging at {:.1} W", watts);
+    }
+
+    Ok(())
+}
+
+/// Cumulative user plus system CPU time of every process, in clock ticks, with the CPU it
+/// last ran on.
+fn cpu_ticks() -> HashMap<u32, (u64, i32)> {
+    let Ok(processes) = all_processes() else {
+        return HashMap::new();
+    };
+    processes
+        .flatten()
+        .filter_map(|process| process.stat().ok())
+        .map(|stat| (stat.pid as u32, (stat.utime + stat.stime, stat.processor.unwrap_or(-1))))
+        .collect()
+}
+
+/// Current clock of each CPU relative to its maximum, from cpufreq.
+fn frequency_weights() -> HashMap<i32, f64> {
+    let read_khz = |path: String| fs::read_to_string(path).ok()?.trim().parse::<f64>().ok();
+    let Ok(entries) = fs::read_dir("/sys/devices/system/cpu") else {
+        return HashMap::new();
+    };
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let cpu: i32 = entry.file_name().to_string_lossy().strip_prefix("cpu")?.parse().ok()?;
+            let dir = entry.path().display().to_string();
+            let current = read_khz(format!("{}/cpufreq/scaling_cur_freq", dir))?;
+            let max = read_khz(format!("{}/cpufreq/cpuinfo_max_freq", dir)).filter(|max| *max > 0.0)?;
+            Some((cpu, (current / max).min(1.0)))
+        })
+        .collect()
+}
+
+/// Sum of the RAPL package energy counters (`intel-rapl:N`, also used on AMD), in microjoules.
+fn package_energy_uj() -> Option<u64> {
+    let entries = fs::read_dir("/sys/class/powercap").ok()?;
+    let mut total = None;
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        // Packages are intel-rapl:0, intel-rapl:1, ...; their subzones have a second colon
+        if !name.starts_with("intel-rapl:") || name.matches(':').count() != 1 {
+            continue;
+        }
+        let energy: u64 = fs::read_to_string(entry.path().join("energy_uj")).ok()?.trim().parse().ok()?;
+        total = Some(total.unwrap_or(0) + energy);
+    }
+    total
+}
+
+/// Current battery discharge rate, if running on battery.
+fn battery_discharge_watts() -> Option<f64> {
+    let entries = fs::read_dir("/sys/class/power_supply").ok()?;
+    let mut total = None;
+    for entry in entries.flatten() {
+        let dir = entry.path();
+        let read = |file: &str| fs::read_to_string(dir.join(file)).ok().map(|s| s.trim().to_string());
+        if read("type").as_deref() != Some("Battery") || read("status").as_deref() != Some("Discharging") {
+            continue;
+        }
+        // power_now is in microwatts; some batteries only report current and voltage
+        let microwatts = match read("power_now").and_then(|p| p.parse::<f64>().ok()) {
+            Some(power) => power,
+            None => {
+                let current: f64 = read("current_now")?.parse().ok()?;
+                let voltage: f64 = read("voltage_now")?.parse().ok()?;
+                current * voltage / 1_000_000.0
+            }
+        };
+        total = Some(total.unwrap_or(0.0) + microwatts / 1_000_000.0);
+    }
+    total
+}
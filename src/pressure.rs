@@ -0,0 +1,65 @@
+//! Pressure stall information (PSI) from /proc/pressure: the share of wall time in which tasks
+//! were stalled waiting for CPU, memory, or I/O.
+
+use std::fmt;
+use std::fs;
+
+/// Stall percentages averaged over the last 10 seconds, 60 seconds, and 5 minutes.
+#[derive(Debug, Clone, Copy)]
+pub struct Stall {
+    pub avg10: f64,
+    pub avg60: f64,
+    pub avg300: f64,
+}
+
+impl fmt::Display for Stall {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:>6.2}% {:>6.2}% {:>6.2}%", self.avg10, self.avg60, self.avg300)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Pressure {
+    /// Time in which at least one task was stalled
+    pub some: Stall,
+    /// Time in which all non-idle tasks were stalled at once (absent for CPU on older kernels)
+    pub full: Option<Stall>,
+}
+
+/// Reads /proc/pressure/<resource> ("cpu", "memory", or "io"). Returns None on kernels built
+/// without PSI or booted with psi=0.
+pub fn read(resource: &str) -> Option<Pressure> {
+    let content = fs::read_to_string(format!("/proc/pressure/{}", resource)).ok()?;
+    let mut some = None;
+    let mut full = None;
+    for line in content.lines() {
+        let kind = line.split_whitespace().next();
+        let value = |key: &str| {
+            line.split_whitespace()
+                .find_map(|field| field.strip_prefix(key)?.strip_prefix('='))
+                .and_then(|v| v.parse().ok())
+        };
+        let stall = Stall { avg10: value("avg10")?, avg60: value("avg60")?, avg300: value("avg300")? };
+        match kind {
+            Some("some") => some = Some(stall),
+            Some("full") => full = Some(stall),
+            _ => {}
+        }
+    }
+    Some(Pressure { some: some?, full })
+}
+
+/// Formats a pressure block for text output, e.g. under a "CPU pressure" heading.
+pub fn format(label: &str, pressure: Option<Pressure>) -> String {
+    let Some(pressure) = pressure else {
+        return format!("{} pressure: not available (kernel without PSI)\n", label);
+    };
+
+    let mut text = format!("{} pressure (share of time stalled):\n", label);
+    text.push_str(&format!("        {:>7} {:>7} {:>7}\n", "10s", "60s", "5min"));
+    text.push_str(&format!("  some  {}\n", pressure.some));
+    if let Some(full) = pressure.full {
+        text.push_str(&format!("  full  {}\n", full));
+    }
+    text
+}
@@ -0,0 +1,206 @@
+use anyhow::Result;
+use std::fmt;
+use std::fs;
+use std::io;
+use sysinfo::System;
+use tabled::{Table, Tabled};
+
+use crate::control;
+use crate::error::CliError;
+use crate::selector::Selection;
+
+const IOPRIO_WHO_PROCESS: libc::c_int = 1;
+const IOPRIO_CLASS_SHIFT: u32 = 13;
+
+/// An I/O scheduling class and level as used by ionice(1), e.g. `idle` or `best-effort:4`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IoPriority {
+    class: u32,
+    level: u32,
+}
+
+impl IoPriority {
+    fn from_raw(raw: libc::c_int) -> Self {
+        IoPriority {
+            class: (raw as u32) >> IOPRIO_CLASS_SHIFT,
+            level: (raw as u32) & ((1 << IOPRIO_CLASS_SHIFT) - 1),
+        }
+    }
+
+    fn raw(&self) -> libc::c_int {
+        ((self.class << IOPRIO_CLASS_SHIFT) | self.level) as libc::c_int
+    }
+}
+
+impl std::str::FromStr for IoPriority {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (class, level) = match s.split_once(':') {
+            Some((class, level)) => (class, Some(level)),
+            None => (s, None),
+        };
+
+        let class = match class {
+            "realtime" | "rt" | "1" => 1,
+            "best-effort" | "be" | "2" => 2,
+            "idle" | "3" => 3,
+            _ => return Err(format!("unknown I/O class '{}' (expected realtime, best-effort, or idle)", class)),
+        };
+
+        let level = match (class, level) {
+            (3, Some(_)) => return Err("the idle class has no level".to_string()),
+            (3, None) => 0,
+            (_, None) => 4,
+            (_, Some(level)) => match level.parse::<u32>() {
+                Ok(level) if level <= 7 => level,
+                _ => return Err(format!("invalid I/O level '{}' (expected 0-7)", level)),
+            },
+        };
+
+        Ok(IoPriority { class, level })
+    }
+}
+
+impl fmt::Display for IoPriority {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.class {
+            0 => write!(f, "default"),
+            1 => write!(f, "realtime:{}", self.level),
+            2 => write!(f, "best-effort:{}", self.level),
+            3 => write!(f, "idle"),
+            other => write!(f, "class {}", other),
+        }
+    }
+}
+
+#[derive(Tabled)]
+struct PriorityRow {
+    #[tabled(rename = "PID")]
+    pid: u32,
+
+    #[tabled(rename = "Name")]
+    name: String,
+
+    #[tabled(rename = "Nice")]
+    nice: String,
+
+    #[tabled(rename = "I/O Priority")]
+    io_priority: String,
+}
+
+/// Shows, and optionally changes, the nice value and I/O priority of the selected processes.
+/// Changes apply to every thread, since Linux schedules threads individually.
+pub fn manage_priority(identifier: &str, nice: Option<i32>, ionice: Option<IoPriority>, tree: bool) -> Result<()> {
+    let mut system = System::new_all();
+    system.refresh_all();
+
+    let mut pids: Vec<u32> = Selection::parse(identifier)?
+        .resolve(&system)
+        .into_iter()
+        .filter(|pid| !control::is_thread(&system, *pid))
+        .collect();
+
+    if pids.is_empty() {
+        return Err(CliError::not_found(format!("Process not found: {}", identifier)));
+    }
+
+    if tree {
+        for pid in pids.clone() {
+            for child in control::descendants(&system, pid) {
+                if !pids.contains(&child) {
+                    pids.push(child);
+                }
+            }
+        }
+    }
+
+    for pid in &pids {
+        for tid in threads(*pid) {
+            if let Some(nice) = nice {
+                set_nice(tid, nice)?;
+            }
+            if let Some(ionice) = ionice {
+                set_io_priority(tid, ionice)?;
+            }
+        }
+    }
+
+    let rows: Vec<PriorityRow> = pids
+        .iter()
+        .map(|pid| PriorityRow {
+            pid: *pid,
+            name: system
+                .process(sysinfo::Pid::from(*pid as usize))
+                .map(|p| p.name().to_string())
+                .unwrap_or_default(),
+            nice: get_nice(*pid).map(|n| n.to_string()).unwrap_or_else(|| "-".to_string()),
+            io_priority: get_io_priority(*pid).map(|p| p.to_string()).unwrap_or_else(|| "-".to_string()),
+        })
+        .collect();
+
+    println!("{}", Table::new(rows));
+    Ok(())
+}
+
+fn threads(pid: u32) -> Vec<u32> {
+    let Ok(entries) = fs::read_dir(format!("/proc/{}/task", pid)) else {
+        return vec![pid];
+    };
+
+    entries
+        .flatten()
+        .filter_map(|e| e.file_name().to_string_lossy().parse().ok())
+        .collect()
+}
+
+fn get_nice(pid: u32) -> Option<i64> {
+    procfs::process::Process::new(pid as i32).ok()?.stat().ok().map(|stat| stat.nice)
+}
+
+fn get_io_priority(pid: u32) -> Option<IoPriority> {
+    // SAFETY: ioprio_get takes two integer arguments and touches no memory
+    let raw = unsafe { libc::syscall(libc::SYS_ioprio_get, IOPRIO_WHO_PROCESS, pid as libc::c_int) };
+    (raw >= 0).then(|| IoPriority::from_raw(raw as libc::c_int))
+}
+
+fn set_nice(tid: u32, nice: i32) -> Result<()> {
+    // SAFETY: setpriority takes integer arguments and touches no memory
+    let result = unsafe { libc::setpriority(libc::PRIO_PROCESS, tid as libc::id_t, nice) };
+    if result == 0 {
+        return Ok(());
+    }
+
+    priority_error(
+        io::Error::last_os_error(),
+        tid,
+        "Lowering a nice value (raising priority) requires CAP_SYS_NICE; run as root or with sudo",
+    )
+}
+
+fn set_io_priority(tid: u32, priority: IoPriority) -> Result<()> {
+    // SAFETY: ioprio_set takes integer arguments and touches no memory
+    let result = unsafe {
+        libc::syscall(libc::SYS_ioprio_set, IOPRIO_WHO_PROCESS, tid as libc::c_int, priority.raw())
+    };
+    if result == 0 {
+        return Ok(());
+    }
+
+    priority_error(
+        io::Error::last_os_error(),
+        tid,
+        "The realtime I/O class, and changing other users' processes, requires CAP_SYS_NICE; run as root or with sudo",
+    )
+}
+
+fn priority_error(err: io::Error, tid: u32, permission_hint: &str) -> Result<()> {
+    match err.raw_os_error() {
+        // The thread exited between listing and changing it
+        Some(libc::ESRCH) => Ok(()),
+        Some(libc::EPERM) | Some(libc::EACCES) => {
+            Err(CliError::permission(format!("Permission denied for PID {}: {}", tid, permission_hint)))
+        }
+        _ => Err(anyhow::Error::new(err).context(format!("Failed to change priority of PID {}", tid))),
+    }
+}
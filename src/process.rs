@@ -1,77 +1,1031 @@
 use anyhow::{Context, Result};
+use clap::ValueEnum;
 use procfs::process::Process;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
+use std::io::{self, Write};
 use std::path::PathBuf;
+use serde::Deserialize;
+use std::time::Duration;
 use sysinfo::{System};
-use tabled::{Table, Tabled, settings::{Width, object::Columns}};
+use tabled::{Table, Tabled, settings::{Color, Disable, Width, location::ByColumnName, object::Rows}};
 use terminal_size::{Width as TermWidth, terminal_size};
+use tokio::sync::mpsc::UnboundedReceiver;
+use tracing::debug;
 
-#[derive(Tabled)]
+use crate::browser::{self, BrowserContext};
+use crate::control::{self, Signal};
+use crate::electron;
+use crate::error::{self, CliError, ErrorKind};
+use crate::faults::{self, FaultCounts, FaultRates};
+use crate::fixture;
+use crate::gpu::{self, GpuUsage};
+use crate::identity::ProcessKey;
+use crate::keys::{self, Key};
+use crate::memory::{self, SharedMemory};
+use crate::names;
+use crate::namespaces::{self, Membership};
+use crate::oom::{self, OomScore};
+use crate::plugins::{self, PluginColumn};
+use crate::output::{self, InfoFormat, ListFormat, PorcelainProcess, PorcelainVersion, format_memory};
+use crate::power::{self, PowerImpact};
+use crate::redact;
+use crate::sandbox::{self, Packaging, SandboxedApp};
+use crate::sched::{self, SchedStats};
+use crate::security::{self, SecurityContext};
+use crate::selector::{self, EnvFilter, ProcessIndex, Selection};
+use crate::smoothing::Smoother;
+use crate::tty::{self, TerminalContext};
+use crate::window::{self, WindowChoice, WindowInfo, WindowPlacement};
+
+/// How long `list --power` samples CPU time for.
+const POWER_SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long the page fault rates of `list --columns faults` and `info --faults` are sampled for.
+const FAULT_SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Rows per page for `list --page` without `--limit`.
+pub const DEFAULT_PAGE_SIZE: usize = 50;
+
+/// What `list --high-memory` counts as high, in MB, unless the profile says otherwise.
+pub const HIGH_MEMORY_MB: u64 = 100;
+
+#[derive(Clone, Tabled)]
 pub struct ProcessInfo {
+    /// `new` or `exited` since the previous refresh, for `list --watch --diff`
+    #[tabled(rename = "Change")]
+    pub change: &'static str,
+
     #[tabled(rename = "PID")]
     pub pid: u32,
 
-    #[tabled(rename = "Name")]
+    #[tabled(rename = "Name", display_with("Self::display_name", self))]
     pub name: String,
 
-    #[tabled(rename = "Memory (MB)")]
-    pub memory_mb: f64,
+    /// Flatpak app ID or snap name, shown only when some process has one
+    #[tabled(rename = "App ID", display_with = "display_option")]
+    pub sandbox: Option<SandboxedApp>,
+
+    #[tabled(rename = "TTY", display_with = "display_option")]
+    pub tty: Option<String>,
+
+    #[tabled(rename = "Memory")]
+    pub memory: String,
+
+    #[tabled(rename = "Memory Δ")]
+    pub memory_delta: String,
+
+    /// With its descendants, for processes shown without a shown ancestor (`--tree-memory`)
+    #[tabled(rename = "Tree Memory", display_with = "display_memory")]
+    pub tree_memory: Option<u64>,
 
     #[tabled(rename = "CPU %")]
     pub cpu_percent: f32,
 
+    #[tabled(rename = "Uptime")]
+    pub uptime: String,
+
+    #[tabled(rename = "I/O")]
+    pub io: String,
+
+    #[tabled(rename = "Threads")]
+    pub threads: usize,
+
+    /// Open file descriptors, counted only when shown, sorted, or filtered by
+    #[tabled(rename = "FDs", display_with = "display_option")]
+    pub fds: Option<usize>,
+
+    #[tabled(rename = "GPU", display_with = "display_option")]
+    pub gpu: Option<GpuUsage>,
+
+    #[tabled(rename = "OOM", display_with = "display_option")]
+    pub oom: Option<OomScore>,
+
+    #[tabled(rename = "Power", display_with = "display_option")]
+    pub power: Option<PowerImpact>,
+
+    #[tabled(rename = "Faults/s", display_with = "display_option")]
+    pub faults: Option<FaultRates>,
+
     #[tabled(rename = "Working Dir")]
     pub working_dir: String,
 
     #[tabled(rename = "Command")]
     pub command: String,
+
+    #[tabled(skip)]
+    pub parent_pid: Option<u32>,
+
+    #[tabled(skip)]
+    pub memory_bytes: u64,
+
+    #[tabled(skip)]
+    pub full_command: Vec<String>,
+
+    #[tabled(skip)]
+    pub start_time: u64,
+
+    #[tabled(skip)]
+    pub io_bytes: u64,
+
+    /// The Electron app a generic `electron` or Chromium helper process belongs to
+    #[tabled(skip)]
+    pub app: Option<String>,
+
+    /// Script, module, or main class of an interpreted process, with `--resolve-names`
+    #[tabled(skip)]
+    pub resolved_name: Option<String>,
+
+    /// Cells of the custom columns from the config, in their order
+    #[tabled(skip)]
+    pub plugins: Vec<String>,
+
+    /// A thread of `parent_pid`, listed as sysinfo lists it
+    #[tabled(skip)]
+    pub thread: bool,
+}
+
+impl ProcessInfo {
+    /// The app or resolved name where one was found, as that is what the user knows the
+    /// process as.
+    fn display_name(&self) -> String {
+        self.app.clone().or_else(|| self.resolved_name.clone()).unwrap_or_else(|| self.name.clone())
+    }
+
+    fn json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "pid": self.pid,
+            "ppid": self.parent_pid,
+            "name": self.name,
+            "app": self.app,
+            "resolved_name": self.resolved_name,
+            "sandbox": self.sandbox.as_ref().map(|app| app.packaging),
+            "app_id": self.sandbox.as_ref().map(|app| &app.app_id),
+            "memory_bytes": self.memory_bytes,
+            "memory": output::format_memory(self.memory_bytes),
+            "tree_memory_bytes": self.tree_memory,
+            "cpu_percent": self.cpu_percent,
+            "start_time": self.start_time,
+            "io_bytes": self.io_bytes,
+            "threads": self.threads,
+            "thread": self.thread,
+            "fds": self.fds,
+            "tty": self.tty,
+            "cwd": Some(self.working_dir.as_str()).filter(|wd| *wd != "N/A"),
+            "command": redact::command(&self.full_command),
+            "gpu": self.gpu.as_ref().map(|gpu| gpu.to_string()),
+            "oom_score": self.oom.map(|oom| oom.score),
+            "power_cores": self.power.map(|power| power.cores),
+            "faults_per_sec": self.faults.map(|faults| serde_json::json!({ "minor": faults.minor, "major": faults.major })),
+        })
+    }
+
+    /// The JSON of `list`, with the custom columns.
+    fn list_json(&self, options: &ListOptions) -> serde_json::Value {
+        let mut json = self.json();
+        if !options.plugin_columns.is_empty() {
+            let cells: serde_json::Map<String, serde_json::Value> = options
+                .plugin_columns
+                .iter()
+                .zip(&self.plugins)
+                .map(|(column, cell)| (column.name.clone(), cell.clone().into()))
+                .collect();
+            json["columns"] = cells.into();
+        }
+        json
+    }
+
+    fn porcelain(&self) -> PorcelainProcess<'_> {
+        PorcelainProcess {
+            pid: self.pid,
+            parent_pid: self.parent_pid,
+            name: &self.name,
+            memory_bytes: self.memory_bytes,
+            cpu_percent: self.cpu_percent,
+            working_dir: Some(self.working_dir.as_str()).filter(|wd| *wd != "N/A"),
+            command: &self.full_command,
+        }
+    }
+}
+
+/// Filtering, sorting, and output options for `list`.
+#[derive(Clone, Default)]
+pub struct ListOptions {
+    pub name_filter: Option<String>,
+    pub cwd: Option<PathBuf>,
+    pub env: Vec<EnvFilter>,
+    /// Only processes using at least this many MB
+    pub high_memory: Option<u64>,
+    /// Only processes with at least this many threads
+    pub min_threads: Option<usize>,
+    /// Only processes with at least this many open file descriptors
+    pub min_fds: Option<usize>,
+    /// Report and compare proportional memory (PSS) instead of RSS
+    pub pss: bool,
+    /// Show the memory of each top-level process's whole tree
+    pub tree_memory: bool,
+    /// Also list kernel threads ([kworker/0:1] and the like)
+    pub kernel_threads: bool,
+    /// Also list top-helper itself
+    pub include_self: bool,
+    /// Only processes with this controlling terminal, e.g. "pts/3"
+    pub tty: Option<String>,
+    /// Show interpreted processes by their script, module, or main class
+    pub resolve_names: bool,
+    /// Only Flatpak or only Snap apps
+    pub sandbox: Option<Packaging>,
+    pub sort: Option<SortKey>,
+    /// Show only this many processes, after sorting
+    pub limit: Option<usize>,
+    /// Skip this many processes, after sorting
+    pub offset: usize,
+    /// Optional columns to show
+    pub columns: Vec<ListColumn>,
+    /// Custom columns from the config, filled by running their commands
+    pub plugin_columns: Vec<PluginColumn>,
+    pub interactive: bool,
+    pub porcelain: Option<PorcelainVersion>,
+    pub format: ListFormat,
+    /// Read processes from a recorded fixture instead of /proc
+    pub fixture: Option<PathBuf>,
+    /// Redraw at this interval until interrupted
+    pub watch: Option<Duration>,
+    /// Mark new and exited processes and show memory changes between redraws
+    pub diff: bool,
+    /// How much each redraw keeps of the previous CPU usage, as `smoothing::Smoother` takes it
+    pub smoothing: f64,
+    /// X display for switching to a process's window from `--watch`
+    pub display: Option<String>,
+}
+
+impl ListOptions {
+    fn sorts_by(&self, field: SortField) -> bool {
+        self.sort.is_some_and(|key| key.field == field)
+    }
+
+    /// Whether the column was asked for; uptime, I/O, threads, FDs, and faults also show when
+    /// sorting by them.
+    fn shows(&self, column: ListColumn) -> bool {
+        let sorted = match column {
+            ListColumn::Uptime => self.sorts_by(SortField::Uptime),
+            ListColumn::Io => self.sorts_by(SortField::Io),
+            ListColumn::Threads => self.sorts_by(SortField::Threads),
+            ListColumn::Fds => self.sorts_by(SortField::Fds),
+            ListColumn::Faults => self.sorts_by(SortField::Faults),
+            ListColumn::Gpu | ListColumn::Power => false,
+        };
+        sorted || self.columns.contains(&column)
+    }
+
+    fn sort_label(&self, field: SortField) -> &'static str {
+        match field {
+            SortField::Memory if self.pss => "proportional memory (PSS)",
+            _ => field.label(),
+        }
+    }
+
+    /// Whether file descriptors need counting, which is one directory read per process.
+    fn counts_fds(&self) -> bool {
+        self.shows(ListColumn::Fds) || self.min_fds.is_some()
+    }
+}
+
+/// A `list` column that is hidden unless asked for with `--columns` or a profile's `columns`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ListColumn {
+    /// GPU usage type and memory, as with --gpu
+    Gpu,
+    /// Estimated energy impact, as with --power
+    Power,
+    Uptime,
+    Io,
+    Threads,
+    /// Open file descriptors
+    Fds,
+    /// Page faults per second, sampled over a second
+    Faults,
+}
+
+/// A column `list` can sort by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortField {
+    Pid,
+    Name,
+    Memory,
+    Cpu,
+    Uptime,
+    Io,
+    Threads,
+    Fds,
+    /// Major, then minor, page faults per second
+    Faults,
+    /// OOM score: the order in which the kernel's OOM killer picks victims
+    Oom,
+}
+
+impl SortField {
+    const NAMES: &'static str = "pid, name, mem, cpu, uptime, io, threads, fds, faults, oom";
+
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "pid" => SortField::Pid,
+            "name" => SortField::Name,
+            "mem" | "memory" => SortField::Memory,
+            "cpu" => SortField::Cpu,
+            "uptime" => SortField::Uptime,
+            "io" => SortField::Io,
+            "threads" => SortField::Threads,
+            "fds" => SortField::Fds,
+            "faults" => SortField::Faults,
+            "oom" => SortField::Oom,
+            _ => return None,
+        })
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            SortField::Pid => "PID",
+            SortField::Name => "name",
+            SortField::Memory => "memory usage",
+            SortField::Cpu => "CPU usage",
+            SortField::Uptime => "uptime",
+            SortField::Io => "I/O",
+            SortField::Threads => "thread count",
+            SortField::Fds => "open file descriptors",
+            SortField::Faults => "page fault rate",
+            SortField::Oom => "OOM score",
+        }
+    }
+}
+
+/// A `--sort` argument: a field and direction, e.g. `mem` or `name:asc`. PID and name sort
+/// ascending by default and everything else biggest first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SortKey {
+    pub field: SortField,
+    pub descending: bool,
+}
+
+impl SortKey {
+    pub fn new(field: SortField) -> Self {
+        SortKey { field, descending: !matches!(field, SortField::Pid | SortField::Name) }
+    }
+
+    fn compare(&self, a: &ProcessInfo, b: &ProcessInfo) -> std::cmp::Ordering {
+        let ordering = match self.field {
+            SortField::Pid => a.pid.cmp(&b.pid),
+            SortField::Name => a.display_name().to_lowercase().cmp(&b.display_name().to_lowercase()),
+            SortField::Memory => a.memory_bytes.cmp(&b.memory_bytes),
+            SortField::Cpu => a.cpu_percent.total_cmp(&b.cpu_percent),
+            // Started earlier means up longer
+            SortField::Uptime => b.start_time.cmp(&a.start_time),
+            SortField::Io => a.io_bytes.cmp(&b.io_bytes),
+            SortField::Threads => a.threads.cmp(&b.threads),
+            SortField::Fds => a.fds.cmp(&b.fds),
+            SortField::Faults => {
+                let (a, b) = (a.faults.unwrap_or_default(), b.faults.unwrap_or_default());
+                a.major.total_cmp(&b.major).then(a.minor.total_cmp(&b.minor))
+            }
+            SortField::Oom => a.oom.map(|oom| oom.score).cmp(&b.oom.map(|oom| oom.score)),
+        };
+        if self.descending { ordering.reverse() } else { ordering }
+    }
+}
+
+impl std::str::FromStr for SortKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, direction) = s.split_once(':').unwrap_or((s, ""));
+        let field = SortField::parse(&name.to_lowercase())
+            .ok_or_else(|| format!("unknown sort field '{}'; expected one of {}", name, SortField::NAMES))?;
+        let key = SortKey::new(field);
+        match direction {
+            "" => Ok(key),
+            "asc" => Ok(SortKey { descending: false, ..key }),
+            "desc" => Ok(SortKey { descending: true, ..key }),
+            _ => Err(format!("unknown sort direction '{}'; expected asc or desc", direction)),
+        }
+    }
+}
+
+#[derive(Tabled)]
+struct TerminalJob {
+    #[tabled(rename = "TTY")]
+    tty: String,
+
+    #[tabled(rename = "Terminal")]
+    location: String,
+
+    #[tabled(rename = "Shell")]
+    shell: String,
+
+    #[tabled(rename = "Running PID")]
+    running_pid: String,
+
+    #[tabled(rename = "Running")]
+    running: String,
+
+    #[tabled(rename = "Working Dir")]
+    working_dir: String,
 }
 
 #[derive(Debug)]
 pub struct DetailedProcessInfo {
     pub pid: u32,
     pub name: String,
-    pub memory_mb: f64,
+    pub app: Option<String>,
+    pub sandbox: Option<SandboxedApp>,
+    pub memory_bytes: u64,
+    /// RSS, PSS, and USS, when the process's maps are readable
+    pub shared_memory: Option<SharedMemory>,
     pub cpu_percent: f32,
     pub working_dir: Option<PathBuf>,
     pub command: Vec<String>,
     pub env_vars: HashMap<String, String>,
     pub parent_pid: Option<u32>,
-    pub window_id: Option<String>,
-    pub window_title: Option<String>,
+    pub windows: Vec<WindowInfo>,
+    /// By window ID, for the windows whose backend could tell
+    pub window_placements: HashMap<String, WindowPlacement>,
+    pub gpu: Option<GpuUsage>,
+    pub oom: Option<OomScore>,
+    pub faults: Option<FaultCounts>,
+    /// Only sampled for `info --faults`
+    pub fault_rates: Option<FaultRates>,
+    pub terminal: Option<TerminalContext>,
+    pub browser: Option<BrowserContext>,
+    /// Only read for `info --security`
+    pub security: Option<SecurityContext>,
+    /// Only read for `info --namespaces`
+    pub namespaces: Option<Vec<Membership>>,
+    /// Only read for `info --sched`
+    pub sched: Option<SchedStats>,
+}
+
+impl DetailedProcessInfo {
+    pub fn json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "pid": self.pid,
+            "ppid": self.parent_pid,
+            "name": self.name,
+            "app": self.app,
+            "sandbox": self.sandbox.as_ref().map(|app| app.packaging),
+            "app_id": self.sandbox.as_ref().map(|app| &app.app_id),
+            "memory_bytes": self.memory_bytes,
+            "memory": output::format_memory(self.memory_bytes),
+            "pss_bytes": self.shared_memory.map(|memory| memory.pss),
+            "uss_bytes": self.shared_memory.map(|memory| memory.uss),
+            "cpu_percent": self.cpu_percent,
+            "cwd": self.working_dir.as_ref().map(|wd| wd.display().to_string()),
+            "command": redact::command(&self.command),
+            "windows": self.windows.iter().map(|window| {
+                let placement = self.window_placements.get(&window.window_id);
+                serde_json::json!({
+                    "window_id": window.window_id,
+                    "title": redact::text(&window.title),
+                    "class": window.class,
+                    "backend": window.backend.name(),
+                    "workspace": placement.and_then(|placement| placement.workspace.as_ref()),
+                    "geometry": placement.and_then(|placement| placement.geometry),
+                    "focused": placement.and_then(|placement| placement.focused),
+                })
+            }).collect::<Vec<_>>(),
+            "terminal": self.terminal.as_ref().map(|terminal| format!("{} tab {}", terminal.emulator, terminal.tab)),
+            "oom_score": self.oom.map(|oom| oom.score),
+            "oom_score_adj": self.oom.map(|oom| oom.adj),
+            "gpu": self.gpu.as_ref().map(|gpu| serde_json::json!({
+                "type": gpu.kind.to_string(),
+                "driver": gpu.driver,
+                "memory_bytes": gpu.memory_bytes,
+            })),
+            "browser": self.browser.as_ref().map(BrowserContext::json),
+            "environment": self.env_vars.iter()
+                .filter(|(key, _)| is_relevant_env_var(key))
+                .map(|(key, value)| (key.clone(), redact::env_value(key, value)))
+                .collect::<BTreeMap<_, _>>(),
+            "security": self.security.as_ref().map(SecurityContext::json),
+            "namespaces": self.namespaces.as_ref().map(|namespaces| namespaces.iter().map(Membership::json).collect::<Vec<_>>()),
+            "sched": self.sched.as_ref().map(SchedStats::json),
+            "page_faults": self.faults.map(|faults| serde_json::json!({
+                "minor": faults.minor,
+                "major": faults.major,
+                "minor_per_sec": self.fault_rates.map(|rates| rates.minor),
+                "major_per_sec": self.fault_rates.map(|rates| rates.major),
+            })),
+        })
+    }
+}
+
+/// Writes one sample of processes as NDJSON, one object per process, each stamped with the time
+/// of the sample. Flushed at once, for pipelines reading as it goes.
+fn write_ndjson(processes: &[ProcessInfo], options: &ListOptions) -> Result<()> {
+    let timestamp = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+    let mut stdout = io::stdout().lock();
+    for process in processes {
+        let mut json = process.list_json(options);
+        json["timestamp"] = timestamp.clone().into();
+        writeln!(stdout, "{}", json)?;
+    }
+    stdout.flush()?;
+    Ok(())
+}
+
+/// Every process but kernel threads, unfiltered, for `monitor --format ndjson`.
+pub fn write_ndjson_sample(system: &System) -> Result<()> {
+    let options = ListOptions::default();
+    write_ndjson(&filter_samples(fixture::collect(system, false), &options), &options)
+}
+
+pub async fn list_processes(options: &ListOptions) -> Result<()> {
+    if let Some(interval) = options.watch {
+        if options.fixture.is_some() {
+            return Err(anyhow::anyhow!("--watch needs a live system, not a --fixture"));
+        }
+        return match options.format {
+            ListFormat::Text => watch_processes(options, interval).await,
+            ListFormat::Ndjson => stream_processes(options, interval).await,
+            ListFormat::Json => Err(anyhow::anyhow!("--watch streams with --format ndjson; json is a single document")),
+        };
+    }
+
+    let mut live = None;
+    let samples = match &options.fixture {
+        Some(dir) => {
+            let live_columns = [ListColumn::Gpu, ListColumn::Power, ListColumn::Faults];
+            if options.interactive
+                || options.pss
+                || options.tree_memory
+                || options.sorts_by(SortField::Oom)
+                || options.counts_fds()
+                || live_columns.iter().any(|&c| options.shows(c))
+            {
+                return Err(anyhow::anyhow!(
+                    "--fixture only covers the process table; --interactive, --pss, --tree-memory, --gpu, --sort \
+                     oom, --power, page faults, and file descriptors need a live system"
+                ));
+            }
+            fixture::load(dir)?
+        }
+        None => {
+            let system = live.insert(System::new_all());
+            system.refresh_all();
+
+            if options.interactive {
+                list_foreground_jobs(system, options);
+                return Ok(());
+            }
+            fixture::collect(system, false)
+        }
+    };
+
+    let sampled = samples.len();
+    let mut processes = filter_samples(samples, options);
+    debug!("{} of {} processes pass the filters", processes.len(), sampled);
+    add_columns(&mut processes, options).await;
+    let total = processes.len();
+    let heading = sort_processes(&mut processes, options);
+    if let Some(system) = &live {
+        add_tree_memory(system, &mut processes, options);
+        add_plugin_columns(&mut processes, options).await;
+    }
+
+    match options.format {
+        ListFormat::Text => {}
+        ListFormat::Json => {
+            // The total lets wrappers page through the list without fetching all of it
+            let json = serde_json::json!({
+                "total": total,
+                "offset": options.offset,
+                "limit": options.limit,
+                "processes": processes.iter().map(|process| process.list_json(options)).collect::<Vec<_>>(),
+            });
+            println!("{}", serde_json::to_string_pretty(&json)?);
+            return Ok(());
+        }
+        ListFormat::Ndjson => return write_ndjson(&processes, options),
+    }
+
+    if let Some(version) = options.porcelain {
+        for process in &processes {
+            println!("{}", process.porcelain().line(version));
+        }
+        return Ok(());
+    }
+
+    if let Some(heading) = heading {
+        println!("{}", heading);
+    }
+    println!("{}", process_table(&processes, options));
+
+    Ok(())
 }
 
-pub async fn list_processes(
-    name_filter: Option<&str>,
-    high_memory: bool,
-    sort_memory: bool,
-    top_memory: Option<usize>,
-    top_cpu: Option<usize>,
-) -> Result<()> {
+/// Redraws the list every `interval` until interrupted. With `--diff`, processes started since
+/// the previous refresh are highlighted, ones that exited stay for one more refresh, and each
+/// row shows how much its memory changed.
+///
+/// On a terminal, keys act on the list between refreshes: the arrows move a cursor over the
+/// rows, `m` and `c` sort by memory or CPU (again to reverse), `/` types a name filter, Enter
+/// shows the selected process's info, `s` switches to its window, `k` terminates it, and `q` or
+/// Ctrl-C quits.
+async fn watch_processes(options: &ListOptions, interval: Duration) -> Result<()> {
+    let mut options = options.clone();
     let mut system = System::new_all();
     system.refresh_all();
 
+    // Without a terminal to read keys from, this only redraws
+    let raw_mode = keys::RawMode::enable();
+    let mut keys = raw_mode.as_ref().map(|_| keys::read_keys());
+    let mut state = WatchState::default();
+    let mut previous: Option<Vec<ProcessInfo>> = None;
+    let mut view: Option<WatchView> = None;
+    let mut cpu = Smoother::new(options.smoothing);
+
+    let ctrl_c = tokio::signal::ctrl_c();
+    tokio::pin!(ctrl_c);
+    // Also gives CPU usage a second sample to be measured against
+    let mut ticker = tokio::time::interval(interval.max(sysinfo::MINIMUM_CPU_UPDATE_INTERVAL));
+    // The first tick completes immediately, before CPU usage can be measured
+    ticker.tick().await;
+
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => {
+                system.refresh_processes();
+                smooth_cpu(&mut cpu, &system);
+                previous = view.take().map(|view| view.processes);
+                view = Some(WatchView::collect(&system, &options, previous.as_deref(), Some(&cpu)).await);
+            }
+            Some(key) = async { keys.as_mut()?.recv().await } => {
+                let Some(current) = &view else {
+                    continue;
+                };
+                match state.handle(key, current, &mut options, &system, &mut keys).await? {
+                    KeyOutcome::Quit => break,
+                    KeyOutcome::Redraw => {}
+                    // Filter and sort changes apply to the same sample, still compared with the
+                    // previous refresh
+                    KeyOutcome::Recollect => {
+                        view = Some(WatchView::collect(&system, &options, previous.as_deref(), Some(&cpu)).await);
+                    }
+                }
+            }
+            _ = &mut ctrl_c => break,
+        }
+
+        if let Some(view) = &view {
+            output::redraw(&view.render(&options, keys.is_some().then_some(&mut state)));
+        }
+    }
+    Ok(())
+}
+
+/// Writes every `interval` the processes that pass the filters as NDJSON, until interrupted.
+async fn stream_processes(options: &ListOptions, interval: Duration) -> Result<()> {
+    let mut system = System::new_all();
+    system.refresh_all();
+    let mut ticker = tokio::time::interval(interval.max(sysinfo::MINIMUM_CPU_UPDATE_INTERVAL));
+    // The first tick completes immediately, before CPU usage can be measured
+    ticker.tick().await;
+    loop {
+        ticker.tick().await;
+        system.refresh_processes();
+        let view = WatchView::collect(&system, options, None, None).await;
+        write_ndjson(&view.processes, options)?;
+    }
+}
+
+/// Folds every process's CPU usage into `cpu`, forgetting processes that have exited.
+fn smooth_cpu(cpu: &mut Smoother<ProcessKey>, system: &System) {
+    for process in system.processes().values() {
+        cpu.update(ProcessKey::of(process), process.cpu_usage() as f64);
+    }
+    cpu.retain(|key, _| key.find(system).is_some());
+}
+
+/// One refresh of `list --watch`, ready to draw.
+struct WatchView {
+    heading: Option<String>,
+    processes: Vec<ProcessInfo>,
+    /// Processes gone since the previous refresh, shown once more with `--diff`
+    exited: Vec<ProcessInfo>,
+}
+
+impl WatchView {
+    /// With `cpu`, processes show and sort by their smoothed CPU usage.
+    async fn collect(
+        system: &System,
+        options: &ListOptions,
+        previous: Option<&[ProcessInfo]>,
+        cpu: Option<&Smoother<ProcessKey>>,
+    ) -> Self {
+        let mut samples = fixture::collect(system, false);
+        if let Some(cpu) = cpu {
+            for sample in &mut samples {
+                if let Some(usage) = cpu.get(&ProcessKey { pid: sample.pid, start_time: sample.start_time }) {
+                    sample.cpu_percent = usage as f32;
+                }
+            }
+        }
+        let mut processes = filter_samples(samples, options);
+        add_columns(&mut processes, options).await;
+        let heading = sort_processes(&mut processes, options);
+        add_tree_memory(system, &mut processes, options);
+        add_plugin_columns(&mut processes, options).await;
+
+        let mut exited = Vec::new();
+        if options.diff
+            && let Some(previous) = previous
+        {
+            let before: HashMap<u32, u64> = previous.iter().map(|p| (p.pid, p.memory_bytes)).collect();
+            for process in &mut processes {
+                match before.get(&process.pid) {
+                    Some(memory_bytes) => process.memory_delta = format_delta(process.memory_bytes as i64 - *memory_bytes as i64),
+                    None => process.change = "new",
+                }
+            }
+            let current: HashSet<u32> = processes.iter().map(|p| p.pid).collect();
+            exited.extend(previous.iter().filter(|p| !current.contains(&p.pid)).map(|p| ProcessInfo {
+                change: "exited",
+                memory_delta: String::new(),
+                ..p.clone()
+            }));
+        }
+        WatchView { heading, processes, exited }
+    }
+
+    /// The screen: the heading, the table, and with keys, the cursor and a key legend.
+    fn render(&self, options: &ListOptions, mut state: Option<&mut WatchState>) -> String {
+        let changes: Vec<&'static str> = self.processes.iter().chain(&self.exited).map(|p| p.change).collect();
+        let mut table = process_table(self.processes.iter().chain(&self.exited), options);
+        if output::use_color() {
+            for (index, change) in changes.iter().enumerate() {
+                let color = match *change {
+                    "new" => Color::FG_GREEN,
+                    "exited" => Color::FG_RED,
+                    _ => continue,
+                };
+                // Row 0 is the header
+                table.modify(Rows::single(index + 1), color);
+            }
+        }
+        let cursor = state.as_deref_mut().and_then(|state| state.cursor(self));
+        if let Some(cursor) = cursor {
+            table.modify(Rows::single(cursor + 1), Color::new("\u{1b}[7m", "\u{1b}[27m"));
+        }
+
+        let mut text = String::new();
+        if let Some(heading) = &self.heading {
+            text.push_str(&format!("{}\n", heading));
+        }
+        text.push_str(&format!("{}\n", table));
+        let Some(state) = state else {
+            return text;
+        };
+
+        let mut shown = Vec::new();
+        if let Some(key) = options.sort {
+            shown.push(format!("sorted by {}{}", options.sort_label(key.field), if key.descending { "" } else { " (ascending)" }));
+        }
+        if let Some(filter) = &options.name_filter {
+            shown.push(format!("filter: {}", filter));
+        }
+        if !shown.is_empty() {
+            text.push_str(&format!("\n{}", shown.join(", ")));
+        }
+        text.push('\n');
+        match (&state.typing, &state.confirm_kill, state.status.take()) {
+            (Some(filter), _, _) => text.push_str(&format!("Filter by name: {}_  (Enter to apply, Esc to cancel)", filter)),
+            (_, Some((pid, name)), _) => text.push_str(&format!("Terminate {} (PID: {})? [y/N]", name, pid)),
+            (_, _, Some(status)) => text.push_str(&status),
+            _ => text.push_str("↑/↓ select  Enter info  s switch  k kill  m/c sort by memory/CPU  / filter  q quit"),
+        }
+        text
+    }
+}
+
+/// The cursor and the input in progress in `list --watch`.
+#[derive(Default)]
+struct WatchState {
+    /// The process under the cursor, by PID so the cursor follows it when rows move
+    selected: Option<u32>,
+    /// Where the cursor was, for when the selected process is gone
+    row: usize,
+    /// The name filter being typed after `/`
+    typing: Option<String>,
+    /// The process waiting for `y` to be terminated
+    confirm_kill: Option<(u32, String)>,
+    /// The outcome of the last action, shown until the next redraw
+    status: Option<String>,
+}
+
+enum KeyOutcome {
+    Quit,
+    Redraw,
+    /// The filter or sort order changed
+    Recollect,
+}
+
+impl WatchState {
+    /// The row under the cursor, among the processes still running.
+    fn cursor(&mut self, view: &WatchView) -> Option<usize> {
+        if view.processes.is_empty() {
+            return None;
+        }
+        let row = match self.selected.and_then(|pid| view.processes.iter().position(|p| p.pid == pid)) {
+            Some(row) => row,
+            None => self.row.min(view.processes.len() - 1),
+        };
+        self.row = row;
+        self.selected = Some(view.processes[row].pid);
+        Some(row)
+    }
+
+    async fn handle(
+        &mut self,
+        key: Key,
+        view: &WatchView,
+        options: &mut ListOptions,
+        system: &System,
+        keys: &mut Option<UnboundedReceiver<Key>>,
+    ) -> Result<KeyOutcome> {
+        if key == Key::Interrupt {
+            return Ok(KeyOutcome::Quit);
+        }
+        if let Some(filter) = &mut self.typing {
+            match key {
+                Key::Char(c) => filter.push(c),
+                Key::Backspace => {
+                    filter.pop();
+                }
+                Key::Enter => {
+                    options.name_filter = self.typing.take().filter(|filter| !filter.is_empty());
+                    return Ok(KeyOutcome::Recollect);
+                }
+                Key::Escape => self.typing = None,
+                Key::Up | Key::Down | Key::Interrupt => {}
+            }
+            return Ok(KeyOutcome::Redraw);
+        }
+
+        if let Some((pid, name)) = self.confirm_kill.take() {
+            self.status = Some(match key {
+                Key::Char('y' | 'Y') if control::own_ancestry(system).contains(&pid) => {
+                    format!("Not terminating {} (PID: {}): top-helper runs inside it", name, pid)
+                }
+                Key::Char('y' | 'Y') => match control::send_signal(pid, Signal::Terminate) {
+                    Ok(()) => format!("Terminated {} (PID: {})", name, pid),
+                    Err(err) => format!("Error: {:#}", err),
+                },
+                _ => "Cancelled".to_string(),
+            });
+            return Ok(KeyOutcome::Redraw);
+        }
+
+        let row = self.cursor(view);
+        let selected = row.map(|row| &view.processes[row]);
+        match key {
+            Key::Char('q') => return Ok(KeyOutcome::Quit),
+            Key::Up => self.selected = row.map(|row| view.processes[row.saturating_sub(1)].pid),
+            Key::Down => self.selected = row.map(|row| view.processes[(row + 1).min(view.processes.len() - 1)].pid),
+            Key::Char(c @ ('m' | 'c')) => {
+                let field = if c == 'm' { SortField::Memory } else { SortField::Cpu };
+                options.sort = Some(match options.sort {
+                    Some(key) if key.field == field => SortKey { descending: !key.descending, ..key },
+                    _ => SortKey::new(field),
+                });
+                return Ok(KeyOutcome::Recollect);
+            }
+            Key::Char('/') => self.typing = Some(options.name_filter.clone().unwrap_or_default()),
+            Key::Char('k') => self.confirm_kill = selected.map(|process| (process.pid, process.name.clone())),
+            Key::Char('s') => {
+                if let Some(process) = selected {
+                    self.status = Some(match switch_to(system, process.pid, options.display.as_deref()) {
+                        Ok(()) => format!("Switched to the window of {} (PID: {})", process.name, process.pid),
+                        Err(err) => format!("Error: {:#}", err),
+                    });
+                }
+            }
+            Key::Enter => {
+                if let Some(process) = selected {
+                    // info prints plain lines, which raw mode would not return to the margin
+                    let cooked = keys::Cooked::enter();
+                    output::redraw("");
+                    let selector = [format!("pid:{}", process.pid)];
+                    if let Err(err) = show_process_info(&selector, &InfoOptions::default()).await {
+                        println!("Error: {:#}", err);
+                    }
+                    println!("\nPress any key to return to the list");
+                    drop(cooked);
+                    if let Some(keys) = keys {
+                        keys.recv().await;
+                    }
+                }
+            }
+            _ => {}
+        }
+        Ok(KeyOutcome::Redraw)
+    }
+}
+
+fn switch_to(system: &System, pid: u32, display: Option<&str>) -> Result<()> {
+    let process = system
+        .process(sysinfo::Pid::from(pid as usize))
+        .ok_or_else(|| CliError::not_found(format!("PID {} exited", pid)))?;
+    let mut plan = window::find_switch_target(system, process, display)?.plan(&WindowChoice::default(), false)?;
+    plan.perform()
+}
+
+/// Turns process samples into table rows, dropping those that don't pass the filters.
+fn filter_samples(samples: Vec<fixture::ProcessSample>, options: &ListOptions) -> Vec<ProcessInfo> {
     let mut processes: Vec<ProcessInfo> = Vec::new();
+    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_secs();
 
-    for (pid, process) in system.processes() {
-        let memory_mb = process.memory() as f64 / 1024.0 / 1024.0;
+    // A fixture's processes are from another run, so none of them is this one
+    let own_pid = options.fixture.is_none().then(std::process::id);
 
+    for mut sample in samples {
         // Apply filters
-        if let Some(filter) = name_filter {
-            if !process.name().to_lowercase().contains(&filter.to_lowercase()) {
-                continue;
-            }
+        if !options.kernel_threads && sample.is_kernel_thread() {
+            continue;
+        }
+
+        // Its threads are listed with it as their parent; the commands it starts are not its own
+        if !options.include_self
+            && own_pid.is_some()
+            && (own_pid == Some(sample.pid) || (sample.thread && own_pid == sample.parent_pid))
+        {
+            continue;
+        }
+
+        let resolved_name = if options.resolve_names { names::display_name(&sample.cmd) } else { None };
+        if let Some(filter) = &options.name_filter
+            && !std::iter::once(&sample.name)
+                .chain(&sample.app)
+                .chain(&resolved_name)
+                .chain(sample.sandbox.as_ref().map(|app| &app.app_id))
+                .any(|name| selector::name_matches(name, &filter.to_lowercase()))
+        {
+            continue;
         }
 
-        if high_memory && memory_mb < 100.0 {
+        if let Some(cwd) = &options.cwd
+            && !sample.cwd.as_ref().is_some_and(|dir| dir.starts_with(cwd))
+        {
             continue;
         }
 
-        let working_dir = get_process_working_dir(pid.as_u32()).unwrap_or_else(|_| "N/A".to_string());
+        if let Some(packaging) = options.sandbox
+            && sample.sandbox.as_ref().is_none_or(|app| app.packaging != packaging)
+        {
+            continue;
+        }
+
+        if options.min_threads.is_some_and(|min| sample.threads < min) {
+            continue;
+        }
 
-        let command = process
-            .cmd()
+        if let Some(tty) = &options.tty
+            && sample.tty.as_deref() != Some(tty.trim_start_matches("/dev/"))
+        {
+            continue;
+        }
+
+        // smaps_rollup walks the whole address space, so PSS is only read for the processes the
+        // cheap filters kept. Processes whose maps are not readable keep their RSS
+        if options.pss
+            && let Some(memory) = memory::shared_memory(sample.pid)
+        {
+            sample.memory_bytes = memory.pss;
+        }
+        if options.high_memory.is_some_and(|threshold| (sample.memory_bytes as f64 / 1024.0 / 1024.0) < threshold as f64) {
+            continue;
+        }
+
+        // Other users' descriptors can't be counted, so they don't pass --min-fds
+        let fds = if options.counts_fds() { count_fds(sample.pid) } else { None };
+        if options.min_fds.is_some_and(|min| fds.is_none_or(|fds| fds < min)) {
+            continue;
+        }
+
+        // Live environments are read only now, after the cheaper filters
+        let env_matches = match options.fixture {
+            Some(_) => selector::environ_matches(&sample.environ, &options.env),
+            None => selector::env_matches(sample.pid, &options.env),
+        };
+        if !env_matches {
+            continue;
+        }
+
+        let working_dir = sample
+            .cwd
+            .as_ref()
+            .map(|cwd| cwd.display().to_string())
+            .unwrap_or_else(|| "N/A".to_string());
+
+        let command = sample
+            .cmd
             .first()
             .map(|c| {
                 if c.len() > 50 {
@@ -83,29 +1037,212 @@ pub async fn list_processes(
             .unwrap_or_else(|| "N/A".to_string());
 
         processes.push(ProcessInfo {
-            pid: pid.as_u32(),
-            name: process.name().to_string(),
-            memory_mb: (memory_mb * 100.0).round() / 100.0,
-            cpu_percent: process.cpu_usage(),
+            change: "",
+            pid: sample.pid,
+            name: sample.name,
+            sandbox: sample.sandbox,
+            tty: sample.tty,
+            memory: output::format_memory(sample.memory_bytes),
+            memory_delta: String::new(),
+            tree_memory: None,
+            cpu_percent: sample.cpu_percent,
+            uptime: output::format_duration(now.saturating_sub(sample.start_time) * 1000),
+            io: format_memory(sample.io_bytes),
+            threads: sample.threads,
+            fds,
+            gpu: None,
+            oom: None,
+            power: None,
+            faults: None,
             working_dir,
             command,
+            parent_pid: sample.parent_pid,
+            memory_bytes: sample.memory_bytes,
+            full_command: sample.cmd,
+            start_time: sample.start_time,
+            io_bytes: sample.io_bytes,
+            app: sample.app,
+            resolved_name,
+            plugins: Vec::new(),
+            thread: sample.thread,
         });
     }
 
-    // Handle sorting and top N filtering
-    if let Some(n) = top_memory {
-        processes.sort_by(|a, b| b.memory_mb.partial_cmp(&a.memory_mb).unwrap());
-        processes.truncate(n);
-        println!("Top {} processes by memory usage:", n);
-    } else if let Some(n) = top_cpu {
-        processes.sort_by(|a, b| b.cpu_percent.partial_cmp(&a.cpu_percent).unwrap());
-        processes.truncate(n);
-        println!("Top {} processes by CPU usage:", n);
-    } else if sort_memory {
-        processes.sort_by(|a, b| b.memory_mb.partial_cmp(&a.memory_mb).unwrap());
+    processes
+}
+
+/// The number of open file descriptors, or None when they are not readable.
+fn count_fds(pid: u32) -> Option<usize> {
+    fs::read_dir(format!("/proc/{}/fd", pid)).ok().map(|entries| entries.count())
+}
+
+/// Fills in the optional GPU, OOM, power, and page fault columns that were asked for.
+async fn add_columns(processes: &mut [ProcessInfo], options: &ListOptions) {
+    if options.shows(ListColumn::Gpu) {
+        let pids: Vec<u32> = processes.iter().map(|p| p.pid).collect();
+        let mut usage = gpu::measure(&pids).await;
+        for process in processes.iter_mut() {
+            process.gpu = usage.remove(&process.pid);
+        }
+    }
+
+    if options.sorts_by(SortField::Oom) {
+        for process in processes.iter_mut() {
+            process.oom = oom::oom_score(process.pid);
+        }
+    }
+
+    if options.shows(ListColumn::Power) {
+        let estimate = power::measure(POWER_SAMPLE_INTERVAL).await;
+        // Processes that did not run during the interval had no impact
+        let idle = PowerImpact { cores: 0.0, watts: estimate.package_watts.map(|_| 0.0) };
+        for process in processes.iter_mut() {
+            process.power = Some(estimate.impacts.get(&process.pid).copied().unwrap_or(idle));
+        }
+    }
+
+    if options.shows(ListColumn::Faults) {
+        let pids: Vec<u32> = processes.iter().map(|p| p.pid).collect();
+        let mut rates = faults::measure(&pids, FAULT_SAMPLE_INTERVAL).await;
+        for process in processes.iter_mut() {
+            process.faults = rates.remove(&process.pid);
+        }
+    }
+}
+
+/// With `--tree-memory`, fills in the memory of each shown process that has no shown ancestor
+/// together with all of its descendants, shown or not. With `--pss` each process counts its
+/// PSS, so pages shared within the tree, such as those of forked workers, count once.
+fn add_tree_memory(system: &System, processes: &mut [ProcessInfo], options: &ListOptions) {
+    if !options.tree_memory {
+        return;
+    }
+    let mut children: HashMap<u32, Vec<u32>> = HashMap::new();
+    for process in system.processes().values().filter(|p| p.thread_kind().is_none()) {
+        if let Some(parent) = process.parent() {
+            children.entry(parent.as_u32()).or_default().push(process.pid().as_u32());
+        }
     }
+    let process = |pid: u32| system.process(sysinfo::Pid::from(pid as usize));
+    let parent = |pid: u32| process(pid).and_then(|p| p.parent()).map(|p| p.as_u32());
+    let memory = |pid: u32| {
+        let rss = process(pid).map(|p| p.memory()).unwrap_or(0);
+        if options.pss { memory::shared_memory(pid).map(|memory| memory.pss).unwrap_or(rss) } else { rss }
+    };
 
-    let mut table = Table::new(processes);
+    let shown: HashSet<u32> = processes.iter().map(|p| p.pid).collect();
+    for process in processes.iter_mut() {
+        if std::iter::successors(parent(process.pid), |&pid| parent(pid)).any(|pid| shown.contains(&pid)) {
+            continue;
+        }
+        let mut total = 0;
+        let mut stack = vec![process.pid];
+        while let Some(pid) = stack.pop() {
+            total += memory(pid);
+            stack.extend(children.get(&pid).into_iter().flatten());
+        }
+        process.tree_memory = Some(total);
+    }
+}
+
+/// Runs the config's custom column commands for the rows that will be shown.
+async fn add_plugin_columns(processes: &mut [ProcessInfo], options: &ListOptions) {
+    if options.plugin_columns.is_empty() {
+        return;
+    }
+    let rows: Vec<plugins::Variables> = processes
+        .iter()
+        .map(|process| plugins::Variables {
+            pid: process.pid,
+            ppid: process.parent_pid,
+            name: process.name.clone(),
+            cwd: Some(process.working_dir.clone()).filter(|wd| wd != "N/A"),
+        })
+        .collect();
+    let values = plugins::column_values(&options.plugin_columns, &rows).await;
+    for (process, cells) in processes.iter_mut().zip(values) {
+        process.plugins = cells;
+    }
+}
+
+/// Sorts by `--sort` and applies `--offset` and `--limit`, returning the heading to print
+/// above the table if any.
+fn sort_processes(processes: &mut Vec<ProcessInfo>, options: &ListOptions) -> Option<String> {
+    if let Some(key) = options.sort {
+        processes.sort_by(|a, b| key.compare(a, b));
+    }
+    let total = processes.len();
+    processes.drain(..options.offset.min(total));
+    if let Some(limit) = options.limit {
+        processes.truncate(limit);
+    }
+
+    let order = options.sort.map(|key| {
+        let direction = if key.descending == SortKey::new(key.field).descending { "" } else { ", reversed" };
+        format!(" by {}{}", options.sort_label(key.field), direction)
+    });
+    if options.offset >= total && total > 0 {
+        return Some(format!("Past the last of {} processes", total));
+    }
+    if options.offset > 0 {
+        return Some(format!(
+            "Processes {}-{} of {}{}:",
+            options.offset + 1,
+            options.offset + processes.len(),
+            total,
+            order.unwrap_or_default()
+        ));
+    }
+    let limit = options.limit?;
+    Some(format!("Top {} processes{}:", limit, order?))
+}
+
+fn process_table<'a>(processes: impl IntoIterator<Item = &'a ProcessInfo>, options: &ListOptions) -> Table {
+    let processes: Vec<&ProcessInfo> = processes.into_iter().collect();
+    let show_app_id = processes.iter().any(|p| p.sandbox.is_some());
+    let plugin_cells: Vec<Vec<String>> = (0..options.plugin_columns.len())
+        .map(|i| processes.iter().map(|p| p.plugins.get(i).cloned().unwrap_or_default()).collect())
+        .collect();
+    let mut builder = Table::builder(processes);
+    // Custom columns go after the built-in values, before the working directory and command
+    let position = ProcessInfo::headers().iter().position(|header| header == "Working Dir").unwrap_or(0);
+    for (i, (column, cells)) in options.plugin_columns.iter().zip(plugin_cells).enumerate() {
+        builder.insert_column(position + i, std::iter::once(column.name.clone()).chain(cells));
+    }
+    let mut table = builder.build();
+    if !show_app_id {
+        table.with(Disable::column(ByColumnName::new("App ID")));
+    }
+    if !options.diff {
+        table.with(Disable::column(ByColumnName::new("Change")));
+        table.with(Disable::column(ByColumnName::new("Memory Δ")));
+    }
+    if !options.shows(ListColumn::Gpu) {
+        table.with(Disable::column(ByColumnName::new("GPU")));
+    }
+    if !options.sorts_by(SortField::Oom) {
+        table.with(Disable::column(ByColumnName::new("OOM")));
+    }
+    let optional = [
+        (ListColumn::Uptime, "Uptime"),
+        (ListColumn::Io, "I/O"),
+        (ListColumn::Threads, "Threads"),
+        (ListColumn::Fds, "FDs"),
+    ];
+    for (column, name) in optional {
+        if !options.shows(column) {
+            table.with(Disable::column(ByColumnName::new(name)));
+        }
+    }
+    if !options.shows(ListColumn::Power) {
+        table.with(Disable::column(ByColumnName::new("Power")));
+    }
+    if !options.shows(ListColumn::Faults) {
+        table.with(Disable::column(ByColumnName::new("Faults/s")));
+    }
+    if !options.tree_memory {
+        table.with(Disable::column(ByColumnName::new("Tree Memory")));
+    }
 
     // Apply terminal width constraints
     if let Some((TermWidth(width), _)) = terminal_size() {
@@ -115,8 +1252,35 @@ pub async fn list_processes(
         let content_width = width.saturating_sub(20);
 
         // Distribute width among columns based on priority
-        // PID: 8, Name: 15, Memory: 12, CPU: 8, Working Dir: flexible, Command: flexible
-        let fixed_width = 8 + 15 + 12 + 8; // 43 chars for fixed columns
+        // PID: 8, Name: 15, TTY: 8, Memory: 12, CPU: 8, GPU: 24, Working Dir: flexible, Command: flexible
+        let mut fixed_width = 8 + 15 + 8 + 12 + 8; // 51 chars for fixed columns
+        if options.diff {
+            fixed_width += 8 + 10;
+        }
+        if options.shows(ListColumn::Gpu) {
+            fixed_width += 24;
+        }
+        if options.sorts_by(SortField::Oom) {
+            fixed_width += 16;
+        }
+        for (column, _) in optional {
+            if options.shows(column) {
+                fixed_width += 12;
+            }
+        }
+        if options.shows(ListColumn::Power) {
+            fixed_width += 10;
+        }
+        if options.shows(ListColumn::Faults) {
+            fixed_width += 16;
+        }
+        if options.tree_memory {
+            fixed_width += 12;
+        }
+        fixed_width += 12 * options.plugin_columns.len();
+        if show_app_id {
+            fixed_width += 24;
+        }
         let remaining_width = content_width.saturating_sub(fixed_width);
 
         if remaining_width > 0 {
@@ -124,49 +1288,318 @@ pub async fn list_processes(
             let command_width = remaining_width.saturating_sub(working_dir_width);
 
             table
-                .modify(Columns::single(4), Width::truncate(working_dir_width).suffix("..."))
-                .modify(Columns::single(5), Width::truncate(command_width).suffix("..."));
+                .modify(ByColumnName::new("Working Dir"), Width::truncate(working_dir_width).suffix("..."))
+                .modify(ByColumnName::new("Command"), Width::truncate(command_width).suffix("..."));
         } else {
             // Terminal is very narrow, apply aggressive truncation
             table
-                .modify(Columns::single(1), Width::truncate(10).suffix("..."))
-                .modify(Columns::single(4), Width::truncate(15).suffix("..."))
-                .modify(Columns::single(5), Width::truncate(20).suffix("..."));
+                .modify(ByColumnName::new("Name"), Width::truncate(10).suffix("..."))
+                .modify(ByColumnName::new("Working Dir"), Width::truncate(15).suffix("..."))
+                .modify(ByColumnName::new("Command"), Width::truncate(20).suffix("..."));
         }
     }
 
-    println!("{}", table);
+    table
+}
 
-    Ok(())
+/// A memory change such as `↑12.0 MB`, or nothing when it is too small to matter.
+fn format_delta(bytes: i64) -> String {
+    if bytes.unsigned_abs() < 100 * 1024 {
+        String::new()
+    } else if bytes > 0 {
+        format!("↑{}", output::format_memory(bytes as u64))
+    } else {
+        format!("↓{}", output::format_memory(bytes.unsigned_abs()))
+    }
+}
+
+/// Selection and output options for `info`.
+#[derive(Default)]
+pub struct InfoOptions {
+    /// Only processes whose working directory is inside this path
+    pub cwd: Option<String>,
+    /// Every process each selector matches instead of the likeliest one
+    pub all_matches: bool,
+    pub table: bool,
+    pub porcelain: Option<PorcelainVersion>,
+    pub format: InfoFormat,
+    pub security: bool,
+    pub namespaces: bool,
+    pub sched: bool,
+    /// Sample page fault rates, which takes a second
+    pub faults: bool,
 }
 
-pub async fn show_process_info(process_identifier: &str) -> Result<()> {
+pub async fn show_process_info(process_identifiers: &[String], options: &InfoOptions) -> Result<()> {
+    let InfoOptions { ref cwd, all_matches, table, porcelain, format, security, namespaces, sched, faults } = *options;
+    let cwd = cwd.as_deref();
     let mut system = System::new_all();
     system.refresh_all();
 
-    let process = if let Ok(pid) = process_identifier.parse::<u32>() {
-        system.process(sysinfo::Pid::from(pid as usize))
-    } else {
-        system
-            .processes()
-            .values()
-            .find(|p| p.name().to_lowercase().contains(&process_identifier.to_lowercase()))
-    };
+    let mut selections = Vec::new();
+    for identifier in process_identifiers {
+        selections.push((identifier.clone(), Selection::from_args(Some(identifier), cwd)?));
+    }
+    // With only --cwd, show everything running in that directory
+    let all_matches = all_matches || selections.is_empty();
+    if selections.is_empty() {
+        let cwd = cwd.unwrap_or(".");
+        selections.push((format!("--cwd {}", cwd), Selection::cwd(cwd)));
+    }
+
+    let mut index = ProcessIndex::new(&system);
+    let mut pids: Vec<u32> = Vec::new();
+    for (identifier, selection) in &selections {
+        let matches = if all_matches {
+            selection.resolve_in(&mut index)
+        } else {
+            match selection.resolve_unique_in(&mut index) {
+                Ok(process) => vec![process.pid().as_u32()],
+                Err(err) if error::error_kind(&err) == Some(ErrorKind::NotFound) => Vec::new(),
+                Err(err) => return Err(err.context(format!("Process selector '{}'", identifier))),
+            }
+        };
+        if matches.is_empty() {
+            eprintln!("Process not found: {}", identifier);
+        }
+        for pid in matches {
+            if !pids.contains(&pid) {
+                pids.push(pid);
+            }
+        }
+    }
+
+    if pids.is_empty() {
+        return Err(CliError::not_found("Process not found"));
+    }
 
-    let process = process.context("Process not found")?;
-    let pid = process.pid().as_u32();
+    let mut infos = Vec::new();
+    for pid in pids {
+        if let Some(process) = system.process(sysinfo::Pid::from(pid as usize)) {
+            infos.push(get_detailed_process_info(&system, pid, process, security, namespaces, sched)?);
+        }
+    }
+    let pids: Vec<u32> = infos.iter().map(|info| info.pid).collect();
+    let mut gpu = gpu::measure(&pids).await;
+    for info in &mut infos {
+        info.gpu = gpu.remove(&info.pid);
+    }
+    if faults {
+        let mut rates = faults::measure(&pids, FAULT_SAMPLE_INTERVAL).await;
+        for info in &mut infos {
+            info.fault_rates = rates.remove(&info.pid);
+        }
+    }
 
-    let detailed_info = get_detailed_process_info(pid, process)?;
+    if let Some(version) = porcelain {
+        for info in &infos {
+            let working_dir = info.working_dir.as_ref().map(|wd| wd.display().to_string());
+            let record = PorcelainProcess {
+                pid: info.pid,
+                parent_pid: info.parent_pid,
+                name: &info.name,
+                memory_bytes: info.memory_bytes,
+                cpu_percent: info.cpu_percent,
+                working_dir: working_dir.as_deref(),
+                command: &info.command,
+            };
+            println!("{}", record.line(version));
+        }
+        return Ok(());
+    }
+
+    if table {
+        print_info_table(&infos);
+        return Ok(());
+    }
 
+    let structured = serde_json::json!({ "processes": infos.iter().map(|info| info.json()).collect::<Vec<_>>() });
+    if let Some(text) = format.render(&structured)? {
+        print!("{}", text);
+        return Ok(());
+    }
+
+    for (i, info) in infos.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        print_process_info(info);
+        if let Some(memberships) = &info.namespaces {
+            namespaces::print_memberships(&system, memberships);
+        }
+    }
+
+    Ok(())
+}
+
+/// `list --interactive`: one row per terminal with what is in its foreground right now.
+fn list_foreground_jobs(system: &System, options: &ListOptions) {
+    let panes = tty::list_tmux_panes().unwrap_or_default();
+    let process = |pid: u32| system.process(sysinfo::Pid::from(pid as usize));
+
+    let rows: Vec<TerminalJob> = tty::foreground_jobs()
+        .into_iter()
+        .filter_map(|job| {
+            if let Some(tty) = &options.tty
+                && job.tty != tty.trim_start_matches("/dev/")
+            {
+                return None;
+            }
+            let shell = process(job.shell_pid)?;
+            // The job itself, or the shell when it's sitting at the prompt
+            let active = job.foreground_pid.and_then(process).unwrap_or(shell);
+
+            if let Some(filter) = &options.name_filter
+                && !selector::name_matches(active.name(), &filter.to_lowercase())
+            {
+                return None;
+            }
+            if let Some(cwd) = &options.cwd
+                && !selector::in_directory(active, cwd)
+            {
+                return None;
+            }
+            if !selector::env_matches(active.pid().as_u32(), &options.env) {
+                return None;
+            }
+
+            let location = tty::terminal_location(system, &job, &panes);
+
+            Some(TerminalJob {
+                tty: job.tty,
+                location,
+                shell: format!("{} ({})", shell.name(), job.shell_pid),
+                running_pid: display_option(&job.foreground_pid),
+                running: match job.foreground_pid {
+                    Some(_) if !active.cmd().is_empty() => active.cmd().join(" "),
+                    Some(_) => active.name().to_string(),
+                    None => "(prompt)".to_string(),
+                },
+                working_dir: active.cwd().map(|cwd| cwd.display().to_string()).unwrap_or_else(|| "N/A".to_string()),
+            })
+        })
+        .collect();
+
+    if rows.is_empty() {
+        println!("No terminals with a shell session found");
+        return;
+    }
+
+    let terminal_width = terminal_size().map(|(TermWidth(w), _)| w as usize).unwrap_or(120);
+    let running_width = terminal_width.saturating_sub(10 + 16 + 20 + 14 + 30 + 20).max(20);
+
+    let mut table = Table::new(rows);
+    table
+        .modify(ByColumnName::new("Working Dir"), Width::truncate(30).suffix("..."))
+        .modify(ByColumnName::new("Running"), Width::truncate(running_width).suffix("..."));
+    println!("{}", table);
+}
+
+fn print_info_table(infos: &[DetailedProcessInfo]) {
+    let rows: Vec<ProcessInfo> = infos
+        .iter()
+        .map(|info| ProcessInfo {
+            change: "",
+            pid: info.pid,
+            name: info.name.clone(),
+            sandbox: info.sandbox.clone(),
+            tty: tty::process_tty(info.pid),
+            memory: output::format_memory(info.memory_bytes),
+            memory_delta: String::new(),
+            tree_memory: None,
+            cpu_percent: info.cpu_percent,
+            uptime: String::new(),
+            io: String::new(),
+            threads: 0,
+            fds: None,
+            gpu: info.gpu.clone(),
+            oom: info.oom,
+            power: None,
+            faults: None,
+            working_dir: info
+                .working_dir
+                .as_ref()
+                .map(|wd| wd.display().to_string())
+                .unwrap_or_else(|| "N/A".to_string()),
+            command: info.command.join(" "),
+            parent_pid: info.parent_pid,
+            memory_bytes: info.memory_bytes,
+            full_command: info.command.clone(),
+            start_time: 0,
+            io_bytes: 0,
+            app: info.app.clone(),
+            resolved_name: None,
+            plugins: Vec::new(),
+            thread: false,
+        })
+        .collect();
+
+    // Full command lines are long, so always fit the table to the terminal (or 80 columns)
+    let terminal_width = terminal_size().map(|(TermWidth(w), _)| w as usize).unwrap_or(80);
+    let command_width = terminal_width.saturating_sub(20 + 51 + 16 + 30).max(20);
+
+    let show_gpu = infos.iter().any(|info| info.gpu.is_some());
+    let command_width = if show_gpu { command_width.saturating_sub(24).max(20) } else { command_width };
+
+    let mut table = Table::new(rows);
+    if !show_gpu {
+        table.with(Disable::column(ByColumnName::new("GPU")));
+    }
+    table.with(Disable::column(ByColumnName::new("Power")));
+    table.with(Disable::column(ByColumnName::new("Faults/s")));
+    table.with(Disable::column(ByColumnName::new("Tree Memory")));
+    table.with(Disable::column(ByColumnName::new("Change")));
+    table.with(Disable::column(ByColumnName::new("Memory Δ")));
+    for column in ["Uptime", "I/O", "Threads", "FDs"] {
+        table.with(Disable::column(ByColumnName::new(column)));
+    }
+    table
+        .modify(ByColumnName::new("Working Dir"), Width::truncate(30).suffix("..."))
+        .modify(ByColumnName::new("Command"), Width::truncate(command_width).suffix("..."));
+
+    println!("{}", table);
+}
+
+fn print_process_info(detailed_info: &DetailedProcessInfo) {
     let terminal_width = terminal_size().map(|(TermWidth(w), _)| w as usize).unwrap_or(80);
     let max_value_width = terminal_width.saturating_sub(25); // Reserve space for labels
 
     println!("Process Information:");
     println!("  PID: {}", detailed_info.pid);
     println!("  Name: {}", detailed_info.name);
-    println!("  Memory: {:.2} MB", detailed_info.memory_mb);
+    if let Some(app) = &detailed_info.app {
+        println!("  App: {}", app);
+    }
+    if let Some(sandbox) = &detailed_info.sandbox {
+        println!("  {}: {}", sandbox.packaging, sandbox.app_id);
+    }
+    match detailed_info.shared_memory {
+        Some(memory) => println!(
+            "  Memory: {} RSS, {} PSS, {} USS",
+            output::format_memory(memory.rss),
+            output::format_memory(memory.pss),
+            output::format_memory(memory.uss)
+        ),
+        None => println!("  Memory: {}", output::format_memory(detailed_info.memory_bytes)),
+    }
     println!("  CPU: {:.1}%", detailed_info.cpu_percent);
 
+    if let Some(gpu) = &detailed_info.gpu {
+        println!("  GPU: {}", gpu);
+    }
+
+    if let Some(oom) = &detailed_info.oom {
+        println!("  OOM Score: {} (oom_score_adj: {})", oom.score, oom.adj);
+    }
+
+    if let Some(faults) = &detailed_info.faults {
+        let rates = match detailed_info.fault_rates {
+            Some(rates) => format!("; now {:.0}/s minor, {:.0}/s major", rates.minor, rates.major),
+            None => String::new(),
+        };
+        println!("  Page Faults: {} minor, {} major{}", faults.minor, faults.major, rates);
+    }
+
     if let Some(wd) = &detailed_info.working_dir {
         let wd_str = wd.display().to_string();
         if wd_str.len() > max_value_width {
@@ -180,6 +1613,14 @@ pub async fn show_process_info(process_identifier: &str) -> Result<()> {
         println!("  Parent PID: {}", ppid);
     }
 
+    if let Some(terminal) = &detailed_info.terminal {
+        println!("  Terminal: {}", terminal);
+    }
+
+    if let Some(browser) = &detailed_info.browser {
+        println!("  Browser: {}", browser.summary());
+    }
+
     let command_str = detailed_info.command.join(" ");
     if command_str.len() > max_value_width {
         println!("  Command: {}...", &command_str[..max_value_width.saturating_sub(3)]);
@@ -187,20 +1628,29 @@ pub async fn show_process_info(process_identifier: &str) -> Result<()> {
         println!("  Command: {}", command_str);
     }
 
-    if let Some(window_id) = &detailed_info.window_id {
-        if window_id.len() > max_value_width {
-            println!("  Window ID: {}...", &window_id[..max_value_width.saturating_sub(3)]);
-        } else {
-            println!("  Window ID: {}", window_id);
+    match detailed_info.windows.as_slice() {
+        [] => {}
+        [window] => print_window(window, detailed_info.window_placements.get(&window.window_id), "  ", max_value_width),
+        windows => {
+            println!("  Windows: {}", windows.len());
+            for (i, window) in windows.iter().enumerate() {
+                println!("    [{}]", i + 1);
+                let placement = detailed_info.window_placements.get(&window.window_id);
+                print_window(window, placement, "      ", max_value_width.saturating_sub(4));
+            }
         }
     }
 
-    if let Some(window_title) = &detailed_info.window_title {
-        if window_title.len() > max_value_width {
-            println!("  Window Title: {}...", &window_title[..max_value_width.saturating_sub(3)]);
-        } else {
-            println!("  Window Title: {}", window_title);
-        }
+    if let Some(browser) = &detailed_info.browser {
+        print_browser_tabs(browser, max_value_width);
+    }
+
+    if let Some(security) = &detailed_info.security {
+        security.print();
+    }
+
+    if let Some(sched) = &detailed_info.sched {
+        sched.print();
     }
 
     println!("\nEnvironment Variables (relevant):");
@@ -214,8 +1664,55 @@ pub async fn show_process_info(process_identifier: &str) -> Result<()> {
             }
         }
     }
+}
+
+fn print_window(window: &WindowInfo, placement: Option<&WindowPlacement>, indent: &str, max_value_width: usize) {
+    println!("{}Window ID: {} ({})", indent, window.window_id, window.backend.name());
+    if window.title.chars().count() > max_value_width {
+        // Titles are rarely ASCII-only, so cut at a character boundary
+        let title: String = window.title.chars().take(max_value_width.saturating_sub(3)).collect();
+        println!("{}Window Title: {}...", indent, title);
+    } else {
+        println!("{}Window Title: {}", indent, window.title);
+    }
+    println!("{}Window Class: {}", indent, window.class);
 
-    Ok(())
+    let Some(placement) = placement else {
+        return;
+    };
+    if let Some(workspace) = &placement.workspace {
+        println!("{}Workspace: {}", indent, workspace);
+    }
+    if let Some(geometry) = &placement.geometry {
+        println!("{}Geometry: {}", indent, geometry);
+    }
+    if let Some(focused) = placement.focused {
+        println!("{}Focused: {}", indent, if focused { "yes" } else { "no" });
+    }
+}
+
+fn print_browser_tabs(browser: &BrowserContext, max_width: usize) {
+    if let Some(note) = &browser.note {
+        println!("\nBrowser Tabs: unknown ({})", note);
+        return;
+    }
+    if browser.tabs.is_empty() {
+        return;
+    }
+    if browser.exact {
+        println!("\nBrowser Tabs (rendered by this process):");
+    } else {
+        println!("\nBrowser Tabs (all open tabs; DevTools doesn't say which renderer hosts each):");
+    }
+    for (title, url) in &browser.tabs {
+        let title = if title.is_empty() { "(untitled)" } else { title.as_str() };
+        let line = format!("{} - {}", title, url);
+        if line.chars().count() > max_width {
+            println!("  {}...", line.chars().take(max_width.saturating_sub(3)).collect::<String>());
+        } else {
+            println!("  {}", line);
+        }
+    }
 }
 
 fn get_process_working_dir(pid: u32) -> Result<String> {
@@ -226,60 +1723,88 @@ fn get_process_working_dir(pid: u32) -> Result<String> {
     Ok(cwd.to_string_lossy().to_string())
 }
 
-fn get_detailed_process_info(
+pub fn get_detailed_process_info(
+    system: &System,
     pid: u32,
     sysinfo_process: &sysinfo::Process,
+    security: bool,
+    namespaces: bool,
+    sched: bool,
 ) -> Result<DetailedProcessInfo> {
-    let memory_mb = sysinfo_process.memory() as f64 / 1024.0 / 1024.0;
-
     let working_dir = get_process_working_dir(pid).ok().map(PathBuf::from);
 
     let mut env_vars = HashMap::new();
-    let mut window_info = None;
+    let mut windows = Vec::new();
 
     // Try to get detailed process info from procfs
-    if let Ok(process) = Process::new(pid as i32) {
-        // Get environment variables
-        if let Ok(environ) = process.environ() {
-            for (key, value) in environ {
-                env_vars.insert(key.to_string_lossy().to_string(), value.to_string_lossy().to_string());
+    match Process::new(pid as i32) {
+        Ok(process) => {
+            // Get environment variables
+            match process.environ() {
+                Ok(environ) => {
+                    for (key, value) in environ {
+                        env_vars.insert(key.to_string_lossy().to_string(), value.to_string_lossy().to_string());
+                    }
+                }
+                Err(err) => debug!("Cannot read the environment of PID {}: {}", pid, err),
             }
-        }
 
-        // Get window information if available
-        window_info = get_window_info_for_process(pid, &env_vars).ok();
+            // Get window information if available
+            windows = get_windows_for_process(pid, &env_vars).unwrap_or_else(|err| {
+                debug!("No windows for PID {}: {:#}", pid, err);
+                Vec::new()
+            });
+        }
+        Err(err) => debug!("Cannot read /proc/{}: {}", pid, err),
     }
 
     Ok(DetailedProcessInfo {
         pid,
         name: sysinfo_process.name().to_string(),
-        memory_mb: (memory_mb * 100.0).round() / 100.0,
+        app: electron::detect(system, pid).map(|app| app.name),
+        sandbox: sandbox::detect(pid),
+        memory_bytes: sysinfo_process.memory(),
+        shared_memory: memory::shared_memory(pid),
         cpu_percent: sysinfo_process.cpu_usage(),
         working_dir,
         command: sysinfo_process.cmd().to_vec(),
         env_vars,
         parent_pid: sysinfo_process.parent().map(|p| p.as_u32()),
-        window_id: window_info.as_ref().map(|(id, _)| id.clone()),
-        window_title: window_info.map(|(_, title)| title),
+        window_placements: windows
+            .iter()
+            .filter_map(|window| match window.backend.placement(window) {
+                Ok(placement) => Some((window.window_id.clone(), placement)),
+                Err(err) => {
+                    debug!("No placement for window {}: {:#}", window.window_id, err);
+                    None
+                }
+            })
+            .collect(),
+        windows,
+        // Filled in by the caller, since telling what the GPU is used for takes a sample
+        gpu: None,
+        oom: oom::oom_score(pid),
+        faults: faults::fault_counts(pid),
+        fault_rates: None,
+        terminal: tty::terminal_context(system, pid),
+        browser: browser::browser_context(system, pid),
+        security: if security { security::security_context(pid) } else { None },
+        namespaces: namespaces.then(|| namespaces::memberships(system, pid)),
+        sched: if sched { sched::sched_stats(pid) } else { None },
     })
 }
 
-fn get_window_info_for_process(
-    _pid: u32,
-    env_vars: &HashMap<String, String>,
-) -> Result<(String, String)> {
-    // Check if process has DISPLAY variable (X11)
-    if env_vars.contains_key("DISPLAY") {
-        // We'll implement X11 window detection in the window module
-        return Err(anyhow::anyhow!("Window detection not implemented yet"));
-    }
-
-    // Check if process has WAYLAND_DISPLAY (Wayland)
-    if env_vars.contains_key("WAYLAND_DISPLAY") {
-        return Err(anyhow::anyhow!("Wayland window detection not implemented yet"));
+/// The process's windows, looked up with the backends `switch` uses. Processes started
+/// without a display (daemons, SSH sessions) are skipped rather than searched for.
+fn get_windows_for_process(pid: u32, env_vars: &HashMap<String, String>) -> Result<Vec<WindowInfo>> {
+    if !env_vars.contains_key("DISPLAY") && !env_vars.contains_key("WAYLAND_DISPLAY") {
+        return Err(anyhow::anyhow!("No display environment detected"));
     }
-
-    Err(anyhow::anyhow!("No display environment detected"))
+    // The process's own X display, which may not be the one this tool inherited
+    let display = env_vars.get("DISPLAY").map(String::as_str);
+    let wayland = env_vars.get("WAYLAND_DISPLAY");
+    debug!("PID {} runs with DISPLAY={:?} and WAYLAND_DISPLAY={:?}", pid, env_vars.get("DISPLAY"), wayland);
+    window::find_windows_by_pid(pid, display)
 }
 
 fn is_relevant_env_var(key: &str) -> bool {
@@ -295,4 +1820,11 @@ fn is_relevant_env_var(key: &str) -> bool {
             | "WINDOWID"
             | "XTERM_VERSION"
     )
-}
\ No newline at end of file
+}
+fn display_memory(bytes: &Option<u64>) -> String {
+    bytes.map(output::format_memory).unwrap_or_else(|| "-".to_string())
+}
+
+fn display_option<T: std::fmt::Display>(value: &Option<T>) -> String {
+    value.as_ref().map(|v| v.to_string()).unwrap_or_else(|| "-".to_string())
+}
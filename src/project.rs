@@ -0,0 +1,233 @@
+use anyhow::Result;
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::path::{Path, PathBuf};
+use sysinfo::System;
+use tabled::{Table, Tabled, settings::{Width, location::ByColumnName}};
+
+use crate::output;
+use crate::tty::{self, TerminalContext};
+
+/// Files that mark a project root when the directory is not inside a git repository.
+const PROJECT_MARKERS: &[&str] = &[
+    "Cargo.toml",
+    "package.json",
+    "pyproject.toml",
+    "setup.py",
+    "go.mod",
+    "pom.xml",
+    "build.gradle",
+    "Gemfile",
+    "composer.json",
+    "CMakeLists.txt",
+    "Makefile",
+];
+
+#[derive(Tabled)]
+struct ProjectMember {
+    #[tabled(rename = "PID")]
+    pid: u32,
+
+    #[tabled(rename = "Name")]
+    name: String,
+
+    #[tabled(rename = "Role")]
+    role: String,
+
+    #[tabled(rename = "Memory")]
+    memory: String,
+
+    #[tabled(rename = "CPU %")]
+    cpu_percent: String,
+
+    #[tabled(rename = "Command")]
+    command: String,
+}
+
+struct Project {
+    is_git: bool,
+    members: Vec<ProjectMember>,
+    /// Terminal tabs whose shell works in the project, by shell PID
+    terminals: BTreeMap<u32, TerminalContext>,
+    memory_bytes: u64,
+    cpu_percent: f32,
+}
+
+/// Finds the project root for working directories, caching every lookup since most
+/// processes of a project share the same few directories.
+struct RootFinder {
+    home: Option<PathBuf>,
+    cache: HashMap<PathBuf, Option<(PathBuf, bool)>>,
+}
+
+impl RootFinder {
+    fn new() -> Self {
+        RootFinder {
+            home: env::var_os("HOME").map(PathBuf::from),
+            cache: HashMap::new(),
+        }
+    }
+
+    /// The enclosing git repository, or failing that the nearest directory with a project
+    /// marker. The filesystem root and the home directory are never treated as projects.
+    fn find(&mut self, dir: &Path) -> Option<(PathBuf, bool)> {
+        if let Some(cached) = self.cache.get(dir) {
+            return cached.clone();
+        }
+
+        let mut nearest_marker = None;
+        let mut root = None;
+        for ancestor in dir.ancestors() {
+            if ancestor.parent().is_none() || self.home.as_deref() == Some(ancestor) {
+                break;
+            }
+            if ancestor.join(".git").exists() {
+                root = Some((ancestor.to_path_buf(), true));
+                break;
+            }
+            if nearest_marker.is_none() && PROJECT_MARKERS.iter().any(|m| ancestor.join(m).is_file()) {
+                nearest_marker = Some(ancestor.to_path_buf());
+            }
+        }
+
+        let root = root.or_else(|| nearest_marker.map(|path| (path, false)));
+        self.cache.insert(dir.to_path_buf(), root.clone());
+        root
+    }
+}
+
+pub async fn show_projects(summary: bool) -> Result<()> {
+    let mut system = System::new_all();
+    system.refresh_all();
+
+    let mut finder = RootFinder::new();
+    let mut projects: BTreeMap<PathBuf, Project> = BTreeMap::new();
+
+    for (pid, process) in system.processes() {
+        let Some(cwd) = process.cwd() else {
+            continue;
+        };
+        let Some((root, is_git)) = finder.find(cwd) else {
+            continue;
+        };
+
+        let project = projects.entry(root).or_insert_with(|| Project {
+            is_git,
+            members: Vec::new(),
+            terminals: BTreeMap::new(),
+            memory_bytes: 0,
+            cpu_percent: 0.0,
+        });
+        if let Some(terminal) = tty::terminal_context(&system, pid.as_u32()) {
+            project.terminals.entry(terminal.shell_pid).or_insert(terminal);
+        }
+        project.memory_bytes += process.memory();
+        project.cpu_percent += process.cpu_usage();
+        project.members.push(ProjectMember {
+            pid: pid.as_u32(),
+            name: process.name().to_string(),
+            role: process_role(process.name(), process.cmd()).to_string(),
+            memory: output::format_memory(process.memory()),
+            cpu_percent: format!("{:.1}", process.cpu_usage()),
+            command: process.cmd().join(" "),
+        });
+    }
+
+    if projects.is_empty() {
+        println!("No processes are running inside a project directory");
+        return Ok(());
+    }
+
+    // Heaviest projects first
+    let mut projects: Vec<(PathBuf, Project)> = projects.into_iter().collect();
+    projects.sort_by_key(|(_, project)| std::cmp::Reverse(project.memory_bytes));
+
+    for (root, mut project) in projects {
+        println!(
+            "{} ({}): {} processes, {}, {:.1}% CPU",
+            display_path(&root),
+            if project.is_git { "git" } else { "project" },
+            project.members.len(),
+            output::format_memory(project.memory_bytes),
+            project.cpu_percent,
+        );
+        for terminal in project.terminals.values() {
+            println!("  Terminal: {}", terminal);
+        }
+
+        if !summary {
+            project.members.sort_by_key(|m| m.pid);
+            let mut table = Table::new(project.members);
+            table.modify(ByColumnName::new("Command"), Width::truncate(60).suffix("..."));
+            println!("{}\n", table);
+        }
+    }
+
+    Ok(())
+}
+
+/// A rough guess at what a process does for its project, from its name and command line.
+fn process_role(name: &str, cmd: &[String]) -> &'static str {
+    // Compare whole leading arguments by basename, so a path containing "jest" or free-form
+    // text further along the command line doesn't count
+    let words: Vec<&str> = cmd
+        .iter()
+        .take(4)
+        .flat_map(|arg| arg.split_whitespace())
+        .map(|word| word.rsplit('/').next().unwrap_or(word))
+        .collect();
+    let has = |word: &str| words.contains(&word);
+    let runs = |program: &str, subcommand: &str| words.windows(2).any(|pair| pair == [program, subcommand]);
+
+    match name {
+        "code" | "codium" | "nvim" | "vim" | "emacs" | "hx" | "helix" | "zed" | "subl" | "kate" | "gedit" => {
+            return "editor";
+        }
+        "rust-analyzer" | "gopls" | "clangd" | "pyright" | "pylsp" | "jdtls" | "lua-language-server" => {
+            return "language server";
+        }
+        "bash" | "zsh" | "fish" | "sh" | "dash" => return "shell",
+        _ => {}
+    }
+
+    if words
+        .iter()
+        .any(|w| w.contains("language-server") || w.contains("langserver") || w.contains("tsserver") || w.ends_with("-lsp"))
+    {
+        "language server"
+    } else if has("pytest") || has("jest") || has("vitest") || has("nextest") || runs("cargo", "test") || runs("go", "test") {
+        "test runner"
+    } else if has("vite")
+        || has("webpack-dev-server")
+        || has("runserver")
+        || has("uvicorn")
+        || runs("next", "dev")
+        || runs("run", "dev")
+        || runs("yarn", "dev")
+        || runs("cargo", "watch")
+        || runs("rails", "server")
+        || runs("flask", "run")
+    {
+        "dev server"
+    } else if matches!(name, "cargo" | "rustc" | "make" | "cc" | "gcc" | "clang" | "tsc" | "ninja") {
+        "build"
+    } else {
+        "-"
+    }
+}
+
+/// The project a directory belongs to, and whether it is a git repository, as `projects`
+/// groups it.
+pub fn project_root(dir: &Path) -> Option<(PathBuf, bool)> {
+    RootFinder::new().find(dir)
+}
+
+/// The path with the home directory shortened to `~`.
+pub fn display_path(path: &Path) -> String {
+    if let Some(home) = env::var_os("HOME")
+        && let Ok(rest) = path.strip_prefix(&home)
+    {
+        return format!("~/{}", rest.display());
+    }
+    path.display().to_string()
+}
@@ -0,0 +1,209 @@
+//! `query` over the history recorded by `record`, filtered by expressions such as
+//! `name=chrome and mem>2000`.
+
+use anyhow::Result;
+use regex::Regex;
+use rusqlite::types::Value;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::OnceLock;
+use std::time::Duration;
+use tabled::{Table, Tabled};
+
+use crate::history::{self, History};
+use crate::output;
+
+#[derive(Tabled)]
+struct SampleRow {
+    #[tabled(rename = "Time")]
+    time: String,
+
+    #[tabled(rename = "PID")]
+    pid: u32,
+
+    #[tabled(rename = "Name")]
+    name: String,
+
+    #[tabled(rename = "Memory")]
+    memory: String,
+
+    #[tabled(rename = "CPU %")]
+    cpu: String,
+}
+
+/// `and`-joined comparisons, e.g. `name=chrome and mem>2000`.
+#[derive(Debug, Clone, Default)]
+pub struct Condition {
+    comparisons: Vec<Comparison>,
+}
+
+#[derive(Debug, Clone)]
+struct Comparison {
+    field: Field,
+    op: Op,
+    value: String,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Field {
+    Name,
+    Pid,
+    /// Resident memory in MB
+    Memory,
+    /// CPU usage in percent of one core
+    Cpu,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    /// Case-insensitive substring match
+    Contains,
+}
+
+const SYNTAX_HINT: &str = "expected comparisons such as `name=chrome and mem>2000`; fields are name, pid, mem (MB), and cpu (%), operators are = != < <= > >= and ~ (contains)";
+
+impl FromStr for Condition {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        static AND: OnceLock<Regex> = OnceLock::new();
+        static COMPARISON: OnceLock<Regex> = OnceLock::new();
+        let and = AND.get_or_init(|| Regex::new(r"(?i)\s+and\s+").unwrap());
+        let comparison = COMPARISON.get_or_init(|| Regex::new(r"^\s*(\w+)\s*(!=|<=|>=|=|<|>|~)\s*(.*?)\s*$").unwrap());
+
+        let mut comparisons = Vec::new();
+        for term in and.split(s.trim()).filter(|term| !term.is_empty()) {
+            let captures = comparison
+                .captures(term)
+                .ok_or_else(|| format!("invalid condition '{}' ({})", term, SYNTAX_HINT))?;
+            let field = match captures[1].to_lowercase().as_str() {
+                "name" => Field::Name,
+                "pid" => Field::Pid,
+                "mem" | "memory" => Field::Memory,
+                "cpu" => Field::Cpu,
+                other => return Err(format!("unknown field '{}' ({})", other, SYNTAX_HINT)),
+            };
+            let op = match &captures[2] {
+                "=" => Op::Eq,
+                "!=" => Op::Ne,
+                "<" => Op::Lt,
+                "<=" => Op::Le,
+                ">" => Op::Gt,
+                ">=" => Op::Ge,
+                _ => Op::Contains,
+            };
+            let value = captures[3].trim_matches(|c| c == '"' || c == '\'').to_string();
+
+            match field {
+                Field::Name if !matches!(op, Op::Eq | Op::Ne | Op::Contains) => {
+                    return Err(format!("'{}' compares a name; use =, !=, or ~", term));
+                }
+                Field::Pid | Field::Memory | Field::Cpu if op == Op::Contains => {
+                    return Err(format!("'{}' compares a number; ~ only applies to name", term));
+                }
+                Field::Pid | Field::Memory | Field::Cpu if value.parse::<f64>().is_err() => {
+                    return Err(format!("'{}' needs a number, not '{}'", term, value));
+                }
+                _ => {}
+            }
+
+            comparisons.push(Comparison { field, op, value });
+        }
+
+        Ok(Condition { comparisons })
+    }
+}
+
+impl Condition {
+    /// Renders the condition as an SQL expression over the history `samples` table, with the
+    /// values bound separately so names are never spliced into the query.
+    fn to_sql(&self) -> (String, Vec<Value>) {
+        if self.comparisons.is_empty() {
+            return ("1".to_string(), Vec::new());
+        }
+
+        let mut clauses = Vec::new();
+        let mut values = Vec::new();
+        for comparison in &self.comparisons {
+            let operator = match comparison.op {
+                Op::Eq => "=",
+                Op::Ne => "!=",
+                Op::Lt => "<",
+                Op::Le => "<=",
+                Op::Gt => ">",
+                Op::Ge => ">=",
+                Op::Contains => "",
+            };
+            // Validated as numeric while parsing
+            let number = comparison.value.parse::<f64>().unwrap_or(0.0);
+
+            match comparison.field {
+                Field::Name if comparison.op == Op::Contains => {
+                    clauses.push("instr(lower(name), lower(?)) > 0".to_string());
+                    values.push(Value::Text(comparison.value.clone()));
+                }
+                Field::Name => {
+                    clauses.push(format!("name {} ? COLLATE NOCASE", operator));
+                    values.push(Value::Text(comparison.value.clone()));
+                }
+                Field::Pid => {
+                    clauses.push(format!("pid {} ?", operator));
+                    values.push(Value::Real(number));
+                }
+                Field::Memory => {
+                    clauses.push(format!("memory_bytes {} ?", operator));
+                    values.push(Value::Real(number * 1024.0 * 1024.0));
+                }
+                Field::Cpu => {
+                    clauses.push(format!("cpu_percent {} ?", operator));
+                    values.push(Value::Real(number));
+                }
+            }
+        }
+
+        (clauses.join(" AND "), values)
+    }
+}
+
+/// Prints recorded samples matching `condition` from the last `since` (or all of history),
+/// newest first.
+pub fn show_query(db: Option<&Path>, condition: &Condition, since: Option<Duration>, limit: usize) -> Result<()> {
+    let path = match db {
+        Some(path) => path.to_path_buf(),
+        None => history::default_path()?,
+    };
+    let history = History::open_existing(&path)?;
+
+    let since = since.map_or(0, |since| chrono::Utc::now().timestamp() - since.as_secs() as i64);
+    let (sql, values) = condition.to_sql();
+    // One extra row tells us whether the output was cut off
+    let mut samples = history.samples(&sql, &values, since, limit + 1)?;
+
+    if samples.is_empty() {
+        println!("No recorded samples match");
+        return Ok(());
+    }
+
+    let truncated = samples.len() > limit;
+    samples.truncate(limit);
+    let rows = samples.into_iter().map(|sample| SampleRow {
+        time: output::format_time(sample.timestamp),
+        pid: sample.pid,
+        name: sample.name,
+        memory: output::format_memory(sample.memory_bytes),
+        cpu: format!("{:.1}", sample.cpu_percent),
+    });
+    println!("{}", Table::new(rows));
+
+    if truncated {
+        println!("\nShowing the newest {} matches; raise --limit or narrow --since to see more", limit);
+    }
+
+    Ok(())
+}
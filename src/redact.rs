@@ -0,0 +1,171 @@
+//! Redaction of secrets in output meant to leave the terminal: JSON, porcelain, fixtures, and
+//! bug-report bundles. Environment variables, command-line flags, and `KEY=VALUE` arguments are
+//! hidden when their name matches a configured pattern, and URL query strings and passwords are
+//! hidden wherever they appear. The global `--no-redact` option turns it off.
+//!
+//! ```toml
+//! [redact]
+//! keys = ["*_TOKEN", "*KEY*", "*SECRET*", "*PASSWORD*"]
+//! patterns = ["ghp_[A-Za-z0-9]+"]
+//! query_strings = true
+//! ```
+
+use regex::{Regex, RegexBuilder};
+use serde::Deserialize;
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::config::Config;
+
+pub const REDACTED: &str = "[REDACTED]";
+
+static DISABLED: AtomicBool = AtomicBool::new(false);
+static REDACTOR: OnceLock<Redactor> = OnceLock::new();
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RedactConfig {
+    /// Glob patterns (`*` wildcards, case-insensitive) for variable and flag names whose
+    /// values are hidden
+    pub keys: Vec<String>,
+    /// Regular expressions hidden wherever they match
+    pub patterns: Vec<String>,
+    /// Hide the query string of every URL
+    pub query_strings: bool,
+}
+
+impl Default for RedactConfig {
+    fn default() -> Self {
+        RedactConfig {
+            keys: ["*TOKEN*", "*KEY*", "*SECRET*", "*PASSWORD*", "*PASSWD*", "*CREDENTIAL*"]
+                .map(String::from)
+                .to_vec(),
+            patterns: Vec::new(),
+            query_strings: true,
+        }
+    }
+}
+
+struct Redactor {
+    keys: Vec<Regex>,
+    patterns: Vec<Regex>,
+    query_string: Option<Regex>,
+    url_password: Regex,
+}
+
+impl Redactor {
+    fn new(config: &RedactConfig) -> Self {
+        let keys = config
+            .keys
+            .iter()
+            .filter_map(|glob| {
+                let pattern = format!("^{}$", regex::escape(glob).replace(r"\*", ".*"));
+                RegexBuilder::new(&pattern).case_insensitive(true).build().ok()
+            })
+            .collect();
+        let patterns = config
+            .patterns
+            .iter()
+            .filter_map(|pattern| match Regex::new(pattern) {
+                Ok(regex) => Some(regex),
+                Err(err) => {
+                    eprintln!("Warning: ignoring invalid redaction pattern '{}': {}", pattern, err);
+                    None
+                }
+            })
+            .collect();
+
+        Redactor {
+            keys,
+            patterns,
+            query_string: config.query_strings.then(|| Regex::new(r"([a-zA-Z][a-zA-Z0-9+.-]*://[^\s?#]*)\?[^\s#]*").unwrap()),
+            url_password: Regex::new(r"(://[^/\s:@]+:)[^/\s@]+@").unwrap(),
+        }
+    }
+
+    fn is_secret_key(&self, key: &str) -> bool {
+        self.keys.iter().any(|regex| regex.is_match(key))
+    }
+
+    fn text(&self, text: &str) -> String {
+        let mut text = self.url_password.replace_all(text, format!("${{1}}{}@", REDACTED)).into_owned();
+        if let Some(regex) = &self.query_string {
+            text = regex.replace_all(&text, format!("${{1}}?{}", REDACTED)).into_owned();
+        }
+        for regex in &self.patterns {
+            text = regex.replace_all(&text, REDACTED).into_owned();
+        }
+        text
+    }
+
+    /// Whether a `--flag` (or `-flag`) names a secret, e.g. `--api-key` matching `*KEY*`.
+    fn is_secret_flag(&self, flag: &str) -> bool {
+        let name = flag.trim_start_matches('-');
+        flag.starts_with('-') && !name.is_empty() && self.is_secret_key(&name.replace('-', "_"))
+    }
+
+    fn command(&self, args: &[String]) -> Vec<String> {
+        let mut redacted = Vec::with_capacity(args.len());
+        let mut hide_next = false;
+        for arg in args {
+            if std::mem::take(&mut hide_next) && !arg.starts_with('-') {
+                redacted.push(REDACTED.to_string());
+                continue;
+            }
+
+            // --flag=VALUE and KEY=VALUE
+            if let Some((name, _)) = arg.split_once('=')
+                && (self.is_secret_flag(name) || (!name.starts_with('-') && self.is_secret_key(name)))
+            {
+                redacted.push(format!("{}={}", name, REDACTED));
+                continue;
+            }
+
+            // --flag VALUE
+            hide_next = self.is_secret_flag(arg);
+            redacted.push(self.text(arg));
+        }
+        redacted
+    }
+}
+
+/// Turns redaction off for this invocation (`--no-redact`).
+pub fn disable() {
+    DISABLED.store(true, Ordering::Relaxed);
+}
+
+fn redactor() -> Option<&'static Redactor> {
+    if DISABLED.load(Ordering::Relaxed) {
+        return None;
+    }
+    // An unreadable config file is reported by the commands that need it; redaction falls
+    // back to the defaults rather than failing open
+    Some(REDACTOR.get_or_init(|| Redactor::new(&Config::load().map(|config| config.redact).unwrap_or_default())))
+}
+
+/// Redacts URL credentials, query strings, and configured patterns in free text such as a
+/// window title.
+pub fn text(text: &str) -> String {
+    match redactor() {
+        Some(redactor) => redactor.text(text),
+        None => text.to_string(),
+    }
+}
+
+/// Redacts an environment variable's value if its name looks secret.
+pub fn env_value(key: &str, value: &str) -> String {
+    match redactor() {
+        Some(redactor) if redactor.is_secret_key(key) => REDACTED.to_string(),
+        Some(redactor) => redactor.text(value),
+        None => value.to_string(),
+    }
+}
+
+/// Redacts the values of secret-looking flags and assignments in a command line, plus
+/// everything `text` would.
+pub fn command(args: &[String]) -> Vec<String> {
+    match redactor() {
+        Some(redactor) => redactor.command(args),
+        None => args.to_vec(),
+    }
+}
@@ -0,0 +1,115 @@
+use anyhow::Result;
+use chrono::{Duration, Local};
+use serde::Deserialize;
+use std::fmt::Write as _;
+
+use crate::history::{self, History};
+use crate::output::{format_duration, format_memory, format_time};
+use crate::sink::{self, Message, Urgency};
+
+const TOP_N: usize = 10;
+
+/// `[report]` in the config file: where the daemon sends the daily report.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReportConfig {
+    pub sinks: Vec<String>,
+}
+
+impl Default for ReportConfig {
+    fn default() -> Self {
+        ReportConfig { sinks: vec!["desktop".to_string()] }
+    }
+}
+
+/// Summarizes the last 24 hours of recorded history and delivers it to the given sinks.
+pub fn daily_report(sinks: &[String]) -> Result<()> {
+    let history = History::open_existing(&history::default_path()?)?;
+    let until = Local::now();
+    let since = until - Duration::days(1);
+    let report = build_report(&history, since.timestamp(), until.timestamp())?;
+
+    let (title, body) = report.trim_end().split_once('\n').unwrap_or((report.as_str(), ""));
+    let message = Message {
+        title: title.to_string(),
+        body: body.trim_start().to_string(),
+        urgency: Urgency::Low,
+        process: None,
+    };
+    sink::deliver(&message, sinks)
+}
+
+fn build_report(history: &History, since: i64, until: i64) -> Result<String> {
+    let mut report = String::new();
+    writeln!(report, "top-helper daily report: {} to {}", format_time(since), format_time(until))?;
+
+    let (samples, first, last) = history.coverage(since, until)?;
+    let (Some(first), Some(last)) = (first, last) else {
+        writeln!(report, "\nNo samples were recorded in this period.")?;
+        return Ok(report);
+    };
+    writeln!(report, "{} samples between {} and {}", samples, format_time(first), format_time(last))?;
+
+    writeln!(report, "\nPeak memory:")?;
+    for (name, bytes) in history.peak_memory(since, until, TOP_N)? {
+        writeln!(report, "  {:<24} {:>10}", name, format_memory(bytes))?;
+    }
+
+    writeln!(report, "\nTop CPU time:")?;
+    let cpu = history.cpu_time(since, until, TOP_N)?;
+    if cpu.is_empty() {
+        writeln!(report, "  (needs at least two samples per process)")?;
+    }
+    for (name, ms) in cpu {
+        writeln!(report, "  {:<24} {:>10}", name, format_duration(ms))?;
+    }
+
+    writeln!(report, "\nNew processes:")?;
+    if history.has_samples_before(since)? {
+        let new = history.new_processes(since, until, 25)?;
+        if new.is_empty() {
+            writeln!(report, "  (none)")?;
+        }
+        for (name, first_seen) in new {
+            writeln!(report, "  {:<24} first seen {}", name, format_time(first_seen as i64))?;
+        }
+    } else {
+        writeln!(report, "  (history starts within this period, so every process would count as new)")?;
+    }
+
+    writeln!(report, "\nAlerts:")?;
+    let alerts = history.alerts(since, until)?;
+    if alerts.is_empty() {
+        writeln!(report, "  (none)")?;
+    }
+    for alert in alerts {
+        writeln!(report, "  {} [{}] {}: {}", format_time(alert.timestamp), alert.event, alert.rule, alert.message)?;
+    }
+
+    Ok(report)
+}
+
+/// Parses a duration such as `250ms`, `90s`, `10m`, `1h`, `2d`, or a plain number of seconds.
+pub fn parse_duration(s: &str) -> Result<std::time::Duration, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let multiplier = match unit.trim() {
+        "ms" => 0.001,
+        "" | "s" | "sec" | "secs" => 1.0,
+        "m" | "min" | "mins" => 60.0,
+        "h" | "hour" | "hours" => 3600.0,
+        "d" | "day" | "days" => 86400.0,
+        _ => return Err(format!("invalid duration '{}' (expected e.g. 30s, 10m, 1h, or 2d)", s)),
+    };
+    match number.parse::<f64>() {
+        Ok(value) if value >= 0.0 => Ok(std::time::Duration::from_secs_f64(value * multiplier)),
+        _ => Err(format!("invalid duration '{}' (expected e.g. 30s, 10m, 1h, or 2d)", s)),
+    }
+}
+
+/// Serde adapter for durations written as strings like `30s` in the config file.
+pub fn deserialize_duration<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<std::time::Duration, D::Error> {
+    let text = <String as serde::Deserialize>::deserialize(deserializer)?;
+    parse_duration(&text).map_err(serde::de::Error::custom)
+}
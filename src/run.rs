@@ -0,0 +1,198 @@
+//! `run -- <command>`: spawns a command and summarizes its resource usage when it exits,
+//! like `/usr/bin/time -v`, counting everything it spawned along the way.
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::Command;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+use sysinfo::System;
+
+use crate::control;
+use crate::output::{self, OutputFormat};
+use crate::redact;
+
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Serialize)]
+pub struct RunSummary {
+    pub command: Vec<String>,
+    pub pid: u32,
+    pub exit_code: Option<i32>,
+    pub signal: Option<i32>,
+    pub duration_secs: f64,
+    pub user_cpu_secs: f64,
+    pub system_cpu_secs: f64,
+    /// Largest resident set of any single process (from the kernel's accounting)
+    pub peak_rss_bytes: u64,
+    /// Largest combined resident set of the command and its descendants (sampled)
+    pub peak_tree_rss_bytes: u64,
+    /// Most processes alive at once (sampled)
+    pub peak_processes: usize,
+    pub read_bytes: u64,
+    pub write_bytes: u64,
+}
+
+/// Runs the command to completion and prints its summary to stderr (or `output`), returning
+/// the exit code to pass on.
+pub fn run_command(
+    command: &[String],
+    watch: bool,
+    format: OutputFormat,
+    output: Option<&Path>,
+) -> Result<i32> {
+    let (program, args) = command.split_first().context("No command given")?;
+    let mut process = Command::new(program);
+    process.args(args);
+    let mut summary = measure(&mut process, command.to_vec(), watch)?;
+    if format == OutputFormat::Json || output.is_some() {
+        summary.command = redact::command(&summary.command);
+    }
+
+    let text = match format {
+        OutputFormat::Json => serde_json::to_string_pretty(&summary)? + "\n",
+        OutputFormat::Text => format_summary(&summary),
+    };
+    match output {
+        Some(path) => fs::write(path, text).with_context(|| format!("Failed to write {}", path.display()))?,
+        None => eprint!("{}", text),
+    }
+
+    Ok(summary.exit_code.unwrap_or_else(|| 128 + summary.signal.unwrap_or(0)))
+}
+
+/// Spawns the command and samples its process tree until it exits. `label` is what the
+/// summary reports as the command line.
+pub fn measure(command: &mut Command, label: Vec<String>, watch: bool) -> Result<RunSummary> {
+    let started = Instant::now();
+    let child = command
+        .spawn()
+        .with_context(|| format!("Failed to run {}", label.first().map(String::as_str).unwrap_or_default()))?;
+    let pid = child.id();
+
+    // Ctrl-C goes to the whole foreground group; let the command handle it and still report.
+    // Ignoring it only after the spawn keeps the default disposition in the command.
+    // SAFETY: signal has no memory-safety preconditions
+    unsafe { libc::signal(libc::SIGINT, libc::SIG_IGN) };
+
+    // Reap the child on its own thread so its exit is timed exactly, not at the next sample
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        let _ = sender.send(wait(pid).map(|(status, usage)| (status, usage, Instant::now())));
+    });
+
+    let mut system = System::new();
+    let mut peak_tree_rss = 0u64;
+    let mut peak_processes = 0usize;
+
+    let waited = loop {
+        system.refresh_processes();
+        let mut tree = vec![pid];
+        tree.extend(control::descendants(&system, pid));
+        let processes: Vec<&sysinfo::Process> = tree
+            .iter()
+            .filter_map(|p| system.process(sysinfo::Pid::from(*p as usize)))
+            .collect();
+        let rss: u64 = processes.iter().map(|p| p.memory()).sum();
+        let cpu: f32 = processes.iter().map(|p| p.cpu_usage()).sum();
+        peak_tree_rss = peak_tree_rss.max(rss);
+        peak_processes = peak_processes.max(processes.len());
+
+        if watch {
+            eprint!(
+                "\r\x1b[K[top-helper] {}  RSS {}  (peak {})  CPU {:.0}%  processes {}",
+                output::format_duration(started.elapsed().as_millis() as u64),
+                output::format_memory(rss),
+                output::format_memory(peak_tree_rss),
+                cpu,
+                processes.len()
+            );
+            io::stderr().flush().ok();
+        }
+
+        match receiver.recv_timeout(SAMPLE_INTERVAL) {
+            Ok(result) => break result,
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => break Err(anyhow::anyhow!("Lost track of the command")),
+        }
+    };
+    // SAFETY: as above
+    unsafe { libc::signal(libc::SIGINT, libc::SIG_DFL) };
+    if watch {
+        eprint!("\r\x1b[K");
+    }
+    let (status, usage, ended) = waited?;
+
+    Ok(RunSummary {
+        command: label,
+        pid,
+        exit_code: libc::WIFEXITED(status).then(|| libc::WEXITSTATUS(status)),
+        signal: libc::WIFSIGNALED(status).then(|| libc::WTERMSIG(status)),
+        duration_secs: ended.duration_since(started).as_secs_f64(),
+        user_cpu_secs: timeval_secs(usage.ru_utime),
+        system_cpu_secs: timeval_secs(usage.ru_stime),
+        // ru_maxrss is in kilobytes
+        peak_rss_bytes: usage.ru_maxrss as u64 * 1024,
+        peak_tree_rss_bytes: peak_tree_rss.max(usage.ru_maxrss as u64 * 1024),
+        peak_processes: peak_processes.max(1),
+        // Block counts are in 512-byte units
+        read_bytes: usage.ru_inblock as u64 * 512,
+        write_bytes: usage.ru_oublock as u64 * 512,
+    })
+}
+
+/// Waits for the child to exit, returning its wait status and the resource usage of it and
+/// all of its descendants that were waited for.
+fn wait(pid: u32) -> Result<(i32, libc::rusage)> {
+    let mut status = 0;
+    // SAFETY: rusage is plain data that wait4 fills in
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+    loop {
+        // SAFETY: status and usage are valid for writes
+        if unsafe { libc::wait4(pid as libc::pid_t, &mut status, 0, &mut usage) } >= 0 {
+            return Ok((status, usage));
+        }
+        let err = io::Error::last_os_error();
+        if err.kind() != io::ErrorKind::Interrupted {
+            return Err(anyhow::Error::new(err).context("Failed to wait for the command"));
+        }
+    }
+}
+
+fn timeval_secs(time: libc::timeval) -> f64 {
+    time.tv_sec as f64 + time.tv_usec as f64 / 1_000_000.0
+}
+
+fn format_summary(summary: &RunSummary) -> String {
+    let ms = |secs: f64| (secs * 1000.0) as u64;
+    let status = match (summary.exit_code, summary.signal) {
+        (Some(code), _) => format!("exit status {}", code),
+        (None, Some(signal)) => format!("killed by signal {}", signal),
+        (None, None) => "unknown".to_string(),
+    };
+
+    let mut text = String::new();
+    text.push_str(&format!("\n[top-helper] {}\n", summary.command.join(" ")));
+    text.push_str(&format!("  Status:            {}\n", status));
+    text.push_str(&format!("  Duration:          {}\n", output::format_duration(ms(summary.duration_secs))));
+    text.push_str(&format!(
+        "  CPU time:          {} user, {} system\n",
+        output::format_duration(ms(summary.user_cpu_secs)),
+        output::format_duration(ms(summary.system_cpu_secs))
+    ));
+    text.push_str(&format!("  Peak RSS:          {} (largest single process)\n", output::format_memory(summary.peak_rss_bytes)));
+    text.push_str(&format!(
+        "  Peak tree RSS:     {} across up to {} processes\n",
+        output::format_memory(summary.peak_tree_rss_bytes),
+        summary.peak_processes
+    ));
+    text.push_str(&format!(
+        "  Disk I/O:          {} read, {} written\n",
+        output::format_memory(summary.read_bytes),
+        output::format_memory(summary.write_bytes)
+    ));
+    text
+}
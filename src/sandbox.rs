@@ -0,0 +1,76 @@
+//! Flatpak and Snap confinement, detected from the cgroup the process runs in: Flatpak starts
+//! each app in an `app-flatpak-<app-id>-<n>.scope`, and snapd in a `snap.<name>.<app>-<uuid>.scope`
+//! (or `snap.<name>.<app>.service` for a snap's daemons).
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Packaging {
+    Flatpak,
+    Snap,
+}
+
+impl fmt::Display for Packaging {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Packaging::Flatpak => write!(f, "Flatpak"),
+            Packaging::Snap => write!(f, "Snap"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SandboxedApp {
+    pub packaging: Packaging,
+    /// Flatpak app ID (org.mozilla.firefox) or snap name (firefox)
+    pub app_id: String,
+}
+
+impl SandboxedApp {
+    /// Window classes the app's windows may have: Flatpak apps usually set the app ID as their
+    /// Wayland app_id or WM_CLASS, and otherwise often its last component.
+    pub fn window_classes(&self) -> Vec<&str> {
+        let mut classes = vec![self.app_id.as_str()];
+        if let Some(last) = self.app_id.rsplit('.').next()
+            && last != self.app_id
+        {
+            classes.push(last);
+        }
+        classes
+    }
+}
+
+impl fmt::Display for SandboxedApp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.app_id, self.packaging)
+    }
+}
+
+/// The Flatpak or Snap app `pid` belongs to, if it runs confined in one.
+pub fn detect(pid: u32) -> Option<SandboxedApp> {
+    let cgroup = fs::read_to_string(format!("/proc/{}/cgroup", pid)).ok()?;
+    from_cgroup(&cgroup)
+}
+
+fn from_cgroup(cgroup: &str) -> Option<SandboxedApp> {
+    let components = cgroup.lines().filter_map(|line| line.rsplit(':').next()).flat_map(|path| path.split('/'));
+    for component in components {
+        if let Some(rest) = component.strip_prefix("app-flatpak-").and_then(|rest| rest.strip_suffix(".scope")) {
+            // app-flatpak-org.mozilla.firefox-12345.scope
+            let app_id = match rest.rsplit_once('-') {
+                Some((app_id, number)) if number.chars().all(|c| c.is_ascii_digit()) => app_id,
+                _ => rest,
+            };
+            return Some(SandboxedApp { packaging: Packaging::Flatpak, app_id: app_id.to_string() });
+        }
+        if let Some(rest) = component.strip_prefix("snap.")
+            && let Some((name, _app)) = rest.split_once('.')
+        {
+            return Some(SandboxedApp { packaging: Packaging::Snap, app_id: name.to_string() });
+        }
+    }
+    None
+}
@@ -0,0 +1,166 @@
+//! A process's scheduling for `info --sched`: its policy and priority, how often it gave up the
+//! CPU or had it taken away (context switches), and how long it waited on a run queue before
+//! getting it (schedstat). Counts are summed over all of the process's threads, from
+//! /proc/<pid>/task.
+
+use procfs::process::Process;
+use std::fmt;
+
+use crate::output;
+
+// Policies from linux/sched.h
+const SCHED_OTHER: u32 = 0;
+const SCHED_FIFO: u32 = 1;
+const SCHED_RR: u32 = 2;
+const SCHED_BATCH: u32 = 3;
+const SCHED_IDLE: u32 = 5;
+const SCHED_DEADLINE: u32 = 6;
+
+#[derive(Debug, Clone)]
+pub struct SchedStats {
+    /// A SCHED_* constant
+    pub policy: Option<u32>,
+    pub nice: i64,
+    /// 1 to 99 for the real-time policies, 0 otherwise
+    pub rt_priority: Option<u32>,
+    pub threads: usize,
+    /// Waits for I/O, locks, or sleeps
+    pub voluntary_switches: u64,
+    /// Preemptions, by a higher priority task or the end of a timeslice
+    pub involuntary_switches: u64,
+    /// None when the kernel doesn't keep schedstats
+    pub run_queue: Option<RunQueue>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RunQueue {
+    /// Nanoseconds on a CPU
+    pub running_ns: u64,
+    /// Nanoseconds runnable but waiting for a CPU
+    pub waiting_ns: u64,
+    pub timeslices: u64,
+}
+
+/// The scheduling of `pid`, or None when its stat is not readable.
+pub fn sched_stats(pid: u32) -> Option<SchedStats> {
+    let process = Process::new(pid as i32).ok()?;
+    let stat = process.stat().ok()?;
+
+    let mut stats = SchedStats {
+        policy: stat.policy,
+        nice: stat.nice,
+        rt_priority: stat.rt_priority,
+        threads: 0,
+        voluntary_switches: 0,
+        involuntary_switches: 0,
+        run_queue: None,
+    };
+    // Threads come and go while they are read, so only the readable ones count
+    for task in process.tasks().ok()?.flatten() {
+        let Ok(status) = task.status() else {
+            continue;
+        };
+        stats.threads += 1;
+        stats.voluntary_switches += status.voluntary_ctxt_switches.unwrap_or(0);
+        stats.involuntary_switches += status.nonvoluntary_ctxt_switches.unwrap_or(0);
+        if let Ok(schedstat) = task.schedstat() {
+            let run_queue = stats.run_queue.get_or_insert_default();
+            run_queue.running_ns += schedstat.sum_exec_runtime;
+            run_queue.waiting_ns += schedstat.run_delay;
+            run_queue.timeslices += schedstat.pcount;
+        }
+    }
+    Some(stats)
+}
+
+impl SchedStats {
+    pub fn json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "policy": policy_name(self.policy),
+            "nice": self.nice,
+            "rt_priority": self.rt_priority,
+            "threads": self.threads,
+            "voluntary_switches": self.voluntary_switches,
+            "involuntary_switches": self.involuntary_switches,
+            "run_queue": self.run_queue.map(|run_queue| serde_json::json!({
+                "running_ns": run_queue.running_ns,
+                "waiting_ns": run_queue.waiting_ns,
+                "timeslices": run_queue.timeslices,
+            })),
+        })
+    }
+
+    /// Prints the "Scheduling:" block of `info`.
+    pub fn print(&self) {
+        println!("\nScheduling:");
+        println!("  Policy: {}", Policy(self.policy, self.rt_priority, self.nice));
+        if self.threads > 1 {
+            println!("  Threads: {}", self.threads);
+        }
+
+        let switches = self.voluntary_switches + self.involuntary_switches;
+        print!("  Context Switches: {} voluntary, {} involuntary", self.voluntary_switches, self.involuntary_switches);
+        if switches > 0 {
+            print!(" ({:.1}% preempted)", self.involuntary_switches as f64 * 100.0 / switches as f64);
+        }
+        println!();
+
+        let Some(run_queue) = self.run_queue else {
+            println!("  Run Queue Wait: unknown (the kernel keeps no schedstats)");
+            return;
+        };
+        println!(
+            "  CPU Time: {} in {} timeslices",
+            format_ns(run_queue.running_ns),
+            run_queue.timeslices
+        );
+        let runnable = run_queue.running_ns + run_queue.waiting_ns;
+        print!("  Run Queue Wait: {}", format_ns(run_queue.waiting_ns));
+        if run_queue.timeslices > 0 {
+            print!(", {:.3}ms per timeslice", run_queue.waiting_ns as f64 / 1e6 / run_queue.timeslices as f64);
+        }
+        if runnable > 0 {
+            print!(" ({:.1}% of runnable time)", run_queue.waiting_ns as f64 * 100.0 / runnable as f64);
+        }
+        println!();
+    }
+}
+
+/// Scheduling delays are often well under a second, so those are shown in milliseconds.
+fn format_ns(ns: u64) -> String {
+    if ns < 1_000_000_000 {
+        format!("{:.1}ms", ns as f64 / 1e6)
+    } else {
+        output::format_duration(ns / 1_000_000)
+    }
+}
+
+fn policy_name(policy: Option<u32>) -> String {
+    match policy {
+        Some(SCHED_FIFO) => "SCHED_FIFO".to_string(),
+        Some(SCHED_RR) => "SCHED_RR".to_string(),
+        Some(SCHED_DEADLINE) => "SCHED_DEADLINE".to_string(),
+        Some(SCHED_BATCH) => "SCHED_BATCH".to_string(),
+        Some(SCHED_IDLE) => "SCHED_IDLE".to_string(),
+        Some(SCHED_OTHER) | None => "SCHED_OTHER".to_string(),
+        Some(policy) => policy.to_string(),
+    }
+}
+
+/// The policy with the priority that matters for it: the real-time priority for SCHED_FIFO and
+/// SCHED_RR, and the nice value for the policies that weigh it.
+struct Policy(Option<u32>, Option<u32>, i64);
+
+impl fmt::Display for Policy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Policy(policy, rt_priority, nice) = *self;
+        match policy {
+            Some(SCHED_FIFO | SCHED_RR) => {
+                write!(f, "{} (real-time priority {})", policy_name(policy), rt_priority.unwrap_or(0))
+            }
+            Some(SCHED_DEADLINE | SCHED_IDLE) => write!(f, "{}", policy_name(policy)),
+            Some(SCHED_BATCH | SCHED_OTHER) | None => write!(f, "{} (nice {})", policy_name(policy), nice),
+            Some(policy) => write!(f, "policy {} (nice {})", policy, nice),
+        }
+    }
+}
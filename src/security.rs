@@ -0,0 +1,257 @@
+//! A process's security context for `info --security`: user and group IDs, capabilities,
+//! no_new_privs, seccomp, and its SELinux or AppArmor label, from /proc/<pid>/status and
+//! /proc/<pid>/attr.
+
+use procfs::process::Process;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use sysinfo::{Groups, Users};
+
+/// Capability names by bit number, as in linux/capability.h.
+const CAPABILITIES: &[&str] = &[
+    "chown",
+    "dac_override",
+    "dac_read_search",
+    "fowner",
+    "fsetid",
+    "kill",
+    "setgid",
+    "setuid",
+    "setpcap",
+    "linux_immutable",
+    "net_bind_service",
+    "net_broadcast",
+    "net_admin",
+    "net_raw",
+    "ipc_lock",
+    "ipc_owner",
+    "sys_module",
+    "sys_rawio",
+    "sys_chroot",
+    "sys_ptrace",
+    "sys_pacct",
+    "sys_admin",
+    "sys_boot",
+    "sys_nice",
+    "sys_resource",
+    "sys_time",
+    "sys_tty_config",
+    "mknod",
+    "lease",
+    "audit_write",
+    "audit_control",
+    "setfcap",
+    "mac_override",
+    "mac_admin",
+    "syslog",
+    "wake_alarm",
+    "block_suspend",
+    "audit_read",
+    "perfmon",
+    "bpf",
+    "checkpoint_restore",
+];
+
+#[derive(Debug, Clone)]
+pub struct SecurityContext {
+    /// Real, effective, saved, and filesystem user IDs
+    pub uids: [u32; 4],
+    /// Real, effective, saved, and filesystem group IDs
+    pub gids: [u32; 4],
+    /// Supplementary groups
+    pub groups: Vec<u32>,
+    pub effective_caps: u64,
+    pub bounding_caps: Option<u64>,
+    pub ambient_caps: Option<u64>,
+    pub no_new_privs: Option<bool>,
+    /// 0 disabled, 1 strict, 2 filter
+    pub seccomp: Option<u32>,
+    pub seccomp_filters: Option<u32>,
+    /// ("SELinux" or "AppArmor", label)
+    pub label: Option<(&'static str, String)>,
+}
+
+/// The security context of `pid`, or None when its status is not readable.
+pub fn security_context(pid: u32) -> Option<SecurityContext> {
+    let status = Process::new(pid as i32).ok()?.status().ok()?;
+    // procfs doesn't parse the filter count
+    let seccomp_filters = fs::read_to_string(format!("/proc/{}/status", pid))
+        .ok()?
+        .lines()
+        .find_map(|line| line.strip_prefix("Seccomp_filters:"))
+        .and_then(|value| value.trim().parse().ok());
+
+    Some(SecurityContext {
+        uids: [status.ruid, status.euid, status.suid, status.fuid],
+        gids: [status.rgid, status.egid, status.sgid, status.fgid],
+        groups: status.groups.iter().map(|&gid| gid as u32).collect(),
+        effective_caps: status.capeff,
+        bounding_caps: status.capbnd,
+        ambient_caps: status.capamb,
+        no_new_privs: status.nonewprivs.map(|value| value != 0),
+        seccomp: status.seccomp,
+        seccomp_filters,
+        label: lsm_label(pid),
+    })
+}
+
+/// The process's label from the active major LSM. Newer kernels have a directory per LSM under
+/// attr/; older ones only have attr/current, which belongs to whichever LSM is enabled.
+fn lsm_label(pid: u32) -> Option<(&'static str, String)> {
+    let read = |file: &str| {
+        let text = fs::read_to_string(format!("/proc/{}/attr/{}", pid, file)).ok()?;
+        let label = text.trim_end_matches(['\0', '\n']).to_string();
+        (!label.is_empty()).then_some(label)
+    };
+    if let Some(label) = read("apparmor/current") {
+        return Some(("AppArmor", label));
+    }
+    let label = read("current")?;
+    if Path::new("/sys/fs/selinux").exists() {
+        Some(("SELinux", label))
+    } else if Path::new("/sys/kernel/security/apparmor").exists() {
+        Some(("AppArmor", label))
+    } else {
+        None
+    }
+}
+
+impl SecurityContext {
+    /// Prints the "Security:" block of `info`, with user and group names resolved.
+    pub fn print(&self) {
+        let users = Users::new_with_refreshed_list();
+        let groups = Groups::new_with_refreshed_list();
+        let user_name = |uid: u32| users.iter().find(|user| **user.id() == uid).map(|user| user.name().to_string());
+        let group_name = |gid: u32| groups.iter().find(|group| **group.id() == gid).map(|group| group.name().to_string());
+
+        println!("\nSecurity:");
+        println!("  User: {}", ids(&self.uids, user_name));
+        println!("  Group: {}", ids(&self.gids, group_name));
+        if !self.groups.is_empty() {
+            let names: Vec<String> = self.groups.iter().map(|&gid| named(gid, group_name(gid))).collect();
+            println!("  Supplementary Groups: {}", names.join(", "));
+        }
+
+        println!("  Capabilities: {}", capabilities(self.effective_caps));
+        if let Some(bounding) = self.bounding_caps
+            && bounding & known_caps() != known_caps()
+        {
+            // Only interesting when something (a container runtime, systemd) has dropped some
+            println!("  Bounding Set: {}", capabilities(bounding));
+        }
+        if let Some(ambient) = self.ambient_caps
+            && ambient != 0
+        {
+            println!("  Ambient: {}", capabilities(ambient));
+        }
+
+        if let Some(no_new_privs) = self.no_new_privs {
+            println!("  No New Privileges: {}", if no_new_privs { "yes" } else { "no" });
+        }
+        if let Some(mode) = self.seccomp {
+            println!("  Seccomp: {}", SeccompMode(mode, self.seccomp_filters));
+        }
+        match &self.label {
+            Some((lsm, label)) => println!("  {}: {}", lsm, label),
+            None => println!("  LSM Label: none (no SELinux or AppArmor)"),
+        }
+    }
+}
+
+impl SecurityContext {
+    /// The context for the structured `info` formats: IDs as numbers and capabilities by name.
+    pub fn json(&self) -> serde_json::Value {
+        let ids = |ids: &[u32; 4]| {
+            serde_json::json!({ "real": ids[0], "effective": ids[1], "saved": ids[2], "filesystem": ids[3] })
+        };
+        serde_json::json!({
+            "uids": ids(&self.uids),
+            "gids": ids(&self.gids),
+            "groups": self.groups,
+            "effective_capabilities": cap_list(self.effective_caps),
+            "bounding_capabilities": self.bounding_caps.map(cap_list),
+            "ambient_capabilities": self.ambient_caps.map(cap_list),
+            "no_new_privs": self.no_new_privs,
+            "seccomp": self.seccomp.map(|mode| match mode {
+                0 => "disabled".to_string(),
+                1 => "strict".to_string(),
+                2 => "filter".to_string(),
+                mode => mode.to_string(),
+            }),
+            "seccomp_filters": self.seccomp_filters,
+            "lsm": self.label.as_ref().map(|(lsm, _)| lsm),
+            "label": self.label.as_ref().map(|(_, label)| label),
+        })
+    }
+}
+
+/// "1000 (alice)" when all four IDs agree, otherwise each one labelled, e.g. for a setuid
+/// program: "real 1000 (alice), effective 0 (root), saved 0 (root), filesystem 0 (root)".
+fn ids(ids: &[u32; 4], name: impl Fn(u32) -> Option<String>) -> String {
+    if ids.iter().all(|&id| id == ids[0]) {
+        return named(ids[0], name(ids[0]));
+    }
+    ["real", "effective", "saved", "filesystem"]
+        .iter()
+        .zip(ids)
+        .map(|(kind, &id)| format!("{} {}", kind, named(id, name(id))))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn named(id: u32, name: Option<String>) -> String {
+    match name {
+        Some(name) => format!("{} ({})", id, name),
+        None => id.to_string(),
+    }
+}
+
+/// Every capability this table knows about.
+fn known_caps() -> u64 {
+    (1u64 << CAPABILITIES.len()) - 1
+}
+
+/// "none", "all (full root privileges)", the names, e.g. "CAP_NET_BIND_SERVICE, CAP_NET_RAW",
+/// or for a mostly privileged set, what is missing: "all except CAP_SYS_ADMIN".
+fn capabilities(mask: u64) -> String {
+    if mask == 0 {
+        return "none".to_string();
+    }
+    let known = mask & known_caps();
+    if known == known_caps() {
+        return "all (full root privileges)".to_string();
+    }
+    if known.count_ones() as usize > CAPABILITIES.len() / 2 {
+        return format!("all except {}", cap_names(!mask & known_caps()));
+    }
+    cap_names(mask)
+}
+
+fn cap_names(mask: u64) -> String {
+    cap_list(mask).join(", ")
+}
+
+fn cap_list(mask: u64) -> Vec<String> {
+    (0..64)
+        .filter(|bit| mask & (1 << bit) != 0)
+        .map(|bit| match CAPABILITIES.get(bit) {
+            Some(name) => format!("CAP_{}", name.to_uppercase()),
+            None => format!("cap_{}", bit),
+        })
+        .collect()
+}
+
+struct SeccompMode(u32, Option<u32>);
+
+impl fmt::Display for SeccompMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SeccompMode(0, _) => write!(f, "disabled"),
+            SeccompMode(1, _) => write!(f, "strict (only read, write, exit, and sigreturn)"),
+            SeccompMode(2, Some(filters)) => write!(f, "filter ({} filter{})", filters, if *filters == 1 { "" } else { "s" }),
+            SeccompMode(2, None) => write!(f, "filter"),
+            SeccompMode(mode, _) => write!(f, "mode {}", mode),
+        }
+    }
+}
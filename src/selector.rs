@@ -0,0 +1,543 @@
+use anyhow::{Context, Result};
+use procfs::process::FDTarget;
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use sysinfo::System;
+
+use crate::error::CliError;
+use crate::holders;
+use crate::output;
+use crate::window;
+
+static NAME_MATCH: OnceLock<NameMatch> = OnceLock::new();
+
+/// How bare names, `name:` selectors, and `list --name` compare with process names, chosen with
+/// the global `--exact` and `--word` flags. Matching ignores case in every mode.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NameMatch {
+    /// Anywhere in the name, so `sh` matches `bash` and `ssh`
+    #[default]
+    Substring,
+    /// As a whole word, so `sh` matches `sh` and `sh-run` but not `bash`
+    Word,
+    /// The whole name
+    Exact,
+}
+
+pub fn set_name_match(mode: NameMatch) {
+    NAME_MATCH.set(mode).ok();
+}
+
+/// Whether `name` matches `text` in the chosen mode. `text` must already be lowercase.
+pub fn name_matches(name: &str, text: &str) -> bool {
+    let name = name.to_lowercase();
+    match NAME_MATCH.get().copied().unwrap_or_default() {
+        NameMatch::Substring => name.contains(text),
+        NameMatch::Word => {
+            let is_word_char = |c: char| c.is_alphanumeric();
+            name.match_indices(text).any(|(start, _)| {
+                !name[..start].ends_with(is_word_char) && !name[start + text.len()..].starts_with(is_word_char)
+            })
+        }
+        NameMatch::Exact => name == text,
+    }
+}
+
+/// A single process selector, e.g. `name:chrome` or `port:8080`.
+#[derive(Debug, Clone)]
+pub enum Selector {
+    /// A bare identifier: a PID if numeric, otherwise a name as with `name:`
+    Auto(String),
+    Pid(u32),
+    Name(String),
+    /// The whole name, case included
+    Exact(String),
+    Regex(Regex),
+    Unit(String),
+    Port(u16),
+    Window(String),
+    Cwd(PathBuf),
+    /// Processes holding a file or anything inside a directory (open, or as working directory)
+    File(PathBuf),
+}
+
+/// Narrows a selection to one process by start time, from a `newest:` or `oldest:` prefix.
+#[derive(Debug, Clone, Copy)]
+enum Pick {
+    Newest,
+    Oldest,
+}
+
+/// A union of selectors separated by commas, e.g. `name:nginx,port:5432`, optionally prefixed
+/// with `newest:` or `oldest:` to keep only one of the processes they match.
+#[derive(Debug, Clone)]
+pub struct Selection {
+    selectors: Vec<Selector>,
+    cwd_filter: Option<PathBuf>,
+    pick: Option<Pick>,
+}
+
+impl Selector {
+    pub fn parse(input: &str) -> Result<Self> {
+        let Some((kind, value)) = input.split_once(':') else {
+            return Ok(Selector::Auto(input.to_string()));
+        };
+
+        let selector = match kind {
+            "pid" => Selector::Pid(value.parse().with_context(|| format!("Invalid PID '{}'", value))?),
+            "name" => Selector::Name(value.to_lowercase()),
+            "exact" => Selector::Exact(value.to_string()),
+            "re" | "regex" => Selector::Regex(
+                Regex::new(value).with_context(|| format!("Invalid regex '{}'", value))?,
+            ),
+            "unit" => Selector::Unit(value.to_string()),
+            "port" => Selector::Port(value.parse().with_context(|| format!("Invalid port '{}'", value))?),
+            "window" => Selector::Window(value.to_lowercase()),
+            "cwd" => Selector::Cwd(resolve_path(value)),
+            "file" => Selector::File(resolve_path(value)),
+            // Not a known prefix (e.g. a name containing ':'), treat as a bare identifier
+            _ => Selector::Auto(input.to_string()),
+        };
+
+        Ok(selector)
+    }
+
+    /// The lowercased name a bare name or `name:` selector asks for.
+    fn name_text(&self) -> Option<String> {
+        match self {
+            Selector::Auto(value) if value.parse::<u32>().is_err() => Some(value.to_lowercase()),
+            Selector::Name(text) => Some(text.clone()),
+            _ => None,
+        }
+    }
+
+    /// PIDs of the processes this selector matches.
+    fn pids(&self, index: &mut ProcessIndex) -> HashSet<u32> {
+        match self {
+            Selector::Auto(value) => match value.parse::<u32>() {
+                Ok(pid) => index.existing(pid),
+                Err(_) => index.name_pids(|name| name_matches(name, &value.to_lowercase())),
+            },
+            Selector::Pid(pid) => index.existing(*pid),
+            Selector::Name(text) => index.name_pids(|name| name_matches(name, text)),
+            Selector::Exact(text) => index.name_pids(|name| name == text),
+            Selector::Regex(re) => index.name_pids(|name| re.is_match(name)),
+            Selector::Unit(unit) => index.unit_pids(unit),
+            Selector::Port(port) => index.port_pids(*port),
+            Selector::Window(text) => index.window_pids(text),
+            Selector::Cwd(path) => index
+                .system
+                .processes()
+                .values()
+                .filter(|process| in_directory(process, path))
+                .map(|process| process.pid().as_u32())
+                .collect(),
+            Selector::File(path) => index.file_pids(path),
+        }
+    }
+}
+
+impl Selection {
+    pub fn parse(input: &str) -> Result<Self> {
+        let (pick, input) = match input.split_once(':') {
+            Some(("newest", rest)) => (Some(Pick::Newest), rest),
+            Some(("oldest", rest)) => (Some(Pick::Oldest), rest),
+            _ => (None, input),
+        };
+        let selectors = input
+            .split(',')
+            .filter(|part| !part.is_empty())
+            .map(Selector::parse)
+            .collect::<Result<Vec<_>>>()?;
+
+        if selectors.is_empty() {
+            return Err(anyhow::anyhow!("Empty process selector"));
+        }
+
+        Ok(Selection { selectors, cwd_filter: None, pick })
+    }
+
+    /// Selects every process whose working directory is inside `path`.
+    pub fn cwd(path: &str) -> Self {
+        Selection {
+            selectors: vec![Selector::Cwd(resolve_path(path))],
+            cwd_filter: None,
+            pick: None,
+        }
+    }
+
+    /// Parses an optional selector, narrowed to processes whose working directory is inside `cwd`.
+    /// At least one of the two must be given.
+    pub fn from_args(input: Option<&str>, cwd: Option<&str>) -> Result<Self> {
+        match (input, cwd) {
+            (Some(input), cwd) => {
+                let mut selection = Selection::parse(input)?;
+                selection.cwd_filter = cwd.map(resolve_path);
+                Ok(selection)
+            }
+            (None, Some(cwd)) => Ok(Selection::cwd(cwd)),
+            (None, None) => Err(anyhow::anyhow!("A process selector or --cwd is required")),
+        }
+    }
+
+    /// Returns the PIDs of all processes matching any of the selectors, sorted by PID, or only the
+    /// newest or oldest of them.
+    pub fn resolve(&self, system: &System) -> Vec<u32> {
+        self.resolve_in(&mut ProcessIndex::new(system))
+    }
+
+    /// Like [`Selection::resolve`], reusing the lookups `index` has already built. Commands that
+    /// resolve several selections should share one index.
+    pub fn resolve_in(&self, index: &mut ProcessIndex) -> Vec<u32> {
+        let mut pids: HashSet<u32> = HashSet::new();
+        for selector in &self.selectors {
+            pids.extend(selector.pids(index));
+        }
+
+        // Sockets, windows, and open files are looked up outside the snapshot, so processes
+        // started since it was taken are dropped here
+        let system = index.system;
+        let mut pids: Vec<u32> = pids
+            .into_iter()
+            .filter(|pid| {
+                system
+                    .process(sysinfo::Pid::from(*pid as usize))
+                    .is_some_and(|p| self.cwd_filter.as_ref().is_none_or(|path| in_directory(p, path)))
+            })
+            .collect();
+
+        if let Some(pick) = self.pick {
+            // Later PIDs break ties between processes started in the same second
+            let started = |pid: &u32| {
+                let start = system.process(sysinfo::Pid::from(*pid as usize)).map_or(0, |p| p.start_time());
+                (start, *pid)
+            };
+            let picked = match pick {
+                Pick::Newest => pids.iter().copied().max_by_key(started),
+                Pick::Oldest => pids.iter().copied().min_by_key(started),
+            };
+            return picked.into_iter().collect();
+        }
+
+        pids.sort();
+        pids
+    }
+
+    /// Whether the process's name is exactly one of the names asked for, rather than only
+    /// containing it. Such matches rank above the others when one process has to be chosen.
+    fn is_exact_name(&self, process: &sysinfo::Process) -> bool {
+        let name = process.name().to_lowercase();
+        self.selectors.iter().filter_map(Selector::name_text).any(|text| text == name)
+    }
+
+    /// The matched processes whose parent did not match, one per application or process tree,
+    /// sorted by PID.
+    pub fn resolve_roots<'a>(&self, system: &'a System) -> Vec<&'a sysinfo::Process> {
+        self.resolve_roots_in(&mut ProcessIndex::new(system))
+    }
+
+    fn resolve_roots_in<'a>(&self, index: &mut ProcessIndex<'a>) -> Vec<&'a sysinfo::Process> {
+        let system = index.system;
+        let pids = self.resolve_in(index);
+        pids.iter()
+            .filter_map(|pid| system.process(sysinfo::Pid::from(*pid as usize)))
+            .filter(|p| p.parent().is_none_or(|parent| !pids.contains(&parent.as_u32())))
+            .collect()
+    }
+
+    /// Resolves to a single process. When several processes match, only the roots of the
+    /// matched set (matches whose parent did not match) are considered, so a multi-process
+    /// application like a browser still resolves to its main process. Of several roots, the
+    /// likeliest one meant wins (see [`Rank`]), with a note on stderr saying why.
+    pub fn resolve_unique<'a>(&self, system: &'a System) -> Result<&'a sysinfo::Process> {
+        self.resolve_unique_in(&mut ProcessIndex::new(system))
+    }
+
+    /// Like [`Selection::resolve_unique`], reusing the lookups `index` has already built.
+    pub fn resolve_unique_in<'a>(&self, index: &mut ProcessIndex<'a>) -> Result<&'a sysinfo::Process> {
+        let roots = self.resolve_roots_in(index);
+
+        match roots.as_slice() {
+            [] => return Err(CliError::not_found("Process not found")),
+            [process] => return Ok(process),
+            _ => {}
+        }
+
+        let mut ranked: Vec<(Rank, &sysinfo::Process)> =
+            roots.iter().map(|process| (Rank::of(self, index, process), *process)).collect();
+        ranked.sort_by(|(a, _), (b, _)| b.cmp(a));
+        let (winner, process) = &ranked[0];
+        let others: Vec<String> =
+            ranked[1..].iter().take(10).map(|(_, p)| format!("{} ({})", p.pid().as_u32(), p.name())).collect();
+
+        let Some(reason) = winner.reason(&ranked[1].0) else {
+            return Err(CliError::multiple_matches(format!(
+                "{} unrelated processes match and none stands out; use a more specific selector such as \
+                 pid:<PID>. Candidates: {} ({}), {}",
+                ranked.len(),
+                process.pid().as_u32(),
+                process.name(),
+                others.join(", ")
+            )));
+        };
+        eprintln!(
+            "{} unrelated processes match; chose {} (PID: {}) because {}. Others: {}",
+            ranked.len(),
+            process.name(),
+            process.pid().as_u32(),
+            reason,
+            others.join(", ")
+        );
+        Ok(process)
+    }
+}
+
+/// How likely a matching process is to be the one meant, compared heuristic by heuristic in
+/// field order: the exact name asked for, then owning a window, then leading a session (such as a
+/// shell or a daemon), then memory use, then how recently it started.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct Rank {
+    exact_name: bool,
+    has_window: bool,
+    session_leader: bool,
+    memory: u64,
+    start_time: u64,
+}
+
+impl Rank {
+    fn of(selection: &Selection, index: &mut ProcessIndex, process: &sysinfo::Process) -> Self {
+        let pid = process.pid();
+        Rank {
+            exact_name: selection.is_exact_name(process),
+            has_window: index.window_owners().contains(&pid.as_u32()),
+            session_leader: process.session_id() == Some(pid),
+            memory: process.memory(),
+            start_time: process.start_time(),
+        }
+    }
+
+    /// Why this ranks above `other`, from the first heuristic that tells them apart; None if
+    /// nothing does.
+    fn reason(&self, other: &Rank) -> Option<String> {
+        let reason = if self.exact_name != other.exact_name {
+            "it has exactly the name given".to_string()
+        } else if self.has_window != other.has_window {
+            "it has a window".to_string()
+        } else if self.session_leader != other.session_leader {
+            "it leads its session".to_string()
+        } else if self.memory != other.memory {
+            format!("it uses the most memory ({})", output::format_memory(self.memory))
+        } else if self.start_time != other.start_time {
+            "it started most recently".to_string()
+        } else {
+            return None;
+        };
+        Some(reason)
+    }
+}
+
+/// Lookups over one snapshot of the system, each built on first use and then shared by every
+/// selector resolved against it. Resolving many selectors (or one with many parts) therefore
+/// scans process names, cgroups, sockets, and windows once rather than once per selector.
+pub struct ProcessIndex<'a> {
+    system: &'a System,
+    /// Process name to PIDs; matching tests each distinct name once
+    names: Option<HashMap<String, Vec<u32>>>,
+    /// cgroup path component (such as `nginx.service`) to PIDs
+    units: Option<HashMap<String, Vec<u32>>>,
+    /// Socket inode to the PIDs holding it
+    sockets: Option<HashMap<u64, Vec<u32>>>,
+    /// Lowercased class and title of every window, with its PID
+    windows: Option<Vec<(String, String, u32)>>,
+    files: HashMap<PathBuf, HashSet<u32>>,
+}
+
+impl<'a> ProcessIndex<'a> {
+    pub fn new(system: &'a System) -> Self {
+        ProcessIndex { system, names: None, units: None, sockets: None, windows: None, files: HashMap::new() }
+    }
+
+    fn existing(&self, pid: u32) -> HashSet<u32> {
+        self.system.process(sysinfo::Pid::from(pid as usize)).map(|_| pid).into_iter().collect()
+    }
+
+    fn name_pids(&mut self, mut matches: impl FnMut(&str) -> bool) -> HashSet<u32> {
+        let system = self.system;
+        let names = self.names.get_or_insert_with(|| {
+            let mut names: HashMap<String, Vec<u32>> = HashMap::new();
+            for process in system.processes().values() {
+                names.entry(process.name().to_string()).or_default().push(process.pid().as_u32());
+            }
+            names
+        });
+
+        names.iter().filter(|(name, _)| matches(name)).flat_map(|(_, pids)| pids.iter().copied()).collect()
+    }
+
+    /// Processes in the given systemd unit, based on their cgroup paths. A unit without a
+    /// suffix is taken to be a service.
+    fn unit_pids(&mut self, unit: &str) -> HashSet<u32> {
+        let system = self.system;
+        let units = self.units.get_or_insert_with(|| {
+            let mut units: HashMap<String, Vec<u32>> = HashMap::new();
+            for pid in system.processes().keys().map(|pid| pid.as_u32()) {
+                let Ok(cgroup) = fs::read_to_string(format!("/proc/{}/cgroup", pid)) else {
+                    continue;
+                };
+                let components: HashSet<&str> = cgroup
+                    .lines()
+                    .filter_map(|line| line.rsplit(':').next())
+                    .flat_map(|path| path.split('/'))
+                    .filter(|component| !component.is_empty())
+                    .collect();
+                for component in components {
+                    units.entry(component.to_string()).or_default().push(pid);
+                }
+            }
+            units
+        });
+
+        let unit = if unit.contains('.') { unit.to_string() } else { format!("{}.service", unit) };
+        units.get(&unit).into_iter().flatten().copied().collect()
+    }
+
+    /// Processes owning a TCP socket listening on, or a UDP socket bound to, the given port.
+    fn port_pids(&mut self, port: u16) -> HashSet<u32> {
+        let mut inodes = HashSet::new();
+        for entries in [procfs::net::tcp(), procfs::net::tcp6()].into_iter().flatten() {
+            inodes.extend(
+                entries
+                    .iter()
+                    .filter(|e| e.local_address.port() == port && e.state == procfs::net::TcpState::Listen)
+                    .map(|e| e.inode),
+            );
+        }
+        for entries in [procfs::net::udp(), procfs::net::udp6()].into_iter().flatten() {
+            inodes.extend(entries.iter().filter(|e| e.local_address.port() == port).map(|e| e.inode));
+        }
+        if inodes.is_empty() {
+            return HashSet::new();
+        }
+
+        let sockets = self.sockets.get_or_insert_with(socket_owners);
+        inodes.iter().filter_map(|inode| sockets.get(inode)).flatten().copied().collect()
+    }
+
+    fn windows(&mut self) -> &[(String, String, u32)] {
+        self.windows.get_or_insert_with(|| {
+            window::list_all_windows(None)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|w| (w.class.to_lowercase(), w.title.to_lowercase(), w.pid))
+                .collect()
+        })
+    }
+
+    fn window_pids(&mut self, text: &str) -> HashSet<u32> {
+        self.windows()
+            .iter()
+            .filter(|(class, title, _)| class.contains(text) || title.contains(text))
+            .map(|(_, _, pid)| *pid)
+            .collect()
+    }
+
+    /// Processes owning at least one window.
+    fn window_owners(&mut self) -> HashSet<u32> {
+        self.windows().iter().map(|(_, _, pid)| *pid).collect()
+    }
+
+    fn file_pids(&mut self, path: &Path) -> HashSet<u32> {
+        self.files
+            .entry(path.to_path_buf())
+            .or_insert_with(|| holders::holders(path, false).into_keys().collect())
+            .clone()
+    }
+}
+
+/// An environment filter from `--env KEY` (variable is set) or `--env KEY=VALUE` (exact value).
+#[derive(Debug, Clone)]
+pub struct EnvFilter {
+    key: String,
+    value: Option<String>,
+}
+
+impl std::str::FromStr for EnvFilter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (key, value) = match s.split_once('=') {
+            Some((key, value)) => (key, Some(value.to_string())),
+            None => (s, None),
+        };
+        if key.is_empty() {
+            return Err("expected KEY or KEY=VALUE".to_string());
+        }
+
+        Ok(EnvFilter { key: key.to_string(), value })
+    }
+}
+
+/// Whether a process's environment satisfies every filter. The environment is read from
+/// /proc only when called, so cheaper filters should run first; unreadable environments never match.
+pub fn env_matches(pid: u32, filters: &[EnvFilter]) -> bool {
+    if filters.is_empty() {
+        return true;
+    }
+
+    let Ok(environ) = procfs::process::Process::new(pid as i32).and_then(|p| p.environ()) else {
+        return false;
+    };
+
+    let environ: HashMap<String, String> = environ
+        .into_iter()
+        .map(|(key, value)| (key.to_string_lossy().into_owned(), value.to_string_lossy().into_owned()))
+        .collect();
+    environ_matches(&environ, filters)
+}
+
+/// Whether an already-read environment satisfies every filter.
+pub fn environ_matches(environ: &HashMap<String, String>, filters: &[EnvFilter]) -> bool {
+    filters.iter().all(|filter| match environ.get(&filter.key) {
+        Some(value) => filter.value.as_ref().is_none_or(|expected| value == expected),
+        None => false,
+    })
+}
+
+pub fn in_directory(process: &sysinfo::Process, path: &Path) -> bool {
+    process.cwd().is_some_and(|cwd| cwd.starts_with(path))
+}
+
+/// Expands `~` and resolves relative paths (e.g. `.`) against the current directory.
+pub fn resolve_path(path: &str) -> PathBuf {
+    let expanded = match path.strip_prefix("~") {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => match env::var_os("HOME") {
+            Some(home) => PathBuf::from(home).join(rest.trim_start_matches('/')),
+            None => PathBuf::from(path),
+        },
+        _ => PathBuf::from(path),
+    };
+
+    fs::canonicalize(&expanded).unwrap_or(expanded)
+}
+
+/// Which processes hold each socket, by inode.
+fn socket_owners() -> HashMap<u64, Vec<u32>> {
+    let mut owners: HashMap<u64, Vec<u32>> = HashMap::new();
+    if let Ok(all) = procfs::process::all_processes() {
+        for process in all.flatten() {
+            let Ok(fds) = process.fd() else {
+                continue;
+            };
+            for fd in fds.flatten() {
+                if let FDTarget::Socket(inode) = fd.target {
+                    owners.entry(inode).or_default().push(process.pid as u32);
+                }
+            }
+        }
+    }
+    owners
+}
@@ -0,0 +1,204 @@
+//! `sensors`: temperatures and fan speeds from the kernel's hwmon drivers, the same data
+//! lm-sensors shows, without needing it installed.
+
+use anyhow::Result;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+use tabled::{Table, Tabled, settings::{Color, object::Cell}};
+
+use crate::output;
+
+/// Used when a sensor reports no limits of its own.
+const DEFAULT_HIGH_CELSIUS: f64 = 70.0;
+const DEFAULT_CRITICAL_CELSIUS: f64 = 85.0;
+
+#[derive(Tabled)]
+struct SensorRow {
+    #[tabled(rename = "Device")]
+    device: String,
+
+    #[tabled(rename = "Sensor")]
+    sensor: String,
+
+    #[tabled(rename = "Reading")]
+    reading: String,
+
+    #[tabled(rename = "High")]
+    high: String,
+
+    #[tabled(rename = "Critical")]
+    critical: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Level {
+    Normal,
+    High,
+    Critical,
+}
+
+struct Reading {
+    row: SensorRow,
+    level: Option<Level>,
+}
+
+/// Prints every temperature and fan sensor once, or (with `watch`) redrawn every `interval`.
+pub async fn show_sensors(interval: Duration, watch: bool) -> Result<()> {
+    loop {
+        let readings = read_sensors();
+        let text = if readings.is_empty() {
+            "No hwmon sensors found in /sys/class/hwmon\n".to_string()
+        } else {
+            render(readings)
+        };
+
+        if !watch {
+            print!("{}", text);
+            return Ok(());
+        }
+        output::redraw(&text);
+        tokio::time::sleep(interval).await;
+    }
+}
+
+fn render(readings: Vec<Reading>) -> String {
+    let levels: Vec<Option<Level>> = readings.iter().map(|reading| reading.level).collect();
+    let mut table = Table::new(readings.into_iter().map(|reading| reading.row));
+
+    if output::use_color() {
+        for (index, level) in levels.iter().enumerate() {
+            let color = match level {
+                Some(Level::Normal) => Color::FG_GREEN,
+                Some(Level::High) => Color::FG_YELLOW,
+                Some(Level::Critical) => Color::FG_RED,
+                None => continue,
+            };
+            // Row 0 is the header; column 2 is the reading
+            table.modify(Cell::new(index + 1, 2), color);
+        }
+    }
+
+    let mut text = format!("{}\n", table);
+    if levels.contains(&Some(Level::Critical)) {
+        text.push_str("\nWarning: some sensors are at or above their critical temperature\n");
+    }
+    text
+}
+
+fn read_sensors() -> Vec<Reading> {
+    let Ok(entries) = fs::read_dir("/sys/class/hwmon") else {
+        return Vec::new();
+    };
+
+    let mut devices: Vec<(u32, std::path::PathBuf)> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let index = entry.file_name().to_string_lossy().strip_prefix("hwmon")?.parse().ok()?;
+            Some((index, entry.path()))
+        })
+        .collect();
+    devices.sort();
+
+    let mut readings = Vec::new();
+    for (_, dir) in devices {
+        let device = device_name(&dir);
+        for (sensor, kind) in sensor_inputs(&dir) {
+            let read = |suffix: &str| read_number(&dir.join(format!("{}_{}", sensor, suffix)));
+            let Some(value) = read("input") else {
+                continue;
+            };
+            let label = read_trimmed(&dir.join(format!("{}_label", sensor))).unwrap_or_else(|| sensor.clone());
+
+            readings.push(match kind {
+                SensorKind::Temperature => {
+                    // Temperatures are in millidegrees Celsius
+                    let celsius = value / 1000.0;
+                    let high = read("max").map(|v| v / 1000.0).filter(|v| *v > 0.0);
+                    let critical = read("crit").map(|v| v / 1000.0).filter(|v| *v > 0.0);
+                    let level = if celsius >= critical.unwrap_or(DEFAULT_CRITICAL_CELSIUS) {
+                        Level::Critical
+                    } else if celsius >= high.unwrap_or(DEFAULT_HIGH_CELSIUS) {
+                        Level::High
+                    } else {
+                        Level::Normal
+                    };
+                    let format = |v: Option<f64>| v.map(|v| format!("{:.1}°C", v)).unwrap_or_else(|| "-".to_string());
+                    Reading {
+                        row: SensorRow {
+                            device: device.clone(),
+                            sensor: label,
+                            reading: format!("{:.1}°C", celsius),
+                            high: format(high),
+                            critical: format(critical),
+                        },
+                        level: Some(level),
+                    }
+                }
+                SensorKind::Fan => Reading {
+                    row: SensorRow {
+                        device: device.clone(),
+                        sensor: label,
+                        reading: format!("{:.0} RPM", value),
+                        high: read("max").map(|v| format!("{:.0} RPM", v)).unwrap_or_else(|| "-".to_string()),
+                        critical: "-".to_string(),
+                    },
+                    level: None,
+                },
+            });
+        }
+    }
+    readings
+}
+
+#[derive(Clone, Copy)]
+enum SensorKind {
+    Temperature,
+    Fan,
+}
+
+/// The `tempN` and `fanN` sensors of a hwmon device, in numeric order.
+fn sensor_inputs(dir: &Path) -> Vec<(String, SensorKind)> {
+    let Ok(files) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut sensors: Vec<(SensorKind, u32, String)> = files
+        .flatten()
+        .filter_map(|file| {
+            let name = file.file_name().to_string_lossy().to_string();
+            let sensor = name.strip_suffix("_input")?;
+            let (kind, number) = if let Some(number) = sensor.strip_prefix("temp") {
+                (SensorKind::Temperature, number)
+            } else {
+                (SensorKind::Fan, sensor.strip_prefix("fan")?)
+            };
+            Some((kind, number.parse().ok()?, sensor.to_string()))
+        })
+        .collect();
+    // Temperatures first, then fans
+    sensors.sort_by_key(|(kind, number, _)| (matches!(kind, SensorKind::Fan), *number));
+    sensors.into_iter().map(|(kind, _, sensor)| (sensor, kind)).collect()
+}
+
+/// What a hwmon device measures, e.g. "CPU (coretemp)" or "NVMe (Samsung SSD 980)".
+fn device_name(dir: &Path) -> String {
+    let driver = read_trimmed(&dir.join("name")).unwrap_or_else(|| "unknown".to_string());
+    match driver.as_str() {
+        "coretemp" | "k10temp" | "zenpower" | "cpu_thermal" => format!("CPU ({})", driver),
+        "amdgpu" | "radeon" | "nouveau" | "i915" | "xe" => format!("GPU ({})", driver),
+        "nvme" => match read_trimmed(&dir.join("device/model")) {
+            Some(model) => format!("NVMe ({})", model),
+            None => "NVMe".to_string(),
+        },
+        _ => driver,
+    }
+}
+
+fn read_trimmed(path: &Path) -> Option<String> {
+    fs::read_to_string(path).ok().map(|s| s.trim().to_string()).filter(|s| !s.is_empty())
+}
+
+fn read_number(path: &Path) -> Option<f64> {
+    read_trimmed(path)?.parse().ok()
+}
@@ -0,0 +1,49 @@
+use std::env;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionType {
+    X11,
+    Wayland,
+    Tty,
+    Unknown,
+}
+
+impl SessionType {
+    pub fn detect() -> Self {
+        match env::var("XDG_SESSION_TYPE").as_deref() {
+            Ok("x11") => return SessionType::X11,
+            Ok("wayland") => return SessionType::Wayland,
+            Ok("tty") => return SessionType::Tty,
+            _ => {}
+        }
+
+        if env::var_os("WAYLAND_DISPLAY").is_some() {
+            SessionType::Wayland
+        } else if env::var_os("DISPLAY").is_some() {
+            SessionType::X11
+        } else if env::var_os("SSH_CONNECTION").is_some() || env::var_os("TERM").is_some() {
+            SessionType::Tty
+        } else {
+            SessionType::Unknown
+        }
+    }
+
+    /// Whether X11 tools (xdotool, wmctrl) can reach a display in this session,
+    /// either natively or through XWayland.
+    pub fn has_x11_display(&self) -> bool {
+        match self {
+            SessionType::X11 => true,
+            SessionType::Wayland => env::var_os("DISPLAY").is_some(),
+            SessionType::Tty | SessionType::Unknown => false,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SessionType::X11 => "x11",
+            SessionType::Wayland => "wayland",
+            SessionType::Tty => "tty",
+            SessionType::Unknown => "unknown",
+        }
+    }
+}
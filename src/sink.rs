@@ -0,0 +1,223 @@
+//! Output sinks: where reports and alerts are delivered.
+//!
+//! Sinks are configured once by name in the `[sinks]` section of the config file and selected
+//! with `--sink NAME`. `stdout` and `desktop` always exist, and `file:PATH` can be used ad hoc.
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::config::Config;
+use crate::error::CliError;
+use crate::identity::ProcessKey;
+use crate::notify::{self, Action, ActionKind, Notification};
+use crate::paths;
+
+/// Set when stdout carries a data stream, such as `monitor --format ndjson`
+static STDOUT_RESERVED: AtomicBool = AtomicBool::new(false);
+
+/// Makes the `stdout` sink write to stderr, so messages stay out of a stream on stdout.
+pub fn reserve_stdout() {
+    STDOUT_RESERVED.store(true, Ordering::Relaxed);
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Urgency {
+    Low,
+    #[default]
+    Normal,
+    Critical,
+}
+
+impl Urgency {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Urgency::Low => "low",
+            Urgency::Normal => "normal",
+            Urgency::Critical => "critical",
+        }
+    }
+
+    fn syslog_priority(&self) -> &'static str {
+        match self {
+            Urgency::Low => "user.info",
+            Urgency::Normal => "user.notice",
+            Urgency::Critical => "user.crit",
+        }
+    }
+}
+
+/// Something to deliver: a one-line title and a (possibly multi-line) body.
+#[derive(Debug, Clone)]
+pub struct Message {
+    pub title: String,
+    pub body: String,
+    pub urgency: Urgency,
+    /// The running process the message is about, which notifications offer actions on
+    pub process: Option<ProcessKey>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)]
+pub enum SinkConfig {
+    Stdout,
+    File {
+        path: String,
+    },
+    /// Desktop notification over D-Bus, or via notify-send
+    Notification {
+        #[serde(default)]
+        urgency: Option<Urgency>,
+        #[serde(default)]
+        icon: Option<String>,
+        /// Buttons shown when the message is about a running process
+        #[serde(default = "default_actions")]
+        actions: Vec<ActionKind>,
+    },
+    /// JSON POST of `{"title", "body", "urgency"}` via curl
+    Webhook {
+        url: String,
+    },
+    /// Mail via the local sendmail
+    Email {
+        to: String,
+        #[serde(default)]
+        from: Option<String>,
+    },
+    Syslog {
+        #[serde(default)]
+        tag: Option<String>,
+    },
+}
+
+fn default_actions() -> Vec<ActionKind> {
+    vec![ActionKind::Switch]
+}
+
+impl SinkConfig {
+    /// Resolves a `--sink` argument: a configured name, a built-in, or `file:PATH`.
+    pub fn resolve(name: &str, config: &Config) -> Result<SinkConfig> {
+        if let Some(sink) = config.sinks.get(name) {
+            return Ok(sink.clone());
+        }
+
+        match name {
+            "stdout" => Ok(SinkConfig::Stdout),
+            "desktop" => Ok(SinkConfig::Notification { urgency: None, icon: None, actions: default_actions() }),
+            _ => match name.strip_prefix("file:") {
+                Some(path) => Ok(SinkConfig::File { path: path.to_string() }),
+                None => Err(CliError::not_found(format!(
+                    "Unknown sink '{}'; define it under [sinks.{}] in {}",
+                    name,
+                    name,
+                    Config::path().map(|p| p.display().to_string()).unwrap_or_else(|| "the config file".to_string())
+                ))),
+            },
+        }
+    }
+
+    pub fn send(&self, message: &Message) -> Result<()> {
+        match self {
+            SinkConfig::Stdout if STDOUT_RESERVED.load(Ordering::Relaxed) => {
+                eprintln!("{}\n{}", message.title, message.body);
+                Ok(())
+            }
+            SinkConfig::Stdout => {
+                println!("{}\n{}", message.title, message.body);
+                Ok(())
+            }
+            SinkConfig::File { path } => {
+                let path = paths::expand_home(path);
+                let mut file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&path)
+                    .with_context(|| format!("Failed to open {}", path.display()))?;
+                writeln!(file, "{}\n{}", message.title, message.body)?;
+                Ok(())
+            }
+            SinkConfig::Notification { urgency, icon, actions } => notify::send(&Notification {
+                summary: &message.title,
+                body: &message.body,
+                urgency: urgency.unwrap_or(message.urgency),
+                icon: icon.as_deref(),
+                actions: match message.process {
+                    Some(process) => actions.iter().map(|&kind| Action { kind, process }).collect(),
+                    None => Vec::new(),
+                },
+            }),
+            SinkConfig::Webhook { url } => {
+                let payload = serde_json::json!({
+                    "title": message.title,
+                    "body": message.body,
+                    "urgency": message.urgency.name(),
+                });
+                run(
+                    "curl",
+                    &["-fsS", "-X", "POST", "-H", "Content-Type: application/json", "--data-binary", "@-", url],
+                    Some(payload.to_string().into_bytes()),
+                )
+            }
+            SinkConfig::Email { to, from } => {
+                let mut mail = String::new();
+                if let Some(from) = from {
+                    mail.push_str(&format!("From: {}\n", from));
+                }
+                mail.push_str(&format!("To: {}\nSubject: {}\n\n{}\n", to, message.title, message.body));
+                run("sendmail", &["-t"], Some(mail.into_bytes()))
+            }
+            SinkConfig::Syslog { tag } => {
+                let text = format!("{}: {}", message.title, message.body.replace('\n', " | "));
+                run(
+                    "logger",
+                    &["-t", tag.as_deref().unwrap_or("top-helper"), "-p", message.urgency.syslog_priority(), &text],
+                    None,
+                )
+            }
+        }
+    }
+}
+
+/// Delivers a message to every named sink, defaulting to stdout when none are given.
+pub fn deliver(message: &Message, sink_names: &[String]) -> Result<()> {
+    if sink_names.is_empty() {
+        return SinkConfig::Stdout.send(message);
+    }
+
+    let config = Config::load()?;
+    for name in sink_names {
+        SinkConfig::resolve(name, &config)?
+            .send(message)
+            .with_context(|| format!("Failed to deliver to sink '{}'", name))?;
+    }
+
+    Ok(())
+}
+
+/// Runs a delivery tool, feeding it `stdin` if given, and fails unless it succeeds.
+pub fn run(program: &str, args: &[&str], stdin: Option<Vec<u8>>) -> Result<()> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(if stdin.is_some() { Stdio::piped() } else { Stdio::null() })
+        .stdout(Stdio::null())
+        .spawn()
+        .map_err(|err| match err.kind() {
+            std::io::ErrorKind::NotFound => CliError::backend_missing(format!("{} is not installed", program)),
+            _ => anyhow::Error::new(err).context(format!("Failed to run {}", program)),
+        })?;
+
+    if let (Some(input), Some(mut pipe)) = (stdin, child.stdin.take()) {
+        pipe.write_all(&input)?;
+    }
+
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(anyhow::anyhow!("{} exited with {}", program, status));
+    }
+
+    Ok(())
+}
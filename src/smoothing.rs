@@ -0,0 +1,54 @@
+//! Exponential smoothing of rates shown by the watch modes, so values don't jump around between
+//! refreshes and whatever is sorted by them keeps its place.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// Exponentially-weighted moving averages of several series, such as one per CPU or process.
+pub struct Smoother<K> {
+    /// Weight of the previous average: 0 shows raw samples, values near 1 change slowly
+    factor: f64,
+    averages: HashMap<K, f64>,
+}
+
+impl<K: Eq + Hash> Smoother<K> {
+    pub fn new(factor: f64) -> Self {
+        Smoother { factor, averages: HashMap::new() }
+    }
+
+    /// Folds a new sample into the series for `key` and returns its smoothed value. The first
+    /// sample of a series is returned as is.
+    pub fn update(&mut self, key: K, sample: f64) -> f64 {
+        let factor = self.factor;
+        let average = self
+            .averages
+            .entry(key)
+            .and_modify(|average| *average = *average * factor + sample * (1.0 - factor))
+            .or_insert(sample);
+        *average
+    }
+
+    /// The smoothed value of the series for `key`, if it has had a sample.
+    pub fn get(&self, key: &K) -> Option<f64> {
+        self.averages.get(key).copied()
+    }
+
+    /// Series seen so far, including ones missing from the latest samples.
+    pub fn keys(&self) -> impl Iterator<Item = &K> {
+        self.averages.keys()
+    }
+
+    /// Forgets series that are no longer worth tracking, given their key and smoothed value,
+    /// e.g. processes that have exited or gone quiet.
+    pub fn retain(&mut self, mut keep: impl FnMut(&K, f64) -> bool) {
+        self.averages.retain(|key, average| keep(key, *average));
+    }
+}
+
+/// Parses a `--smoothing` factor between 0 (off) and 1 (exclusive).
+pub fn parse_factor(s: &str) -> Result<f64, String> {
+    match s.trim().parse::<f64>() {
+        Ok(factor) if (0.0..1.0).contains(&factor) => Ok(factor),
+        _ => Err(format!("invalid smoothing factor '{}' (expected a number from 0 up to, but not including, 1)", s)),
+    }
+}
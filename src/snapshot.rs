@@ -0,0 +1,214 @@
+//! Named snapshots of the process set, to see later what is new, gone, or has grown since, e.g.
+//! what an installer left running or how now compares to right after boot.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use sysinfo::System;
+use tabled::{Table, Tabled, settings::{Width, location::ByColumnName}};
+
+use crate::error::CliError;
+use crate::identity::ProcessKey;
+use crate::paths;
+use crate::redact;
+use crate::output::{self, format_memory};
+
+/// Growth below this is noise from normal allocation, not worth listing.
+const MIN_GROWTH_BYTES: u64 = 10 * 1024 * 1024;
+
+#[derive(Serialize, Deserialize)]
+struct Snapshot {
+    /// Unix time the snapshot was taken
+    taken_at: i64,
+    processes: Vec<SnapshotProcess>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct SnapshotProcess {
+    pid: u32,
+    start_time: u64,
+    name: String,
+    #[serde(default)]
+    cmd: Vec<String>,
+    memory_bytes: u64,
+}
+
+impl SnapshotProcess {
+    fn key(&self) -> ProcessKey {
+        ProcessKey { pid: self.pid, start_time: self.start_time }
+    }
+
+    fn command(&self) -> String {
+        if self.cmd.is_empty() { self.name.clone() } else { self.cmd.join(" ") }
+    }
+}
+
+#[derive(Tabled)]
+struct ChangeRow {
+    #[tabled(rename = "Change")]
+    change: &'static str,
+
+    #[tabled(rename = "PID")]
+    pid: u32,
+
+    #[tabled(rename = "Name")]
+    name: String,
+
+    #[tabled(rename = "Memory")]
+    memory: String,
+
+    #[tabled(rename = "Command")]
+    command: String,
+}
+
+/// Saves the current process set under `name`, replacing an earlier snapshot of that name.
+pub fn save(name: &str) -> Result<()> {
+    let mut system = System::new_all();
+    system.refresh_all();
+
+    let snapshot = Snapshot { taken_at: chrono::Utc::now().timestamp(), processes: current(&system) };
+    let path = snapshot_path(name)?;
+    let json = serde_json::to_string_pretty(&snapshot)?;
+    fs::write(&path, json).with_context(|| format!("Failed to write {}", path.display()))?;
+
+    println!("Saved {} processes to snapshot '{}' ({})", snapshot.processes.len(), name, path.display());
+    Ok(())
+}
+
+/// Shows which processes are new, gone, exec'd into something else, or grown by more than
+/// 10 MB since the snapshot `name` was taken.
+pub fn diff(name: &str) -> Result<()> {
+    let path = snapshot_path(name)?;
+    if !path.exists() {
+        return Err(CliError::not_found(format!(
+            "No snapshot named '{}'; take one with `top-helper snapshot save {}`",
+            name, name
+        )));
+    }
+    let content = fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let snapshot: Snapshot =
+        serde_json::from_str(&content).with_context(|| format!("Invalid snapshot file {}", path.display()))?;
+
+    let mut system = System::new_all();
+    system.refresh_all();
+    // Keyed by PID and start time: a reused PID is a new process, while one that exec'd
+    // since is the same process under a new name
+    let before: BTreeMap<ProcessKey, SnapshotProcess> = snapshot.processes.into_iter().map(|p| (p.key(), p)).collect();
+    let now: BTreeMap<ProcessKey, SnapshotProcess> = current(&system).into_iter().map(|p| (p.key(), p)).collect();
+
+    let mut rows = Vec::new();
+    for (key, process) in &now {
+        match before.get(key) {
+            None => rows.push(row("new", process, format_memory(process.memory_bytes))),
+            Some(old) => {
+                let mut name = process.name.clone();
+                if old.name != process.name {
+                    name = format!("{} (was {})", process.name, old.name);
+                }
+                let grew = process.memory_bytes.saturating_sub(old.memory_bytes);
+                if grew >= MIN_GROWTH_BYTES {
+                    let memory = format!("{} -> {}", format_memory(old.memory_bytes), format_memory(process.memory_bytes));
+                    rows.push(ChangeRow { name, ..row("grown", process, memory) });
+                } else if old.name != process.name {
+                    rows.push(ChangeRow { name, ..row("exec'd", process, format_memory(process.memory_bytes)) });
+                }
+            }
+        }
+    }
+    for (key, process) in &before {
+        if !now.contains_key(key) {
+            rows.push(row("gone", process, format_memory(process.memory_bytes)));
+        }
+    }
+
+    let age = (chrono::Utc::now().timestamp() - snapshot.taken_at).max(0) as u64;
+    println!(
+        "Changes since snapshot '{}' taken {} ({} ago):",
+        name,
+        output::format_time(snapshot.taken_at),
+        output::format_duration(age * 1000)
+    );
+    if rows.is_empty() {
+        println!("  (none)");
+        return Ok(());
+    }
+
+    let counts = ["new", "gone", "grown", "exec'd"]
+        .iter()
+        .map(|change| (change, rows.iter().filter(|row| row.change == *change).count()))
+        .filter(|(_, count)| *count > 0)
+        .map(|(change, count)| format!("{} {}", count, change))
+        .collect::<Vec<_>>();
+
+    let mut table = Table::new(rows);
+    table.modify(ByColumnName::new("Command"), Width::truncate(60).suffix("..."));
+    println!("{}", table);
+    println!("{}", counts.join(", "));
+
+    Ok(())
+}
+
+/// Lists saved snapshots, newest first.
+pub fn list() -> Result<()> {
+    let dir = snapshots_dir()?;
+    let mut snapshots: Vec<(i64, String, usize)> = Vec::new();
+    for entry in fs::read_dir(&dir).with_context(|| format!("Failed to read {}", dir.display()))? {
+        let path = entry?.path();
+        let (Some(name), Some("json")) = (path.file_stem(), path.extension().and_then(|ext| ext.to_str())) else {
+            continue;
+        };
+        let Some(snapshot) = fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<Snapshot>(&content).ok())
+        else {
+            continue;
+        };
+        snapshots.push((snapshot.taken_at, name.to_string_lossy().to_string(), snapshot.processes.len()));
+    }
+
+    if snapshots.is_empty() {
+        println!("No snapshots saved; take one with `top-helper snapshot save NAME`");
+        return Ok(());
+    }
+    snapshots.sort_by_key(|(taken_at, _, _)| std::cmp::Reverse(*taken_at));
+    for (taken_at, name, count) in snapshots {
+        println!("{:<24} {}  {} processes", name, output::format_time(taken_at), count);
+    }
+    Ok(())
+}
+
+/// Every process except top-helper itself, which would otherwise show up as gone in every diff.
+fn current(system: &System) -> Vec<SnapshotProcess> {
+    let own = std::process::id();
+    system
+        .processes()
+        .values()
+        .filter(|p| p.thread_kind().is_none() && p.pid().as_u32() != own)
+        .map(|process| SnapshotProcess {
+            pid: process.pid().as_u32(),
+            start_time: process.start_time(),
+            name: process.name().to_string(),
+            cmd: redact::command(process.cmd()),
+            memory_bytes: process.memory(),
+        })
+        .collect()
+}
+
+fn row(change: &'static str, process: &SnapshotProcess, memory: String) -> ChangeRow {
+    ChangeRow { change, pid: process.pid, name: process.name.clone(), memory, command: process.command() }
+}
+
+fn snapshots_dir() -> Result<PathBuf> {
+    let dir = paths::data_dir()?.join("snapshots");
+    fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    Ok(dir)
+}
+
+fn snapshot_path(name: &str) -> Result<PathBuf> {
+    if name.is_empty() || name.starts_with('.') || name.contains('/') {
+        return Err(anyhow::anyhow!("Invalid snapshot name '{}'; use letters, digits, '-', or '_'", name));
+    }
+    Ok(snapshots_dir()?.join(format!("{}.json", name)))
+}
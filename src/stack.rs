@@ -0,0 +1,330 @@
+//! `stack`: why a process is blocked or spinning, from what the kernel reports about each of its
+//! threads: run state, the kernel function it sleeps in (`wchan`), its kernel stack (root only),
+//! and CPU time over a short sample. `--native` adds user-space stacks from `eu-stack` or `gdb`.
+
+use anyhow::{Context, Result};
+use procfs::process::{Process, Task};
+use std::collections::BTreeMap;
+use std::fs;
+use std::process::Command;
+use std::time::Duration;
+use sysinfo::System;
+use tabled::{Table, Tabled, settings::{Width, location::ByColumnName}};
+
+use crate::error::CliError;
+use crate::selector::Selection;
+use crate::window::command_exists;
+
+/// How long per-thread CPU time is measured over.
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Per-thread CPU usage from which a thread counts as spinning.
+const SPINNING_PERCENT: f64 = 50.0;
+
+/// Kernel functions threads commonly sleep in, by prefix, and what waiting there means.
+const WAIT_REASONS: &[(&str, &str)] = &[
+    ("futex", "waiting on a lock or condition variable"),
+    ("do_epoll_wait", "waiting for events"),
+    ("ep_poll", "waiting for events"),
+    ("do_select", "waiting for events"),
+    ("do_sys_poll", "waiting for events"),
+    ("poll_schedule_timeout", "waiting for events"),
+    ("hrtimer_nanosleep", "sleeping on a timer"),
+    ("do_nanosleep", "sleeping on a timer"),
+    ("pipe_read", "waiting to read from a pipe"),
+    ("pipe_write", "waiting for room in a full pipe"),
+    ("pipe_wait", "waiting on a pipe"),
+    ("n_tty_read", "waiting for terminal input"),
+    ("wait_woken", "waiting on a socket or terminal"),
+    ("unix_stream_read", "waiting to read from a Unix socket"),
+    ("sk_wait_data", "waiting to read from a socket"),
+    ("inet_csk_accept", "waiting for incoming connections"),
+    ("do_wait", "waiting for a child process to exit"),
+    ("kernel_wait4", "waiting for a child process to exit"),
+    ("io_schedule", "waiting for disk I/O"),
+    ("folio_wait", "waiting for disk I/O"),
+    ("wait_on_page", "waiting for disk I/O"),
+    ("jbd2", "waiting for the filesystem journal"),
+    ("nfs", "waiting on NFS"),
+    ("do_sigtimedwait", "waiting for a signal"),
+    ("sigsuspend", "waiting for a signal"),
+    ("do_signal_stop", "stopped by a signal"),
+    ("ptrace_stop", "stopped by a debugger"),
+    ("io_uring", "waiting for io_uring completions"),
+    ("inotify", "waiting for file change events"),
+];
+
+#[derive(Tabled)]
+struct ThreadRow {
+    #[tabled(rename = "TID")]
+    tid: i32,
+
+    #[tabled(rename = "Name")]
+    name: String,
+
+    #[tabled(rename = "State")]
+    state: String,
+
+    #[tabled(rename = "CPU %")]
+    cpu_percent: String,
+
+    #[tabled(rename = "Syscall")]
+    syscall: String,
+
+    #[tabled(rename = "Waiting In")]
+    wchan: String,
+
+    #[tabled(rename = "Meaning")]
+    meaning: String,
+}
+
+struct ThreadState {
+    tid: i32,
+    name: String,
+    state: char,
+    cpu_percent: f64,
+    /// Kernel function the thread sleeps in, or None while it runs
+    wchan: Option<String>,
+    /// Kernel stack frames as function names, when readable (root only)
+    kernel_stack: Vec<String>,
+    /// Name or number of the system call in progress
+    syscall: Option<String>,
+}
+
+impl ThreadState {
+    fn meaning(&self) -> String {
+        match self.state {
+            'R' if self.syscall.is_none() => return "running in user space".to_string(),
+            'R' => return "running in the kernel".to_string(),
+            'D' => {
+                let reason = self.wchan.as_deref().and_then(wait_reason).unwrap_or("in the kernel");
+                return format!("blocked uninterruptibly, {}", reason);
+            }
+            'T' | 't' => return "stopped".to_string(),
+            'Z' => return "exited, not yet reaped by its parent".to_string(),
+            _ => {}
+        }
+        self.wchan
+            .as_deref()
+            .and_then(wait_reason)
+            .or_else(|| self.kernel_stack.iter().find_map(|frame| wait_reason(frame)))
+            .unwrap_or("sleeping")
+            .to_string()
+    }
+}
+
+/// Prints each thread's state with a diagnosis, followed by kernel stacks (grouped by identical
+/// stack) when `kernel` is set and user-space stacks when `native` is set.
+pub async fn show_stack(identifier: &str, kernel: bool, native: bool) -> Result<()> {
+    let selection = Selection::parse(identifier)?;
+    let mut system = System::new_all();
+    system.refresh_all();
+    let process = selection.resolve_unique(&system)?;
+    let pid = process.pid().as_u32();
+    let name = process.name().to_string();
+
+    let threads = sample_threads(pid).await?;
+    println!("PID {} ({}): {} thread{}", pid, name, threads.len(), if threads.len() == 1 { "" } else { "s" });
+    println!("{}\n", diagnosis(&threads));
+
+    let rows: Vec<ThreadRow> = threads
+        .iter()
+        .map(|thread| ThreadRow {
+            tid: thread.tid,
+            name: thread.name.clone(),
+            state: thread.state.to_string(),
+            cpu_percent: format!("{:.1}", thread.cpu_percent),
+            syscall: thread.syscall.clone().unwrap_or_else(|| "-".to_string()),
+            wchan: thread.wchan.clone().unwrap_or_else(|| "-".to_string()),
+            meaning: thread.meaning(),
+        })
+        .collect();
+    let mut table = Table::new(rows);
+    table.modify(ByColumnName::new("Name"), Width::truncate(16));
+    println!("{}", table);
+
+    if kernel {
+        print_kernel_stacks(&threads);
+    }
+    if native {
+        let stacks = native_stacks(pid)?;
+        println!("\nUser-space stacks:");
+        print!("{}", stacks);
+    }
+    Ok(())
+}
+
+/// Reads every thread twice, `SAMPLE_INTERVAL` apart, for its CPU usage in between.
+async fn sample_threads(pid: u32) -> Result<Vec<ThreadState>> {
+    let process = Process::new(pid as i32).with_context(|| format!("PID {} is gone", pid))?;
+    let cpu_ticks = |task: &Task| task.stat().map(|stat| stat.utime + stat.stime).unwrap_or(0);
+    let before: BTreeMap<i32, u64> = process.tasks()?.flatten().map(|task| (task.tid, cpu_ticks(&task))).collect();
+    tokio::time::sleep(SAMPLE_INTERVAL).await;
+
+    let ticks_per_second = procfs::ticks_per_second() as f64;
+    let mut threads = Vec::new();
+    for task in process.tasks()?.flatten() {
+        let Ok(stat) = task.stat() else {
+            continue;
+        };
+        let ticks = (stat.utime + stat.stime).saturating_sub(before.get(&task.tid).copied().unwrap_or(stat.utime + stat.stime));
+        let dir = format!("/proc/{}/task/{}", pid, task.tid);
+        let kernel_stack = kernel_stack(&dir);
+        threads.push(ThreadState {
+            tid: task.tid,
+            name: stat.comm.clone(),
+            state: stat.state,
+            cpu_percent: ticks as f64 / ticks_per_second / SAMPLE_INTERVAL.as_secs_f64() * 100.0,
+            wchan: fs::read_to_string(format!("{}/wchan", dir))
+                .ok()
+                .map(|wchan| wchan.trim().to_string())
+                .filter(|wchan| !wchan.is_empty() && wchan != "0"),
+            syscall: syscall(&dir, &kernel_stack),
+            kernel_stack,
+        });
+    }
+    if threads.is_empty() {
+        return Err(CliError::permission(format!("The threads of PID {} are not readable", pid)));
+    }
+    Ok(threads)
+}
+
+/// One line saying what the process as a whole is up to.
+fn diagnosis(threads: &[ThreadState]) -> String {
+    if let Some(blocked) = threads.iter().find(|thread| thread.state == 'D') {
+        return format!("Blocked: thread {} ({}) is {}.", blocked.tid, blocked.name, blocked.meaning());
+    }
+
+    let mut spinning: Vec<&ThreadState> = threads.iter().filter(|t| t.cpu_percent >= SPINNING_PERCENT).collect();
+    if !spinning.is_empty() {
+        spinning.sort_by(|a, b| b.cpu_percent.total_cmp(&a.cpu_percent));
+        let busiest: Vec<String> = spinning
+            .iter()
+            .take(3)
+            .map(|t| format!("{} ({}, {:.0}%, {})", t.tid, t.name, t.cpu_percent, t.meaning()))
+            .collect();
+        let threads = if spinning.len() == 1 { "thread" } else { "threads" };
+        return format!("Busy: {} {} on CPU: {}.", spinning.len(), threads, busiest.join("; "));
+    }
+
+    // Idle: the most common reason threads are waiting
+    let mut reasons: BTreeMap<String, usize> = BTreeMap::new();
+    for thread in threads {
+        *reasons.entry(thread.meaning()).or_default() += 1;
+    }
+    let mut reasons: Vec<(String, usize)> = reasons.into_iter().collect();
+    reasons.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+    let parts: Vec<String> = reasons.iter().map(|(reason, count)| format!("{} {}", count, reason)).collect();
+    format!("Idle: {}.", parts.join(", "))
+}
+
+/// Kernel stacks, with threads sharing the same stack listed together.
+fn print_kernel_stacks(threads: &[ThreadState]) {
+    if threads.iter().all(|thread| thread.kernel_stack.is_empty()) {
+        println!("\nKernel stacks are not readable (they need root).");
+        return;
+    }
+
+    let mut groups: Vec<(Vec<String>, Vec<i32>)> = Vec::new();
+    for thread in threads.iter().filter(|thread| !thread.kernel_stack.is_empty()) {
+        match groups.iter_mut().find(|(stack, _)| *stack == thread.kernel_stack) {
+            Some((_, tids)) => tids.push(thread.tid),
+            None => groups.push((thread.kernel_stack.clone(), vec![thread.tid])),
+        }
+    }
+    groups.sort_by_key(|(_, tids)| std::cmp::Reverse(tids.len()));
+
+    for (stack, tids) in groups {
+        let tids: Vec<String> = tids.iter().map(|tid| tid.to_string()).collect();
+        println!("\nKernel stack of thread{} {}:", if tids.len() == 1 { "" } else { "s" }, tids.join(", "));
+        for frame in stack {
+            println!("  {}", frame);
+        }
+    }
+}
+
+/// User-space stacks of every thread from `eu-stack`, or `gdb` when elfutils isn't installed.
+/// Both attach with ptrace, so they need the same user (and a permissive ptrace_scope) or root.
+fn native_stacks(pid: u32) -> Result<String> {
+    let pid = pid.to_string();
+    let output = if command_exists("eu-stack") {
+        Command::new("eu-stack").args(["-p", &pid]).output().context("Failed to run eu-stack")?
+    } else if command_exists("gdb") {
+        Command::new("gdb")
+            .args(["-batch", "-nx", "-ex", "thread apply all bt", "-p", &pid])
+            .output()
+            .context("Failed to run gdb")?
+    } else {
+        return Err(CliError::backend_missing("--native needs eu-stack (elfutils) or gdb"));
+    };
+
+    if !output.status.success() && output.stdout.is_empty() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(CliError::permission(format!("Could not attach to PID {}: {}", pid, stderr.trim())));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Function names from `/proc/<pid>/task/<tid>/stack`, e.g. `hrtimer_nanosleep` for
+/// `[<0>] hrtimer_nanosleep+0x7a/0x100`.
+pub fn kernel_stack(dir: &str) -> Vec<String> {
+    fs::read_to_string(format!("{}/stack", dir))
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| line.split_whitespace().nth(1))
+        .map(|frame| frame.split('+').next().unwrap_or(frame).to_string())
+        .collect()
+}
+
+/// The system call in progress: named from the kernel stack's syscall entry point
+/// (`__x64_sys_clock_nanosleep`) when readable, otherwise from the number in
+/// /proc/<pid>/syscall.
+pub fn syscall(dir: &str, kernel_stack: &[String]) -> Option<String> {
+    let named = kernel_stack.iter().find_map(|frame| {
+        ["__x64_sys_", "__arm64_sys_", "__ia32_sys_", "__se_sys_", "__do_sys_"]
+            .iter()
+            .find_map(|prefix| frame.strip_prefix(prefix))
+    });
+    if let Some(name) = named {
+        return Some(name.to_string());
+    }
+    let content = fs::read_to_string(format!("{}/syscall", dir)).ok()?;
+    let number = content.split_whitespace().next()?;
+    // "running" while the thread runs in user space, -1 when it isn't in a system call
+    let number = number.parse::<i64>().ok().filter(|number| *number >= 0)?;
+    Some(syscall_name(number).map(str::to_string).unwrap_or_else(|| format!("#{}", number)))
+}
+
+/// Names of the system calls threads are most often found blocked in, by number, for when the
+/// kernel stack isn't readable.
+#[cfg(target_arch = "x86_64")]
+const SYSCALL_NAMES: &[(i64, &str)] = &[
+    (0, "read"), (1, "write"), (3, "close"), (7, "poll"), (16, "ioctl"), (17, "pread64"),
+    (18, "pwrite64"), (19, "readv"), (20, "writev"), (23, "select"), (34, "pause"), (35, "nanosleep"),
+    (42, "connect"), (43, "accept"), (44, "sendto"), (45, "recvfrom"), (46, "sendmsg"), (47, "recvmsg"),
+    (61, "wait4"), (72, "fcntl"), (73, "flock"), (74, "fsync"), (75, "fdatasync"), (128, "rt_sigtimedwait"),
+    (130, "rt_sigsuspend"), (202, "futex"), (230, "clock_nanosleep"), (232, "epoll_wait"), (257, "openat"),
+    (270, "pselect6"), (271, "ppoll"), (281, "epoll_pwait"), (288, "accept4"), (426, "io_uring_enter"),
+    (441, "epoll_pwait2"),
+];
+
+#[cfg(target_arch = "aarch64")]
+const SYSCALL_NAMES: &[(i64, &str)] = &[
+    (22, "epoll_pwait"), (25, "fcntl"), (29, "ioctl"), (32, "flock"), (56, "openat"), (57, "close"),
+    (63, "read"), (64, "write"), (65, "readv"), (66, "writev"), (67, "pread64"), (68, "pwrite64"),
+    (72, "pselect6"), (73, "ppoll"), (82, "fsync"), (83, "fdatasync"), (98, "futex"), (101, "nanosleep"),
+    (115, "clock_nanosleep"), (133, "rt_sigsuspend"), (137, "rt_sigtimedwait"), (202, "accept"),
+    (203, "connect"), (206, "sendto"), (207, "recvfrom"), (211, "sendmsg"), (212, "recvmsg"),
+    (242, "accept4"), (260, "wait4"), (426, "io_uring_enter"), (441, "epoll_pwait2"),
+];
+
+#[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+const SYSCALL_NAMES: &[(i64, &str)] = &[];
+
+fn syscall_name(number: i64) -> Option<&'static str> {
+    SYSCALL_NAMES.iter().find(|(n, _)| *n == number).map(|(_, name)| *name)
+}
+
+fn wait_reason(function: &str) -> Option<&'static str> {
+    WAIT_REASONS.iter().find(|(prefix, _)| function.starts_with(prefix)).map(|(_, reason)| *reason)
+}
@@ -0,0 +1,129 @@
+//! `status`: a one-line summary of CPU and memory use with the top consumers, for status bars
+//! that poll a command (Waybar, Polybar, i3blocks, i3status wrappers).
+
+use anyhow::Result;
+use std::collections::HashMap;
+use std::time::Duration;
+use sysinfo::System;
+
+use crate::output::{StatusFormat, format_memory};
+
+/// How long CPU usage is measured over; short enough for a bar polling every few seconds.
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Usage (CPU or memory, in percent) from which the bar turns to warning and critical.
+const WARNING_PERCENT: f64 = 80.0;
+const CRITICAL_PERCENT: f64 = 95.0;
+
+struct Status {
+    cpu_percent: f64,
+    used_memory: u64,
+    total_memory: u64,
+    /// Processes grouped by name: (name, CPU percent of all cores, resident bytes)
+    consumers: Vec<(String, f64, u64)>,
+}
+
+impl Status {
+    fn memory_percent(&self) -> f64 {
+        if self.total_memory == 0 { 0.0 } else { self.used_memory as f64 * 100.0 / self.total_memory as f64 }
+    }
+
+    /// "normal", "warning", or "critical", by whichever of CPU and memory is higher.
+    fn class(&self) -> &'static str {
+        let peak = self.cpu_percent.max(self.memory_percent());
+        if peak >= CRITICAL_PERCENT {
+            "critical"
+        } else if peak >= WARNING_PERCENT {
+            "warning"
+        } else {
+            "normal"
+        }
+    }
+
+    fn text(&self) -> String {
+        format!("CPU {:.0}% MEM {:.0}%", self.cpu_percent, self.memory_percent())
+    }
+
+    /// The top `top` consumers of CPU and of memory, one per line.
+    fn tooltip(&self, top: usize) -> String {
+        let mut by_cpu = self.consumers.clone();
+        by_cpu.sort_by(|a, b| b.1.total_cmp(&a.1));
+        let mut by_memory = self.consumers.clone();
+        by_memory.sort_by_key(|(_, _, memory)| std::cmp::Reverse(*memory));
+
+        let mut lines = vec![format!("CPU {:.0}%", self.cpu_percent)];
+        lines.extend(
+            by_cpu
+                .iter()
+                .take(top)
+                .filter(|(_, cpu, _)| *cpu >= 0.5)
+                .map(|(name, cpu, _)| format!("  {:.0}%  {}", cpu, name)),
+        );
+        lines.push(format!(
+            "Memory {} of {}",
+            format_memory(self.used_memory),
+            format_memory(self.total_memory)
+        ));
+        lines.extend(by_memory.iter().take(top).map(|(name, _, memory)| format!("  {}  {}", format_memory(*memory), name)));
+        lines.join("\n")
+    }
+}
+
+/// Prints the status once in `format`, listing the `top` biggest consumers in the tooltip.
+pub async fn show_status(format: StatusFormat, top: usize) -> Result<()> {
+    let status = sample().await;
+
+    match format {
+        StatusFormat::Text => println!("{}\n{}", status.text(), status.tooltip(top)),
+        StatusFormat::Waybar => {
+            let json = serde_json::json!({
+                "text": status.text(),
+                "tooltip": status.tooltip(top),
+                "class": status.class(),
+                "percentage": status.cpu_percent.max(status.memory_percent()).round() as u64,
+            });
+            println!("{}", json);
+        }
+        StatusFormat::I3blocks => {
+            // Full text, short text, and color, one per line
+            println!("{}", status.text());
+            println!("{:.0}% {:.0}%", status.cpu_percent, status.memory_percent());
+            match status.class() {
+                "critical" => println!("#FF5555"),
+                "warning" => println!("#FFB86C"),
+                _ => {}
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn sample() -> Status {
+    let mut system = System::new();
+    system.refresh_cpu();
+    system.refresh_processes();
+    tokio::time::sleep(SAMPLE_INTERVAL.max(sysinfo::MINIMUM_CPU_UPDATE_INTERVAL)).await;
+    system.refresh_cpu();
+    system.refresh_processes();
+    system.refresh_memory();
+
+    let own = std::process::id();
+    let cores = system.cpus().len().max(1) as f64;
+    let mut consumers: HashMap<String, (f64, u64)> = HashMap::new();
+    for process in system.processes().values() {
+        if process.thread_kind().is_some() || process.pid().as_u32() == own {
+            continue;
+        }
+        let entry = consumers.entry(process.name().to_string()).or_default();
+        entry.0 += process.cpu_usage() as f64 / cores;
+        entry.1 += process.memory();
+    }
+
+    Status {
+        cpu_percent: system.global_cpu_info().cpu_usage() as f64,
+        used_memory: system.used_memory(),
+        total_memory: system.total_memory(),
+        consumers: consumers.into_iter().map(|(name, (cpu, memory))| (name, cpu, memory)).collect(),
+    }
+}
+
@@ -0,0 +1,213 @@
+//! Window backend speaking the i3 IPC protocol, shared by Sway (Wayland) and i3 (X11).
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::env;
+use std::io::{Read, Write};
+use std::os::unix::net::UnixStream;
+use std::process::Command;
+
+use crate::window::{self, Backend, Geometry, WindowInfo, WindowPlacement};
+
+const MAGIC: &[u8] = b"i3-ipc";
+const RUN_COMMAND: u32 = 0;
+const GET_TREE: u32 = 4;
+
+#[derive(Debug, Deserialize)]
+struct Node {
+    id: i64,
+    #[serde(rename = "type")]
+    kind: Option<String>,
+    name: Option<String>,
+    rect: Option<Rect>,
+    #[serde(default)]
+    focused: bool,
+    // Only Sway reports the client PID; i3 nodes carry an X11 window id instead
+    pid: Option<u32>,
+    app_id: Option<String>,
+    window: Option<u64>,
+    window_properties: Option<WindowProperties>,
+    #[serde(default)]
+    nodes: Vec<Node>,
+    #[serde(default)]
+    floating_nodes: Vec<Node>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Rect {
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+}
+
+#[derive(Debug, Deserialize)]
+struct WindowProperties {
+    class: Option<String>,
+    title: Option<String>,
+}
+
+pub fn is_running() -> bool {
+    if env::var_os("SWAYSOCK").is_some() || env::var_os("I3SOCK").is_some() {
+        return true;
+    }
+
+    // i3 does not always export I3SOCK, but the socket can still be found via `i3 --get-socketpath`
+    env::var("XDG_CURRENT_DESKTOP").is_ok_and(|desktop| desktop.eq_ignore_ascii_case("i3"))
+}
+
+pub fn list_windows() -> Result<Vec<WindowInfo>> {
+    let tree = tree()?;
+    let mut windows = Vec::new();
+    collect_windows(&tree, &mut windows);
+    Ok(windows)
+}
+
+/// The window that has focus, if any does.
+pub fn focused_window() -> Result<Option<WindowInfo>> {
+    let tree = tree()?;
+    let Some(id) = focused_id(&tree) else {
+        return Ok(None);
+    };
+    let mut windows = Vec::new();
+    collect_windows(&tree, &mut windows);
+    Ok(windows.into_iter().find(|window| window.window_id == id.to_string()))
+}
+
+fn focused_id(node: &Node) -> Option<i64> {
+    if node.focused {
+        return Some(node.id);
+    }
+    node.nodes.iter().chain(&node.floating_nodes).find_map(focused_id)
+}
+
+/// Workspace, position, and focus of the container `con_id`.
+pub fn placement(con_id: &str) -> Result<WindowPlacement> {
+    let tree = tree()?;
+    let id: i64 = con_id.parse().with_context(|| format!("Invalid container id {}", con_id))?;
+    find_placement(&tree, id, None).with_context(|| format!("i3/Sway has no container {}", con_id))
+}
+
+/// Searches the tree for the container, remembering the workspace on the way down.
+fn find_placement(node: &Node, id: i64, workspace: Option<&str>) -> Option<WindowPlacement> {
+    let workspace = if node.kind.as_deref() == Some("workspace") { node.name.as_deref() } else { workspace };
+    if node.id == id {
+        return Some(WindowPlacement {
+            workspace: workspace.map(str::to_string),
+            geometry: node.rect.as_ref().map(|rect| Geometry { x: rect.x, y: rect.y, width: rect.width, height: rect.height }),
+            focused: Some(node.focused),
+        });
+    }
+    node.nodes.iter().chain(&node.floating_nodes).find_map(|child| find_placement(child, id, workspace))
+}
+
+fn tree() -> Result<Node> {
+    let response = request(GET_TREE, "")?;
+    serde_json::from_slice(&response).context("Failed to parse i3/Sway tree")
+}
+
+pub fn focus_window(con_id: &str) -> Result<()> {
+    run_on(con_id, "focus", "focus")
+}
+
+/// Moves the window to the scratchpad, the closest i3 and Sway have to minimizing it.
+pub fn minimize_window(con_id: &str) -> Result<()> {
+    run_on(con_id, "move scratchpad", "minimize")
+}
+
+fn run_on(con_id: &str, command: &str, verb: &str) -> Result<()> {
+    let response = request(RUN_COMMAND, &format!("[con_id={}] {}", con_id, command))?;
+    let results: Vec<serde_json::Value> =
+        serde_json::from_slice(&response).context("Failed to parse i3/Sway command reply")?;
+
+    if results.iter().all(|r| r["success"].as_bool() == Some(true)) {
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!("i3/Sway refused to {} container {}", verb, con_id))
+    }
+}
+
+fn collect_windows(node: &Node, windows: &mut Vec<WindowInfo>) {
+    let is_window = node.app_id.is_some() || node.window.is_some();
+    if is_window {
+        let pid = node
+            .pid
+            .or_else(|| node.window.and_then(|w| window::get_window_pid(&w.to_string(), None).ok()));
+
+        if let Some(pid) = pid {
+            let properties = node.window_properties.as_ref();
+            let class = node
+                .app_id
+                .clone()
+                .or_else(|| properties.and_then(|p| p.class.clone()))
+                .unwrap_or_else(|| "Unknown".to_string());
+            let title = node
+                .name
+                .clone()
+                .or_else(|| properties.and_then(|p| p.title.clone()))
+                .unwrap_or_default();
+
+            windows.push(WindowInfo {
+                window_id: node.id.to_string(),
+                title,
+                class,
+                pid,
+                backend: Backend::Sway,
+                display: None,
+            });
+        }
+    }
+
+    for child in node.nodes.iter().chain(&node.floating_nodes) {
+        collect_windows(child, windows);
+    }
+}
+
+fn request(message_type: u32, payload: &str) -> Result<Vec<u8>> {
+    let path = socket_path()?;
+    let mut stream = UnixStream::connect(&path)
+        .with_context(|| format!("Failed to connect to i3/Sway socket {}", path))?;
+
+    let mut message = Vec::with_capacity(MAGIC.len() + 8 + payload.len());
+    message.extend_from_slice(MAGIC);
+    message.extend_from_slice(&(payload.len() as u32).to_ne_bytes());
+    message.extend_from_slice(&message_type.to_ne_bytes());
+    message.extend_from_slice(payload.as_bytes());
+    stream.write_all(&message).context("Failed to send i3/Sway request")?;
+
+    let mut header = [0u8; 14];
+    stream
+        .read_exact(&mut header)
+        .context("Failed to read i3/Sway reply header")?;
+    if &header[..6] != MAGIC {
+        return Err(anyhow::anyhow!("Invalid i3/Sway reply"));
+    }
+
+    let length = u32::from_ne_bytes(header[6..10].try_into().unwrap()) as usize;
+    let mut reply = vec![0u8; length];
+    stream
+        .read_exact(&mut reply)
+        .context("Failed to read i3/Sway reply")?;
+
+    Ok(reply)
+}
+
+fn socket_path() -> Result<String> {
+    if let Ok(path) = env::var("SWAYSOCK") {
+        return Ok(path);
+    }
+    if let Ok(path) = env::var("I3SOCK") {
+        return Ok(path);
+    }
+
+    let output = Command::new("i3")
+        .arg("--get-socketpath")
+        .output()
+        .context("Failed to locate the i3 socket")?;
+
+    if !output.status.success() {
+        return Err(anyhow::anyhow!("i3 --get-socketpath failed"));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
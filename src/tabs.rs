@@ -0,0 +1,217 @@
+//! Terminal emulator tabs: which shell runs in which tab of which emulator, and what that shell
+//! is doing.
+//!
+//! A tab's shell is a direct child of the emulator process, and its position among the
+//! emulator's shells (in start order) is its tab number. kitty, alacritty, and xterm also put
+//! the X window ID in the shell's `WINDOWID`. kitty additionally reports tab titles and can
+//! focus a tab through its remote control, when `allow_remote_control` is enabled.
+
+use anyhow::Result;
+use procfs::process::Process;
+use std::collections::HashMap;
+use std::process::Command;
+use sysinfo::{System, ThreadKind};
+use tabled::{Table, Tabled, settings::{Width, location::ByColumnName}};
+
+use crate::tty;
+
+#[derive(Debug, Clone)]
+pub struct Tab {
+    pub emulator: String,
+    pub emulator_pid: u32,
+    /// 1-based position of the tab's shell among the emulator's shells, in start order
+    pub index: usize,
+    pub shell_pid: u32,
+    pub tty: Option<String>,
+    /// X window from the shell's WINDOWID
+    pub window_id: Option<String>,
+    /// Tab title, as reported by kitty
+    pub title: Option<String>,
+    /// Leader of the foreground job, or None when the shell itself is in the foreground
+    pub foreground_pid: Option<u32>,
+    /// kitty's remote control socket from the shell's KITTY_LISTEN_ON
+    kitty_socket: Option<String>,
+}
+
+impl Tab {
+    /// A command that brings this tab to the front within its emulator, where the emulator
+    /// can be told to: `kitty @ focus-window` for kitty.
+    pub fn focus_command(&self) -> Option<Vec<String>> {
+        if self.emulator != "kitty" {
+            return None;
+        }
+        let mut command = vec!["kitty".to_string(), "@".to_string()];
+        if let Some(socket) = &self.kitty_socket {
+            command.extend(["--to".to_string(), socket.clone()]);
+        }
+        command.extend(["focus-window".to_string(), "--match".to_string(), format!("pid:{}", self.shell_pid)]);
+        Some(command)
+    }
+}
+
+#[derive(Tabled)]
+struct TabRow {
+    #[tabled(rename = "Terminal")]
+    terminal: String,
+
+    #[tabled(rename = "Title")]
+    title: String,
+
+    #[tabled(rename = "TTY")]
+    tty: String,
+
+    #[tabled(rename = "Shell PID")]
+    shell_pid: u32,
+
+    #[tabled(rename = "Running")]
+    running: String,
+
+    #[tabled(rename = "Working Dir")]
+    working_dir: String,
+}
+
+/// Every tab of every running terminal emulator, by emulator and then tab number.
+pub fn list_tabs(system: &System) -> Vec<Tab> {
+    let mut emulators: Vec<&sysinfo::Process> = system
+        .processes()
+        .values()
+        .filter(|p| p.thread_kind().is_none() && tty::TERMINAL_EMULATORS.contains(&p.name()))
+        .collect();
+    emulators.sort_by_key(|p| p.pid());
+
+    let mut tabs = Vec::new();
+    for emulator in emulators {
+        let mut shells: Vec<&sysinfo::Process> = system
+            .processes()
+            .values()
+            .filter(|p| p.parent() == Some(emulator.pid()) && p.thread_kind() != Some(ThreadKind::Userland))
+            .collect();
+        shells.sort_by_key(|p| (p.start_time(), p.pid()));
+
+        let name = emulator.name().trim_end_matches('-').to_string();
+        let mut kitty_titles = HashMap::new();
+        for (index, shell) in shells.into_iter().enumerate() {
+            let shell_pid = shell.pid().as_u32();
+            let stat = Process::new(shell_pid as i32).and_then(|p| p.stat()).ok();
+            let environ = shell_environ(shell_pid);
+            let kitty_socket = environ.get("KITTY_LISTEN_ON").cloned();
+            if name == "kitty" && kitty_titles.is_empty() {
+                kitty_titles = kitty_window_titles(kitty_socket.as_deref());
+            }
+
+            tabs.push(Tab {
+                emulator: name.clone(),
+                emulator_pid: emulator.pid().as_u32(),
+                index: index + 1,
+                shell_pid,
+                tty: stat.as_ref().and_then(tty::tty_name),
+                window_id: environ.get("WINDOWID").cloned(),
+                title: kitty_titles.get(&shell_pid).cloned(),
+                foreground_pid: stat
+                    .as_ref()
+                    .filter(|stat| stat.tpgid > 0 && stat.tpgid != stat.pid)
+                    .map(|stat| stat.tpgid as u32),
+                kitty_socket,
+            });
+        }
+    }
+    tabs
+}
+
+/// The tab `pid` runs in: the one whose shell is the process itself or one of its ancestors.
+pub fn find_tab(system: &System, pid: u32) -> Option<Tab> {
+    let tabs = list_tabs(system);
+    let mut current = system.process(sysinfo::Pid::from(pid as usize))?;
+    loop {
+        if let Some(tab) = tabs.iter().find(|tab| tab.shell_pid == current.pid().as_u32()) {
+            return Some(tab.clone());
+        }
+        current = system.process(current.parent()?)?;
+    }
+}
+
+/// Prints each terminal tab with its shell, foreground job, and working directory.
+pub fn show_tabs() -> Result<()> {
+    let mut system = System::new_all();
+    system.refresh_all();
+
+    let tabs = list_tabs(&system);
+    if tabs.is_empty() {
+        println!("No terminal emulator tabs found");
+        return Ok(());
+    }
+
+    let process = |pid: u32| system.process(sysinfo::Pid::from(pid as usize));
+    let rows: Vec<TabRow> = tabs
+        .iter()
+        .map(|tab| {
+            // The job itself, or the shell when it's sitting at the prompt
+            let active = tab.foreground_pid.and_then(process).or_else(|| process(tab.shell_pid));
+            TabRow {
+                terminal: format!("{} tab {}", tab.emulator, tab.index),
+                title: tab.title.clone().unwrap_or_else(|| "-".to_string()),
+                tty: tab.tty.clone().unwrap_or_else(|| "-".to_string()),
+                shell_pid: tab.shell_pid,
+                running: match (tab.foreground_pid, active) {
+                    (Some(_), Some(active)) if !active.cmd().is_empty() => active.cmd().join(" "),
+                    (Some(_), Some(active)) => active.name().to_string(),
+                    _ => "(prompt)".to_string(),
+                },
+                working_dir: active
+                    .and_then(|p| p.cwd())
+                    .map(|cwd| cwd.display().to_string())
+                    .unwrap_or_else(|| "N/A".to_string()),
+            }
+        })
+        .collect();
+
+    let mut table = Table::new(rows);
+    table
+        .modify(ByColumnName::new("Title"), Width::truncate(30).suffix("..."))
+        .modify(ByColumnName::new("Running"), Width::truncate(50).suffix("..."))
+        .modify(ByColumnName::new("Working Dir"), Width::truncate(40).suffix("..."));
+    println!("{}", table);
+    Ok(())
+}
+
+fn shell_environ(pid: u32) -> HashMap<String, String> {
+    Process::new(pid as i32)
+        .and_then(|p| p.environ())
+        .map(|environ| {
+            environ
+                .into_iter()
+                .map(|(key, value)| (key.to_string_lossy().into_owned(), value.to_string_lossy().into_owned()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Tab titles by the PID of each kitty window's shell, from `kitty @ ls`. Empty when remote
+/// control is off or unreachable.
+fn kitty_window_titles(socket: Option<&str>) -> HashMap<u32, String> {
+    let mut command = Command::new("kitty");
+    command.arg("@");
+    if let Some(socket) = socket {
+        command.args(["--to", socket]);
+    }
+    let Some(output) = command.arg("ls").output().ok().filter(|output| output.status.success()) else {
+        return HashMap::new();
+    };
+    let Ok(os_windows) = serde_json::from_slice::<serde_json::Value>(&output.stdout) else {
+        return HashMap::new();
+    };
+
+    let mut titles = HashMap::new();
+    let list = |value: &serde_json::Value, key: &str| value[key].as_array().cloned().unwrap_or_default();
+    for os_window in os_windows.as_array().into_iter().flatten() {
+        for tab in list(os_window, "tabs") {
+            let title = tab["title"].as_str().unwrap_or_default().to_string();
+            for window in list(&tab, "windows") {
+                if let Some(pid) = window["pid"].as_u64() {
+                    titles.insert(pid as u32, title.clone());
+                }
+            }
+        }
+    }
+    titles
+}
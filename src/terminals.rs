@@ -0,0 +1,94 @@
+//! `terminals`: processes grouped by their controlling terminal, so each tab, pane, or console
+//! shows what is running in it.
+
+use anyhow::Result;
+use std::collections::BTreeMap;
+use sysinfo::{System, ThreadKind};
+use tabled::{Table, Tabled, settings::{Width, location::ByColumnName}};
+
+use crate::output;
+use crate::tty;
+
+#[derive(Tabled)]
+struct TerminalProcess {
+    #[tabled(rename = "PID")]
+    pid: u32,
+
+    #[tabled(rename = "Name")]
+    name: String,
+
+    #[tabled(rename = "Job")]
+    job: &'static str,
+
+    #[tabled(rename = "Memory")]
+    memory: String,
+
+    #[tabled(rename = "Command")]
+    command: String,
+}
+
+/// Prints one table per terminal, in terminal order, with where the terminal lives (tmux pane
+/// or emulator tab) and whether each process is in its foreground job.
+pub fn show_terminals() -> Result<()> {
+    let mut system = System::new_all();
+    system.refresh_all();
+
+    let Ok(all) = procfs::process::all_processes() else {
+        return Err(anyhow::anyhow!("Failed to read /proc"));
+    };
+    let mut terminals: BTreeMap<(String, u32, String), Vec<TerminalProcess>> = BTreeMap::new();
+    for stat in all.flatten().filter_map(|process| process.stat().ok()) {
+        let Some(tty) = tty::tty_name(&stat) else {
+            continue;
+        };
+        let Some(process) = system.process(sysinfo::Pid::from(stat.pid as usize)) else {
+            continue;
+        };
+        if process.thread_kind() == Some(ThreadKind::Userland) {
+            continue;
+        }
+
+        terminals.entry(sort_key(&tty)).or_default().push(TerminalProcess {
+            pid: stat.pid as u32,
+            name: process.name().to_string(),
+            job: if stat.pgrp == stat.tpgid { "foreground" } else { "background" },
+            memory: output::format_memory(process.memory()),
+            command: if process.cmd().is_empty() { process.name().to_string() } else { process.cmd().join(" ") },
+        });
+    }
+
+    if terminals.is_empty() {
+        println!("No processes have a controlling terminal");
+        return Ok(());
+    }
+
+    let panes = tty::list_tmux_panes().unwrap_or_default();
+    let jobs = tty::foreground_jobs();
+    for (index, ((_, _, name), mut processes)) in terminals.into_iter().enumerate() {
+        if index > 0 {
+            println!();
+        }
+        let location = jobs
+            .iter()
+            .find(|job| job.tty == name)
+            .map(|job| tty::terminal_location(&system, job, &panes))
+            .filter(|location| location != "-");
+        match location {
+            Some(location) => println!("{} ({})", name, location),
+            None => println!("{}", name),
+        }
+
+        processes.sort_by_key(|process| process.pid);
+        let mut table = Table::new(processes);
+        table.modify(ByColumnName::new("Command"), Width::truncate(60).suffix("..."));
+        println!("{}", table);
+    }
+
+    Ok(())
+}
+
+/// Orders pts/2 before pts/10 by splitting the trailing number off the terminal name.
+fn sort_key(tty: &str) -> (String, u32, String) {
+    let prefix = tty.trim_end_matches(|c: char| c.is_ascii_digit());
+    (prefix.to_string(), tty[prefix.len()..].parse().unwrap_or(0), tty.to_string())
+}
@@ -0,0 +1,365 @@
+//! Disposable display servers with dummy windows, for end-to-end tests of window lookup,
+//! matching, and switching.
+//!
+//! `top-helper --test-env <command>` runs a command inside one. Other crates can drive the same
+//! helpers from their own tests:
+//!
+//! ```no_run
+//! use top_helper::testenv::{Backend, TestEnv};
+//!
+//! let mut env = TestEnv::start(Backend::X11)?;
+//! let pid = env.spawn_window("editor", "Editor")?;
+//! let status = env.command("top-helper").args(["switch", &pid.to_string(), "--dry-run"]).status()?;
+//! assert!(status.success());
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+//!
+//! The dummy windows are drawn by the `test-window` subcommand of the top-helper binary, so
+//! that binary must be available: by default the running executable, otherwise set with
+//! [`TestEnv::with_helper`].
+
+use anyhow::{Context, Result};
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+use x11rb::connection::Connection;
+use x11rb::protocol::xproto::{AtomEnum, ConnectionExt as _, CreateWindowAux, PropMode, WindowClass};
+use x11rb::wrapper::ConnectionExt as _;
+
+/// How long to wait for a server or window to come up before giving up.
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Session variables that would point tools at the real desktop instead of the test one.
+const CLEARED_VARS: &[&str] = &[
+    "DISPLAY",
+    "WAYLAND_DISPLAY",
+    "SWAYSOCK",
+    "I3SOCK",
+    "HYPRLAND_INSTANCE_SIGNATURE",
+    "XDG_CURRENT_DESKTOP",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Backend {
+    /// An Xvfb server
+    X11,
+    /// Headless Sway, with the dummy windows running under Xwayland
+    Wayland,
+}
+
+/// A running headless display server. Everything it started is stopped when it is dropped.
+pub struct TestEnv {
+    backend: Backend,
+    server: Child,
+    vars: Vec<(String, String)>,
+    /// Private XDG_RUNTIME_DIR of a Wayland compositor
+    runtime_dir: Option<PathBuf>,
+    helper: PathBuf,
+    windows: Vec<Child>,
+}
+
+impl TestEnv {
+    pub fn start(backend: Backend) -> Result<Self> {
+        let helper = std::env::current_exe().context("Failed to locate the top-helper binary")?;
+        match backend {
+            Backend::X11 => Self::start_xvfb(helper),
+            Backend::Wayland => Self::start_sway(helper),
+        }
+    }
+
+    /// Uses another top-helper binary to draw the dummy windows, e.g. when the tests run from a
+    /// test harness rather than top-helper itself.
+    pub fn with_helper(mut self, helper: impl Into<PathBuf>) -> Self {
+        self.helper = helper.into();
+        self
+    }
+
+    pub fn backend(&self) -> Backend {
+        self.backend
+    }
+
+    /// The environment variables that point a client at this server.
+    pub fn vars(&self) -> &[(String, String)] {
+        &self.vars
+    }
+
+    /// A command set up to run against this server instead of the real desktop.
+    pub fn command(&self, program: impl AsRef<std::ffi::OsStr>) -> Command {
+        let mut command = Command::new(program);
+        for var in CLEARED_VARS {
+            command.env_remove(var);
+        }
+        command.envs(self.vars.iter().map(|(key, value)| (key, value)));
+        command
+    }
+
+    /// Opens a window with the given title and WM_CLASS, returning the PID of its process once
+    /// the window is mapped.
+    pub fn spawn_window(&mut self, title: &str, class: &str) -> Result<u32> {
+        match self.backend {
+            Backend::X11 => {
+                let mut child = self
+                    .command(&self.helper)
+                    .args(["test-window", "--title", title, "--class", class])
+                    .stdout(Stdio::piped())
+                    .stderr(Stdio::null())
+                    .spawn()
+                    .with_context(|| format!("Failed to run {}", self.helper.display()))?;
+                let pid = child.id();
+
+                // The window prints a line once it is mapped
+                let mut line = String::new();
+                let stdout = child.stdout.take().context("Test window has no stdout")?;
+                BufReader::new(stdout).read_line(&mut line)?;
+                self.windows.push(child);
+                if line.trim() != "ready" {
+                    return Err(anyhow::anyhow!("Test window '{}' failed to start", title));
+                }
+                Ok(pid)
+            }
+            Backend::Wayland => {
+                // Windows started by the compositor get its Xwayland DISPLAY
+                let quote = |s: &str| format!("'{}'", s.replace('\'', r"'\''"));
+                sway_request(
+                    self.var("SWAYSOCK"),
+                    RUN_COMMAND,
+                    &format!(
+                        "exec {} test-window --title {} --class {}",
+                        quote(&self.helper.to_string_lossy()),
+                        quote(title),
+                        quote(class)
+                    ),
+                )?;
+
+                let started = Instant::now();
+                loop {
+                    let tree: serde_json::Value =
+                        serde_json::from_slice(&sway_request(self.var("SWAYSOCK"), GET_TREE, "")?)?;
+                    if let Some(pid) = find_node_pid(&tree, title) {
+                        return Ok(pid);
+                    }
+                    if started.elapsed() > STARTUP_TIMEOUT {
+                        return Err(anyhow::anyhow!("Test window '{}' did not appear", title));
+                    }
+                    std::thread::sleep(Duration::from_millis(50));
+                }
+            }
+        }
+    }
+
+    fn var(&self, key: &str) -> &str {
+        self.vars
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value.as_str())
+            .unwrap_or_default()
+    }
+
+    fn start_xvfb(helper: PathBuf) -> Result<Self> {
+        let display = (99..200)
+            .find(|n| {
+                !Path::new(&format!("/tmp/.X11-unix/X{}", n)).exists() && !Path::new(&format!("/tmp/.X{}-lock", n)).exists()
+            })
+            .context("No free X display number")?;
+
+        let mut server = Command::new("Xvfb")
+            .args([&format!(":{}", display), "-screen", "0", "1280x800x24", "-nolisten", "tcp"])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|err| spawn_error(err, "Xvfb", "xvfb"))?;
+
+        let socket = PathBuf::from(format!("/tmp/.X11-unix/X{}", display));
+        if let Err(err) = wait_for(&mut server, "Xvfb", || socket.exists()) {
+            let _ = server.kill();
+            return Err(err);
+        }
+
+        Ok(TestEnv {
+            backend: Backend::X11,
+            server,
+            vars: vec![
+                ("DISPLAY".to_string(), format!(":{}", display)),
+                ("XDG_SESSION_TYPE".to_string(), "x11".to_string()),
+            ],
+            runtime_dir: None,
+            helper,
+            windows: Vec::new(),
+        })
+    }
+
+    fn start_sway(helper: PathBuf) -> Result<Self> {
+        let runtime_dir = std::env::temp_dir().join(format!("top-helper-test-{}", std::process::id()));
+        fs::create_dir_all(&runtime_dir)
+            .with_context(|| format!("Failed to create {}", runtime_dir.display()))?;
+        fs::set_permissions(&runtime_dir, fs::Permissions::from_mode(0o700))?;
+        let config = runtime_dir.join("sway.conf");
+        fs::write(&config, "xwayland enable\n")?;
+        let ipc_socket = runtime_dir.join("sway-ipc.sock");
+
+        let mut command = Command::new("sway");
+        for var in CLEARED_VARS {
+            command.env_remove(var);
+        }
+        let spawned = command
+            .arg("--config")
+            .arg(&config)
+            .env("XDG_RUNTIME_DIR", &runtime_dir)
+            .env("SWAYSOCK", &ipc_socket)
+            .env("WLR_BACKENDS", "headless")
+            .env("WLR_RENDERER", "pixman")
+            .env("WLR_LIBINPUT_NO_DEVICES", "1")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+        let mut server = match spawned {
+            Ok(server) => server,
+            Err(err) => {
+                let _ = fs::remove_dir_all(&runtime_dir);
+                return Err(spawn_error(err, "sway", "sway"));
+            }
+        };
+
+        let wayland_display = || {
+            fs::read_dir(&runtime_dir).ok()?.flatten().find_map(|entry| {
+                let name = entry.file_name().to_string_lossy().to_string();
+                (name.starts_with("wayland-") && !name.ends_with(".lock")).then_some(name)
+            })
+        };
+        let ready = wait_for(&mut server, "sway", || ipc_socket.exists() && wayland_display().is_some());
+        let mut env = TestEnv {
+            backend: Backend::Wayland,
+            server,
+            vars: Vec::new(),
+            runtime_dir: Some(runtime_dir.clone()),
+            helper,
+            windows: Vec::new(),
+        };
+        // Dropping the half-started environment stops the server and removes its directory
+        ready?;
+
+        env.vars = vec![
+            ("XDG_SESSION_TYPE".to_string(), "wayland".to_string()),
+            ("XDG_RUNTIME_DIR".to_string(), runtime_dir.display().to_string()),
+            ("WAYLAND_DISPLAY".to_string(), wayland_display().unwrap_or_default()),
+            ("SWAYSOCK".to_string(), ipc_socket.display().to_string()),
+        ];
+        Ok(env)
+    }
+}
+
+impl Drop for TestEnv {
+    fn drop(&mut self) {
+        for window in &mut self.windows {
+            let _ = window.kill();
+            let _ = window.wait();
+        }
+        // Clients started by the compositor lose their connection and exit with it
+        let _ = self.server.kill();
+        let _ = self.server.wait();
+        if let Some(dir) = &self.runtime_dir {
+            let _ = fs::remove_dir_all(dir);
+        }
+    }
+}
+
+fn spawn_error(err: io::Error, program: &str, package: &str) -> anyhow::Error {
+    if err.kind() == io::ErrorKind::NotFound {
+        anyhow::anyhow!("{} is not installed (install the {} package)", program, package)
+    } else {
+        anyhow::Error::new(err).context(format!("Failed to start {}", program))
+    }
+}
+
+/// Polls until `ready` holds, failing if the server exits or takes too long.
+fn wait_for(server: &mut Child, name: &str, ready: impl Fn() -> bool) -> Result<()> {
+    let started = Instant::now();
+    while !ready() {
+        if let Some(status) = server.try_wait()? {
+            return Err(anyhow::anyhow!("{} exited during startup ({})", name, status));
+        }
+        if started.elapsed() > STARTUP_TIMEOUT {
+            return Err(anyhow::anyhow!("{} did not start within {:?}", name, STARTUP_TIMEOUT));
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    Ok(())
+}
+
+const MAGIC: &[u8] = b"i3-ipc";
+const RUN_COMMAND: u32 = 0;
+const GET_TREE: u32 = 4;
+
+/// A minimal i3 IPC exchange; the full client lives in the binary's window backends.
+fn sway_request(socket: &str, message_type: u32, payload: &str) -> Result<Vec<u8>> {
+    let mut stream =
+        UnixStream::connect(socket).with_context(|| format!("Failed to connect to Sway at {}", socket))?;
+    let mut message = MAGIC.to_vec();
+    message.extend_from_slice(&(payload.len() as u32).to_ne_bytes());
+    message.extend_from_slice(&message_type.to_ne_bytes());
+    message.extend_from_slice(payload.as_bytes());
+    stream.write_all(&message)?;
+
+    let mut header = [0u8; 14];
+    stream.read_exact(&mut header)?;
+    let length = u32::from_ne_bytes(header[6..10].try_into()?) as usize;
+    let mut response = vec![0u8; length];
+    stream.read_exact(&mut response)?;
+    Ok(response)
+}
+
+fn find_node_pid(node: &serde_json::Value, title: &str) -> Option<u32> {
+    if node["name"].as_str() == Some(title)
+        && let Some(pid) = node["pid"].as_u64()
+    {
+        return Some(pid as u32);
+    }
+    ["nodes", "floating_nodes"]
+        .iter()
+        .filter_map(|key| node[key].as_array())
+        .flatten()
+        .find_map(|child| find_node_pid(child, title))
+}
+
+/// Opens an X11 window with the given title and class on $DISPLAY, prints "ready" once it is
+/// mapped, and keeps it open until the server goes away or the process is killed.
+pub fn run_test_window(title: &str, class: &str) -> Result<()> {
+    let (conn, screen_num) = x11rb::connect(None).context("Failed to connect to the X server")?;
+    let screen = &conn.setup().roots[screen_num];
+    let window = conn.generate_id()?;
+    conn.create_window(
+        x11rb::COPY_DEPTH_FROM_PARENT,
+        window,
+        screen.root,
+        0,
+        0,
+        400,
+        300,
+        0,
+        WindowClass::INPUT_OUTPUT,
+        x11rb::COPY_FROM_PARENT,
+        &CreateWindowAux::new().background_pixel(screen.white_pixel),
+    )?;
+
+    let atom = |name: &str| -> Result<u32> { Ok(conn.intern_atom(false, name.as_bytes())?.reply()?.atom) };
+    let utf8_string = atom("UTF8_STRING")?;
+    conn.change_property8(PropMode::REPLACE, window, AtomEnum::WM_NAME, AtomEnum::STRING, title.as_bytes())?;
+    conn.change_property8(PropMode::REPLACE, window, atom("_NET_WM_NAME")?, utf8_string, title.as_bytes())?;
+    // WM_CLASS is the instance name and the class name, each NUL-terminated
+    let wm_class = format!("{}\0{}\0", class.to_lowercase(), class);
+    conn.change_property8(PropMode::REPLACE, window, AtomEnum::WM_CLASS, AtomEnum::STRING, wm_class.as_bytes())?;
+    conn.change_property32(PropMode::REPLACE, window, atom("_NET_WM_PID")?, AtomEnum::CARDINAL, &[std::process::id()])?;
+    conn.map_window(window)?;
+    conn.sync()?;
+
+    println!("ready");
+    io::stdout().flush()?;
+
+    loop {
+        conn.wait_for_event().context("Lost the connection to the X server")?;
+    }
+}
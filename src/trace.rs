@@ -0,0 +1,361 @@
+//! `trace`: which system calls a process spends its time in, over a few seconds.
+//!
+//! With enough privileges (ptrace or perf_event access to the process), `strace -c` or
+//! `perf trace -s` count every call with its latency. Without them, the threads'
+//! /proc/<pid>/task/<tid>/syscall files are sampled instead: that has no call counts, but still
+//! shows the dominant blocking system call, which is usually what matters for a stuck process.
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use procfs::process::Process;
+use std::collections::HashMap;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+use sysinfo::System;
+use tabled::{Table, Tabled};
+
+use crate::error::CliError;
+use crate::selector::Selection;
+use crate::stack;
+use crate::window::command_exists;
+
+/// How often threads are sampled without a tracer.
+const SAMPLE_PERIOD: Duration = Duration::from_millis(10);
+
+/// Sampled rows for threads that were not in a system call.
+const USER_SPACE: &str = "(user space)";
+const OUTSIDE_SYSCALL: &str = "(blocked, no syscall)";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum TraceMethod {
+    /// strace, then perf, then sampling, whichever works first
+    Auto,
+    Strace,
+    Perf,
+    /// Sample /proc; needs no privileges beyond reading the process
+    Sample,
+}
+
+impl TraceMethod {
+    fn name(&self) -> &'static str {
+        match self {
+            TraceMethod::Auto => "auto",
+            TraceMethod::Strace => "strace",
+            TraceMethod::Perf => "perf",
+            TraceMethod::Sample => "sampling",
+        }
+    }
+}
+
+/// Time spent in one system call over the trace.
+#[derive(Debug, Clone, Default)]
+struct SyscallStat {
+    name: String,
+    /// Number of calls, unknown when sampling
+    calls: Option<u64>,
+    errors: Option<u64>,
+    /// Time spent in the call; estimated from sample counts when sampling
+    seconds: f64,
+}
+
+#[derive(Tabled)]
+struct SyscallRow {
+    #[tabled(rename = "Syscall")]
+    name: String,
+
+    #[tabled(rename = "Calls")]
+    calls: String,
+
+    #[tabled(rename = "Errors")]
+    errors: String,
+
+    #[tabled(rename = "Total Time")]
+    total: String,
+
+    #[tabled(rename = "Avg Time")]
+    average: String,
+
+    #[tabled(rename = "% Time")]
+    percent: String,
+}
+
+/// Traces the process `identifier` selects for `duration` and prints a per-syscall summary.
+pub async fn trace_process(identifier: &str, duration: Duration, method: TraceMethod) -> Result<()> {
+    let selection = Selection::parse(identifier)?;
+    let mut system = System::new_all();
+    system.refresh_all();
+    let process = selection.resolve_unique(&system)?;
+    let pid = process.pid().as_u32();
+
+    let methods: Vec<TraceMethod> = match method {
+        TraceMethod::Auto => [TraceMethod::Strace, TraceMethod::Perf]
+            .into_iter()
+            .filter(|method| command_exists(method.name()))
+            .chain([TraceMethod::Sample])
+            .collect(),
+        TraceMethod::Strace | TraceMethod::Perf if !command_exists(method.name()) => {
+            return Err(CliError::backend_missing(format!("{} is not installed", method.name())));
+        }
+        method => vec![method],
+    };
+
+    let automatic = method == TraceMethod::Auto;
+    for method in methods {
+        println!(
+            "Tracing PID {} ({}) for {} with {}...",
+            pid,
+            process.name(),
+            format_seconds(duration.as_secs_f64()),
+            method.name()
+        );
+        let result = match method {
+            TraceMethod::Strace => strace(pid, duration).await,
+            TraceMethod::Perf => perf(pid, duration).await,
+            _ => sample(pid, duration).await,
+        };
+        match result {
+            Ok(mut stats) => {
+                print_summary(method, &mut stats);
+                return Ok(());
+            }
+            // Sampling is the last resort, and an explicitly chosen method has no fallback
+            Err(err) if automatic && method != TraceMethod::Sample => {
+                eprintln!("{:#}; trying the next method", err);
+            }
+            Err(err) => return Err(err),
+        }
+    }
+    unreachable!("automatic tracing always ends with sampling")
+}
+
+fn print_summary(method: TraceMethod, stats: &mut [SyscallStat]) {
+    if stats.is_empty() {
+        println!("No system calls were seen; the process is running in user space or exited.");
+        return;
+    }
+    stats.sort_by(|a, b| b.seconds.total_cmp(&a.seconds));
+    let total: f64 = stats.iter().map(|stat| stat.seconds).sum();
+    let percent = |seconds: f64| if total > 0.0 { seconds * 100.0 / total } else { 0.0 };
+
+    let dominant = &stats[0];
+    let activity = match dominant.name.as_str() {
+        USER_SPACE => "running in user space".to_string(),
+        OUTSIDE_SYSCALL => "blocked outside a system call (e.g. on a page fault)".to_string(),
+        name => format!("in {}", name),
+    };
+    println!(
+        "Most time is spent {} ({:.1}% of {} time).\n",
+        activity,
+        percent(dominant.seconds),
+        if method == TraceMethod::Sample { "sampled" } else { "syscall" }
+    );
+
+    let rows: Vec<SyscallRow> = stats
+        .iter()
+        .map(|stat| SyscallRow {
+            name: stat.name.clone(),
+            calls: stat.calls.map(|calls| calls.to_string()).unwrap_or_else(|| "-".to_string()),
+            errors: stat.errors.map(|errors| errors.to_string()).unwrap_or_else(|| "-".to_string()),
+            total: format_seconds(stat.seconds),
+            average: match stat.calls {
+                Some(calls) if calls > 0 => format_seconds(stat.seconds / calls as f64),
+                _ => "-".to_string(),
+            },
+            percent: format!("{:.1}", percent(stat.seconds)),
+        })
+        .collect();
+    println!("{}", Table::new(rows));
+    if method == TraceMethod::Sample {
+        println!("Sampled every {} ms; install strace or perf (and run with privileges) for call counts and latencies.", SAMPLE_PERIOD.as_millis());
+    }
+}
+
+/// `strace -c -w -f` attached for `duration`, then interrupted so it detaches and prints its
+/// summary. `-w` reports wall-clock latency, so calls that block count for how long they block.
+async fn strace(pid: u32, duration: Duration) -> Result<Vec<SyscallStat>> {
+    let child = Command::new("strace")
+        .args(["-c", "-w", "-f", "-p", &pid.to_string()])
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Failed to run strace")?;
+    let output = stop_after(child, duration).await?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if stderr.contains("Operation not permitted") {
+        return Err(CliError::permission(format!("strace may not attach to PID {}", pid)));
+    }
+    parse_strace(&stderr).ok_or_else(|| anyhow::anyhow!("strace failed: {}", stderr.trim()))
+}
+
+/// Parses `strace -c` output:
+///
+/// ```text
+/// % time     seconds  usecs/call     calls    errors syscall
+/// ------ ----------- ----------- --------- --------- ----------------
+///  99.99    5.004540     5004540         1           clock_nanosleep
+/// ------ ----------- ----------- --------- --------- ----------------
+/// 100.00    5.004940      454994        11         2 total
+/// ```
+fn parse_strace(output: &str) -> Option<Vec<SyscallStat>> {
+    let mut lines = output.lines().skip_while(|line| !line.starts_with("% time"));
+    lines.next()?;
+    lines.next()?; // The dashed line under the header
+
+    let mut stats = Vec::new();
+    for line in lines.take_while(|line| !line.starts_with("------")) {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 5 {
+            continue;
+        }
+        stats.push(SyscallStat {
+            name: fields[fields.len() - 1].to_string(),
+            calls: fields[3].parse().ok(),
+            // The errors column is blank when there were none
+            errors: if fields.len() > 5 { fields[4].parse().ok() } else { Some(0) },
+            seconds: fields[1].parse().unwrap_or(0.0),
+        });
+    }
+    Some(stats)
+}
+
+/// `perf trace -s` on the process for `duration`.
+async fn perf(pid: u32, duration: Duration) -> Result<Vec<SyscallStat>> {
+    let child = Command::new("perf")
+        .args(["trace", "-s", "-p", &pid.to_string()])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Failed to run perf")?;
+    let output = stop_after(child, duration).await?;
+    let text = format!("{}{}", String::from_utf8_lossy(&output.stdout), String::from_utf8_lossy(&output.stderr));
+    if text.contains("Permission denied") || text.contains("perf_event_paranoid") {
+        return Err(CliError::permission(format!("perf may not trace PID {}", pid)));
+    }
+    parse_perf(&text).ok_or_else(|| anyhow::anyhow!("perf trace failed: {}", text.trim()))
+}
+
+/// Parses the per-thread tables of `perf trace -s`, adding up the threads:
+///
+/// ```text
+///    syscall            calls  errors  total       min       avg       max       stddev
+///                                      (msec)    (msec)    (msec)    (msec)        (%)
+///    --------------- --------  ------ -------- --------- --------- ---------     ------
+///    futex                 12      0   123.456     0.001    10.288    50.123     12.34%
+/// ```
+///
+/// Older perf versions have no errors column.
+fn parse_perf(output: &str) -> Option<Vec<SyscallStat>> {
+    let mut by_name: HashMap<String, SyscallStat> = HashMap::new();
+    let mut seen_table = false;
+    let mut has_errors = false;
+    let mut in_table = false;
+    for line in output.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("syscall") && trimmed.contains("calls") {
+            seen_table = true;
+            has_errors = trimmed.contains("errors");
+            continue;
+        }
+        if trimmed.starts_with("---") {
+            in_table = seen_table;
+            continue;
+        }
+        if trimmed.is_empty() {
+            in_table = false;
+            continue;
+        }
+        if !in_table {
+            continue;
+        }
+
+        let fields: Vec<&str> = trimmed.split_whitespace().collect();
+        let total_index = if has_errors { 3 } else { 2 };
+        if fields.len() <= total_index {
+            continue;
+        }
+        let stat = by_name.entry(fields[0].to_string()).or_insert_with(|| SyscallStat {
+            name: fields[0].to_string(),
+            calls: Some(0),
+            errors: has_errors.then_some(0),
+            seconds: 0.0,
+        });
+        stat.calls = stat.calls.map(|calls| calls + fields[1].parse::<u64>().unwrap_or(0));
+        if has_errors {
+            stat.errors = stat.errors.map(|errors| errors + fields[2].parse::<u64>().unwrap_or(0));
+        }
+        stat.seconds += fields[total_index].parse::<f64>().unwrap_or(0.0) / 1000.0;
+    }
+    seen_table.then(|| by_name.into_values().collect())
+}
+
+/// Lets a tracer run for `duration` and then interrupts it, which makes both strace and perf
+/// detach and print their summary.
+async fn stop_after(child: std::process::Child, duration: Duration) -> Result<std::process::Output> {
+    let started = Instant::now();
+    let mut child = child;
+    while started.elapsed() < duration {
+        // A tracer that couldn't attach, or whose target exited, is done early
+        if child.try_wait()?.is_some() {
+            return Ok(child.wait_with_output()?);
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    // SAFETY: kill has no memory-safety preconditions. The child is not reaped until
+    // wait_with_output below, so its PID cannot have been reused by another process.
+    // Child::kill would send SIGKILL, which loses the tracer's summary
+    unsafe {
+        libc::kill(child.id() as libc::pid_t, libc::SIGINT);
+    }
+    Ok(child.wait_with_output()?)
+}
+
+/// Samples every thread's current system call from /proc every `SAMPLE_PERIOD`. Each sample
+/// counts as `SAMPLE_PERIOD` spent in whatever the thread was doing.
+async fn sample(pid: u32, duration: Duration) -> Result<Vec<SyscallStat>> {
+    let process = Process::new(pid as i32).with_context(|| format!("PID {} is gone", pid))?;
+    let mut counts: HashMap<String, u64> = HashMap::new();
+    let started = Instant::now();
+    while started.elapsed() < duration {
+        let Ok(tasks) = process.tasks() else {
+            break; // Exited
+        };
+        for task in tasks.flatten() {
+            let dir = format!("/proc/{}/task/{}", pid, task.tid);
+            let state = std::fs::read_to_string(format!("{}/syscall", dir)).unwrap_or_default();
+            let name = match state.split_whitespace().next() {
+                Some("running") => USER_SPACE.to_string(),
+                Some("-1") => OUTSIDE_SYSCALL.to_string(),
+                Some(_) => match stack::syscall(&dir, &stack::kernel_stack(&dir)) {
+                    Some(name) => name,
+                    None => continue,
+                },
+                None => continue,
+            };
+            *counts.entry(name).or_default() += 1;
+        }
+        tokio::time::sleep(SAMPLE_PERIOD).await;
+    }
+    if counts.is_empty() {
+        return Err(CliError::permission(format!("The threads of PID {} are not readable", pid)));
+    }
+
+    Ok(counts
+        .into_iter()
+        .map(|(name, count)| SyscallStat {
+            name,
+            calls: None,
+            errors: None,
+            seconds: count as f64 * SAMPLE_PERIOD.as_secs_f64(),
+        })
+        .collect())
+}
+
+fn format_seconds(seconds: f64) -> String {
+    if seconds >= 1.0 {
+        format!("{:.2}s", seconds)
+    } else if seconds >= 0.001 {
+        format!("{:.2}ms", seconds * 1000.0)
+    } else {
+        format!("{:.1}µs", seconds * 1_000_000.0)
+    }
+}
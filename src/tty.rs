@@ -0,0 +1,209 @@
+use anyhow::{Context, Result};
+use procfs::process::Process;
+use std::fmt;
+use std::process::Command;
+use sysinfo::{System, ThreadKind};
+
+/// Terminal emulators whose direct children are the shells of their tabs and windows, by
+/// process name (`comm`, so truncated to 15 characters).
+pub const TERMINAL_EMULATORS: &[&str] = &[
+    "kitty",
+    "alacritty",
+    "gnome-terminal-",
+    "wezterm-gui",
+    "konsole",
+    "xfce4-terminal",
+    "foot",
+    "tilix",
+    "terminator",
+    "xterm",
+    "urxvt",
+];
+
+#[derive(Debug, Clone)]
+pub struct TmuxPane {
+    pub target: String,
+    pub pane_pid: u32,
+    pub tty: String,
+}
+
+/// The terminal emulator tab a process runs in, and what is in the foreground there.
+#[derive(Debug, Clone)]
+pub struct TerminalContext {
+    pub emulator: String,
+    /// 1-based position of the tab's shell among the emulator's shells, in start order
+    pub tab: usize,
+    pub shell_pid: u32,
+    /// Command line of the foreground job, or None when the shell itself is in the foreground
+    pub foreground: Option<String>,
+}
+
+impl fmt::Display for TerminalContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} tab {}", self.emulator, self.tab)?;
+        match &self.foreground {
+            Some(command) => write!(f, " running `{}`", command),
+            None => write!(f, " at the shell prompt"),
+        }
+    }
+}
+
+/// Finds the terminal emulator a process runs under by walking up its ancestry. The ancestor
+/// directly below the emulator is the tab's shell; its terminal's foreground process group
+/// (`tpgid`, what tcgetpgrp would return) is the job currently running there.
+pub fn terminal_context(system: &System, pid: u32) -> Option<TerminalContext> {
+    let mut current = system.process(sysinfo::Pid::from(pid as usize))?;
+    let emulator = loop {
+        let parent = system.process(current.parent()?)?;
+        if TERMINAL_EMULATORS.contains(&parent.name()) {
+            break parent;
+        }
+        current = parent;
+    };
+    let shell_pid = current.pid().as_u32();
+
+    let mut shells: Vec<&sysinfo::Process> = system
+        .processes()
+        .values()
+        .filter(|p| p.parent() == Some(emulator.pid()) && p.thread_kind() != Some(ThreadKind::Userland))
+        .collect();
+    shells.sort_by_key(|p| (p.start_time(), p.pid()));
+    let tab = shells.iter().position(|p| p.pid().as_u32() == shell_pid)? + 1;
+
+    let tpgid = Process::new(shell_pid as i32).ok()?.stat().ok()?.tpgid;
+    let foreground = if tpgid > 0 && tpgid as u32 != shell_pid {
+        system
+            .process(sysinfo::Pid::from(tpgid as usize))
+            .map(|p| if p.cmd().is_empty() { p.name().to_string() } else { p.cmd().join(" ") })
+    } else {
+        None
+    };
+
+    Some(TerminalContext {
+        emulator: emulator.name().trim_end_matches('-').to_string(),
+        tab,
+        shell_pid,
+        foreground,
+    })
+}
+
+/// Returns the controlling terminal of a process (e.g. "pts/3" or "tty2"), if any.
+pub fn process_tty(pid: u32) -> Option<String> {
+    let stat = Process::new(pid as i32).ok()?.stat().ok()?;
+    tty_name(&stat)
+}
+
+pub fn tty_name(stat: &procfs::process::Stat) -> Option<String> {
+    if stat.tty_nr == 0 {
+        return None;
+    }
+
+    let (major, minor) = stat.tty_nr();
+    match major {
+        136..=143 => Some(format!("pts/{}", (major - 136) * 256 + minor)),
+        4 if minor < 64 => Some(format!("tty{}", minor)),
+        4 => Some(format!("ttyS{}", minor - 64)),
+        _ => Some(format!("{}:{}", major, minor)),
+    }
+}
+
+/// What a terminal is running right now: its session's shell and the foreground process group.
+pub struct ForegroundJob {
+    pub tty: String,
+    pub shell_pid: u32,
+    /// Leader of the foreground process group, or None when the shell itself is in the foreground
+    pub foreground_pid: Option<u32>,
+}
+
+/// Where a terminal lives, e.g. "tmux work:1.0" or "kitty tab 3", or "-" if unknown.
+pub fn terminal_location(system: &System, job: &ForegroundJob, panes: &[TmuxPane]) -> String {
+    match panes.iter().find(|pane| pane.tty == job.tty) {
+        Some(pane) => format!("tmux {}", pane.target),
+        None => terminal_context(system, job.shell_pid)
+            .map(|terminal| format!("{} tab {}", terminal.emulator, terminal.tab))
+            .unwrap_or_else(|| "-".to_string()),
+    }
+}
+
+/// One entry per terminal with a session on it. The foreground group is the terminal's `tpgid`
+/// from /proc/<pid>/stat, the same value tcgetpgrp(3) returns on the terminal itself.
+pub fn foreground_jobs() -> Vec<ForegroundJob> {
+    let Ok(all) = procfs::process::all_processes() else {
+        return Vec::new();
+    };
+
+    let mut jobs: Vec<(i32, ForegroundJob)> = all
+        .flatten()
+        .filter_map(|process| process.stat().ok())
+        // Session leaders that own a terminal: the login shell of each tab, pane, or console
+        .filter(|stat| stat.pid == stat.session && stat.tty_nr != 0)
+        .filter_map(|stat| {
+            let job = ForegroundJob {
+                tty: tty_name(&stat)?,
+                shell_pid: stat.pid as u32,
+                foreground_pid: (stat.tpgid > 0 && stat.tpgid != stat.pid).then_some(stat.tpgid as u32),
+            };
+            Some((stat.tty_nr, job))
+        })
+        .collect();
+
+    jobs.sort_by_key(|(tty_nr, job)| (*tty_nr, job.shell_pid));
+    jobs.into_iter().map(|(_, job)| job).collect()
+}
+
+pub fn list_tmux_panes() -> Result<Vec<TmuxPane>> {
+    let output = Command::new("tmux")
+        .args([
+            "list-panes",
+            "-a",
+            "-F",
+            "#{pane_pid} #{session_name}:#{window_index}.#{pane_index} #{pane_tty}",
+        ])
+        .output()
+        .context("Failed to run tmux list-panes")?;
+
+    if !output.status.success() {
+        return Err(anyhow::anyhow!("tmux list-panes failed"));
+    }
+
+    let pane_list = String::from_utf8_lossy(&output.stdout);
+    let mut panes = Vec::new();
+
+    for line in pane_list.lines() {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        if parts.len() >= 3
+            && let Ok(pane_pid) = parts[0].parse::<u32>()
+        {
+            panes.push(TmuxPane {
+                target: parts[1].to_string(),
+                pane_pid,
+                tty: parts[2].trim_start_matches("/dev/").to_string(),
+            });
+        }
+    }
+
+    Ok(panes)
+}
+
+/// Finds the tmux pane a process runs in by walking up its ancestry until a pane's shell is found.
+pub fn find_tmux_pane(pid: u32) -> Option<TmuxPane> {
+    let panes = list_tmux_panes().ok()?;
+    if panes.is_empty() {
+        return None;
+    }
+
+    let mut current = pid;
+    while current > 1 {
+        if let Some(pane) = panes.iter().find(|p| p.pane_pid == current) {
+            return Some(pane.clone());
+        }
+        current = parent_pid(current)?;
+    }
+
+    None
+}
+
+fn parent_pid(pid: u32) -> Option<u32> {
+    let stat = Process::new(pid as i32).ok()?.stat().ok()?;
+    Some(stat.ppid as u32)
+}
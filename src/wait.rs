@@ -0,0 +1,177 @@
+//! Waits for a process that top-helper did not start to exit, then reports it.
+//!
+//! Only a parent can collect a process's exit status. The kernel does expose it in
+//! /proc/<pid>/stat while the process is a zombie, so the exit is detected with a pidfd (which
+//! becomes readable the moment the process dies) and the status is read before the parent reaps
+//! it. When the parent is faster, the status is reported as unknown.
+
+use anyhow::{Context, Result};
+use std::fmt;
+use std::io;
+use std::process::Command;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use sysinfo::System;
+
+use crate::control;
+use crate::output;
+use crate::selector::Selection;
+use crate::sink::{self, Message, Urgency};
+
+#[derive(Debug, Clone, Copy)]
+enum ExitStatus {
+    Code(i32),
+    Signal(i32),
+    Unknown,
+}
+
+impl ExitStatus {
+    /// Decodes a wait(2) status word.
+    fn from_wait_status(status: i32) -> Self {
+        if status & 0x7f == 0 {
+            ExitStatus::Code((status >> 8) & 0xff)
+        } else {
+            ExitStatus::Signal(status & 0x7f)
+        }
+    }
+}
+
+impl fmt::Display for ExitStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExitStatus::Code(0) => write!(f, "exited successfully"),
+            ExitStatus::Code(code) => write!(f, "exited with status {}", code),
+            ExitStatus::Signal(signal) => write!(f, "was killed by signal {}", signal),
+            ExitStatus::Unknown => write!(f, "exited (status unknown)"),
+        }
+    }
+}
+
+/// Blocks until the selected process exits, then prints a summary, delivers it to the given
+/// sinks, and runs `exec` with the details in its environment.
+pub fn wait_for_exit(identifier: &str, sinks: &[String], exec: Option<&str>) -> Result<()> {
+    let mut system = System::new_all();
+    system.refresh_all();
+
+    let process = Selection::parse(identifier)?.resolve_unique(&system)?;
+    let pid = process.pid().as_u32();
+    let name = process.name().to_string();
+    let start_time = process.start_time();
+    if control::own_ancestry(&system).contains(&pid) {
+        return Err(anyhow::anyhow!("Refusing to wait for top-helper itself or the processes running it"));
+    }
+
+    println!("Waiting for {} (PID: {}) to exit...", name, pid);
+    let (status, final_name) = wait_pid(pid)?;
+
+    // The PID stays the same across exec, so a wrapper script that execs into the real program
+    // is still followed; report it under the program it became
+    let started_as = final_name.as_ref().filter(|final_name| **final_name != name).map(|_| name.clone());
+    let name = final_name.unwrap_or(name);
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let runtime_ms = now.saturating_sub(start_time) * 1000;
+    let summary = format!(
+        "{} (PID: {}{}) {} after running for {}",
+        name,
+        pid,
+        started_as.map(|started_as| format!(", started as {}", started_as)).unwrap_or_default(),
+        status,
+        output::format_duration(runtime_ms)
+    );
+    println!("{}", summary);
+
+    if !sinks.is_empty() {
+        let message = Message {
+            title: format!("{} finished", name),
+            body: summary,
+            urgency: match status {
+                ExitStatus::Code(0) | ExitStatus::Unknown => Urgency::Normal,
+                _ => Urgency::Critical,
+            },
+            process: None,
+        };
+        sink::deliver(&message, sinks)?;
+    }
+
+    if let Some(exec) = exec {
+        let (code, signal) = match status {
+            ExitStatus::Code(code) => (code.to_string(), String::new()),
+            ExitStatus::Signal(signal) => (String::new(), signal.to_string()),
+            ExitStatus::Unknown => (String::new(), String::new()),
+        };
+        let result = Command::new("sh")
+            .args(["-c", exec])
+            .env("TOP_HELPER_PID", pid.to_string())
+            .env("TOP_HELPER_NAME", &name)
+            .env("TOP_HELPER_EXIT_CODE", code)
+            .env("TOP_HELPER_SIGNAL", signal)
+            .env("TOP_HELPER_RUNTIME_SECS", (runtime_ms / 1000).to_string())
+            .status()
+            .context("Failed to run --exec command")?;
+        if !result.success() {
+            return Err(anyhow::anyhow!("--exec command failed with {}", result));
+        }
+    }
+
+    Ok(())
+}
+
+/// Waits for `pid` to exit and returns its status and final name, which differs from the name
+/// it had when it was selected if it exec'd in the meantime.
+fn wait_pid(pid: u32) -> Result<(ExitStatus, Option<String>)> {
+    // SAFETY: pidfd_open takes a pid and flags and returns a new descriptor or -1
+    let fd = unsafe { libc::syscall(libc::SYS_pidfd_open, pid as libc::pid_t, 0) } as libc::c_int;
+    if fd < 0 {
+        let err = io::Error::last_os_error();
+        if err.raw_os_error() == Some(libc::ESRCH) {
+            return Ok((ExitStatus::Unknown, None));
+        }
+        // Kernels before 5.3 have no pidfds
+        let mut name = None;
+        while let Some(current) = alive_name(pid) {
+            name = Some(current);
+            std::thread::sleep(Duration::from_millis(100));
+        }
+        return Ok((ExitStatus::Unknown, name));
+    }
+
+    let mut pollfd = libc::pollfd { fd, events: libc::POLLIN, revents: 0 };
+    loop {
+        // SAFETY: pollfd is a valid pollfd array of length 1
+        let ready = unsafe { libc::poll(&mut pollfd, 1, -1) };
+        if ready > 0 {
+            break;
+        }
+        let err = io::Error::last_os_error();
+        if err.kind() != io::ErrorKind::Interrupted {
+            // SAFETY: fd is an open descriptor owned here
+            unsafe { libc::close(fd) };
+            return Err(anyhow::Error::new(err).context("Failed to wait for the process"));
+        }
+    }
+
+    // Read the status while the process is still a zombie; the pidfd keeps the PID from being
+    // reused until it is closed
+    let zombie = procfs::process::Process::new(pid as i32)
+        .and_then(|process| process.stat())
+        .ok()
+        .filter(|stat| stat.state == 'Z');
+    let status = zombie
+        .as_ref()
+        .and_then(|stat| stat.exit_code)
+        .map(ExitStatus::from_wait_status)
+        .unwrap_or(ExitStatus::Unknown);
+
+    // SAFETY: fd is an open descriptor owned here
+    unsafe { libc::close(fd) };
+    Ok((status, zombie.map(|stat| stat.comm)))
+}
+
+/// The name of `pid` if it is still running.
+fn alive_name(pid: u32) -> Option<String> {
+    procfs::process::Process::new(pid as i32)
+        .and_then(|process| process.stat())
+        .ok()
+        .filter(|stat| stat.state != 'Z')
+        .map(|stat| stat.comm)
+}
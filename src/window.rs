@@ -1,74 +1,904 @@
 use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fmt;
+use std::io::{self, BufRead, Write};
+use std::path::Path;
 use std::process::Command;
+use std::sync::OnceLock;
 use sysinfo::{System};
+use tabled::{Table, Tabled, settings::{Width, object::Columns}};
+use tracing::{debug, trace};
 
-#[derive(Debug)]
+use crate::electron::{self, ElectronApp};
+use crate::error::{CliError, ErrorKind};
+use crate::focus;
+use crate::hyprland;
+use crate::output::{self, OutputFormat};
+use crate::redact;
+use crate::sandbox::{self, SandboxedApp};
+use crate::selector::Selection;
+use crate::session::SessionType;
+use crate::sway;
+use crate::tabs::{self, Tab};
+use crate::tty;
+
+#[derive(Debug, Clone)]
 pub struct WindowInfo {
     pub window_id: String,
     pub title: String,
     pub class: String,
     pub pid: u32,
+    pub backend: Backend,
+    pub display: Option<String>,
+}
+
+/// Position and size of a window, in root window (or compositor layout) coordinates.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Geometry {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl fmt::Display for Geometry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}x{} at {},{}", self.width, self.height, self.x, self.y)
+    }
+}
+
+/// Where a window is and whether it has focus; each part is None when the backend can't tell.
+#[derive(Debug, Clone, Default)]
+pub struct WindowPlacement {
+    pub workspace: Option<String>,
+    pub geometry: Option<Geometry>,
+    pub focused: Option<bool>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    Hyprland,
+    Sway,
+    Xdotool,
+    Wmctrl,
 }
 
-pub async fn switch_to_process_window(process_identifier: &str) -> Result<()> {
+impl Backend {
+    /// All backends, in order of preference.
+    pub fn all() -> &'static [Backend] {
+        &[Backend::Hyprland, Backend::Sway, Backend::Xdotool, Backend::Wmctrl]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Backend::Hyprland => "hyprland",
+            Backend::Sway => "sway",
+            Backend::Xdotool => "xdotool",
+            Backend::Wmctrl => "wmctrl",
+        }
+    }
+
+    pub fn required_tool(&self) -> Option<&'static str> {
+        match self {
+            Backend::Hyprland | Backend::Sway => None,
+            Backend::Xdotool => Some("xdotool"),
+            Backend::Wmctrl => Some("wmctrl"),
+        }
+    }
+
+    /// Whether the backend can reach windows in this session. X11 backends also work
+    /// from any session when an X display is given explicitly.
+    pub fn supports_session(&self, session: SessionType, display: Option<&str>) -> bool {
+        match self {
+            Backend::Hyprland => hyprland::is_running(),
+            Backend::Sway => sway::is_running(),
+            Backend::Xdotool | Backend::Wmctrl => display.is_some() || session.has_x11_display(),
+        }
+    }
+
+    pub fn is_available(&self, session: SessionType, display: Option<&str>) -> bool {
+        self.supports_session(session, display) && self.required_tool().is_none_or(command_exists)
+    }
+
+    pub fn find_window_by_pid(&self, target_pid: u32, display: Option<&str>) -> Result<WindowInfo> {
+        let mut windows = self.find_windows_by_pid(target_pid, display)?;
+        Ok(windows.remove(0))
+    }
+
+    /// Every window of the process, or of its direct children when it has none; never empty.
+    pub fn find_windows_by_pid(&self, target_pid: u32, display: Option<&str>) -> Result<Vec<WindowInfo>> {
+        match self {
+            Backend::Hyprland => select_windows_for_pid(hyprland::list_windows()?, target_pid),
+            Backend::Sway => select_windows_for_pid(sway::list_windows()?, target_pid),
+            Backend::Xdotool => find_x11_windows_by_pid(target_pid, display),
+            Backend::Wmctrl => find_windows_by_process_name(target_pid, display),
+        }
+    }
+
+    pub fn list_windows(&self, display: Option<&str>) -> Result<Vec<WindowInfo>> {
+        match self {
+            Backend::Hyprland => hyprland::list_windows(),
+            Backend::Sway => sway::list_windows(),
+            Backend::Xdotool => list_x11_windows(display),
+            Backend::Wmctrl => list_wmctrl_windows(display),
+        }
+    }
+
+    pub fn placement(&self, window: &WindowInfo) -> Result<WindowPlacement> {
+        match self {
+            Backend::Hyprland => hyprland::placement(&window.window_id),
+            Backend::Sway => sway::placement(&window.window_id),
+            Backend::Xdotool | Backend::Wmctrl => x11_placement(window),
+        }
+    }
+
+    pub fn focused_window(&self, display: Option<&str>) -> Result<Option<WindowInfo>> {
+        match self {
+            Backend::Hyprland => hyprland::focused_window(),
+            Backend::Sway => sway::focused_window(),
+            Backend::Xdotool | Backend::Wmctrl => x11_focused_window(*self, display),
+        }
+    }
+
+    /// The shell command equivalent to focusing the window with this backend.
+    pub fn focus_command(&self, window: &WindowInfo) -> String {
+        match self {
+            Backend::Hyprland => format!("hyprctl dispatch focuswindow address:{}", window.window_id),
+            Backend::Sway => format!("swaymsg '[con_id={}] focus'", window.window_id),
+            Backend::Xdotool => format!("{}xdotool windowactivate {}", display_prefix(window), window.window_id),
+            Backend::Wmctrl => format!("{}wmctrl -i -a {}", display_prefix(window), window.window_id),
+        }
+    }
+
+    /// Minimizes the window, or on tiling compositors moves it out of sight.
+    pub fn minimize(&self, window: &WindowInfo) -> Result<()> {
+        let mut command = match self {
+            Backend::Hyprland => return hyprland::minimize_window(&window.window_id),
+            Backend::Sway => return sway::minimize_window(&window.window_id),
+            Backend::Xdotool => {
+                let mut command = x11_command("xdotool", window.display.as_deref());
+                command.args(["windowminimize", &window.window_id]);
+                command
+            }
+            Backend::Wmctrl => {
+                let mut command = x11_command("wmctrl", window.display.as_deref());
+                command.args(["-i", "-r", &window.window_id, "-b", "add,hidden"]);
+                command
+            }
+        };
+        let output = command.output().with_context(|| format!("Failed to run {}", self.name()))?;
+        if !output.status.success() {
+            return Err(anyhow::anyhow!(
+                "{} could not minimize window {}: {}",
+                self.name(),
+                window.window_id,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        Ok(())
+    }
+
+    fn focus(&self, window: &WindowInfo) -> Result<()> {
+        match self {
+            Backend::Hyprland => hyprland::focus_window(&window.window_id),
+            Backend::Sway => sway::focus_window(&window.window_id),
+            Backend::Xdotool | Backend::Wmctrl => {
+                let mut command = x11_command(self.name(), window.display.as_deref());
+                if *self == Backend::Xdotool {
+                    command.args(["windowactivate", &window.window_id]);
+                } else {
+                    command.args(["-i", "-a", &window.window_id]);
+                }
+                let output = run_tool(&mut command).with_context(|| format!("Failed to run {}", self.name()))?;
+                if !output.status.success() {
+                    let stderr = String::from_utf8_lossy(&output.stderr);
+                    return Err(anyhow::anyhow!("{} could not activate window {}: {}", self.name(), window.window_id, stderr.trim()));
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+fn display_prefix(window: &WindowInfo) -> String {
+    window
+        .display
+        .as_ref()
+        .map(|display| format!("DISPLAY={} ", display))
+        .unwrap_or_default()
+}
+
+/// Picks the windows owned by the target process, falling back to windows owned by its direct children.
+fn select_windows_for_pid(windows: Vec<WindowInfo>, target_pid: u32) -> Result<Vec<WindowInfo>> {
+    let (own, others): (Vec<WindowInfo>, Vec<WindowInfo>) = windows.into_iter().partition(|w| w.pid == target_pid);
+    if !own.is_empty() {
+        return Ok(own);
+    }
+
+    let child_pids = get_process_children(target_pid)?;
+    let children: Vec<WindowInfo> = others.into_iter().filter(|w| child_pids.contains(&w.pid)).collect();
+    if children.is_empty() {
+        return Err(anyhow::anyhow!("No window found for PID {}", target_pid));
+    }
+    Ok(children)
+}
+
+/// A backend to try before the others, from the profile's `window_backend`.
+static PREFERRED_BACKEND: OnceLock<Backend> = OnceLock::new();
+
+pub fn prefer_backend(backend: Backend) {
+    PREFERRED_BACKEND.set(backend).ok();
+}
+
+pub fn available_backends(session: SessionType, display: Option<&str>) -> Vec<Backend> {
+    let mut backends: Vec<Backend> = Backend::all()
+        .iter()
+        .copied()
+        .filter(|backend| {
+            if !backend.supports_session(session, display) {
+                trace!("{} backend: not usable in a {} session", backend.name(), session.name());
+                return false;
+            }
+            if let Some(tool) = backend.required_tool().filter(|tool| !command_exists(tool)) {
+                trace!("{} backend: {} is not installed", backend.name(), tool);
+                return false;
+            }
+            true
+        })
+        .collect();
+    if let Some(preferred) = PREFERRED_BACKEND.get() {
+        backends.sort_by_key(|backend| backend != preferred);
+    }
+    let on_display = display.map(|display| format!(" on {}", display)).unwrap_or_default();
+    let names: Vec<&str> = backends.iter().map(|backend| backend.name()).collect();
+    debug!("Window backends for {} session{}: {}", session.name(), on_display, if names.is_empty() { "none".to_string() } else { names.join(", ") });
+    backends
+}
+
+/// Builds a command for an X11 tool, targeting the given display instead of the inherited `$DISPLAY`.
+fn x11_command(program: &str, display: Option<&str>) -> Command {
+    let mut command = Command::new(program);
+    if let Some(display) = display {
+        command.env("DISPLAY", display);
+    }
+    command
+}
+
+/// Runs a window tool, tracing the call and what it printed to stderr when it failed.
+fn run_tool(command: &mut Command) -> io::Result<std::process::Output> {
+    let output = command.output();
+    let call = format!("{:?}", command).replace('"', "");
+    match &output {
+        Ok(output) if output.status.success() => trace!("{}: ok", call),
+        Ok(output) => trace!("{}: {} ({})", call, output.status, String::from_utf8_lossy(&output.stderr).trim()),
+        Err(err) => trace!("{}: {}", call, err),
+    }
+    output
+}
+
+/// Lists the local X displays (e.g. ":0", ":1") from the sockets in /tmp/.X11-unix.
+pub fn x11_displays() -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir("/tmp/.X11-unix") else {
+        return Vec::new();
+    };
+
+    let mut displays: Vec<(u32, String)> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let number = name.strip_prefix('X')?.parse::<u32>().ok()?;
+            Some((number, format!(":{}", number)))
+        })
+        .collect();
+
+    displays.sort();
+    displays.into_iter().map(|(_, display)| display).collect()
+}
+
+/// The X display a process was started on, from its own `DISPLAY` environment variable.
+pub fn process_display(pid: u32) -> Option<String> {
+    let environ = procfs::process::Process::new(pid as i32).ok()?.environ().ok()?;
+    environ
+        .get(std::ffi::OsStr::new("DISPLAY"))
+        .map(|display| display.to_string_lossy().to_string())
+}
+
+/// An X window ID as a number; xdotool prints decimal IDs, wmctrl hexadecimal ones.
+pub fn x11_window_id(window_id: &str) -> Option<u32> {
+    match window_id.strip_prefix("0x") {
+        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+        None => window_id.parse().ok(),
+    }
+}
+
+/// The window in the root window's `_NET_ACTIVE_WINDOW`, read directly from the X server since
+/// focus tracking polls it several times a second.
+fn x11_focused_window(backend: Backend, display: Option<&str>) -> Result<Option<WindowInfo>> {
+    use x11rb::connection::Connection;
+    use x11rb::protocol::xproto::{AtomEnum, ConnectionExt as _};
+
+    let (conn, screen_num) = x11rb::connect(display).context("Failed to connect to the X server")?;
+    let root = conn.setup().roots[screen_num].root;
+    let atom = |name: &str| -> Result<u32> { Ok(conn.intern_atom(false, name.as_bytes())?.reply()?.atom) };
+    let property = |window: u32, property: u32, kind: u32| -> Option<Vec<u8>> {
+        Some(conn.get_property(false, window, property, kind, 0, 1024).ok()?.reply().ok()?.value)
+    };
+
+    let (active_window, net_wm_name, utf8_string) = (atom("_NET_ACTIVE_WINDOW")?, atom("_NET_WM_NAME")?, atom("UTF8_STRING")?);
+    let Some(id) = conn
+        .get_property(false, root, active_window, AtomEnum::WINDOW, 0, 1)?
+        .reply()?
+        .value32()
+        .and_then(|mut values| values.next())
+        .filter(|&id| id != x11rb::NONE)
+    else {
+        return Ok(None);
+    };
+    let Some(pid) = x11_client_pid(&conn, id) else {
+        return Ok(None);
+    };
+
+    let text = |bytes: Vec<u8>| String::from_utf8_lossy(&bytes).into_owned();
+    let title = property(id, net_wm_name, utf8_string)
+        .filter(|name| !name.is_empty())
+        .or_else(|| property(id, AtomEnum::WM_NAME.into(), AtomEnum::STRING.into()))
+        .map(text)
+        .unwrap_or_default();
+    // WM_CLASS holds the instance and then the class, each NUL-terminated
+    let class = property(id, AtomEnum::WM_CLASS.into(), AtomEnum::STRING.into())
+        .and_then(|bytes| bytes.split(|&b| b == 0).nth(1).map(|class| class.to_vec()))
+        .map(text)
+        .unwrap_or_else(|| "Unknown".to_string());
+
+    Ok(Some(WindowInfo {
+        window_id: id.to_string(),
+        title,
+        class,
+        pid,
+        backend,
+        display: display.map(str::to_string),
+    }))
+}
+
+/// An X window's placement from the EWMH properties the window manager maintains:
+/// `_NET_WM_DESKTOP` and `_NET_DESKTOP_NAMES` for the workspace, `_NET_ACTIVE_WINDOW` for focus.
+fn x11_placement(window: &WindowInfo) -> Result<WindowPlacement> {
+    use x11rb::connection::Connection;
+    use x11rb::protocol::xproto::{AtomEnum, ConnectionExt as _};
+
+    let id = x11_window_id(&window.window_id).with_context(|| format!("Invalid X window id {}", window.window_id))?;
+
+    let (conn, screen_num) = x11rb::connect(window.display.as_deref()).context("Failed to connect to the X server")?;
+    let root = conn.setup().roots[screen_num].root;
+    let atom = |name: &str| -> Result<u32> { Ok(conn.intern_atom(false, name.as_bytes())?.reply()?.atom) };
+    let cardinal = |owner: u32, property: u32, kind: AtomEnum| -> Option<u32> {
+        conn.get_property(false, owner, property, kind, 0, 1).ok()?.reply().ok()?.value32()?.next()
+    };
+
+    let (wm_desktop, desktop_names, active_window) =
+        (atom("_NET_WM_DESKTOP")?, atom("_NET_DESKTOP_NAMES")?, atom("_NET_ACTIVE_WINDOW")?);
+
+    let geometry = conn.get_geometry(id)?.reply()?;
+    let origin = conn.translate_coordinates(id, root, 0, 0)?.reply()?;
+
+    let workspace = cardinal(id, wm_desktop, AtomEnum::CARDINAL).map(|desktop| {
+        // 0xFFFFFFFF: shown on every workspace
+        if desktop == u32::MAX {
+            return "all".to_string();
+        }
+        let names = conn
+            .get_property(false, root, desktop_names, AtomEnum::ANY, 0, 1024)
+            .ok()
+            .and_then(|cookie| cookie.reply().ok())
+            .map(|reply| reply.value)
+            .unwrap_or_default();
+        let name = names
+            .split(|&b| b == 0)
+            .nth(desktop as usize)
+            .map(|name| String::from_utf8_lossy(name).into_owned())
+            .filter(|name| !name.is_empty());
+        match name {
+            Some(name) if name != (desktop + 1).to_string() => format!("{} ({})", desktop + 1, name),
+            _ => (desktop + 1).to_string(),
+        }
+    });
+    let active = cardinal(root, active_window, AtomEnum::WINDOW);
+
+    Ok(WindowPlacement {
+        workspace,
+        geometry: Some(Geometry {
+            x: origin.dst_x.into(),
+            y: origin.dst_y.into(),
+            width: geometry.width.into(),
+            height: geometry.height.into(),
+        }),
+        focused: active.map(|active| active == id),
+    })
+}
+
+/// PID of the local client that created a window, via the X-Resource extension. Unlike
+/// `_NET_WM_PID` this works for any window, including unmapped helper windows.
+pub fn x11_client_pid(conn: &impl x11rb::connection::Connection, window: u32) -> Option<u32> {
+    use x11rb::protocol::res::{ClientIdMask, ClientIdSpec, ConnectionExt as _};
+
+    let spec = ClientIdSpec { client: window, mask: ClientIdMask::LOCAL_CLIENT_PID };
+    let reply = conn.res_query_client_ids(&[spec]).ok()?.reply().ok()?;
+
+    reply
+        .ids
+        .iter()
+        .find(|id| id.spec.mask.contains(ClientIdMask::LOCAL_CLIENT_PID))
+        .and_then(|id| id.value.first().copied())
+}
+
+pub fn command_exists(program: &str) -> bool {
+    let Some(paths) = env::var_os("PATH") else {
+        return false;
+    };
+
+    env::split_paths(&paths).any(|dir| Path::new(&dir).join(program).is_file())
+}
+
+/// The outcome of resolving (and possibly switching to) a process's window.
+#[derive(Debug, Serialize)]
+pub struct SwitchResolution {
+    pub pid: u32,
+    pub process_name: String,
+    /// Electron app the process belongs to, e.g. "Slack"
+    pub app: Option<String>,
+    /// Flatpak or Snap app the process runs confined in
+    pub sandbox: Option<SandboxedApp>,
+    pub window_id: String,
+    pub window_pid: u32,
+    pub class: String,
+    pub title: String,
+    pub backend: &'static str,
+    /// How many windows there were to choose from
+    pub windows: usize,
+    /// Terminal tab the process runs in, e.g. "kitty tab 3"
+    pub tab: Option<String>,
+    pub action: String,
+    pub dry_run: bool,
+    pub switched: Option<bool>,
+    pub error: Option<String>,
+}
+
+/// Which of a process's windows to switch to when it has several.
+#[derive(Debug, Clone, Default)]
+pub struct WindowChoice {
+    /// 1-based position among the (title-matching) windows
+    pub index: Option<usize>,
+    /// Part of the title, matched case-insensitively
+    pub title: Option<String>,
+    /// Ask which one when several windows are left
+    pub prompt: bool,
+    /// Take the window after the focused one, for stepping through them
+    pub cycle: bool,
+}
+
+/// A process's window, found but not switched to yet.
+pub struct PlannedSwitch {
+    pub resolution: SwitchResolution,
+    window: WindowInfo,
+    tab_command: Option<Vec<String>>,
+}
+
+impl PlannedSwitch {
+    /// Activates the window, and the process's terminal tab in it, recording the outcome in the
+    /// resolution.
+    pub fn perform(&mut self) -> Result<()> {
+        let result = activate_window(&self.window).and_then(|()| match &self.tab_command {
+            Some(command) => focus_tab(command),
+            None => Ok(()),
+        });
+        self.resolution.switched = Some(result.is_ok());
+        self.resolution.error = result.as_ref().err().map(|err| err.to_string());
+        // Only a window activated by its ID is known to have been focused; tool-goto-window picks
+        // one of the class's windows itself. Only `switch` relies on the history, and not having
+        // it is no reason to fail one
+        if result.is_ok() && !self.window.window_id.is_empty() {
+            focus::record(&self.window).ok();
+        }
+        result
+    }
+}
+
+/// The windows `switch` can go to for a process, with what was learned finding them.
+pub struct SwitchTarget {
+    pid: u32,
+    process_name: String,
+    app: Option<ElectronApp>,
+    sandbox: Option<SandboxedApp>,
+    tab: Option<Tab>,
+    /// Never empty
+    pub windows: Vec<WindowInfo>,
+}
+
+/// Finds the windows `switch` could activate for a process.
+pub fn find_switch_target(system: &System, process: &sysinfo::Process, display: Option<&str>) -> Result<SwitchTarget> {
+    let pid = process.pid().as_u32();
+
+    // Prefer the display the process itself runs on over the one inherited by this tool
+    let display = display.map(str::to_string).or_else(|| process_display(pid));
+
+    // Try to find window associated with this process. An Electron helper's windows belong to
+    // the app's main process, a Flatpak app reports its PID inside the sandbox, and a command
+    // running in a terminal tab has no window of its own, so fall back to its terminal
+    // emulator's window.
+    let app = electron::detect(system, pid);
+    let sandbox = sandbox::detect(pid);
+    let tab = tabs::find_tab(system, pid);
+    let windows = find_windows_by_pid(pid, display.as_deref()).or_else(|err| {
+        if let Some(app) = &app {
+            debug!("Trying the window of {}'s main process {}", app.name, app.main_pid);
+        }
+        match app.as_ref().and_then(|app| app_window(app, display.as_deref())) {
+            Some(window) => Ok(vec![window]),
+            None => Err(err),
+        }
+    });
+    let windows = windows.or_else(|err| {
+        if let Some(sandbox) = &sandbox {
+            debug!("Trying windows with the classes of sandboxed app {:?}", sandbox.window_classes());
+        }
+        match sandbox.as_ref().and_then(|sandbox| sandbox_window(sandbox, display.as_deref())) {
+            Some(window) => Ok(vec![window]),
+            None => Err(err),
+        }
+    });
+    let windows = windows.or_else(|err| match &tab {
+        Some(tab) => {
+            debug!("Trying the window of the {} (PID {}) the process runs in", tab.emulator, tab.emulator_pid);
+            match tab.window_id.as_deref().and_then(|id| tab_window(id, tab.emulator_pid, display.as_deref())) {
+                Some(window) => Ok(vec![window]),
+                None => find_windows_by_pid(tab.emulator_pid, display.as_deref()).map_err(|_| err),
+            }
+        }
+        None => Err(err),
+    })?;
+
+    Ok(SwitchTarget { pid, process_name: process.name().to_string(), app, sandbox, tab, windows })
+}
+
+impl SwitchTarget {
+    /// Picks one of the windows and works out how to activate it, without activating it.
+    pub fn plan(self, choice: &WindowChoice, dry_run: bool) -> Result<PlannedSwitch> {
+        let window_count = self.windows.len();
+        let window = choose_window(self.windows, choice)?;
+
+        let mut action = if window.window_id.is_empty() {
+            format!("tool-goto-window switch {}", extract_program_name(&window)?)
+        } else {
+            window.backend.focus_command(&window)
+        };
+        let tab_command = self.tab.as_ref().and_then(Tab::focus_command);
+        if let Some(command) = &tab_command {
+            action = format!("{} && {}", action, command.join(" "));
+        }
+
+        let resolution = SwitchResolution {
+            pid: self.pid,
+            process_name: self.process_name,
+            app: self.app.map(|app| app.name),
+            sandbox: self.sandbox,
+            window_id: window.window_id.clone(),
+            window_pid: window.pid,
+            class: window.class.clone(),
+            // Titles often carry URLs and document names, and this only goes out as JSON
+            title: redact::text(&window.title),
+            backend: window.backend.name(),
+            windows: window_count,
+            tab: self.tab.as_ref().map(|tab| format!("{} tab {}", tab.emulator, tab.index)),
+            action,
+            dry_run,
+            switched: None,
+            error: None,
+        };
+        Ok(PlannedSwitch { resolution, window, tab_command })
+    }
+}
+
+/// Narrows a process's windows down to one: by title, then by position, cycling, or asking.
+/// Without any of those the window that had focus last is taken, or else the first.
+fn choose_window(mut windows: Vec<WindowInfo>, choice: &WindowChoice) -> Result<WindowInfo> {
+    if let Some(title) = &choice.title {
+        let needle = title.to_lowercase();
+        windows.retain(|window| window.title.to_lowercase().contains(&needle));
+        if windows.is_empty() {
+            return Err(CliError::not_found(format!("No window of the process has '{}' in its title", title)));
+        }
+    }
+
+    if let Some(index) = choice.index {
+        let count = windows.len();
+        return windows.into_iter().nth(index.saturating_sub(1)).ok_or_else(|| {
+            CliError::not_found(format!("No window {}; there are only {} to choose from", index, count))
+        });
+    }
+
+    let recent = most_recent(&windows);
+    if windows.len() > 1 && choice.cycle {
+        // The focused window, or the one last switched to when the backend can't tell
+        let current = focused_window(windows[0].display.as_deref())
+            .ok()
+            .flatten()
+            .map_or(Some(recent), |focused| windows.iter().position(|window| focus::same_window(window, &focused)));
+        // Coming from another app, start at its most recently used window
+        let next = current.map_or(recent, |current| (current + 1) % windows.len());
+        return Ok(windows.remove(next));
+    }
+    if windows.len() > 1 && choice.prompt {
+        return prompt_for_window(windows, recent);
+    }
+    Ok(windows.remove(recent))
+}
+
+/// Index of the window that had focus most recently; the first when none is in the history.
+fn most_recent(windows: &[WindowInfo]) -> usize {
+    focus::last_focused(windows)
+        .into_iter()
+        .max_by_key(|&(_, timestamp)| timestamp)
+        .map_or(0, |(index, _)| index)
+}
+
+fn prompt_for_window(mut windows: Vec<WindowInfo>, default: usize) -> Result<WindowInfo> {
+    println!("The process has {} windows:", windows.len());
+    for (i, window) in windows.iter().enumerate() {
+        println!("  {}. {} - {} (ID: {})", i + 1, window.class, window.title, window.window_id);
+    }
+    print!("Switch to which one? [{}] ", default + 1);
+    io::stdout().flush()?;
+
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    let index = match answer.trim() {
+        "" => default + 1,
+        answer => answer.parse().ok().filter(|index| (1..=windows.len()).contains(index)).context("Aborted")?,
+    };
+    Ok(windows.remove(index - 1))
+}
+
+pub async fn switch_to_process_window(
+    process_identifier: Option<&str>,
+    cwd: Option<&str>,
+    dry_run: bool,
+    format: OutputFormat,
+    display: Option<&str>,
+    choice: &WindowChoice,
+) -> Result<()> {
     let mut system = System::new_all();
     system.refresh_all();
 
     // Find the process
-    let process = if let Ok(pid) = process_identifier.parse::<u32>() {
-        system.process(sysinfo::Pid::from(pid as usize))
-    } else {
-        system
-            .processes()
-            .values()
-            .find(|p| p.name().to_lowercase().contains(&process_identifier.to_lowercase()))
+    let process = Selection::from_args(process_identifier, cwd)?.resolve_unique(&system)?;
+    let pid = process.pid().as_u32();
+
+    let target = match find_switch_target(&system, process, display) {
+        Ok(target) => target,
+        Err(err) => {
+            if format == OutputFormat::Text {
+                print_switch_fallback(process, &err);
+            }
+            return Err(err);
+        }
     };
+    // Only ask on a terminal, and never in the middle of JSON output
+    let prompt = choice.prompt && format == OutputFormat::Text && output::can_prompt();
+    let choice = WindowChoice { prompt, ..choice.clone() };
+    let mut plan = target.plan(&choice, dry_run)?;
 
-    let process = process.context("Process not found")?;
-    let pid = process.pid().as_u32();
+    if format == OutputFormat::Text {
+        let (resolution, window) = (&plan.resolution, &plan.window);
+        match &resolution.app {
+            Some(app) => println!("Found window for {} (process '{}', PID: {})", app, resolution.process_name, pid),
+            None => println!("Found window for process '{}' (PID: {})", resolution.process_name, pid),
+        }
+        println!("Window: {} - {} (ID: {}, PID: {})", window.class, window.title, window.window_id, window.pid);
+        if resolution.windows > 1 && choice.index.is_none() && choice.title.is_none() && !choice.prompt && !choice.cycle {
+            println!("One of {} windows; pick another with --window-index, --title, or --cycle", resolution.windows);
+        }
+        if let Some(display) = &window.display {
+            println!("Display: {}", display);
+        }
+        if let Some(tab) = &resolution.tab {
+            println!("Tab: {}", tab);
+        }
+    }
+
+    if dry_run {
+        match format {
+            OutputFormat::Text => println!("Would switch to window using: {}", plan.resolution.action),
+            OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&plan.resolution)?),
+        }
+        return Ok(());
+    }
+
+    if format == OutputFormat::Text {
+        println!("Switching to window using: {}", plan.resolution.action);
+    }
+
+    let result = plan.perform();
+
+    match format {
+        OutputFormat::Text => {
+            if result.is_ok() {
+                println!("Successfully switched to window");
+            }
+        }
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&plan.resolution)?),
+    }
 
-    // Try to find window associated with this process
-    let window = find_window_by_pid(pid)?;
+    result.context("Failed to switch window")
+}
 
-    // Extract the program name for tool-goto-window
-    let program_name = extract_program_name(&window)?;
+/// Activates the window by its ID, or, for a window known only by its class, hands it to
+/// tool-goto-window.
+fn activate_window(window: &WindowInfo) -> Result<()> {
+    if !window.window_id.is_empty() {
+        return window.backend.focus(window);
+    }
 
-    println!("Found window for process '{}' (PID: {})", process.name(), pid);
-    println!("Window: {} - {}", window.class, window.title);
-    println!("Switching to window using: tool-goto-window switch {}", program_name);
+    let program_name = extract_program_name(window)?;
+    debug!("Activating window {} (class {}) as '{}' with tool-goto-window", window.window_id, window.class, program_name);
 
     // Use tool-goto-window to switch
-    let output = Command::new("tool-goto-window")
-        .arg("switch")
-        .arg(&program_name)
-        .output()
-        .context("Failed to execute tool-goto-window")?;
+    let mut command = x11_command("tool-goto-window", window.display.as_deref());
+    let output = match run_tool(command.arg("switch").arg(&program_name)) {
+        Ok(output) => output,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            return Err(CliError::backend_missing(
+                "tool-goto-window is not installed (run `top-helper doctor` to see what else is missing)",
+            ));
+        }
+        Err(err) => return Err(err).context("Failed to execute tool-goto-window"),
+    };
 
-    if output.status.success() {
-        println!("Successfully switched to window");
-    } else {
+    if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
-        println!("Failed to switch window: {}", stderr);
+        return Err(anyhow::anyhow!("{}", stderr.trim()));
     }
 
     Ok(())
 }
 
-pub fn find_window_by_pid(target_pid: u32) -> Result<WindowInfo> {
-    // First try X11 approach
-    if let Ok(window) = find_x11_window_by_pid(target_pid) {
-        return Ok(window);
+/// An Electron app's window: the one its main process owns, or else one whose class is the app's
+/// name, since apps run by a shared `electron` binary set their own WM_CLASS.
+fn app_window(app: &ElectronApp, display: Option<&str>) -> Option<WindowInfo> {
+    find_window_by_pid(app.main_pid, display).ok().or_else(|| {
+        list_all_windows(display)
+            .ok()?
+            .into_iter()
+            .find(|window| window.class.eq_ignore_ascii_case(&app.name))
+    })
+}
+
+/// A Flatpak or Snap app's window, by its app ID as the window class (org.gnome.Calculator), or
+/// by the ID's last component for apps whose WM_CLASS is a short name instead.
+fn sandbox_window(sandbox: &SandboxedApp, display: Option<&str>) -> Option<WindowInfo> {
+    let windows = list_all_windows(display).ok()?;
+    sandbox.window_classes().into_iter().find_map(|class| {
+        windows.iter().find(|window| window.class.eq_ignore_ascii_case(class)).cloned()
+    })
+}
+
+/// The window a tab's shell was given in WINDOWID, which picks the right one out of an
+/// emulator's several windows. Needs xdotool.
+fn tab_window(window_id: &str, emulator_pid: u32, display: Option<&str>) -> Option<WindowInfo> {
+    if !command_exists("xdotool") {
+        return None;
+    }
+    let title = get_window_title(window_id, display).ok()?;
+    Some(WindowInfo {
+        window_id: window_id.to_string(),
+        title,
+        class: get_window_class(window_id, display).unwrap_or_else(|_| "Unknown".to_string()),
+        pid: emulator_pid,
+        backend: Backend::Xdotool,
+        display: display.map(str::to_string),
+    })
+}
+
+/// Runs a terminal emulator's command for bringing a tab to the front.
+fn focus_tab(command: &[String]) -> Result<()> {
+    let output = match Command::new(&command[0]).args(&command[1..]).output() {
+        Ok(output) => output,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            return Err(CliError::backend_missing(format!("{} is not installed", command[0])));
+        }
+        Err(err) => return Err(err).with_context(|| format!("Failed to run {}", command[0])),
+    };
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow::anyhow!("Failed to focus the tab: {}", stderr.trim()));
+    }
+    Ok(())
+}
+
+pub fn find_window_by_pid(target_pid: u32, display: Option<&str>) -> Result<WindowInfo> {
+    let mut windows = find_windows_by_pid(target_pid, display)?;
+    Ok(windows.remove(0))
+}
+
+/// Every window of the process from the first backend that finds any; never empty.
+pub fn find_windows_by_pid(target_pid: u32, display: Option<&str>) -> Result<Vec<WindowInfo>> {
+    let session = SessionType::detect();
+    let backends = available_backends(session, display);
+
+    if backends.is_empty() {
+        let expected: Vec<&str> = Backend::all().iter().map(|b| b.name()).collect();
+        return Err(CliError::backend_missing(format!(
+            "No window backend available for {} session (supported: {}; run `top-helper doctor` for details)",
+            session.name(),
+            expected.join(", ")
+        )));
+    }
+
+    // Try each available backend in order of preference
+    let mut last_error = None;
+    for backend in backends {
+        debug!("Looking for windows of PID {} with the {} backend", target_pid, backend.name());
+        match backend.find_windows_by_pid(target_pid, display) {
+            Ok(windows) => {
+                debug!("{} backend found {} window(s) of PID {}", backend.name(), windows.len(), target_pid);
+                return Ok(windows);
+            }
+            Err(err) => {
+                debug!("{} backend: {:#}", backend.name(), err);
+                last_error = Some(err);
+            }
+        }
+    }
+
+    let not_found = CliError::new(ErrorKind::NotFound, format!("No window found for PID {}", target_pid));
+    Err(match last_error {
+        Some(err) => err.context(not_found),
+        None => not_found.into(),
+    })
+}
+
+/// Prints where a process can be found when no window could be switched to,
+/// along with the closest action that will actually work in this session.
+fn print_switch_fallback(process: &sysinfo::Process, err: &anyhow::Error) {
+    let pid = process.pid().as_u32();
+    let session = SessionType::detect();
+
+    println!("Could not switch to a window for '{}' (PID: {}): {}", process.name(), pid, err);
+    println!("Session type: {}", session.name());
+
+    let pane = tty::find_tmux_pane(pid);
+    let process_tty = tty::process_tty(pid);
+    let working_dir = process.cwd().map(|p| p.display().to_string());
+
+    if let Some(pane) = &pane {
+        println!("  tmux pane: {} (/dev/{})", pane.target, pane.tty);
+    }
+    if let Some(tty) = &process_tty {
+        println!("  TTY: /dev/{}", tty);
+    }
+    if let Some(wd) = &working_dir {
+        println!("  Working Directory: {}", wd);
     }
 
-    // If X11 fails, try alternative approaches
-    find_window_by_process_name(target_pid)
+    let suggestion = if let Some(pane) = &pane {
+        format!("tmux switch-client -t {}", pane.target)
+    } else if let Some(tty) = &process_tty {
+        format!("switch to the terminal attached to /dev/{}", tty)
+    } else if session.has_x11_display() && available_backends(session, None).is_empty() {
+        "install xdotool or wmctrl to enable window switching".to_string()
+    } else if let Some(wd) = &working_dir {
+        format!("cd {}", wd)
+    } else {
+        format!("top-helper info {}", pid)
+    };
+
+    println!("Suggested action: {}", suggestion);
 }
 
-fn find_x11_window_by_pid(target_pid: u32) -> Result<WindowInfo> {
+fn find_x11_windows_by_pid(target_pid: u32, display: Option<&str>) -> Result<Vec<WindowInfo>> {
     // Get all window IDs
-    let output = Command::new("xdotool")
-        .args(&["search", "--onlyvisible", "."])
-        .output()
+    let output = run_tool(x11_command("xdotool", display).args(["search", "--onlyvisible", "."]))
         .context("Failed to run xdotool search")?;
 
     if !output.status.success() {
@@ -76,50 +906,58 @@ fn find_x11_window_by_pid(target_pid: u32) -> Result<WindowInfo> {
     }
 
     let window_ids = String::from_utf8_lossy(&output.stdout);
+    let child_pids = get_process_children(target_pid)?;
+    debug!(
+        "xdotool lists {} visible windows; looking for PID {} or its children {:?}",
+        window_ids.lines().count(),
+        target_pid,
+        child_pids
+    );
 
+    // Windows of the process itself, and of its direct children in case it has none
+    let mut own = Vec::new();
+    let mut children = Vec::new();
     for window_id in window_ids.lines() {
         let window_id = window_id.trim();
         if window_id.is_empty() {
             continue;
         }
 
-        // Get window PID
-        if let Ok(window_pid) = get_window_pid(window_id) {
-            if window_pid == target_pid {
-                let title = get_window_title(window_id).unwrap_or_else(|_| "Unknown".to_string());
-                let class = get_window_class(window_id).unwrap_or_else(|_| "Unknown".to_string());
-
-                return Ok(WindowInfo {
-                    window_id: window_id.to_string(),
-                    title,
-                    class,
-                    pid: target_pid,
-                });
+        let window_pid = match get_window_pid(window_id, display) {
+            Ok(pid) => pid,
+            Err(err) => {
+                trace!("Skipping window {}: {:#}", window_id, err);
+                continue;
             }
-        }
-
-        // Also check child processes
-        if let Ok(child_pids) = get_process_children(target_pid) {
-            if let Ok(window_pid) = get_window_pid(window_id) {
-                if child_pids.contains(&window_pid) {
-                    let title = get_window_title(window_id).unwrap_or_else(|_| "Unknown".to_string());
-                    let class = get_window_class(window_id).unwrap_or_else(|_| "Unknown".to_string());
+        };
+        let matches = if window_pid == target_pid {
+            &mut own
+        } else if child_pids.contains(&window_pid) {
+            &mut children
+        } else {
+            continue;
+        };
 
-                    return Ok(WindowInfo {
-                        window_id: window_id.to_string(),
-                        title,
-                        class,
-                        pid: window_pid,
-                    });
-                }
-            }
-        }
+        let title = get_window_title(window_id, display).unwrap_or_else(|_| "Unknown".to_string());
+        let class = get_window_class(window_id, display).unwrap_or_else(|_| "Unknown".to_string());
+        matches.push(WindowInfo {
+            window_id: window_id.to_string(),
+            title,
+            class,
+            pid: window_pid,
+            backend: Backend::Xdotool,
+            display: display.map(str::to_string),
+        });
     }
 
-    Err(anyhow::anyhow!("No window found for PID {}", target_pid))
+    match (own.is_empty(), children.is_empty()) {
+        (false, _) => Ok(own),
+        (true, false) => Ok(children),
+        (true, true) => Err(anyhow::anyhow!("No window found for PID {}", target_pid)),
+    }
 }
 
-fn find_window_by_process_name(target_pid: u32) -> Result<WindowInfo> {
+fn find_windows_by_process_name(target_pid: u32, display: Option<&str>) -> Result<Vec<WindowInfo>> {
     let mut system = System::new_all();
     system.refresh_all();
 
@@ -130,44 +968,45 @@ fn find_window_by_process_name(target_pid: u32) -> Result<WindowInfo> {
     let process_name = process.name();
 
     // Try to find window by process name using wmctrl
-    let output = Command::new("wmctrl")
-        .args(&["-l", "-p"])
-        .output()
-        .context("Failed to run wmctrl")?;
+    let output = run_tool(x11_command("wmctrl", display).args(["-l", "-p"])).context("Failed to run wmctrl")?;
 
     if !output.status.success() {
         return Err(anyhow::anyhow!("wmctrl failed"));
     }
 
     let window_list = String::from_utf8_lossy(&output.stdout);
+    let mut windows = Vec::new();
 
     for line in window_list.lines() {
         let parts: Vec<&str> = line.split_whitespace().collect();
-        if parts.len() >= 4 {
-            if let Ok(window_pid) = parts[2].parse::<u32>() {
-                if window_pid == target_pid {
-                    let window_id = parts[0];
-                    let title = parts[4..].join(" ");
-
-                    return Ok(WindowInfo {
-                        window_id: window_id.to_string(),
-                        title: title.clone(),
-                        class: process_name.to_string(),
-                        pid: target_pid,
-                    });
-                }
-            }
+        if parts.len() >= 4
+            && let Ok(window_pid) = parts[2].parse::<u32>()
+            && window_pid == target_pid
+        {
+            let window_id = parts[0];
+            let title = parts[4..].join(" ");
+
+            windows.push(WindowInfo {
+                window_id: window_id.to_string(),
+                title: title.clone(),
+                class: process_name.to_string(),
+                pid: target_pid,
+                backend: Backend::Wmctrl,
+                display: display.map(str::to_string),
+            });
         }
     }
 
-    Err(anyhow::anyhow!("No window found for process {}", process_name))
+    if windows.is_empty() {
+        debug!("wmctrl lists {} windows, none with _NET_WM_PID {}", window_list.lines().count(), target_pid);
+        return Err(anyhow::anyhow!("No window found for process {}", process_name));
+    }
+    Ok(windows)
 }
 
-fn get_window_pid(window_id: &str) -> Result<u32> {
-    let output = Command::new("xdotool")
-        .args(&["getwindowpid", window_id])
-        .output()
-        .context("Failed to get window PID")?;
+pub fn get_window_pid(window_id: &str, display: Option<&str>) -> Result<u32> {
+    let output =
+        run_tool(x11_command("xdotool", display).args(["getwindowpid", window_id])).context("Failed to get window PID")?;
 
     if !output.status.success() {
         return Err(anyhow::anyhow!("Failed to get PID for window {}", window_id));
@@ -180,10 +1019,8 @@ fn get_window_pid(window_id: &str) -> Result<u32> {
         .context("Failed to parse PID")
 }
 
-fn get_window_title(window_id: &str) -> Result<String> {
-    let output = Command::new("xdotool")
-        .args(&["getwindowname", window_id])
-        .output()
+fn get_window_title(window_id: &str, display: Option<&str>) -> Result<String> {
+    let output = run_tool(x11_command("xdotool", display).args(["getwindowname", window_id]))
         .context("Failed to get window title")?;
 
     if !output.status.success() {
@@ -193,10 +1030,8 @@ fn get_window_title(window_id: &str) -> Result<String> {
     Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
 }
 
-fn get_window_class(window_id: &str) -> Result<String> {
-    let output = Command::new("xprop")
-        .args(&["-id", window_id, "WM_CLASS"])
-        .output()
+fn get_window_class(window_id: &str, display: Option<&str>) -> Result<String> {
+    let output = run_tool(x11_command("xprop", display).args(["-id", window_id, "WM_CLASS"]))
         .context("Failed to get window class")?;
 
     if !output.status.success() {
@@ -205,10 +1040,10 @@ fn get_window_class(window_id: &str) -> Result<String> {
 
     let class_info = String::from_utf8_lossy(&output.stdout);
     // Parse WM_CLASS output: WM_CLASS(STRING) = "instance", "class"
-    if let Some(start) = class_info.find('"') {
-        if let Some(end) = class_info[start + 1..].find('"') {
-            return Ok(class_info[start + 1..start + 1 + end].to_string());
-        }
+    if let Some(start) = class_info.find('"')
+        && let Some(end) = class_info[start + 1..].find('"')
+    {
+        return Ok(class_info[start + 1..start + 1 + end].to_string());
     }
 
     Ok("Unknown".to_string())
@@ -257,27 +1092,82 @@ fn extract_program_name(window: &WindowInfo) -> Result<String> {
     Ok(program_name.to_string())
 }
 
-pub fn list_all_windows() -> Result<Vec<WindowInfo>> {
-    let mut windows = Vec::new();
-
-    // Try X11 first
-    if let Ok(x11_windows) = list_x11_windows() {
-        windows.extend(x11_windows);
+pub fn list_all_windows(display: Option<&str>) -> Result<Vec<WindowInfo>> {
+    // Use the first available backend that reports any windows
+    for backend in available_backends(SessionType::detect(), display) {
+        match backend.list_windows(display) {
+            Ok(windows) if !windows.is_empty() => return Ok(windows),
+            Ok(_) => debug!("{} backend lists no windows", backend.name()),
+            Err(err) => debug!("{} backend could not list windows: {:#}", backend.name(), err),
+        }
     }
 
-    // If no X11 windows found, try wmctrl
-    if windows.is_empty() {
-        if let Ok(wmctrl_windows) = list_wmctrl_windows() {
-            windows.extend(wmctrl_windows);
+    Ok(Vec::new())
+}
+
+/// The window that has focus now, from the first available backend.
+pub fn focused_window(display: Option<&str>) -> Result<Option<WindowInfo>> {
+    let session = SessionType::detect();
+    let Some(backend) = available_backends(session, display).into_iter().next() else {
+        return Err(CliError::backend_missing(format!("No window backend available for {} session", session.name())));
+    };
+    backend.focused_window(display)
+}
+
+#[derive(Tabled)]
+struct WindowRow {
+    #[tabled(rename = "Display")]
+    display: String,
+
+    #[tabled(rename = "Window ID")]
+    window_id: String,
+
+    #[tabled(rename = "PID")]
+    pid: u32,
+
+    #[tabled(rename = "Class")]
+    class: String,
+
+    #[tabled(rename = "Title")]
+    title: String,
+}
+
+pub async fn list_windows(display: Option<&str>, all_displays: bool) -> Result<()> {
+    let displays: Vec<Option<String>> = if let Some(display) = display {
+        vec![Some(display.to_string())]
+    } else if all_displays {
+        x11_displays().into_iter().map(Some).collect()
+    } else {
+        vec![None]
+    };
+
+    let mut rows = Vec::new();
+    for display in &displays {
+        for window in list_all_windows(display.as_deref())? {
+            rows.push(WindowRow {
+                display: window
+                    .display
+                    .clone()
+                    .or_else(|| env::var("DISPLAY").ok())
+                    .unwrap_or_else(|| window.backend.name().to_string()),
+                window_id: window.window_id,
+                pid: window.pid,
+                class: window.class,
+                title: window.title,
+            });
         }
     }
 
-    Ok(windows)
+    let mut table = Table::new(rows);
+    table.modify(Columns::single(4), Width::truncate(60).suffix("..."));
+    println!("{}", table);
+
+    Ok(())
 }
 
-fn list_x11_windows() -> Result<Vec<WindowInfo>> {
-    let output = Command::new("xdotool")
-        .args(&["search", "--onlyvisible", "."])
+fn list_x11_windows(display: Option<&str>) -> Result<Vec<WindowInfo>> {
+    let output = x11_command("xdotool", display)
+        .args(["search", "--onlyvisible", "."])
         .output()
         .context("Failed to run xdotool search")?;
 
@@ -294,15 +1184,17 @@ fn list_x11_windows() -> Result<Vec<WindowInfo>> {
             continue;
         }
 
-        if let Ok(pid) = get_window_pid(window_id) {
-            let title = get_window_title(window_id).unwrap_or_else(|_| "Unknown".to_string());
-            let class = get_window_class(window_id).unwrap_or_else(|_| "Unknown".to_string());
+        if let Ok(pid) = get_window_pid(window_id, display) {
+            let title = get_window_title(window_id, display).unwrap_or_else(|_| "Unknown".to_string());
+            let class = get_window_class(window_id, display).unwrap_or_else(|_| "Unknown".to_string());
 
             windows.push(WindowInfo {
                 window_id: window_id.to_string(),
                 title,
                 class,
                 pid,
+                backend: Backend::Xdotool,
+                display: display.map(str::to_string),
             });
         }
     }
@@ -310,9 +1202,9 @@ fn list_x11_windows() -> Result<Vec<WindowInfo>> {
     Ok(windows)
 }
 
-fn list_wmctrl_windows() -> Result<Vec<WindowInfo>> {
-    let output = Command::new("wmctrl")
-        .args(&["-l", "-p"])
+fn list_wmctrl_windows(display: Option<&str>) -> Result<Vec<WindowInfo>> {
+    let output = x11_command("wmctrl", display)
+        .args(["-l", "-p"])
         .output()
         .context("Failed to run wmctrl")?;
 
@@ -325,18 +1217,20 @@ fn list_wmctrl_windows() -> Result<Vec<WindowInfo>> {
 
     for line in window_list.lines() {
         let parts: Vec<&str> = line.split_whitespace().collect();
-        if parts.len() >= 4 {
-            if let Ok(pid) = parts[2].parse::<u32>() {
-                let window_id = parts[0];
-                let title = parts[4..].join(" ");
-
-                windows.push(WindowInfo {
-                    window_id: window_id.to_string(),
-                    title: title.clone(),
-                    class: "Unknown".to_string(),
-                    pid,
-                });
-            }
+        if parts.len() >= 4
+            && let Ok(pid) = parts[2].parse::<u32>()
+        {
+            let window_id = parts[0];
+            let title = parts[4..].join(" ");
+
+            windows.push(WindowInfo {
+                window_id: window_id.to_string(),
+                title: title.clone(),
+                class: "Unknown".to_string(),
+                pid,
+                backend: Backend::Wmctrl,
+                display: display.map(str::to_string),
+            });
         }
     }
 